@@ -0,0 +1,209 @@
+/*
+ * libpathrs: safe path resolution on Linux
+ * Copyright (C) 2019-2024 Aleksa Sarai <cyphar@cyphar.com>
+ * Copyright (C) 2019-2024 SUSE LLC
+ *
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Lesser General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or (at your
+ * option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY
+ * or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License
+ * for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+/*
+ * File: benches/resolve.rs
+ *
+ * Benchmarks for the core libpathrs operations, used to catch performance
+ * regressions and to compare the kernel and emulated resolver backends. Run
+ * with "make bench" (or "cargo bench -p pathrs-benches").
+ */
+
+use pathrs::{flags::OpenFlags, ResolverBackend, Root};
+
+use std::{
+    fs::{self, File, Permissions},
+    os::unix::fs::{symlink, PermissionsExt},
+    path::{Path, PathBuf},
+};
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use rustix::fs::Dir;
+use tempfile::TempDir;
+
+/// Number of nested directories in the "deep" fixture.
+const DEEP_DEPTH: usize = 32;
+/// Number of symlinks in the "symlinks" fixture chain.
+const SYMLINK_CHAIN_LENGTH: usize = 16;
+/// Number of entries in the "read_dir" fixture directory.
+const READ_DIR_ENTRIES: usize = 128;
+
+fn deep_path() -> PathBuf {
+    let mut path = PathBuf::from("deep");
+    for _ in 0..DEEP_DEPTH {
+        path.push("d");
+    }
+    path
+}
+
+/// Create the fixture tree used by the resolve benchmarks.
+fn create_resolve_tree() -> TempDir {
+    let dir = TempDir::new().expect("create tempdir");
+    let root = dir.path();
+
+    // Shallow path.
+    fs::create_dir_all(root.join("shallow/a")).expect("create shallow tree");
+    File::create(root.join("shallow/a/file")).expect("create shallow file");
+
+    // Deep path.
+    let deep = root.join(deep_path());
+    fs::create_dir_all(&deep).expect("create deep tree");
+    File::create(deep.join("file")).expect("create deep file");
+
+    // A chain of symlinks, mixing absolute (in-root) and relative targets as
+    // well as ".." components, ending in the shallow file.
+    fs::create_dir_all(root.join("symlinks/dir")).expect("create symlinks tree");
+    symlink("/shallow/a/file", root.join("symlinks/link0")).expect("create symlink");
+    for i in 1..SYMLINK_CHAIN_LENGTH {
+        let target = if i % 2 == 0 {
+            format!("/symlinks/link{}", i - 1)
+        } else {
+            format!("dir/../link{}", i - 1)
+        };
+        symlink(target, root.join(format!("symlinks/link{i}"))).expect("create symlink");
+    }
+
+    // A directory with lots of entries.
+    fs::create_dir_all(root.join("readdir")).expect("create readdir tree");
+    for i in 0..READ_DIR_ENTRIES {
+        File::create(root.join(format!("readdir/file{i}"))).expect("create readdir entry");
+    }
+
+    dir
+}
+
+/// Create a small tree (3 levels with a fanout of 4) for remove_all.
+fn create_remove_tree(dir: &Path) {
+    for a in 0..4 {
+        for b in 0..4 {
+            let subdir = dir.join(format!("tree/{a}/{b}"));
+            fs::create_dir_all(&subdir).expect("create remove_all tree");
+            for c in 0..4 {
+                File::create(subdir.join(format!("file{c}"))).expect("create remove_all file");
+            }
+        }
+    }
+}
+
+fn open_root(dir: &Path) -> Root {
+    Root::open(dir).expect("open root")
+}
+
+fn bench_resolve(c: &mut Criterion) {
+    let dir = create_resolve_tree();
+
+    let mut group = c.benchmark_group("resolve");
+    for backend in [
+        ResolverBackend::KernelOpenat2,
+        ResolverBackend::EmulatedOpath,
+    ] {
+        if !backend.supported() {
+            continue;
+        }
+        let root = open_root(dir.path()).with_resolver_backend(backend);
+        let backend = format!("{backend:?}");
+
+        for (name, path) in [
+            ("shallow", PathBuf::from("shallow/a/file")),
+            ("deep", deep_path().join("file")),
+            (
+                "symlinks",
+                PathBuf::from(format!("symlinks/link{}", SYMLINK_CHAIN_LENGTH - 1)),
+            ),
+        ] {
+            group.bench_with_input(BenchmarkId::new(name, &backend), &path, |b, path| {
+                b.iter(|| root.resolve(path).expect("resolve path"))
+            });
+        }
+    }
+    group.finish();
+}
+
+fn bench_mkdir_all(c: &mut Criterion) {
+    let perm = Permissions::from_mode(0o755);
+    let path = deep_path();
+
+    let mut group = c.benchmark_group("mkdir_all");
+    group.bench_function("new", |b| {
+        b.iter_batched(
+            || TempDir::new().expect("create tempdir"),
+            |dir| {
+                open_root(dir.path())
+                    .mkdir_all(&path, &perm)
+                    .expect("mkdir_all");
+                dir
+            },
+            BatchSize::PerIteration,
+        )
+    });
+
+    let dir = create_resolve_tree();
+    let root = open_root(dir.path());
+    group.bench_function("existing", |b| {
+        b.iter(|| root.mkdir_all(&path, &perm).expect("mkdir_all"))
+    });
+    group.finish();
+}
+
+fn bench_remove_all(c: &mut Criterion) {
+    c.bench_function("remove_all", |b| {
+        b.iter_batched(
+            || {
+                let dir = TempDir::new().expect("create tempdir");
+                create_remove_tree(dir.path());
+                dir
+            },
+            |dir| {
+                open_root(dir.path())
+                    .remove_all("tree")
+                    .expect("remove_all");
+                dir
+            },
+            BatchSize::PerIteration,
+        )
+    });
+}
+
+fn bench_read_dir(c: &mut Criterion) {
+    let dir = create_resolve_tree();
+    let root = open_root(dir.path());
+
+    c.bench_function("read_dir", |b| {
+        b.iter(|| {
+            let dir = root
+                .resolve("readdir")
+                .expect("resolve directory")
+                .reopen(OpenFlags::O_RDONLY | OpenFlags::O_DIRECTORY)
+                .expect("reopen directory");
+            Dir::read_from(dir)
+                .expect("read directory")
+                .collect::<Result<Vec<_>, _>>()
+                .expect("read directory entries")
+        })
+    });
+}
+
+criterion_group!(
+    benches,
+    bench_resolve,
+    bench_mkdir_all,
+    bench_remove_all,
+    bench_read_dir
+);
+criterion_main!(benches);
@@ -28,6 +28,7 @@ from .._pathrs import CBuffer, CString, ProcfsBase
 class CError:
     saved_errno: int
     description: CString
+    kind: int
 
 ErrorId: TypeAlias = int
 RawFd: TypeAlias = int
@@ -47,7 +47,7 @@ while [ "$#" -gt 0 ]; do
 done
 
 function nextest_run() {
-	features=("capi")
+	features=("capi" "tokio")
 
 	if [ -v CARGO_TARGET_X86_64_UNKNOWN_LINUX_GNU_RUNNER ]; then
 		unset CARGO_TARGET_X86_64_UNKNOWN_LINUX_GNU_RUNNER
@@ -68,6 +68,57 @@ enum pathrs_proc_base_t {
 };
 typedef uint64_t pathrs_proc_base_t;
 
+/**
+ * The category of error described by a pathrs_error_t. This allows C users to
+ * distinguish between errors that have the same errno value (for instance, a
+ * safety violation and a genuine EXDEV from the kernel both have a
+ * saved_errno of EXDEV).
+ */
+enum pathrs_error_kind_t {
+    /**
+     * Some internal error occurred. For more information, see the textual
+     * description of the error.
+     */
+    PATHRS_ERRKIND_INTERNAL_ERROR = 0,
+    /**
+     * The underlying error came from a system call. saved_errno contains the
+     * errno(3) value of the error (if available).
+     */
+    PATHRS_ERRKIND_OS_ERROR = 1,
+    /**
+     * The requested feature is not implemented in libpathrs.
+     */
+    PATHRS_ERRKIND_NOT_IMPLEMENTED = 2,
+    /**
+     * The requested feature is not supported by the system.
+     */
+    PATHRS_ERRKIND_NOT_SUPPORTED = 3,
+    /**
+     * The provided arguments to libpathrs were invalid.
+     */
+    PATHRS_ERRKIND_INVALID_ARGUMENT = 4,
+    /**
+     * libpathrs encountered a state where the safety of the operation could
+     * not be guaranteed. This is usually the result of an attack by a
+     * malicious program.
+     */
+    PATHRS_ERRKIND_SAFETY_VIOLATION = 5,
+    /**
+     * The requested lock is already held by someone else.
+     */
+    PATHRS_ERRKIND_ALREADY_HELD = 6,
+    /**
+     * The operation did not complete within the configured time budget.
+     */
+    PATHRS_ERRKIND_TIMED_OUT = 7,
+    /**
+     * The operation was aborted because it would have exceeded a configured
+     * resource limit.
+     */
+    PATHRS_ERRKIND_LIMIT_EXCEEDED = 8,
+};
+typedef uint64_t pathrs_error_kind_t;
+
 /**
  * Attempts to represent a Rust Error type in C. This structure must be freed
  * using pathrs_errorinfo_free().
@@ -82,6 +133,13 @@ typedef struct __CBINDGEN_ALIGNED(8) {
      * Textual description of the error.
      */
     const char *description;
+    /**
+     * The category of the error (one of the PATHRS_ERRKIND_* values). This can
+     * be used to distinguish between errors with the same saved_errno, such
+     * as a SafetyViolation (which has a saved_errno of EXDEV) and a genuine
+     * EXDEV returned by the kernel.
+     */
+    pathrs_error_kind_t kind;
 } pathrs_error_t;
 
 /**
@@ -114,9 +172,10 @@ int pathrs_open_root(const char *path);
  * writing. This does not consume the original file descriptor. (This can be
  * used with non-O_PATH file descriptors as well.)
  *
- * It should be noted that the use of O_CREAT *is not* supported (and will
- * result in an error). Handles only refer to *existing* files. Instead you
- * need to use pathrs_inroot_creat().
+ * It should be noted that the use of O_CREAT, O_EXCL and O_TMPFILE *is not*
+ * supported (and will result in an error). Handles only refer to *existing*
+ * files. Instead you need to use pathrs_inroot_creat(). O_PATH is also not
+ * supported -- to get a copy of an O_PATH file descriptor, use dup(2).
  *
  * In addition, O_NOCTTY is automatically set when opening the path. If you
  * want to use the path as a controlling terminal, you will have to do
@@ -134,6 +193,26 @@ int pathrs_open_root(const char *path);
  */
 int pathrs_reopen(int fd, int flags);
 
+/**
+ * Check whether the given file descriptor is an O_PATH file descriptor.
+ *
+ * File descriptors returned by pathrs_inroot_resolve() and
+ * pathrs_inroot_resolve_nofollow() are O_PATH file descriptors, which cannot
+ * be used for reading or writing (attempting to do so will fail with EBADF).
+ * Use pathrs_reopen() to get a file descriptor that can be used for I/O.
+ *
+ * # Return Value
+ *
+ * On success, this function returns 1 if fd is an O_PATH file descriptor and
+ * 0 otherwise.
+ *
+ * If an error occurs, this function will return a negative error code. To
+ * retrieve information about the error (such as a string describing the error,
+ * the system errno(7) value associated with the error, etc), use
+ * pathrs_errorinfo().
+ */
+int pathrs_is_opath(int fd);
+
 /**
  * Resolve the given path within the rootfs referenced by root_fd. The path
  * *must already exist*, otherwise an error will occur.
@@ -24,9 +24,7 @@ use crate::{
     },
     error::{Error, ErrorImpl},
     flags::{OpenFlags, RenameFlags},
-    procfs::GLOBAL_PROCFS_HANDLE,
-    utils::FdExt,
-    InodeType, Root, RootRef,
+    HandleRef, InodeType, Root, RootRef,
 };
 
 use std::{
@@ -44,9 +42,12 @@ use libc::{c_char, c_int, c_uint, dev_t, size_t};
 /// equivalent to
 ///
 /// ```c
-/// fd = open(path, O_PATH|O_DIRECTORY);
+/// fd = open(path, O_PATH|O_DIRECTORY|O_NOFOLLOW);
 /// ```
 ///
+/// except that if the final component of `path` is a symlink, the operation
+/// fails with `ELOOP` (rather than `ENOTDIR`).
+///
 /// # Return Value
 ///
 /// On success, this function returns a file descriptor that can be used as a
@@ -68,9 +69,10 @@ pub unsafe extern "C" fn pathrs_open_root(path: *const c_char) -> RawFd {
 /// writing. This does not consume the original file descriptor. (This can be
 /// used with non-O_PATH file descriptors as well.)
 ///
-/// It should be noted that the use of O_CREAT *is not* supported (and will
-/// result in an error). Handles only refer to *existing* files. Instead you
-/// need to use pathrs_inroot_creat().
+/// It should be noted that the use of O_CREAT, O_EXCL and O_TMPFILE *is not*
+/// supported (and will result in an error). Handles only refer to *existing*
+/// files. Instead you need to use pathrs_inroot_creat(). O_PATH is also not
+/// supported -- to get a copy of an O_PATH file descriptor, use dup(2).
 ///
 /// In addition, O_NOCTTY is automatically set when opening the path. If you
 /// want to use the path as a controlling terminal, you will have to do
@@ -89,9 +91,31 @@ pub unsafe extern "C" fn pathrs_open_root(path: *const c_char) -> RawFd {
 pub extern "C" fn pathrs_reopen(fd: CBorrowedFd<'_>, flags: c_int) -> RawFd {
     let flags = OpenFlags::from_bits_retain(flags);
 
+    || -> Result<_, Error> { HandleRef::from_fd(fd.try_as_borrowed_fd()?).reopen(flags) }()
+        .into_c_return()
+}
+
+/// Check whether the given file descriptor is an O_PATH file descriptor.
+///
+/// File descriptors returned by pathrs_inroot_resolve() and
+/// pathrs_inroot_resolve_nofollow() are O_PATH file descriptors, which cannot
+/// be used for reading or writing (attempting to do so will fail with EBADF).
+/// Use pathrs_reopen() to get a file descriptor that can be used for I/O.
+///
+/// # Return Value
+///
+/// On success, this function returns 1 if fd is an O_PATH file descriptor and
+/// 0 otherwise.
+///
+/// If an error occurs, this function will return a negative error code. To
+/// retrieve information about the error (such as a string describing the error,
+/// the system errno(7) value associated with the error, etc), use
+/// pathrs_errorinfo().
+#[no_mangle]
+pub extern "C" fn pathrs_is_opath(fd: CBorrowedFd<'_>) -> c_int {
     || -> Result<_, Error> {
-        fd.try_as_borrowed_fd()?
-            .reopen(&GLOBAL_PROCFS_HANDLE, flags)
+        let fd = fd.try_as_borrowed_fd()?;
+        HandleRef::from_fd(fd).is_o_path().map(c_int::from)
     }()
     .into_c_return()
 }
@@ -493,9 +517,7 @@ pub unsafe extern "C" fn pathrs_inroot_mknod(
             libc::S_IFBLK => InodeType::BlockDevice(perms, dev),
             libc::S_IFCHR => InodeType::CharacterDevice(perms, dev),
             libc::S_IFIFO => InodeType::Fifo(perms),
-            libc::S_IFSOCK => Err(ErrorImpl::NotImplemented {
-                feature: "mknod(S_IFSOCK)".into(),
-            })?,
+            libc::S_IFSOCK => InodeType::Socket(perms),
             _ => Err(ErrorImpl::InvalidArgument {
                 name: "mode".into(),
                 description: "invalid S_IFMT mask".into(),
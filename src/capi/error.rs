@@ -19,7 +19,7 @@
 
 use crate::{
     capi::{ret::CReturn, utils::Leakable},
-    error::Error,
+    error::{Error, ErrorKind},
 };
 
 use std::{
@@ -32,6 +32,7 @@ use std::{
 
 use libc::{c_char, c_int};
 use once_cell::sync::Lazy;
+use open_enum::open_enum;
 use rand::{self, Rng};
 
 // TODO: Switch this to using a slab or similar structure, possibly using a less
@@ -59,6 +60,64 @@ pub(crate) fn store_error(err: Error) -> CReturn {
     }
 }
 
+/// The category of error described by a pathrs_error_t. This allows C users to
+/// distinguish between errors that have the same errno value (for instance, a
+/// safety violation and a genuine EXDEV from the kernel both have a
+/// saved_errno of EXDEV).
+#[open_enum]
+#[repr(u64)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[allow(non_camel_case_types, dead_code)]
+pub enum CErrorKind {
+    /// Some internal error occurred. For more information, see the textual
+    /// description of the error.
+    PATHRS_ERRKIND_INTERNAL_ERROR = 0,
+
+    /// The underlying error came from a system call. saved_errno contains the
+    /// errno(3) value of the error (if available).
+    PATHRS_ERRKIND_OS_ERROR = 1,
+
+    /// The requested feature is not implemented in libpathrs.
+    PATHRS_ERRKIND_NOT_IMPLEMENTED = 2,
+
+    /// The requested feature is not supported by the system.
+    PATHRS_ERRKIND_NOT_SUPPORTED = 3,
+
+    /// The provided arguments to libpathrs were invalid.
+    PATHRS_ERRKIND_INVALID_ARGUMENT = 4,
+
+    /// libpathrs encountered a state where the safety of the operation could
+    /// not be guaranteed. This is usually the result of an attack by a
+    /// malicious program.
+    PATHRS_ERRKIND_SAFETY_VIOLATION = 5,
+
+    /// The requested lock is already held by someone else.
+    PATHRS_ERRKIND_ALREADY_HELD = 6,
+
+    /// The operation did not complete within the configured time budget.
+    PATHRS_ERRKIND_TIMED_OUT = 7,
+
+    /// The operation was aborted because it would have exceeded a configured
+    /// resource limit.
+    PATHRS_ERRKIND_LIMIT_EXCEEDED = 8,
+}
+
+impl From<ErrorKind> for CErrorKind {
+    fn from(kind: ErrorKind) -> Self {
+        match kind {
+            ErrorKind::InternalError => CErrorKind::PATHRS_ERRKIND_INTERNAL_ERROR,
+            ErrorKind::OsError(_) => CErrorKind::PATHRS_ERRKIND_OS_ERROR,
+            ErrorKind::NotImplemented => CErrorKind::PATHRS_ERRKIND_NOT_IMPLEMENTED,
+            ErrorKind::NotSupported => CErrorKind::PATHRS_ERRKIND_NOT_SUPPORTED,
+            ErrorKind::InvalidArgument => CErrorKind::PATHRS_ERRKIND_INVALID_ARGUMENT,
+            ErrorKind::SafetyViolation => CErrorKind::PATHRS_ERRKIND_SAFETY_VIOLATION,
+            ErrorKind::AlreadyHeld => CErrorKind::PATHRS_ERRKIND_ALREADY_HELD,
+            ErrorKind::TimedOut => CErrorKind::PATHRS_ERRKIND_TIMED_OUT,
+            ErrorKind::LimitExceeded => CErrorKind::PATHRS_ERRKIND_LIMIT_EXCEEDED,
+        }
+    }
+}
+
 /// Attempts to represent a Rust Error type in C. This structure must be freed
 /// using pathrs_errorinfo_free().
 // NOTE: This API is exposed to library users in a read-only manner with memory
@@ -76,6 +135,12 @@ pub struct CError {
 
     /// Textual description of the error.
     pub description: *const c_char,
+
+    /// The category of the error (one of the PATHRS_ERRKIND_* values). This can
+    /// be used to distinguish between errors with the same saved_errno, such
+    /// as a SafetyViolation (which has a saved_errno of EXDEV) and a genuine
+    /// EXDEV returned by the kernel.
+    pub kind: CErrorKind,
 }
 
 impl Leakable for CError {}
@@ -108,6 +173,7 @@ impl From<&Error> for CError {
         CError {
             saved_errno: saved_errno.into(),
             description: desc.into_raw(),
+            kind: err.kind().into(),
         }
     }
 }
@@ -273,6 +339,31 @@ mod tests {
             libc::EXDEV as u64,
             "cerror should contain EXDEV errno for SafetyViolation"
         );
+        assert_eq!(
+            cerr.kind,
+            CErrorKind::PATHRS_ERRKIND_SAFETY_VIOLATION,
+            "cerror should have SAFETY_VIOLATION kind for SafetyViolation"
+        );
+    }
+
+    #[test]
+    fn cerror_exdev_oserror_kind() {
+        let err = Error::from(ErrorImpl::OsError {
+            operation: "fake operation".into(),
+            source: IOError::from_raw_os_error(libc::EXDEV),
+        });
+
+        let cerr = CError::from(&err);
+        assert_eq!(
+            cerr.saved_errno,
+            libc::EXDEV as u64,
+            "cerror should contain EXDEV errno for OsError(EXDEV)"
+        );
+        assert_eq!(
+            cerr.kind,
+            CErrorKind::PATHRS_ERRKIND_OS_ERROR,
+            "cerror should have OS_ERROR kind for OsError(EXDEV)"
+        );
     }
 
     #[test]
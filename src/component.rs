@@ -0,0 +1,453 @@
+/*
+ * libpathrs: safe path resolution on Linux
+ * Copyright (C) 2019-2024 Aleksa Sarai <cyphar@cyphar.com>
+ * Copyright (C) 2019-2024 SUSE LLC
+ *
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Lesser General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or (at your
+ * option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY
+ * or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License
+ * for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+#![forbid(unsafe_code)]
+
+use crate::error::{Error, ErrorImpl};
+
+use std::{
+    ffi::{OsStr, OsString},
+    os::unix::ffi::{OsStrExt, OsStringExt},
+    path::{Path, PathBuf},
+};
+
+/// A single validated path component.
+///
+/// A [`Component`] is guaranteed to be a single non-empty path component that
+/// can be safely used as the final argument to an `*at(2)` syscall. That is, it
+/// does not contain any `/` or nul bytes and it is neither `.` nor `..`.
+///
+/// This is intended for APIs where the caller is expected to provide a single
+/// filename (as opposed to a path which will be resolved inside a [`Root`]),
+/// so that passing an attacker-controlled string which contains path
+/// separators is rejected when the [`Component`] is constructed rather than
+/// being silently interpreted as a path.
+///
+/// [`Root`]: crate::Root
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Component(OsString);
+
+impl Component {
+    /// Construct a new [`Component`], verifying that `name` is a single path
+    /// component.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::InvalidArgument`] if `name` is empty, is `.` or
+    /// `..`, or contains a `/` or nul byte.
+    ///
+    /// [`ErrorKind::InvalidArgument`]: crate::error::ErrorKind::InvalidArgument
+    pub fn new<S: AsRef<OsStr>>(name: S) -> Result<Self, Error> {
+        let name = name.as_ref();
+        let description = match name.as_bytes() {
+            b"" => "component must not be empty",
+            b"." | b".." => "component must not be '.' or '..'",
+            bytes if bytes.contains(&b'/') => "component must not contain '/'",
+            bytes if bytes.contains(&b'\0') => "component must not contain nul bytes",
+            _ => return Ok(Self(name.to_os_string())),
+        };
+        Err(ErrorImpl::InvalidArgument {
+            name: "component".into(),
+            description: format!("{name:?} is not a single path component: {description}").into(),
+        })?
+    }
+
+    /// Get the underlying [`OsStr`] for this component.
+    pub fn as_os_str(&self) -> &OsStr {
+        &self.0
+    }
+
+    /// Get this component as a [`Path`].
+    pub fn as_path(&self) -> &Path {
+        Path::new(&self.0)
+    }
+
+    /// Convert this [`Component`] into the underlying [`OsString`].
+    pub fn into_os_string(self) -> OsString {
+        self.0
+    }
+}
+
+impl AsRef<OsStr> for Component {
+    fn as_ref(&self) -> &OsStr {
+        self.as_os_str()
+    }
+}
+
+impl AsRef<Path> for Component {
+    fn as_ref(&self) -> &Path {
+        self.as_path()
+    }
+}
+
+impl TryFrom<&str> for Component {
+    type Error = Error;
+
+    /// Shorthand for [`Component::new`].
+    fn try_from(name: &str) -> Result<Self, Self::Error> {
+        Self::new(name)
+    }
+}
+
+impl TryFrom<&OsStr> for Component {
+    type Error = Error;
+
+    /// Shorthand for [`Component::new`].
+    fn try_from(name: &OsStr) -> Result<Self, Self::Error> {
+        Self::new(name)
+    }
+}
+
+impl TryFrom<&Path> for Component {
+    type Error = Error;
+
+    /// Shorthand for [`Component::new`].
+    fn try_from(name: &Path) -> Result<Self, Self::Error> {
+        Self::new(name)
+    }
+}
+
+/// Maximum length of a single path component on Linux.
+// MSRV(1.63): Use libc::NAME_MAX once we can require a newer libc.
+const NAME_MAX: usize = 255;
+
+/// Lexically clean and validate a user-provided path before it is used with
+/// [`Root`] methods.
+///
+/// This is intended for programs which take paths as input (such as
+/// command-line arguments) and want consistent validation and a cleaned-up
+/// version of the path to display to users. The following transformations
+/// are applied:
+///
+///  * Repeated `/` separators are collapsed into a single `/`.
+///  * `.` components are removed.
+///  * `..` components are left untouched, since they cannot be resolved
+///    lexically without knowing whether the preceding components are
+///    symlinks. [`Root`] resolution handles them safely.
+///  * A trailing `/` (or trailing `/.`) is preserved as a single trailing `/`,
+///    since it requires the final component to be a directory.
+///
+/// If the path is cleaned to nothing, `.` (or `/` for absolute paths) is
+/// returned.
+///
+/// **NOTE**: This is a purely lexical operation which does not touch the
+/// filesystem. It does not resolve symlinks and it does not stop the path
+/// from referencing `..` or absolute symlinks, so it is *not* a security
+/// boundary by itself -- the safety guarantees come from resolving the path
+/// inside a [`Root`].
+///
+/// # Errors
+///
+/// Returns [`ErrorKind::InvalidArgument`] if `path` is empty, contains a nul
+/// byte, is longer than `PATH_MAX`, or has a component longer than
+/// `NAME_MAX`.
+///
+/// [`Root`]: crate::Root
+/// [`ErrorKind::InvalidArgument`]: crate::error::ErrorKind::InvalidArgument
+pub fn clean_input_path<S: AsRef<OsStr>>(path: S) -> Result<PathBuf, Error> {
+    let path = path.as_ref();
+    let bytes = path.as_bytes();
+
+    let invalid = |description: String| -> Result<PathBuf, Error> {
+        Err(ErrorImpl::InvalidArgument {
+            name: "path".into(),
+            description: format!("{path:?} is not a valid path: {description}").into(),
+        })?
+    };
+
+    if bytes.is_empty() {
+        return invalid("path must not be empty".into());
+    }
+    if bytes.contains(&b'\0') {
+        return invalid("path must not contain nul bytes".into());
+    }
+    // PATH_MAX includes the trailing nul byte.
+    if bytes.len() >= libc::PATH_MAX as usize {
+        return invalid(format!(
+            "path is longer than PATH_MAX ({} bytes)",
+            libc::PATH_MAX
+        ));
+    }
+
+    let mut components = Vec::new();
+    for part in bytes.split(|&b| b == b'/') {
+        match part {
+            b"" | b"." => continue,
+            part if part.len() > NAME_MAX => {
+                return invalid(format!(
+                    "component {:?} is longer than NAME_MAX ({NAME_MAX} bytes)",
+                    OsStr::from_bytes(part),
+                ));
+            }
+            part => components.push(part),
+        }
+    }
+
+    let is_absolute = bytes.starts_with(b"/");
+    let is_directory = bytes.ends_with(b"/") || bytes.ends_with(b"/.");
+
+    let mut cleaned = Vec::with_capacity(bytes.len());
+    if is_absolute {
+        cleaned.push(b'/');
+    }
+    for (idx, part) in components.iter().enumerate() {
+        if idx > 0 {
+            cleaned.push(b'/');
+        }
+        cleaned.extend_from_slice(part);
+    }
+    if cleaned.is_empty() {
+        cleaned.push(b'.');
+    } else if is_directory && !components.is_empty() {
+        cleaned.push(b'/');
+    }
+
+    Ok(OsString::from_vec(cleaned).into())
+}
+
+/// Check whether `name` is a single path component that is also a valid
+/// filename on Windows.
+///
+/// This is intended for programs (such as archive extractors) which produce
+/// trees that will later be used on Windows, and want to reject names which
+/// cannot be represented there. All of these names are perfectly valid on
+/// Linux, and [`Root`] operations accept them regardless -- this is purely an
+/// input validation helper, and it has nothing to do with the safety of path
+/// resolution.
+///
+/// `name` is considered portable if all of the following are true:
+///
+///  * It is a valid [`Component`] (non-empty, not `.` or `..`, and does not
+///    contain `/` or nul bytes).
+///  * It is valid UTF-8 and is at most 255 UTF-16 code units long.
+///  * It does not contain ASCII control characters (`0x01`-`0x1F`) or any of
+///    `<`, `>`, `:`, `"`, `\`, `|`, `?` or `*`.
+///  * It does not end with `.` or a space.
+///  * It is not a reserved device name (`CON`, `PRN`, `AUX`, `NUL`,
+///    `COM0`-`COM9`, `LPT0`-`LPT9`, or the `COM` and `LPT` names with a
+///    superscript `¹`, `²` or `³`), compared case-insensitively and ignoring
+///    any extension and trailing spaces before it (so `con.txt` and
+///    `Aux .tar.gz` are also reserved).
+///
+/// [`Root`]: crate::Root
+pub fn is_portable_component<S: AsRef<OsStr>>(name: S) -> bool {
+    const RESERVED_CHARS: &[char] = &['<', '>', ':', '"', '\\', '|', '?', '*'];
+    const RESERVED_NAMES: &[&str] = &["CON", "PRN", "AUX", "NUL"];
+    const RESERVED_PREFIXES: &[&str] = &["COM", "LPT"];
+    const RESERVED_SUFFIXES: &[char] = &[
+        '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', '\u{b9}', '\u{b2}', '\u{b3}',
+    ];
+
+    let name = name.as_ref();
+    if Component::new(name).is_err() {
+        return false;
+    }
+    let name = match name.to_str() {
+        Some(name) => name,
+        None => return false,
+    };
+
+    if name.encode_utf16().count() > 255
+        || name
+            .chars()
+            .any(|c| c.is_ascii_control() || RESERVED_CHARS.contains(&c))
+        || name.ends_with(['.', ' '])
+    {
+        return false;
+    }
+
+    let stem = name
+        .split('.')
+        .next()
+        .unwrap_or_default()
+        .trim_end_matches(' ')
+        .to_ascii_uppercase();
+    let is_reserved = RESERVED_NAMES.contains(&stem.as_str())
+        || RESERVED_PREFIXES.iter().any(|prefix| {
+            stem.strip_prefix(prefix).map_or(false, |suffix| {
+                let mut chars = suffix.chars();
+                matches!(
+                    (chars.next(), chars.next()),
+                    (Some(c), None) if RESERVED_SUFFIXES.contains(&c)
+                )
+            })
+        });
+    !is_reserved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{clean_input_path, is_portable_component, Component, NAME_MAX};
+    use crate::error::ErrorKind;
+
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt, path::Path};
+
+    use pretty_assertions::assert_eq;
+
+    macro_rules! component_tests {
+        ($($test_name:ident ( $name:expr ) => $expected:expr );+ $(;)?) => {
+            $(
+                paste::paste! {
+                    #[test]
+                    fn [<component_ $test_name>]() {
+                        let name = OsStr::from_bytes($name);
+                        let expected: Result<&[u8], ErrorKind> = $expected;
+                        assert_eq!(
+                            Component::new(name)
+                                .as_ref()
+                                .map(|c| c.as_os_str().as_bytes())
+                                .map_err(|err| err.kind()),
+                            expected,
+                            "unexpected result for Component::new({name:?})",
+                        );
+                    }
+                }
+            )*
+        }
+    }
+
+    macro_rules! clean_input_path_tests {
+        ($($test_name:ident ( $path:expr ) => $expected:expr );+ $(;)?) => {
+            $(
+                paste::paste! {
+                    #[test]
+                    fn [<clean_input_path_ $test_name>]() {
+                        let path = $path;
+                        let path = OsStr::from_bytes(path.as_ref());
+                        let expected: Result<&[u8], ErrorKind> = $expected;
+                        assert_eq!(
+                            clean_input_path(path)
+                                .as_ref()
+                                .map(|p| p.as_os_str().as_bytes())
+                                .map_err(|err| err.kind()),
+                            expected,
+                            "unexpected result for clean_input_path({path:?})",
+                        );
+                    }
+                }
+            )*
+        }
+    }
+
+    component_tests! {
+        plain(b"foo") => Ok(b"foo");
+        dotfile(b".foo") => Ok(b".foo");
+        dotdotfile(b"..foo") => Ok(b"..foo");
+        trailing_dots(b"foo..") => Ok(b"foo..");
+        triple_dot(b"...") => Ok(b"...");
+        non_utf8(b"\xff\xfe") => Ok(b"\xff\xfe");
+        empty(b"") => Err(ErrorKind::InvalidArgument);
+        dot(b".") => Err(ErrorKind::InvalidArgument);
+        dotdot(b"..") => Err(ErrorKind::InvalidArgument);
+        root(b"/") => Err(ErrorKind::InvalidArgument);
+        absolute(b"/foo") => Err(ErrorKind::InvalidArgument);
+        trailing_slash(b"foo/") => Err(ErrorKind::InvalidArgument);
+        multiple(b"foo/bar") => Err(ErrorKind::InvalidArgument);
+        dotdot_escape(b"../foo") => Err(ErrorKind::InvalidArgument);
+        nul(b"foo\0bar") => Err(ErrorKind::InvalidArgument);
+        trailing_nul(b"foo\0") => Err(ErrorKind::InvalidArgument);
+    }
+
+    clean_input_path_tests! {
+        plain(b"foo/bar") => Ok(b"foo/bar");
+        absolute(b"/foo/bar") => Ok(b"/foo/bar");
+        dot(b".") => Ok(b".");
+        dot_slash(b"./") => Ok(b".");
+        root(b"/") => Ok(b"/");
+        root_dot(b"//./.") => Ok(b"/");
+        double_slash(b"foo//bar///baz") => Ok(b"foo/bar/baz");
+        leading_double_slash(b"//foo") => Ok(b"/foo");
+        dot_components(b"./foo/./bar/.") => Ok(b"foo/bar/");
+        trailing_slash(b"foo/bar//") => Ok(b"foo/bar/");
+        dotdot_kept(b"foo/../bar/..") => Ok(b"foo/../bar/..");
+        dotdot_root(b"/../foo") => Ok(b"/../foo");
+        non_utf8(b"\xff/./\xfe") => Ok(b"\xff/\xfe");
+        name_max([b'a'; NAME_MAX]) => Ok(&[b'a'; NAME_MAX]);
+        empty(b"") => Err(ErrorKind::InvalidArgument);
+        nul(b"foo/\0bar") => Err(ErrorKind::InvalidArgument);
+        too_long_component([b'a'; NAME_MAX + 1]) => Err(ErrorKind::InvalidArgument);
+        too_long_path(b"a/".repeat(libc::PATH_MAX as usize / 2)) => Err(ErrorKind::InvalidArgument);
+    }
+
+    macro_rules! is_portable_component_tests {
+        ($($test_name:ident ( $name:expr ) => $expected:expr );+ $(;)?) => {
+            $(
+                paste::paste! {
+                    #[test]
+                    fn [<is_portable_component_ $test_name>]() {
+                        let name = $name;
+                        let name = OsStr::from_bytes(name.as_ref());
+                        assert_eq!(
+                            is_portable_component(name),
+                            $expected,
+                            "unexpected result for is_portable_component({name:?})",
+                        );
+                    }
+                }
+            )*
+        }
+    }
+
+    is_portable_component_tests! {
+        plain(b"foo") => true;
+        extension(b"foo.tar.gz") => true;
+        dotfile(b".foo") => true;
+        unicode("f\u{f6}\u{f6}".as_bytes()) => true;
+        reserved_prefix(b"CONSOLE") => true;
+        reserved_middle(b"foo.con") => true;
+        com_two_digits(b"COM10") => true;
+        name_max([b'a'; 255]) => true;
+        empty(b"") => false;
+        dot(b".") => false;
+        dotdot(b"..") => false;
+        slash(b"foo/bar") => false;
+        nul(b"foo\0") => false;
+        non_utf8(b"\xff\xfe") => false;
+        too_long([b'a'; 256]) => false;
+        too_long_utf16("\u{1f600}".repeat(128).into_bytes()) => false;
+        backslash(b"foo\\bar") => false;
+        colon(b"foo:bar") => false;
+        star(b"foo*") => false;
+        question(b"foo?") => false;
+        pipe(b"a|b") => false;
+        quote(b"\"foo\"") => false;
+        angle(b"<foo>") => false;
+        control(b"foo\x01") => false;
+        trailing_dot(b"foo.") => false;
+        trailing_space(b"foo ") => false;
+        con(b"CON") => false;
+        con_lower(b"con") => false;
+        con_extension(b"con.txt") => false;
+        aux_space_extension(b"Aux .tar.gz") => false;
+        nul_name(b"NUL") => false;
+        prn(b"prn.log") => false;
+        com1(b"COM1") => false;
+        lpt9(b"lpt9.txt") => false;
+        com0(b"COM0") => false;
+        com_superscript("COM\u{b9}".as_bytes()) => false;
+        lpt_superscript("lpt\u{b3}.txt".as_bytes()) => false;
+    }
+
+    #[test]
+    fn component_as_path() {
+        let component = Component::try_from("foo").expect("foo is a valid component");
+        assert_eq!(component.as_path(), Path::new("foo"));
+        assert_eq!(component.into_os_string(), "foo");
+    }
+}
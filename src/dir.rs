@@ -0,0 +1,253 @@
+/*
+ * libpathrs: safe path resolution on Linux
+ * Copyright (C) 2019-2024 Aleksa Sarai <cyphar@cyphar.com>
+ * Copyright (C) 2019-2024 SUSE LLC
+ *
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Lesser General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or (at your
+ * option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY
+ * or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License
+ * for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+#![forbid(unsafe_code)]
+
+use crate::{
+    error::{Error, ErrorImpl},
+    flags::ResolverFlags,
+    Handle, InodeType, ReadDir, RootRef,
+};
+
+use std::{
+    fs::{Metadata, Permissions},
+    io::Error as IOError,
+    os::unix::io::{AsFd, BorrowedFd, OwnedFd},
+    path::Path,
+};
+
+/// A [`Handle`] which is known to reference a directory.
+///
+/// This only exposes operations which make sense for directories, all of
+/// which are scoped to the directory (in the same way as [`Handle::resolve`]),
+/// so code which walks directory trees cannot accidentally do file operations
+/// on a directory handle. Use [`Handle::into_dir`] to get a [`Dir`], and
+/// [`Dir::into_handle`] to get back the underlying (untyped) [`Handle`].
+///
+/// As with [`Handle`], this is only a reference to an already-resolved path --
+/// the usual safety rules about not using the underlying file descriptor
+/// directly apply.
+#[derive(Debug)]
+pub struct Dir {
+    inner: Handle,
+}
+
+impl Dir {
+    /// Get a [`RootRef`] scoped to this directory, with the [`ReopenPolicy`]
+    /// of the underlying [`Handle`].
+    ///
+    /// [`ReopenPolicy`]: crate::flags::ReopenPolicy
+    fn as_root(&self) -> RootRef<'_> {
+        RootRef::from_fd(self.inner.as_fd()).with_reopen_policy(self.inner.reopen_policy())
+    }
+
+    /// Get the metadata of the directory.
+    #[inline]
+    pub fn metadata(&self) -> Result<Metadata, Error> {
+        self.inner.metadata()
+    }
+
+    /// Iterate over the entries of the directory.
+    ///
+    /// This is equivalent to [`RootRef::read_dir`] on `.` -- the directory is
+    /// re-opened as an `O_DIRECTORY` handle which is held by the returned
+    /// [`ReadDir`]. The `.` and `..` entries are not returned.
+    pub fn read_dir(&self) -> Result<ReadDir, Error> {
+        self.as_root().read_dir(".")
+    }
+
+    /// Resolve `path` beneath the directory, treating the directory as a root.
+    ///
+    /// See [`Handle::resolve`] for more details about how `path` is scoped to
+    /// the directory.
+    #[inline]
+    pub fn resolve<P: AsRef<Path>>(&self, path: P, flags: ResolverFlags) -> Result<Handle, Error> {
+        self.inner.resolve(path, flags)
+    }
+
+    /// Resolve `path` beneath the directory (in the same way as
+    /// [`Dir::resolve`]) and convert the result into a [`Dir`].
+    ///
+    /// # Errors
+    ///
+    /// If `path` is not a directory, an `ENOTDIR` error is returned.
+    pub fn open_dir<P: AsRef<Path>>(&self, path: P, flags: ResolverFlags) -> Result<Dir, Error> {
+        self.resolve(path, flags)?.into_dir()
+    }
+
+    /// Create a new directory at `path` beneath the directory, with the mode
+    /// given by `perm` (the process umask still applies).
+    ///
+    /// This is equivalent to [`RootRef::create`] with an
+    /// [`InodeType::Directory`], with `path` scoped to the directory (in the
+    /// same way as [`Dir::resolve`]). The parent of `path` must already exist.
+    pub fn mkdir<P: AsRef<Path>>(&self, path: P, perm: &Permissions) -> Result<(), Error> {
+        self.as_root()
+            .create(path, &InodeType::Directory(perm.clone()))
+    }
+
+    /// Borrow the underlying [`Handle`].
+    #[inline]
+    pub fn as_handle(&self) -> &Handle {
+        &self.inner
+    }
+
+    /// Convert the [`Dir`] back into the underlying [`Handle`].
+    #[inline]
+    pub fn into_handle(self) -> Handle {
+        self.inner
+    }
+}
+
+impl Handle {
+    /// Convert the handle into a [`Dir`], after checking that it references a
+    /// directory.
+    ///
+    /// The underlying file descriptor is kept as-is (so a [`Dir`] made from an
+    /// `O_PATH` handle is also an `O_PATH` handle), and operations that need a
+    /// non-`O_PATH` file descriptor (such as [`Dir::read_dir`]) re-open the
+    /// directory with `O_DIRECTORY` as necessary.
+    ///
+    /// # Errors
+    ///
+    /// If the handle does not reference a directory, an `ENOTDIR` error is
+    /// returned.
+    #[doc(alias = "open_dir")]
+    pub fn into_dir(self) -> Result<Dir, Error> {
+        if !self.metadata()?.is_dir() {
+            Err(ErrorImpl::OsError {
+                operation: "convert handle into directory".into(),
+                source: IOError::from_raw_os_error(libc::ENOTDIR),
+            })?
+        }
+        Ok(Dir { inner: self })
+    }
+}
+
+impl From<Dir> for Handle {
+    /// Shorthand for [`Dir::into_handle`].
+    fn from(dir: Dir) -> Self {
+        dir.into_handle()
+    }
+}
+
+impl From<Dir> for OwnedFd {
+    /// Unwrap a [`Dir`] to reveal the underlying [`OwnedFd`].
+    ///
+    /// **Note**: This method is primarily intended to allow for file descriptor
+    /// passing or otherwise transmitting file descriptor information. If you
+    /// want to get a [`File`] handle for general use, please use
+    /// [`Handle::reopen`] instead.
+    ///
+    /// [`File`]: std::fs::File
+    #[inline]
+    fn from(dir: Dir) -> Self {
+        dir.inner.into()
+    }
+}
+
+impl AsFd for Dir {
+    /// Access the underlying file descriptor for a [`Dir`].
+    ///
+    /// **Note**: This method is primarily intended to allow for tests and other
+    /// code to check the status of the underlying file descriptor. It is not
+    /// safe to use this [`BorrowedFd`] directly to do filesystem operations.
+    /// Please use the provided [`Dir`] methods.
+    #[inline]
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.inner.as_fd()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{error::ErrorKind, flags::ResolverFlags, tests::common as tests_common, Root};
+
+    use std::{
+        fs::Permissions,
+        os::unix::fs::{MetadataExt, PermissionsExt},
+    };
+
+    use anyhow::Error;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn into_dir() -> Result<(), Error> {
+        let root_dir = tests_common::create_basic_tree()?;
+        let root = Root::open(&root_dir)?;
+
+        let dir = root.resolve("b")?.into_dir()?;
+        assert!(dir.metadata()?.is_dir(), "dir should be a directory");
+
+        let mut names = dir
+            .read_dir()?
+            .map(|entry| entry.map(|entry| entry.file_name()))
+            .collect::<Result<Vec<_>, _>>()?;
+        names.sort();
+        assert_eq!(
+            names,
+            ["c", "fifo", "sock"],
+            "read_dir should list the directory"
+        );
+
+        // Sub-operations are scoped to the directory.
+        let sub = dir.open_dir("/c", ResolverFlags::empty())?;
+        assert_eq!(
+            sub.metadata()?.ino(),
+            root.metadata("b/c")?.ino(),
+            "open_dir should resolve relative to the directory"
+        );
+        assert_eq!(
+            dir.resolve("../c/file", ResolverFlags::empty())?
+                .metadata()?
+                .ino(),
+            root.metadata("b/c/file")?.ino(),
+            "resolve should not be able to escape the directory"
+        );
+        sub.mkdir("newdir", &Permissions::from_mode(0o711))?;
+        assert!(
+            root.metadata("b/c/newdir")?.is_dir(),
+            "mkdir should create a directory inside the directory"
+        );
+        assert_eq!(
+            sub.mkdir("newdir", &Permissions::from_mode(0o711))
+                .expect_err("mkdir of existing directory should fail")
+                .kind(),
+            ErrorKind::OsError(Some(libc::EEXIST)),
+            "mkdir of existing directory should fail with EEXIST"
+        );
+
+        assert_eq!(
+            root.resolve("b/c/file")?
+                .into_dir()
+                .expect_err("into_dir on a file should fail")
+                .kind(),
+            ErrorKind::OsError(Some(libc::ENOTDIR)),
+            "into_dir on a file should fail with ENOTDIR"
+        );
+        assert_eq!(
+            dir.open_dir("c/file", ResolverFlags::empty())
+                .expect_err("open_dir on a file should fail")
+                .kind(),
+            ErrorKind::OsError(Some(libc::ENOTDIR)),
+            "open_dir on a file should fail with ENOTDIR"
+        );
+        Ok(())
+    }
+}
@@ -29,7 +29,10 @@
 
 use crate::{resolvers::opath::SymlinkStackError, syscalls::Error as SyscallError};
 
-use std::{borrow::Cow, io::Error as IOError};
+use std::{
+    borrow::Cow,
+    io::{Error as IOError, ErrorKind as IOErrorKind},
+};
 
 // TODO: Add a backtrace to Error. We would just need to add an automatic
 //       Backtrace::capture() in From. But it's not clear whether we want to
@@ -53,6 +56,13 @@ impl<E: Into<ErrorImpl>> From<E> for Error {
 }
 
 impl Error {
+    /// Get the [`ErrorKind`] of the underlying cause of this error.
+    ///
+    /// Context added to the error while it was being returned does not affect
+    /// the kind of the error, so this can be used to programmatically
+    /// distinguish (for instance) a [`ErrorKind::SafetyViolation`] from an
+    /// `ENOENT` returned by the kernel (which is represented as
+    /// [`ErrorKind::OsError`] with the raw errno value).
     pub fn kind(&self) -> ErrorKind {
         self.0.kind()
     }
@@ -60,6 +70,112 @@ impl Error {
     pub(crate) fn is_safety_violation(&self) -> bool {
         self.0.is_safety_violation()
     }
+
+    /// Get structured information about each level of the error chain.
+    ///
+    /// The returned list starts with the outermost context and ends with the
+    /// underlying error that caused the failure. This is intended for
+    /// structured logging, where you may want to index errors by errno or
+    /// operation rather than parsing the [`Display`] output of the error.
+    ///
+    /// [`Display`]: std::fmt::Display
+    pub fn chain_details(&self) -> Vec<ErrorDetail> {
+        let mut details = Vec::new();
+        let mut err = &*self.0;
+        loop {
+            let (operation, errno) = match err {
+                ErrorImpl::Wrapped { context, source } => {
+                    details.push(ErrorDetail {
+                        context: context.to_string(),
+                        kind: err.kind(),
+                        operation: None,
+                        errno: None,
+                    });
+                    err = source;
+                    continue;
+                }
+                ErrorImpl::OsError { operation, source } => {
+                    (Some(operation.to_string()), source.raw_os_error())
+                }
+                ErrorImpl::RawOsError { operation, source } => (
+                    Some(operation.to_string()),
+                    source.root_cause().raw_os_error(),
+                ),
+                _ => (None, None),
+            };
+            // Include the underlying causes (if any) in the context of the
+            // final entry, since they are not libpathrs errors.
+            let mut context = err.to_string();
+            let mut source = std::error::Error::source(err);
+            while let Some(cause) = source {
+                context = format!("{context}: {cause}");
+                source = cause.source();
+            }
+            details.push(ErrorDetail {
+                context,
+                kind: err.kind(),
+                operation,
+                errno,
+            });
+            break;
+        }
+        details
+    }
+}
+
+/// Structured information about a single level of an [`Error`] chain.
+///
+/// See [`Error::chain_details`] for more details.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ErrorDetail {
+    context: String,
+    kind: ErrorKind,
+    operation: Option<String>,
+    errno: Option<i32>,
+}
+
+impl ErrorDetail {
+    /// The human-readable description of this level of the error chain.
+    #[inline]
+    pub fn context(&self) -> &str {
+        &self.context
+    }
+
+    /// The [`ErrorKind`] of this level of the error chain.
+    #[inline]
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// The name of the operation that failed, if this level of the error chain
+    /// is an error returned by the operating system.
+    #[inline]
+    pub fn operation(&self) -> Option<&str> {
+        self.operation.as_deref()
+    }
+
+    /// The errno returned by the operating system, if this level of the error
+    /// chain is an error returned by the operating system.
+    #[inline]
+    pub fn errno(&self) -> Option<i32> {
+        self.errno
+    }
+}
+
+impl From<Error> for IOError {
+    /// Convert an [`Error`] into an [`std::io::Error`].
+    ///
+    /// If the underlying cause of the error was an error returned by the
+    /// operating system, the returned [`std::io::Error`] will have the same
+    /// errno value (though the context of the [`Error`] will be lost).
+    /// Otherwise, an [`std::io::ErrorKind::Other`] error wrapping the [`Error`]
+    /// is returned.
+    fn from(err: Error) -> Self {
+        match err.kind() {
+            ErrorKind::OsError(Some(errno)) => IOError::from_raw_os_error(errno),
+            _ => IOError::new(IOErrorKind::Other, err),
+        }
+    }
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -80,6 +196,15 @@ pub(crate) enum ErrorImpl {
     #[error("violation of safety requirement: {description}")]
     SafetyViolation { description: Cow<'static, str> },
 
+    #[error("lock is already held: {description}")]
+    AlreadyHeld { description: Cow<'static, str> },
+
+    #[error("operation timed out: {description}")]
+    TimedOut { description: Cow<'static, str> },
+
+    #[error("limit exceeded: {description}")]
+    LimitExceeded { description: Cow<'static, str> },
+
     #[error("broken symlink stack during iteration: {description}")]
     BadSymlinkStackError {
         description: Cow<'static, str>,
@@ -124,6 +249,21 @@ pub enum ErrorKind {
     /// be guaranteeed. This is usually the result of an attack by a malicious
     /// program.
     SafetyViolation,
+    /// The requested lock is already held by someone else (see
+    /// [`Root::create_lockfile`]).
+    ///
+    /// [`Root::create_lockfile`]: crate::Root::create_lockfile
+    AlreadyHeld,
+    /// The operation did not complete within the configured time budget (see
+    /// [`Root::set_resolve_deadline`]).
+    ///
+    /// [`Root::set_resolve_deadline`]: crate::Root::set_resolve_deadline
+    TimedOut,
+    /// The operation was aborted because it would have exceeded a configured
+    /// resource limit (see [`Root::set_read_limit`]).
+    ///
+    /// [`Root::set_read_limit`]: crate::Root::set_read_limit
+    LimitExceeded,
     /// Some internal error occurred. For more information, see the string
     /// description of the original [`Error`].
     InternalError,
@@ -141,6 +281,9 @@ impl ErrorImpl {
             Self::NotSupported { .. } => ErrorKind::NotSupported,
             Self::InvalidArgument { .. } => ErrorKind::InvalidArgument,
             Self::SafetyViolation { .. } => ErrorKind::SafetyViolation,
+            Self::TimedOut { .. } => ErrorKind::TimedOut,
+            Self::LimitExceeded { .. } => ErrorKind::LimitExceeded,
+            Self::AlreadyHeld { .. } => ErrorKind::AlreadyHeld,
             // Any syscall-related errors get mapped to an OsError, since the
             // distinction doesn't matter to users checking error values.
             Self::OsError { source, .. } => ErrorKind::OsError(source.raw_os_error()),
@@ -165,12 +308,16 @@ impl ErrorKind {
     ///
     /// Aside from fetching the errno represented by standard
     /// [`ErrorKind::OsError`] errors, pure-Rust errors are also mapped to C
-    /// errno values where appropriate.
-    pub(crate) fn errno(&self) -> Option<i32> {
+    /// errno values where appropriate (this is the errno returned by the C API
+    /// for errors of this kind).
+    pub fn errno(&self) -> Option<i32> {
         match self {
             ErrorKind::NotImplemented => Some(libc::ENOSYS),
             ErrorKind::InvalidArgument => Some(libc::EINVAL),
             ErrorKind::SafetyViolation => Some(libc::EXDEV),
+            ErrorKind::TimedOut => Some(libc::ETIMEDOUT),
+            ErrorKind::AlreadyHeld => Some(libc::EEXIST),
+            ErrorKind::LimitExceeded => Some(libc::EFBIG),
             ErrorKind::OsError(errno) => *errno,
             _ => None,
         }
@@ -248,10 +395,127 @@ mod tests {
             Some(libc::EXDEV),
             "ErrorKind::SafetyViolation is equivalent to EXDEV"
         );
+        assert_eq!(
+            ErrorKind::TimedOut.errno(),
+            Some(libc::ETIMEDOUT),
+            "ErrorKind::TimedOut is equivalent to ETIMEDOUT"
+        );
+        assert_eq!(
+            ErrorKind::AlreadyHeld.errno(),
+            Some(libc::EEXIST),
+            "ErrorKind::AlreadyHeld is equivalent to EEXIST"
+        );
+        assert_eq!(
+            ErrorKind::LimitExceeded.errno(),
+            Some(libc::EFBIG),
+            "ErrorKind::LimitExceeded is equivalent to EFBIG"
+        );
         assert_eq!(
             ErrorKind::OsError(Some(libc::ENOANO)).errno(),
             Some(libc::ENOANO),
             "ErrorKind::OsError(...)::errno() returns the inner errno"
         );
     }
+
+    #[test]
+    fn error_kind_wrapped() {
+        let err: Error = ErrorImpl::OsError {
+            operation: "open foo".into(),
+            source: IOError::from_raw_os_error(libc::ELOOP),
+        }
+        .wrap("some context")
+        .into();
+        assert_eq!(
+            err.kind(),
+            ErrorKind::OsError(Some(libc::ELOOP)),
+            "wrapped OS error should have the kind of the underlying errno"
+        );
+        assert_eq!(err.kind().errno(), Some(libc::ELOOP));
+
+        let err: Error = ErrorImpl::SafetyViolation {
+            description: "bad".into(),
+        }
+        .wrap("some context")
+        .into();
+        assert_eq!(
+            err.kind(),
+            ErrorKind::SafetyViolation,
+            "wrapped safety violation should still be a safety violation"
+        );
+        assert_eq!(err.kind().errno(), Some(libc::EXDEV));
+    }
+
+    #[test]
+    fn error_into_io_error() {
+        let err: Error = ErrorImpl::OsError {
+            operation: "open foo".into(),
+            source: IOError::from_raw_os_error(libc::ENOENT),
+        }
+        .wrap("some context")
+        .into();
+        let io_err = IOError::from(err);
+        assert_eq!(
+            io_err.raw_os_error(),
+            Some(libc::ENOENT),
+            "OS error should be converted to an io::Error with the same errno"
+        );
+
+        let err: Error = ErrorImpl::SafetyViolation {
+            description: "bad".into(),
+        }
+        .wrap("some context")
+        .into();
+        let io_err = IOError::from(err);
+        assert_eq!(io_err.kind(), IOErrorKind::Other);
+        assert_eq!(io_err.raw_os_error(), None);
+        assert_eq!(
+            io_err.to_string(),
+            "some context",
+            "non-OS error should be wrapped in the io::Error"
+        );
+    }
+
+    #[test]
+    fn error_chain_details() {
+        let err: Error = ErrorImpl::OsError {
+            operation: "open foo".into(),
+            source: IOError::from_raw_os_error(libc::ENOENT),
+        }
+        .wrap("inner context")
+        .wrap("outer context")
+        .into();
+
+        let details = err.chain_details();
+        assert_eq!(details.len(), 3, "chain details: {details:?}");
+        assert_eq!(details[0].context(), "outer context");
+        assert_eq!(details[1].context(), "inner context");
+        for detail in &details[..2] {
+            assert_eq!(detail.operation(), None, "wrapped errors have no operation");
+            assert_eq!(detail.errno(), None, "wrapped errors have no errno");
+            assert_eq!(detail.kind(), ErrorKind::OsError(Some(libc::ENOENT)));
+        }
+        assert!(
+            details[2].context().starts_with("open foo failed: "),
+            "leaf context should include the underlying cause: {:?}",
+            details[2].context()
+        );
+        assert_eq!(details[2].operation(), Some("open foo"));
+        assert_eq!(details[2].errno(), Some(libc::ENOENT));
+
+        let err: Error = ErrorImpl::InvalidArgument {
+            name: "flags".into(),
+            description: "bad flags".into(),
+        }
+        .into();
+        assert_eq!(
+            err.chain_details(),
+            vec![ErrorDetail {
+                context: "invalid flags argument: bad flags".into(),
+                kind: ErrorKind::InvalidArgument,
+                operation: None,
+                errno: None,
+            }],
+            "non-OS errors have a single detail without errno"
+        );
+    }
 }
@@ -24,6 +24,7 @@
 use crate::syscalls;
 
 use bitflags::bitflags;
+use rustix::fs::StatxFlags;
 
 bitflags! {
     /// Wrapper for the underlying `libc`'s `O_*` flags.
@@ -194,6 +195,14 @@ bitflags! {
     pub struct RenameFlags: libc::c_uint {
         const RENAME_EXCHANGE = libc::RENAME_EXCHANGE;
         const RENAME_NOREPLACE = libc::RENAME_NOREPLACE;
+        /// Atomically create a whiteout (a `0:0` character device, as used by
+        /// overlayfs) at the source path while renaming it.
+        ///
+        /// Creating the whiteout requires `CAP_MKNOD` (otherwise the rename
+        /// will fail with `EPERM`), and not all filesystems support whiteouts.
+        /// The kernel does not permit combining this flag with
+        /// [`RenameFlags::RENAME_EXCHANGE`] (such renames will fail with
+        /// `EINVAL`).
         const RENAME_WHITEOUT = libc::RENAME_WHITEOUT;
 
         // Don't clobber unknown RENAME_* bits.
@@ -201,6 +210,65 @@ bitflags! {
     }
 }
 
+bitflags! {
+    /// Access modes to check for with [`Root::access`] and [`Root::access_as`].
+    ///
+    /// The flag values and their meaning are identical to the `R_OK`, `W_OK`
+    /// and `X_OK` flags described in the [`access(2)`] man page. An empty set
+    /// of flags is equivalent to `F_OK` (only check that the path can be
+    /// resolved).
+    ///
+    /// [`access(2)`]: http://man7.org/linux/man-pages/man2/access.2.html
+    /// [`Root::access`]: crate::Root::access
+    /// [`Root::access_as`]: crate::Root::access_as
+    #[derive(Default, PartialEq, Eq, Debug, Clone, Copy)]
+    pub struct AccessMode: libc::c_int {
+        const R_OK = libc::R_OK;
+        const W_OK = libc::W_OK;
+        const X_OK = libc::X_OK;
+    }
+}
+
+bitflags! {
+    /// Set of fields to request with [`Root::statx`].
+    ///
+    /// The flag values and their meaning are identical to the `STATX_*` mask
+    /// flags described in the [`statx(2)`] man page. Note that the kernel may
+    /// return more fields than were requested (and may not return requested
+    /// fields if the filesystem does not support them), so you should check
+    /// [`Statx::mask`] to see which fields are actually valid.
+    ///
+    /// [`statx(2)`]: http://man7.org/linux/man-pages/man2/statx.2.html
+    /// [`Root::statx`]: crate::Root::statx
+    /// [`Statx::mask`]: crate::Statx::mask
+    #[derive(Default, PartialEq, Eq, Debug, Clone, Copy)]
+    pub struct StatxMask: u32 {
+        const TYPE = StatxFlags::TYPE.bits();
+        const MODE = StatxFlags::MODE.bits();
+        const NLINK = StatxFlags::NLINK.bits();
+        const UID = StatxFlags::UID.bits();
+        const GID = StatxFlags::GID.bits();
+        const ATIME = StatxFlags::ATIME.bits();
+        const MTIME = StatxFlags::MTIME.bits();
+        const CTIME = StatxFlags::CTIME.bits();
+        const INO = StatxFlags::INO.bits();
+        const SIZE = StatxFlags::SIZE.bits();
+        const BLOCKS = StatxFlags::BLOCKS.bits();
+        /// All of the fields returned by `stat(2)`.
+        const BASIC_STATS = StatxFlags::BASIC_STATS.bits();
+        /// The inode birth time (not supported by all filesystems).
+        const BTIME = StatxFlags::BTIME.bits();
+        /// The mount ID of the mount containing the inode (since Linux 5.8).
+        const MNT_ID = StatxFlags::MNT_ID.bits();
+    }
+}
+
+impl From<StatxMask> for StatxFlags {
+    fn from(mask: StatxMask) -> Self {
+        Self::from_bits_retain(mask.bits())
+    }
+}
+
 impl From<RenameFlags> for rustix::fs::RenameFlags {
     fn from(flags: RenameFlags) -> Self {
         Self::from_bits_retain(flags.bits())
@@ -290,5 +358,357 @@ bitflags! {
     pub struct ResolverFlags: u64 {
         // TODO: We should probably have our own bits...
         const NO_SYMLINKS = libc::RESOLVE_NO_SYMLINKS;
+
+        /// Reject any path resolution which would need to go above the root,
+        /// rather than silently clamping it to the root.
+        ///
+        /// By default, lookups inside a [`Root`] are scoped as though the root
+        /// were `/` (this is equivalent to `RESOLVE_IN_ROOT`), so `..` at the
+        /// root stays at the root and absolute paths and absolute symlinks are
+        /// resolved relative to the root. With this flag set, path resolution
+        /// is instead scoped with `RESOLVE_BENEATH` semantics:
+        ///
+        ///  * A `..` component (including one inside a symlink target) which
+        ///    would go above the root results in an `EXDEV` error.
+        ///  * Absolute paths and absolute symlinks result in an `EXDEV` error,
+        ///    even if they would end up inside the root.
+        ///
+        /// `..` components which stay inside the root (such as `a/../b`) are
+        /// still permitted. This is mostly useful for callers which only ever
+        /// expect to resolve relative paths and would like an attempt to
+        /// escape to be a hard error.
+        ///
+        /// [`Root`]: crate::Root
+        const NO_ESCAPE_BENEATH = libc::RESOLVE_BENEATH;
+
+        /// Do not permit path resolution to cross a mount point (this is
+        /// equivalent to `RESOLVE_NO_XDEV`). Attempting to walk into a
+        /// different mount (including through a symlink or `..`) results in
+        /// an `EXDEV` error.
+        ///
+        /// Note that the kernel and emulated resolver backends detect mount
+        /// crossings differently. `openat2(2)` checks the mount each component
+        /// is on, so walking into a bind-mount of the same filesystem is
+        /// rejected. The emulated backend can only compare the `st_dev` of
+        /// each component against the `st_dev` of the root, which means that
+        /// bind-mounts within the same filesystem are not detected (and
+        /// filesystems like btrfs which use different `st_dev` values for
+        /// subvolumes are treated as different mounts).
+        const NO_CROSS_MOUNT = libc::RESOLVE_NO_XDEV;
+
+        /// Refuse to traverse any magic-links (such as `/proc/$pid/fd/$n` or
+        /// `/proc/$pid/root`) during path resolution (this is equivalent to
+        /// `RESOLVE_NO_MAGICLINKS`). This is mostly useful for hardening
+        /// lookups inside a [`Root`] which has `/proc` mounted inside it.
+        ///
+        /// The `openat2(2)` backend always blocks magic-links (returning an
+        /// `ELOOP` error), so this flag has no additional effect there.
+        ///
+        /// Without this flag, the emulated backend only blocks symlinks on
+        /// magic-link filesystems (like procfs) which have absolute targets
+        /// (returning an `ELOOP` error), while other magic-links (such as those
+        /// for pipes and sockets) are treated as regular symlinks. With this
+        /// flag, the emulated backend returns a `SafetyViolation` error for
+        /// any symlink on a magic-link filesystem whose target looks like a
+        /// magic-link. There is no way for userspace to detect magic-links
+        /// directly, so this detection is only best-effort.
+        ///
+        /// [`Root`]: crate::Root
+        const NO_MAGICLINKS = libc::RESOLVE_NO_MAGICLINKS;
+
+        /// Refuse to walk into (or return a handle to) an automount point
+        /// (such as an `autofs` mount which has not been mounted yet), so that
+        /// path resolution never triggers an automount. Attempting to resolve
+        /// through an automount point results in an `EXDEV` error.
+        ///
+        /// `openat2(2)` has no way of avoiding automounts for intermediate
+        /// components, so path resolution with this flag always uses the
+        /// emulated resolver backend (or fails with
+        /// [`ErrorKind::NotSupported`] if [`ResolverBackend::KernelOpenat2`]
+        /// was explicitly requested). The emulated backend opens each
+        /// component with `O_PATH | O_NOFOLLOW` (which does not trigger
+        /// automounts) and rejects any directory marked with
+        /// `STATX_ATTR_AUTOMOUNT` before walking into it. If the kernel cannot
+        /// report `STATX_ATTR_AUTOMOUNT` (Linux 5.8 or later is needed), an
+        /// [`ErrorKind::NotSupported`] error is returned.
+        ///
+        /// Note that this is not a `RESOLVE_*` flag, and it is never passed to
+        /// the kernel.
+        ///
+        /// [`ErrorKind::NotSupported`]: crate::error::ErrorKind::NotSupported
+        /// [`ResolverBackend::KernelOpenat2`]: crate::ResolverBackend::KernelOpenat2
+        const NO_AUTOMOUNT = 1 << 32;
+    }
+}
+
+/// How absolute symlink targets are handled during resolution inside a
+/// [`Root`].
+///
+/// [`Root`]: crate::Root
+#[derive(Default, PartialEq, Eq, Hash, Debug, Clone, Copy)]
+#[non_exhaustive]
+pub enum AbsoluteSymlinkPolicy {
+    /// Absolute symlink targets are resolved relative to the root (this is
+    /// equivalent to `RESOLVE_IN_ROOT`). For instance, a symlink to `/etc/foo`
+    /// is treated as a symlink to `<root>/etc/foo`.
+    #[default]
+    InRoot,
+    /// Walking into an absolute symlink results in an `ELOOP` error. This is
+    /// useful for verifying that a directory tree contains no absolute
+    /// symlinks.
+    ///
+    /// This is not supported by `openat2(2)`, so path resolution with this
+    /// policy always uses the emulated resolver backend (or fails with
+    /// [`ErrorKind::NotSupported`] if [`ResolverBackend::KernelOpenat2`] was
+    /// explicitly requested).
+    ///
+    /// [`ErrorKind::NotSupported`]: crate::error::ErrorKind::NotSupported
+    /// [`ResolverBackend::KernelOpenat2`]: crate::ResolverBackend::KernelOpenat2
+    Error,
+}
+
+/// How the setuid and setgid bits are handled when a [`Root`] creates a
+/// regular file or changes its mode.
+///
+/// This applies to the mode passed to [`Root::create`] (for
+/// [`InodeType::File`]), [`Root::create_file`] (and the methods built on top of
+/// it, such as [`Root::write`] and [`Root::copy`]), [`Root::create_tmpfile`],
+/// [`Root::create_lockfile`], [`Root::reflink`] and [`Root::chmod`]. Only
+/// regular files are affected, as the setuid and setgid bits do not grant
+/// privileges for other inode types (in particular, the setgid bit on
+/// directories only controls group inheritance).
+///
+/// Note that [`Root::mkdir_all`] always rejects modes containing the setuid or
+/// setgid bits (because `mkdirat(2)` silently ignores them), regardless of
+/// this policy.
+///
+/// [`Root`]: crate::Root
+/// [`Root::create`]: crate::Root::create
+/// [`InodeType::File`]: crate::InodeType::File
+/// [`Root::create_file`]: crate::Root::create_file
+/// [`Root::write`]: crate::Root::write
+/// [`Root::copy`]: crate::Root::copy
+/// [`Root::create_tmpfile`]: crate::Root::create_tmpfile
+/// [`Root::create_lockfile`]: crate::Root::create_lockfile
+/// [`Root::reflink`]: crate::Root::reflink
+/// [`Root::chmod`]: crate::Root::chmod
+/// [`Root::mkdir_all`]: crate::Root::mkdir_all
+#[derive(Default, PartialEq, Eq, Hash, Debug, Clone, Copy)]
+#[non_exhaustive]
+pub enum SetidPolicy {
+    /// The mode is passed through unchanged, faithfully creating setuid and
+    /// setgid files if requested.
+    #[default]
+    Allow,
+    /// The `S_ISUID` and `S_ISGID` bits are silently cleared from the mode.
+    Strip,
+    /// Modes containing `S_ISUID` or `S_ISGID` are rejected with an
+    /// [`ErrorKind::SafetyViolation`] error, and no file is created or
+    /// modified.
+    ///
+    /// [`ErrorKind::SafetyViolation`]: crate::error::ErrorKind::SafetyViolation
+    Error,
+}
+
+/// Policy controlling how a [`Handle`] is re-opened with [`Handle::reopen`].
+///
+/// Re-opening an `O_PATH` handle is done through `/proc/thread-self/fd/$n`
+/// magic-links (Linux has no `O_EMPTYPATH`, and `openat(2)` does not support
+/// `AT_EMPTY_PATH`, so there is no other way of upgrading an `O_PATH` file
+/// descriptor). This policy controls the additional safety checks done around
+/// that operation. The default policy matches the historical behaviour of
+/// [`Handle::reopen`].
+///
+/// The policy of a [`Handle`] is inherited from the [`Root`] that produced it
+/// (see [`Root::set_reopen_policy`]).
+///
+/// Note that handles to symlinks (such as those from
+/// [`Root::resolve_nofollow`]) can never be re-opened, regardless of the
+/// policy. The magic-link jumps to the symlink itself rather than following
+/// it, so the only possible result would be another `O_PATH` handle to the
+/// symlink (which [`Handle::reopen`] does not permit -- use
+/// [`Handle::try_clone`] instead).
+///
+/// [`Handle`]: crate::Handle
+/// [`Handle::try_clone`]: crate::Handle::try_clone
+/// [`Root::resolve_nofollow`]: crate::Root::resolve_nofollow
+/// [`Handle::reopen`]: crate::Handle::reopen
+/// [`Root`]: crate::Root
+/// [`Root::set_reopen_policy`]: crate::Root::set_reopen_policy
+#[derive(Default, PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub struct ReopenPolicy {
+    verify_inode: bool,
+}
+
+impl ReopenPolicy {
+    /// Whether the re-opened file is verified to be the same inode as the
+    /// original handle.
+    #[inline]
+    pub fn verify_inode(&self) -> bool {
+        self.verify_inode
+    }
+
+    /// Verify that the re-opened file references the same inode as the
+    /// original handle (default: `false`).
+    ///
+    /// The `/proc/thread-self/fd/$n` magic-link always references the inode
+    /// of the original file descriptor, so the inode should only differ if
+    /// `/proc` has been tampered with in a way that evades the existing procfs
+    /// hardening. Enabling this costs two extra `fstat(2)` calls per re-open,
+    /// and results in an [`ErrorKind::SafetyViolation`] error if the inodes
+    /// differ.
+    ///
+    /// [`ErrorKind::SafetyViolation`]: crate::error::ErrorKind::SafetyViolation
+    #[inline]
+    pub fn with_verify_inode(mut self, verify: bool) -> Self {
+        self.verify_inode = verify;
+        self
+    }
+}
+
+/// `serde` support for the flag types. Flags are (de)serialised as a sequence
+/// of flag names (such as `["O_RDONLY", "O_CLOEXEC"]`) rather than raw bits,
+/// to keep configuration files human-readable and independent of the
+/// numerical values of the flags. Unknown flag names are rejected when
+/// deserialising, and flag sets containing bits without a name cannot be
+/// serialised.
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use super::{OpenFlags, RenameFlags, ResolverFlags};
+
+    use std::{fmt, marker::PhantomData};
+
+    use bitflags::Flags;
+    use serde::{
+        de::{self, Deserialize, Deserializer, SeqAccess, Visitor},
+        ser::{self, Serialize, SerializeSeq, Serializer},
+    };
+
+    fn serialize_flags<F: Flags, S: Serializer>(flags: &F, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        F::Bits: fmt::Debug,
+    {
+        let mut names = flags.iter_names();
+        let mut seq = serializer.serialize_seq(None)?;
+        for (name, _) in &mut names {
+            seq.serialize_element(name)?;
+        }
+        if !names.remaining().is_empty() {
+            return Err(ser::Error::custom(format!(
+                "flag set contains unnamed bits {:#x?}",
+                names.remaining().bits()
+            )));
+        }
+        seq.end()
+    }
+
+    struct FlagsVisitor<F> {
+        kind: &'static str,
+        _flags: PhantomData<F>,
+    }
+
+    impl<'de, F: Flags> Visitor<'de> for FlagsVisitor<F> {
+        type Value = F;
+
+        fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "a sequence of {} names", self.kind)
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut flags = F::empty();
+            while let Some(name) = seq.next_element::<String>()? {
+                let flag = F::from_name(&name).ok_or_else(|| {
+                    de::Error::custom(format!("unknown {} name {name:?}", self.kind))
+                })?;
+                flags.insert(flag);
+            }
+            Ok(flags)
+        }
+    }
+
+    macro_rules! impl_serde_flags {
+        ($($flags:ident),* $(,)?) => {
+            $(
+                impl Serialize for $flags {
+                    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                        serialize_flags(self, serializer)
+                    }
+                }
+
+                impl<'de> Deserialize<'de> for $flags {
+                    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                        deserializer.deserialize_seq(FlagsVisitor {
+                            kind: stringify!($flags),
+                            _flags: PhantomData,
+                        })
+                    }
+                }
+            )*
+        };
+    }
+
+    impl_serde_flags!(OpenFlags, RenameFlags, ResolverFlags);
+
+    #[cfg(test)]
+    mod tests {
+        use crate::flags::{OpenFlags, RenameFlags, ResolverFlags};
+
+        use anyhow::Error;
+        use pretty_assertions::assert_eq;
+
+        #[test]
+        fn serde_roundtrip() -> Result<(), Error> {
+            let flags = ResolverFlags::NO_SYMLINKS | ResolverFlags::NO_CROSS_MOUNT;
+            let json = serde_json::to_string(&flags)?;
+            assert_eq!(json, r#"["NO_SYMLINKS","NO_CROSS_MOUNT"]"#);
+            assert_eq!(serde_json::from_str::<ResolverFlags>(&json)?, flags);
+
+            let flags = OpenFlags::O_PATH | OpenFlags::O_DIRECTORY | OpenFlags::O_CLOEXEC;
+            let json = serde_json::to_string(&flags)?;
+            assert_eq!(serde_json::from_str::<OpenFlags>(&json)?, flags);
+
+            let flags = RenameFlags::RENAME_EXCHANGE;
+            let json = serde_json::to_string(&flags)?;
+            assert_eq!(json, r#"["RENAME_EXCHANGE"]"#);
+            assert_eq!(serde_json::from_str::<RenameFlags>(&json)?, flags);
+            Ok(())
+        }
+
+        #[test]
+        fn serde_empty() -> Result<(), Error> {
+            assert_eq!(serde_json::to_string(&ResolverFlags::empty())?, "[]");
+            assert_eq!(serde_json::to_string(&RenameFlags::empty())?, "[]");
+            // O_RDONLY has no bits set, so it is equivalent to an empty set.
+            assert_eq!(serde_json::to_string(&OpenFlags::O_RDONLY)?, "[]");
+            assert_eq!(
+                serde_json::from_str::<ResolverFlags>("[]")?,
+                ResolverFlags::empty()
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn serde_unknown_flag() {
+            let err = serde_json::from_str::<ResolverFlags>(r#"["NO_SYMLINKS", "NO_FOO"]"#)
+                .expect_err("unknown flag names should be rejected");
+            assert!(
+                err.to_string()
+                    .contains(r#"unknown ResolverFlags name "NO_FOO""#),
+                "unexpected error {err}"
+            );
+            assert!(
+                serde_json::from_str::<ResolverFlags>(r#""NO_SYMLINKS""#).is_err(),
+                "non-sequence flags should be rejected"
+            );
+        }
+
+        #[test]
+        fn serde_unnamed_bits() {
+            let flags = ResolverFlags::from_bits_retain(1 << 63);
+            assert!(
+                serde_json::to_string(&flags).is_err(),
+                "flags with unnamed bits should not be serialised"
+            );
+        }
     }
 }
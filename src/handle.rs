@@ -20,17 +20,99 @@
 #![forbid(unsafe_code)]
 
 use crate::{
-    error::{Error, ErrorImpl},
-    flags::OpenFlags,
+    error::{Error, ErrorExt, ErrorImpl, ErrorKind},
+    flags::{OpenFlags, ReopenPolicy, ResolverFlags},
     procfs::GLOBAL_PROCFS_HANDLE,
+    syscalls,
     utils::FdExt,
+    ResolverBackend, RootRef,
 };
 
 use std::{
-    fs::File,
-    os::unix::io::{AsFd, BorrowedFd, OwnedFd},
+    ffi::{OsStr, OsString},
+    fs::{File, Metadata},
+    io::{self, Read, Seek, SeekFrom, Write},
+    os::unix::{
+        fs::{FileTypeExt, MetadataExt},
+        io::{AsFd, BorrowedFd, OwnedFd},
+    },
+    path::{Path, PathBuf},
 };
 
+use rustix::{
+    fs::{self as rustix_fs, StatxFlags, XattrFlags},
+    io::Errno,
+};
+
+/// A snapshot of the identity and change state of an inode, used to cheaply
+/// check whether a cached [`Handle`] is still up-to-date.
+///
+/// Tokens are created with [`Handle::revalidation_token`] and checked with
+/// [`Handle::revalidate`]. The following fields contribute to the token:
+///
+/// * `st_dev` and `st_ino`, which identify the inode. These are reliable on all
+///   filesystems, though inode numbers can be re-used once an inode has been
+///   freed.
+/// * `stx_btime` (the inode birth time), if the kernel and filesystem support
+///   it (Linux 4.11 or later, and most local filesystems such as ext4, xfs and
+///   btrfs). This protects against inode number re-use, but is not available
+///   on many network and pseudo-filesystems.
+/// * `st_ctime` (with nanosecond precision), which is updated by the kernel
+///   whenever the contents or metadata of the inode change. This is reliable on
+///   local filesystems, but filesystems with coarse timestamp granularity (or
+///   network filesystems with attribute caching) may miss changes which happen
+///   in quick succession. Note that `st_ctime` cannot be set by userspace, so
+///   (unlike `st_mtime`) it cannot be trivially rolled back.
+///
+/// The kernel's internal change cookie (`i_version`) is not currently exposed
+/// to userspace by `statx(2)`, so it cannot be used here.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RevalToken {
+    dev: u64,
+    ino: u64,
+    btime: Option<(i64, u32)>,
+    ctime: (i64, u32),
+}
+
+impl RevalToken {
+    fn from_fd<Fd: AsFd>(fd: Fd) -> Result<Self, Error> {
+        let fd = fd.as_fd();
+        let want_mask = StatxFlags::BASIC_STATS | StatxFlags::BTIME;
+        match syscalls::statx(fd, "", want_mask) {
+            Ok(stx) => {
+                let got_mask = StatxFlags::from_bits_retain(stx.stx_mask);
+                Ok(Self {
+                    dev: rustix_fs::makedev(stx.stx_dev_major, stx.stx_dev_minor),
+                    ino: stx.stx_ino,
+                    btime: got_mask
+                        .contains(StatxFlags::BTIME)
+                        .then_some((stx.stx_btime.tv_sec, stx.stx_btime.tv_nsec)),
+                    ctime: (stx.stx_ctime.tv_sec, stx.stx_ctime.tv_nsec),
+                })
+            }
+            // Fall back to fstat(2) on pre-4.11 (pre-statx) kernels.
+            Err(err)
+                if matches!(
+                    err.root_cause().raw_os_error(),
+                    Some(libc::ENOSYS) | Some(libc::EINVAL)
+                ) =>
+            {
+                let meta = fd.metadata().wrap("fstat handle for revalidation token")?;
+                Ok(Self {
+                    dev: meta.dev(),
+                    ino: meta.ino(),
+                    btime: None,
+                    ctime: (meta.ctime(), meta.ctime_nsec() as u32),
+                })
+            }
+            Err(err) => Err(ErrorImpl::RawOsError {
+                operation: "statx handle for revalidation token".into(),
+                source: err,
+            })?,
+        }
+    }
+}
+
 /// A handle to an existing inode within a [`Root`].
 ///
 /// This handle references an already-resolved path which can be used for the
@@ -49,13 +131,67 @@ use std::{
 #[derive(Debug)]
 pub struct Handle {
     inner: OwnedFd,
+    backend: Option<ResolverBackend>,
+    reopen_policy: ReopenPolicy,
+    /// Lazily re-opened [`File`] used for the [`Read`], [`Write`] and [`Seek`]
+    /// implementations.
+    io_file: Option<(File, OpenFlags)>,
 }
 
 impl Handle {
     /// Wrap an [`OwnedFd`] into a [`Handle`].
     #[inline]
     pub fn from_fd<Fd: Into<OwnedFd>>(fd: Fd) -> Self {
-        Self { inner: fd.into() }
+        Self {
+            inner: fd.into(),
+            backend: None,
+            reopen_policy: ReopenPolicy::default(),
+            io_file: None,
+        }
+    }
+
+    // Tag the handle with the resolver backend that produced it.
+    #[inline]
+    pub(crate) fn with_backend(mut self, backend: ResolverBackend) -> Self {
+        self.backend = Some(backend);
+        self
+    }
+
+    /// Get the [`ResolverBackend`] which was used to produce this [`Handle`].
+    ///
+    /// This is purely informational metadata intended to help with debugging,
+    /// and has no effect on how the [`Handle`] behaves. It is only set for
+    /// handles returned from path resolution within a [`Root`] (such as
+    /// [`Root::resolve`]) -- handles constructed with [`Handle::from_fd`] (or
+    /// created by other operations) will return `None`.
+    ///
+    /// [`Root`]: crate::Root
+    /// [`Root::resolve`]: crate::Root::resolve
+    #[inline]
+    pub fn backend(&self) -> Option<ResolverBackend> {
+        self.backend
+    }
+
+    /// Get the [`ReopenPolicy`] used by [`Handle::reopen`] for this
+    /// [`Handle`].
+    ///
+    /// Handles returned from path resolution within a [`Root`] inherit the
+    /// policy of the [`Root`] (see [`Root::set_reopen_policy`]), otherwise the
+    /// default policy is used.
+    ///
+    /// [`Root`]: crate::Root
+    /// [`Root::set_reopen_policy`]: crate::Root::set_reopen_policy
+    #[inline]
+    pub fn reopen_policy(&self) -> ReopenPolicy {
+        self.reopen_policy
+    }
+
+    /// Set the [`ReopenPolicy`] used by [`Handle::reopen`] for this
+    /// [`Handle`].
+    #[inline]
+    pub fn with_reopen_policy(mut self, policy: ReopenPolicy) -> Self {
+        self.reopen_policy = policy;
+        self
     }
 
     /// Borrow this [`Handle`] as a [`HandleRef`].
@@ -67,16 +203,27 @@ impl Handle {
     pub fn as_ref(&self) -> HandleRef<'_> {
         HandleRef {
             inner: self.as_fd(),
+            reopen_policy: self.reopen_policy,
         }
     }
 
     /// Create a copy of an existing [`Handle`].
     ///
     /// The new handle is completely independent from the original, but
-    /// references the same underlying file.
+    /// references the same underlying file. The underlying file descriptor is
+    /// duplicated (so a copy of an `O_PATH` handle is also an `O_PATH` handle),
+    /// and the [`ResolverBackend`] and [`ReopenPolicy`] of the handle are
+    /// preserved. Dropping either handle does not affect the other.
+    ///
+    /// This is useful when a resolved handle needs to be given to multiple
+    /// owners.
     #[inline]
     pub fn try_clone(&self) -> Result<Self, Error> {
-        self.as_ref().try_clone()
+        self.as_ref().try_clone().map(|handle| Self {
+            backend: self.backend,
+            reopen_policy: self.reopen_policy,
+            ..handle
+        })
     }
 
     /// "Upgrade" the handle to a usable [`File`] handle.
@@ -92,12 +239,306 @@ impl Handle {
     /// 2. `ioctl(fd, TIOCSCTTY, 0)` will set the fd as the controlling terminal
     ///    (if you don't have one already, and the fd references a TTY).
     ///
+    /// The safety checks done while re-opening the handle are controlled by
+    /// the [`ReopenPolicy`] of the handle (see [`Handle::reopen_policy`]).
+    ///
+    /// `O_CREAT`, `O_EXCL`, `O_TMPFILE` and `O_PATH` are rejected with
+    /// [`ErrorKind::InvalidArgument`] (see [`HandleRef::reopen`] for more
+    /// details).
+    ///
     /// [`Root::create`]: crate::Root::create
+    /// [`ErrorKind::InvalidArgument`]: crate::error::ErrorKind::InvalidArgument
     #[doc(alias = "pathrs_reopen")]
     #[inline]
     pub fn reopen<F: Into<OpenFlags>>(&self, flags: F) -> Result<File, Error> {
         self.as_ref().reopen(flags)
     }
+
+    /// Get a [`RevalToken`] capturing the current identity and change state of
+    /// the inode referenced by this handle.
+    ///
+    /// See [`HandleRef::revalidation_token`] for more details.
+    #[inline]
+    pub fn revalidation_token(&self) -> Result<RevalToken, Error> {
+        self.as_ref().revalidation_token()
+    }
+
+    /// Check whether the inode referenced by this handle is unchanged since
+    /// `token` was created.
+    ///
+    /// See [`HandleRef::revalidate`] for more details.
+    #[inline]
+    pub fn revalidate(&self, token: &RevalToken) -> Result<bool, Error> {
+        self.as_ref().revalidate(token)
+    }
+
+    /// Check whether this handle and `other` reference the same inode.
+    ///
+    /// See [`HandleRef::same_inode_as`] for more details.
+    #[inline]
+    pub fn same_inode_as(&self, other: &Handle) -> Result<bool, Error> {
+        self.as_ref().same_inode_as(&other.as_ref())
+    }
+
+    /// Get the metadata of the inode referenced by this handle.
+    ///
+    /// See [`HandleRef::metadata`] for more details.
+    #[inline]
+    pub fn metadata(&self) -> Result<Metadata, Error> {
+        self.as_ref().metadata()
+    }
+
+    /// Check whether the underlying file descriptor is an `O_PATH` file
+    /// descriptor.
+    ///
+    /// See [`HandleRef::is_o_path`] for more details.
+    #[inline]
+    pub fn is_o_path(&self) -> Result<bool, Error> {
+        self.as_ref().is_o_path()
+    }
+
+    /// Synchronise the data and metadata of the inode referenced by this
+    /// handle to disk.
+    ///
+    /// See [`HandleRef::sync_all`] for more details.
+    #[inline]
+    pub fn sync_all(&self) -> Result<(), Error> {
+        self.as_ref().sync_all()
+    }
+
+    /// Synchronise the data (but not necessarily the metadata) of the inode
+    /// referenced by this handle to disk.
+    ///
+    /// See [`HandleRef::sync_data`] for more details.
+    #[inline]
+    pub fn sync_data(&self) -> Result<(), Error> {
+        self.as_ref().sync_data()
+    }
+
+    /// Get the value of the extended attribute `name` of the inode referenced
+    /// by this handle.
+    ///
+    /// See [`HandleRef::get_xattr`] for more details.
+    #[inline]
+    pub fn get_xattr<N: AsRef<OsStr>>(&self, name: N) -> Result<Option<Vec<u8>>, Error> {
+        self.as_ref().get_xattr(name)
+    }
+
+    /// Set the value of the extended attribute `name` of the inode referenced
+    /// by this handle.
+    ///
+    /// See [`HandleRef::set_xattr`] for more details.
+    #[inline]
+    pub fn set_xattr<N: AsRef<OsStr>>(&self, name: N, value: &[u8]) -> Result<(), Error> {
+        self.as_ref().set_xattr(name, value)
+    }
+
+    /// Remove the extended attribute `name` of the inode referenced by this
+    /// handle.
+    ///
+    /// See [`HandleRef::remove_xattr`] for more details.
+    #[inline]
+    pub fn remove_xattr<N: AsRef<OsStr>>(&self, name: N) -> Result<(), Error> {
+        self.as_ref().remove_xattr(name)
+    }
+
+    /// List the names of the extended attributes of the inode referenced by
+    /// this handle.
+    ///
+    /// See [`HandleRef::list_xattr`] for more details.
+    #[inline]
+    pub fn list_xattr(&self) -> Result<Vec<OsString>, Error> {
+        self.as_ref().list_xattr()
+    }
+
+    /// Get the current path of the file referenced by this handle, for
+    /// diagnostic purposes only.
+    ///
+    /// See [`HandleRef::as_frozen_path`] for more details.
+    #[inline]
+    pub fn as_frozen_path(&self) -> Result<PathBuf, Error> {
+        self.as_ref().as_frozen_path()
+    }
+
+    /// Resolve `path` beneath the directory referenced by this handle, treating
+    /// the handle as a root.
+    ///
+    /// See [`HandleRef::resolve`] for more details.
+    #[inline]
+    pub fn resolve<P: AsRef<Path>>(&self, path: P, flags: ResolverFlags) -> Result<Handle, Error> {
+        self.as_ref().resolve(path, flags)
+    }
+
+    /// Re-open the handle with the provided `flags` and convert it into a
+    /// [`File`], after checking that the handle references a file that makes
+    /// sense to do I/O on.
+    ///
+    /// [`Handle::reopen`] will happily re-open any kind of inode, which can
+    /// lead to confusing errors much later (such as a `read` failing with
+    /// `EISDIR`, or blocking forever on a FIFO). This method checks the type of
+    /// the inode first, and returns an [`ErrorKind::InvalidArgument`] error
+    /// describing the problem if:
+    ///
+    ///  * The handle references a directory and `flags` does not contain
+    ///    [`OpenFlags::O_DIRECTORY`].
+    ///  * The handle references something other than a regular file, directory
+    ///    or symlink (such as a FIFO, socket or device).
+    ///
+    /// Otherwise, this is identical to [`Handle::reopen`]. If you know what you
+    /// are doing and need to re-open other kinds of inodes, use
+    /// [`Handle::reopen`] directly.
+    ///
+    /// [`ErrorKind::InvalidArgument`]: crate::error::ErrorKind::InvalidArgument
+    pub fn into_file<F: Into<OpenFlags>>(self, flags: F) -> Result<File, Error> {
+        let flags = flags.into();
+        let file_type = self.metadata()?.file_type();
+
+        if file_type.is_dir() {
+            if !flags.contains(OpenFlags::O_DIRECTORY) {
+                Err(ErrorImpl::InvalidArgument {
+                    name: "flags".into(),
+                    description: "handle references a directory but O_DIRECTORY was not requested"
+                        .into(),
+                })?
+            }
+        } else if !file_type.is_file() && !file_type.is_symlink() {
+            let kind = if file_type.is_fifo() {
+                "fifo"
+            } else if file_type.is_socket() {
+                "socket"
+            } else if file_type.is_char_device() {
+                "character device"
+            } else if file_type.is_block_device() {
+                "block device"
+            } else {
+                "file of unknown type"
+            };
+            Err(ErrorImpl::InvalidArgument {
+                name: "handle".into(),
+                description: format!("handle references a {kind} rather than a regular file")
+                    .into(),
+            })?
+        }
+
+        self.reopen(flags)
+    }
+
+    /// Re-open the handle with the provided `flags` and convert it into a
+    /// [`tokio::fs::File`].
+    ///
+    /// This is intended for async programs which resolve paths on a blocking
+    /// thread (path resolution is always blocking) and then want to do I/O on
+    /// the resulting file with [`tokio`]. Aside from consuming the [`Handle`],
+    /// this is identical to [`Handle::reopen`] followed by
+    /// [`tokio::fs::File::from_std`].
+    ///
+    /// [`tokio::fs::File`] does all I/O using blocking operations on a
+    /// separate thread pool, so the file must not be non-blocking (otherwise
+    /// operations on FIFOs and similar files would spuriously fail with
+    /// `EAGAIN`). As such, `flags` must not contain [`OpenFlags::O_NONBLOCK`].
+    /// [`OpenFlags::O_PATH`] is also not useful here, since `O_PATH` files
+    /// cannot be used for I/O.
+    ///
+    /// This method is only available with the `tokio` feature.
+    #[cfg(feature = "tokio")]
+    pub fn into_tokio_file<F: Into<OpenFlags>>(self, flags: F) -> Result<tokio::fs::File, Error> {
+        let flags = flags.into();
+        if flags.contains(OpenFlags::O_NONBLOCK) {
+            Err(ErrorImpl::InvalidArgument {
+                name: "flags".into(),
+                description: "tokio files cannot be opened with O_NONBLOCK".into(),
+            })?
+        }
+        self.reopen(flags).map(tokio::fs::File::from_std)
+    }
+
+    // Get the lazily re-opened file used for the std::io implementations,
+    // re-opening the handle if this is the first I/O operation. The file is
+    // opened with only the access mode needed by the caller (O_RDONLY or
+    // O_WRONLY), and is upgraded to O_RDWR (keeping the current offset) if a
+    // later operation needs the other access mode.
+    fn io_file(&mut self, mut access: OpenFlags) -> Result<&mut File, Error> {
+        let current = self.io_file.as_mut().map(|(file, flags)| (file, *flags));
+        let new_file = match current {
+            None => Some(Handle::reopen(self, access)?),
+            Some((_, flags)) if flags == OpenFlags::O_RDWR || flags == access => None,
+            Some((file, _)) => {
+                let offset = file.stream_position().map_err(|err| ErrorImpl::OsError {
+                    operation: "get current offset of handle io file".into(),
+                    source: err,
+                })?;
+                let mut new_file = Handle::reopen(self, OpenFlags::O_RDWR)?;
+                new_file
+                    .seek(SeekFrom::Start(offset))
+                    .map_err(|err| ErrorImpl::OsError {
+                        operation: "restore offset of upgraded handle io file".into(),
+                        source: err,
+                    })?;
+                access = OpenFlags::O_RDWR;
+                Some(new_file)
+            }
+        };
+        if let Some(file) = new_file {
+            self.io_file = Some((file, access));
+        }
+        Ok(&mut self
+            .io_file
+            .as_mut()
+            .expect("io_file should have just been set")
+            .0)
+    }
+}
+
+/// Read from the file referenced by the [`Handle`].
+///
+/// On the first I/O operation, the [`Handle`] is transparently re-opened (using
+/// the same safe re-opening logic as [`Handle::reopen`]) and the resulting
+/// [`File`] is used for all subsequent I/O operations on this [`Handle`]. The
+/// file is only opened with the access mode needed for the operation (reads
+/// and seeks use `O_RDONLY`, writes use `O_WRONLY`). If a later operation
+/// needs the other access mode, the file is re-opened with `O_RDWR` and the
+/// current file offset is carried over.
+///
+/// This means that the first I/O operation may fail if the inode cannot be
+/// opened for reading (such as a handle to a symlink from
+/// [`Root::resolve_nofollow`], or a socket). If you need more control over how
+/// the file is opened, use [`Handle::reopen`] instead.
+///
+/// [`Root::resolve_nofollow`]: crate::Root::resolve_nofollow
+impl Read for Handle {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.io_file(OpenFlags::O_RDONLY)?.read(buf)
+    }
+}
+
+/// Write to the file referenced by the [`Handle`].
+///
+/// See the [`Read`] implementation for details about how the [`Handle`] is
+/// re-opened. If the file cannot be re-opened for writing, writes will fail
+/// with the error returned by the re-open.
+impl Write for Handle {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.io_file(OpenFlags::O_WRONLY)?.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.io_file {
+            Some((ref mut file, _)) => file.flush(),
+            // No I/O has been done, so there is nothing to flush.
+            None => Ok(()),
+        }
+    }
+}
+
+/// Seek the file referenced by the [`Handle`].
+///
+/// See the [`Read`] implementation for details about how the [`Handle`] is
+/// re-opened. Note that the offset is associated with the re-opened file, not
+/// the underlying `O_PATH` file descriptor of the [`Handle`].
+impl Seek for Handle {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.io_file(OpenFlags::O_RDONLY)?.seek(pos)
+    }
 }
 
 impl From<OwnedFd> for Handle {
@@ -148,12 +589,31 @@ impl AsFd for Handle {
 #[derive(Copy, Clone, Debug)]
 pub struct HandleRef<'fd> {
     inner: BorrowedFd<'fd>,
+    reopen_policy: ReopenPolicy,
 }
 
 impl HandleRef<'_> {
     /// Wrap a [`BorrowedFd`] into a [`HandleRef`].
     pub fn from_fd(inner: BorrowedFd<'_>) -> HandleRef<'_> {
-        HandleRef { inner }
+        HandleRef {
+            inner,
+            reopen_policy: ReopenPolicy::default(),
+        }
+    }
+
+    /// Get the [`ReopenPolicy`] used by [`HandleRef::reopen`] for this
+    /// [`HandleRef`].
+    #[inline]
+    pub fn reopen_policy(&self) -> ReopenPolicy {
+        self.reopen_policy
+    }
+
+    /// Set the [`ReopenPolicy`] used by [`HandleRef::reopen`] for this
+    /// [`HandleRef`].
+    #[inline]
+    pub fn with_reopen_policy(mut self, policy: ReopenPolicy) -> Self {
+        self.reopen_policy = policy;
+        self
     }
 
     /// Create a copy of a [`HandleRef`].
@@ -177,7 +637,7 @@ impl HandleRef<'_> {
                 }
                 .into()
             })
-            .map(Handle::from_fd)
+            .map(|handle| Handle::from_fd(handle).with_reopen_policy(self.reopen_policy))
     }
 
     /// "Upgrade" the handle to a usable [`File`] handle.
@@ -193,12 +653,380 @@ impl HandleRef<'_> {
     /// 2. `ioctl(fd, TIOCSCTTY, 0)` will set the fd as the controlling terminal
     ///    (if you don't have one already, and the fd references a TTY).
     ///
+    /// The safety checks done while re-opening the handle are controlled by
+    /// the [`ReopenPolicy`] of the handle (see [`HandleRef::reopen_policy`]).
+    ///
+    /// `O_CREAT`, `O_EXCL`, `O_TMPFILE` and `O_PATH` make no sense when
+    /// re-opening an existing file, so `flags` containing any of them are
+    /// rejected with [`ErrorKind::InvalidArgument`]. If you want to create a
+    /// file, use [`Root::create`] (or one of its friends). If you want a
+    /// copy of an `O_PATH` handle, use [`HandleRef::try_clone`].
+    ///
     /// [`Root::create`]: crate::Root::create
+    /// [`ErrorKind::InvalidArgument`]: crate::error::ErrorKind::InvalidArgument
     #[doc(alias = "pathrs_reopen")]
     pub fn reopen<F: Into<OpenFlags>>(&self, flags: F) -> Result<File, Error> {
+        let flags = flags.into();
+        for (flag, name) in [
+            (OpenFlags::O_CREAT, "O_CREAT"),
+            (OpenFlags::O_EXCL, "O_EXCL"),
+            (OpenFlags::O_TMPFILE, "O_TMPFILE"),
+            (OpenFlags::O_PATH, "O_PATH"),
+        ] {
+            // NOTE: O_TMPFILE contains O_DIRECTORY, so we need to use
+            // contains() rather than intersects().
+            if flags.contains(flag) {
+                Err(ErrorImpl::InvalidArgument {
+                    name: "flags".into(),
+                    description: format!("{name} cannot be used when re-opening a handle").into(),
+                })?
+            }
+        }
+
+        self.inner
+            .reopen(&GLOBAL_PROCFS_HANDLE, flags, self.reopen_policy)
+            .map(File::from)
+    }
+
+    /// Get the metadata of the inode referenced by this handle.
+    ///
+    /// This is equivalent to [`File::metadata`], and works with `O_PATH`
+    /// handles (including handles to symlinks returned by
+    /// [`Root::resolve_nofollow`], in which case the metadata of the symlink
+    /// itself is returned).
+    ///
+    /// [`Root::resolve_nofollow`]: crate::Root::resolve_nofollow
+    pub fn metadata(&self) -> Result<Metadata, Error> {
+        // File::metadata uses statx(AT_EMPTY_PATH) (or fstat(2) as a
+        // fallback), both of which work on O_PATH file descriptors.
         self.inner
-            .reopen(&GLOBAL_PROCFS_HANDLE, flags.into())
+            .try_clone_to_owned()
             .map(File::from)
+            .and_then(|file| file.metadata())
+            .map_err(|err| {
+                ErrorImpl::OsError {
+                    operation: "get handle metadata".into(),
+                    source: err,
+                }
+                .into()
+            })
+    }
+
+    /// Check whether the underlying file descriptor is an `O_PATH` file
+    /// descriptor.
+    ///
+    /// Handles returned by [`Root::resolve`] and friends are always `O_PATH`
+    /// file descriptors, which cannot be used for I/O (attempting to read from
+    /// or write to them will fail with `EBADF`). However, handles created with
+    /// [`HandleRef::from_fd`] or [`Handle::from_fd`] may reference any kind of
+    /// file descriptor. To do I/O on an `O_PATH` handle, you need to use
+    /// [`HandleRef::reopen`].
+    ///
+    /// [`Root::resolve`]: crate::Root::resolve
+    pub fn is_o_path(&self) -> Result<bool, Error> {
+        syscalls::fcntl_getfl(self.inner)
+            .map(|flags| flags.contains(OpenFlags::O_PATH))
+            .map_err(|err| {
+                ErrorImpl::RawOsError {
+                    operation: "get handle file status flags".into(),
+                    source: err,
+                }
+                .into()
+            })
+    }
+
+    // Get a file descriptor for the handle that can be passed to fsync(2).
+    fn sync_file(&self) -> Result<File, Error> {
+        if self.is_o_path()? {
+            // Only regular files and directories can be synced, so check the
+            // inode type before re-opening the handle (opening FIFOs or device
+            // inodes could block or have side-effects).
+            let file_type = self
+                .metadata()
+                .wrap("fstat handle for syncing")?
+                .file_type();
+            if !file_type.is_file() && !file_type.is_dir() {
+                Err(ErrorImpl::OsError {
+                    operation: "re-open handle for syncing".into(),
+                    source: io::Error::from_raw_os_error(if file_type.is_symlink() {
+                        libc::ELOOP
+                    } else {
+                        libc::EINVAL
+                    }),
+                })?
+            }
+            // fsync(2) doesn't work on O_PATH file descriptors, but a
+            // read-only re-opened file descriptor is enough (fsync(2) doesn't
+            // need write access, and this also works for directories). If we
+            // don't have read access to a regular file, a write-only file
+            // descriptor works just as well.
+            match self.reopen(OpenFlags::O_RDONLY | OpenFlags::O_NONBLOCK) {
+                Err(err)
+                    if file_type.is_file()
+                        && err.kind() == ErrorKind::OsError(Some(libc::EACCES)) =>
+                {
+                    self.reopen(OpenFlags::O_WRONLY | OpenFlags::O_NONBLOCK)
+                }
+                res => res,
+            }
+            .wrap("re-open handle for syncing")
+        } else {
+            self.inner
+                .try_clone_to_owned()
+                .map(File::from)
+                .map_err(|err| {
+                    ErrorImpl::OsError {
+                        operation: "clone handle for syncing".into(),
+                        source: err,
+                    }
+                    .into()
+                })
+        }
+    }
+
+    /// Synchronise the data and metadata of the inode referenced by this
+    /// handle to disk.
+    ///
+    /// This is equivalent to [`File::sync_all`] (i.e., `fsync(2)`). Because
+    /// `fsync(2)` does not work on `O_PATH` file descriptors, if this handle is
+    /// an `O_PATH` handle it is first re-opened read-only with
+    /// [`HandleRef::reopen`] (or write-only, if the caller does not have read
+    /// access to a regular file). Only regular files and directories can be
+    /// synced this way -- handles to symlinks fail with `ELOOP` and handles to
+    /// other inode types fail with `EINVAL`, without being re-opened.
+    ///
+    /// Note that making the creation (or renaming) of a file durable also
+    /// requires syncing the directory containing it -- see
+    /// [`Root::sync_parent`].
+    ///
+    /// [`Root::sync_parent`]: crate::Root::sync_parent
+    #[doc(alias = "fsync")]
+    pub fn sync_all(&self) -> Result<(), Error> {
+        self.sync_file()?.sync_all().map_err(|err| {
+            ErrorImpl::OsError {
+                operation: "fsync handle".into(),
+                source: err,
+            }
+            .into()
+        })
+    }
+
+    /// Synchronise the data (but not necessarily the metadata) of the inode
+    /// referenced by this handle to disk.
+    ///
+    /// This is equivalent to [`File::sync_data`] (i.e., `fdatasync(2)`). See
+    /// [`HandleRef::sync_all`] for details about how `O_PATH` handles are
+    /// handled.
+    #[doc(alias = "fdatasync")]
+    pub fn sync_data(&self) -> Result<(), Error> {
+        self.sync_file()?.sync_data().map_err(|err| {
+            ErrorImpl::OsError {
+                operation: "fdatasync handle".into(),
+                source: err,
+            }
+            .into()
+        })
+    }
+
+    /// Get a [`RevalToken`] capturing the current identity and change state of
+    /// the inode referenced by this handle.
+    ///
+    /// The token can be stored alongside a cached handle and later passed to
+    /// [`HandleRef::revalidate`] to cheaply check whether the inode has been
+    /// modified, without needing to re-resolve the path. See [`RevalToken`]
+    /// for information about which fields make up the token and how reliable
+    /// they are on different filesystems.
+    pub fn revalidation_token(&self) -> Result<RevalToken, Error> {
+        RevalToken::from_fd(self.inner)
+    }
+
+    /// Check whether the inode referenced by this handle is unchanged since
+    /// `token` was created.
+    ///
+    /// Returns `false` if the handle references a different inode to the one
+    /// `token` was created for, or if the inode has been modified since then.
+    /// Note that this only checks the inode itself -- it does not tell you
+    /// whether the path used to resolve the handle still refers to the same
+    /// inode (for that, you need to re-resolve the path).
+    pub fn revalidate(&self, token: &RevalToken) -> Result<bool, Error> {
+        Ok(self.revalidation_token()? == *token)
+    }
+
+    /// Check whether this handle and `other` reference the same inode.
+    ///
+    /// Two handles reference the same inode if they have the same device and
+    /// inode numbers (`st_dev` and `st_ino`). This is useful for detecting
+    /// loops when walking a tree, or for checking that a re-resolved path
+    /// still references the inode that was originally resolved.
+    ///
+    /// Note that referencing the same inode does not mean the handles were
+    /// resolved from the same path (hardlinks and bind-mounts of the same
+    /// inode are considered the same), nor does resolving the same path twice
+    /// guarantee that the handles reference the same inode (the path may have
+    /// been modified in between).
+    pub fn same_inode_as(&self, other: &HandleRef<'_>) -> Result<bool, Error> {
+        self.inner
+            .is_same_inode(other.inner)
+            .wrap("compare handle inodes")
+    }
+
+    /// Get the value of the extended attribute `name` of the inode referenced
+    /// by this handle.
+    ///
+    /// Returns `Ok(None)` if the inode does not have an extended attribute
+    /// called `name`.
+    ///
+    /// All of the extended attribute methods use the `f*xattr(2)` syscalls on
+    /// the handle itself, and so act on the exact inode referenced by the
+    /// handle (including when the handle references a symlink). Note that
+    /// Linux does not permit `user.*` extended attributes on symlinks.
+    ///
+    /// Older kernels do not support `f*xattr(2)` on `O_PATH` file descriptors,
+    /// in which case the handle is re-opened (through libpathrs's hardened
+    /// `/proc` handle, in the same way as [`HandleRef::reopen`]) with
+    /// `O_RDONLY|O_NONBLOCK` and the operation is retried. This requires read
+    /// access to the inode, and is not possible for handles to symlinks (an
+    /// [`ErrorKind::NotSupported`] error is returned instead).
+    pub fn get_xattr<N: AsRef<OsStr>>(&self, name: N) -> Result<Option<Vec<u8>>, Error> {
+        let name = name.as_ref();
+        self.xattr_op("get handle xattr", |fd| {
+            match syscalls::fgetxattr(fd, name) {
+                Ok(value) => Ok(Some(value)),
+                Err(err) if err.errno() == Errno::NODATA => Ok(None),
+                Err(err) => Err(err),
+            }
+        })
+    }
+
+    /// Set the value of the extended attribute `name` of the inode referenced
+    /// by this handle, creating it if necessary.
+    ///
+    /// See [`HandleRef::get_xattr`] for more details about how extended
+    /// attributes are accessed.
+    pub fn set_xattr<N: AsRef<OsStr>>(&self, name: N, value: &[u8]) -> Result<(), Error> {
+        let name = name.as_ref();
+        self.xattr_op("set handle xattr", |fd| {
+            syscalls::fsetxattr(fd, name, value, XattrFlags::empty())
+        })
+    }
+
+    /// Remove the extended attribute `name` of the inode referenced by this
+    /// handle.
+    ///
+    /// If the inode does not have an extended attribute called `name`, an
+    /// `ENODATA` error is returned. See [`HandleRef::get_xattr`] for more
+    /// details about how extended attributes are accessed.
+    pub fn remove_xattr<N: AsRef<OsStr>>(&self, name: N) -> Result<(), Error> {
+        let name = name.as_ref();
+        self.xattr_op("remove handle xattr", |fd| syscalls::fremovexattr(fd, name))
+    }
+
+    /// List the names of the extended attributes of the inode referenced by
+    /// this handle.
+    ///
+    /// Note that only the names of extended attributes the caller has
+    /// permission to see are returned (for instance, `trusted.*` attributes
+    /// are only visible with `CAP_SYS_ADMIN`). See [`HandleRef::get_xattr`]
+    /// for more details about how extended attributes are accessed.
+    pub fn list_xattr(&self) -> Result<Vec<OsString>, Error> {
+        self.xattr_op("list handle xattrs", |fd| syscalls::flistxattr(fd))
+    }
+
+    // Run an f*xattr(2) operation on the handle, falling back to re-opening
+    // the handle if the kernel doesn't support f*xattr(2) on O_PATH file
+    // descriptors (which results in EBADF).
+    fn xattr_op<T, F>(&self, operation: &'static str, op: F) -> Result<T, Error>
+    where
+        F: Fn(BorrowedFd<'_>) -> Result<T, syscalls::Error>,
+    {
+        let wrap_err = |err| {
+            ErrorImpl::RawOsError {
+                operation: operation.into(),
+                source: err,
+            }
+            .into()
+        };
+        match op(self.inner) {
+            Err(err) if err.errno() == Errno::BADF => {
+                // Symlinks cannot be re-opened, and we can't use path-based
+                // l*xattr(2) without looking up an unverified magic-link.
+                if self.inner.metadata()?.is_symlink() {
+                    Err(ErrorImpl::NotSupported {
+                        feature: "extended attributes of symlink handles (f*xattr on O_PATH)"
+                            .into(),
+                    })?
+                }
+                // O_NONBLOCK avoids blocking on FIFOs.
+                let file = self
+                    .reopen(OpenFlags::O_RDONLY | OpenFlags::O_NONBLOCK | OpenFlags::O_NOCTTY)
+                    .wrap("re-open handle for xattr operation")?;
+                op(file.as_fd()).map_err(wrap_err)
+            }
+            res => res.map_err(wrap_err),
+        }
+    }
+
+    /// Get the current path of the file referenced by this handle, for
+    /// diagnostic purposes only.
+    ///
+    /// The path is read from the `/proc/thread-self/fd/$n` magic-link of the
+    /// handle (using libpathrs's hardened `/proc` handle), and is the path of
+    /// the file relative to the process's root directory at the time it was
+    /// read (not relative to any [`Root`]).
+    ///
+    /// **This path must only be used for diagnostic purposes**, such as
+    /// logging or error messages. The file can be renamed or unlinked at any
+    /// time (including by an attacker), so the path must never be used to
+    /// re-open or otherwise operate on the file -- use the handle itself (or
+    /// [`Root`] methods) for that.
+    ///
+    /// If the file has been unlinked, the kernel appends `" (deleted)"` to the
+    /// path. This suffix is returned as-is (note that it is not possible to
+    /// distinguish this from a file whose name actually ends with
+    /// `" (deleted)"`).
+    ///
+    /// [`Root`]: crate::Root
+    pub fn as_frozen_path(&self) -> Result<PathBuf, Error> {
+        self.inner
+            .as_unsafe_path(&GLOBAL_PROCFS_HANDLE)
+            .wrap("get handle path from procfs")
+    }
+
+    /// Resolve `path` beneath the directory referenced by this handle, treating
+    /// the handle as a root.
+    ///
+    /// This is useful for doing repeated lookups relative to a directory deep
+    /// within a [`Root`] without having to re-walk the path from the top of
+    /// the [`Root`] each time. It is equivalent to using [`RootRef::from_fd`]
+    /// on this handle (with the provided resolver `flags` and this handle's
+    /// [`ReopenPolicy`]) and calling [`RootRef::resolve`].
+    ///
+    /// Note that resolution is scoped to the directory referenced by this
+    /// handle, **not** the [`Root`] the handle was originally resolved from.
+    /// This means that:
+    ///
+    ///  * `..` components can never walk above the directory referenced by
+    ///    this handle (`..` at the handle acts like `..` at `/` and stays at
+    ///    the handle), even if the handle is deep within a [`Root`].
+    ///  * Absolute paths and absolute symlink targets are resolved relative to
+    ///    the directory referenced by this handle, not the original [`Root`].
+    ///  * If the directory referenced by this handle is moved during
+    ///    resolution, the same safety checks as for a [`Root`] apply.
+    ///
+    /// Since the handle's directory is inside the original [`Root`], the
+    /// resulting [`Handle`] is also always inside the original [`Root`].
+    ///
+    /// # Errors
+    ///
+    /// If this handle does not reference a directory, resolution will fail
+    /// with `ENOTDIR`.
+    ///
+    /// [`Root`]: crate::Root
+    pub fn resolve<P: AsRef<Path>>(&self, path: P, flags: ResolverFlags) -> Result<Handle, Error> {
+        RootRef::from_fd(self.inner)
+            .with_resolver_flags(flags)
+            .with_reopen_policy(self.reopen_policy)
+            .resolve(path)
     }
 
     // TODO: All the different stat* interfaces?
@@ -230,9 +1058,24 @@ impl AsFd for HandleRef<'_> {
 
 #[cfg(test)]
 mod tests {
-    use crate::{HandleRef, Root};
+    use crate::{
+        error::ErrorKind,
+        flags::{OpenFlags, ResolverFlags},
+        syscalls,
+        tests::common as tests_common,
+        Handle, HandleRef, InodeType, ResolverBackend, Root,
+    };
 
-    use std::os::unix::io::{AsFd, AsRawFd};
+    use std::{
+        fs::{self, Permissions},
+        io::{Read, Seek, SeekFrom, Write},
+        os::unix::{
+            fs::{self as unix_fs, MetadataExt, PermissionsExt},
+            io::{AsFd, AsRawFd},
+        },
+        thread,
+        time::Duration,
+    };
 
     use anyhow::Error;
     use pretty_assertions::assert_eq;
@@ -256,4 +1099,612 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn backend_from_fd() -> Result<(), Error> {
+        let handle = Handle::from_fd(Root::open(".")?);
+        assert_eq!(
+            handle.backend(),
+            None,
+            "Handle::from_fd should not have a backend"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn backend_resolve() -> Result<(), Error> {
+        for backend in [
+            ResolverBackend::KernelOpenat2,
+            ResolverBackend::EmulatedOpath,
+        ] {
+            if !backend.supported() {
+                continue;
+            }
+            let root = Root::open(".")?.with_resolver_backend(backend);
+
+            let handle = root.resolve(".")?;
+            assert_eq!(
+                handle.backend(),
+                Some(backend),
+                "Root::resolve should tag handle with backend"
+            );
+            assert_eq!(
+                handle.try_clone()?.backend(),
+                Some(backend),
+                "Handle::try_clone should preserve backend"
+            );
+
+            let handle = root.resolve_nofollow(".")?;
+            assert_eq!(
+                handle.backend(),
+                Some(backend),
+                "Root::resolve_nofollow should tag handle with backend"
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn is_o_path() -> Result<(), Error> {
+        let root_dir = tests_common::create_basic_tree()?;
+        let root = Root::open(&root_dir)?;
+
+        let handle = root.resolve("b/c/file")?;
+        assert!(handle.is_o_path()?, "resolved handle should be O_PATH");
+        assert!(
+            handle.as_ref().is_o_path()?,
+            "HandleRef of resolved handle should be O_PATH"
+        );
+
+        let file = handle.reopen(OpenFlags::O_RDONLY)?;
+        assert!(
+            !HandleRef::from_fd(file.as_fd()).is_o_path()?,
+            "reopened file should not be O_PATH"
+        );
+        assert!(
+            !Handle::from_fd(file).is_o_path()?,
+            "reopened file should not be O_PATH"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn try_clone() -> Result<(), Error> {
+        let root_dir = tests_common::create_basic_tree()?;
+        let root = Root::open(&root_dir)?;
+        fs::write(root_dir.path().join("b/c/file"), "clone contents")?;
+
+        let handle = root.resolve("b/c/file")?;
+        let meta = handle.metadata()?;
+        let clone = handle.try_clone()?;
+        assert_ne!(
+            handle.as_fd().as_raw_fd(),
+            clone.as_fd().as_raw_fd(),
+            "cloned handle should have a different fd"
+        );
+        assert!(
+            clone.is_o_path()?,
+            "clone of O_PATH handle should be O_PATH"
+        );
+        assert_eq!(
+            clone.backend(),
+            handle.backend(),
+            "clone should keep backend"
+        );
+        assert_eq!(
+            clone.reopen_policy(),
+            handle.reopen_policy(),
+            "clone should keep reopen policy"
+        );
+
+        // Dropping the original must not affect the clone.
+        drop(handle);
+        let clone_meta = clone.metadata()?;
+        assert_eq!(
+            (clone_meta.dev(), clone_meta.ino()),
+            (meta.dev(), meta.ino()),
+            "clone should reference the same inode"
+        );
+        let mut contents = String::new();
+        clone
+            .reopen(OpenFlags::O_RDONLY)?
+            .read_to_string(&mut contents)?;
+        assert_eq!(
+            contents, "clone contents",
+            "re-opened clone should read the original file contents"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn as_frozen_path() -> Result<(), Error> {
+        let root_dir = tests_common::create_basic_tree()?;
+        let root_dir = fs::canonicalize(root_dir.path())?;
+        let root = Root::open(&root_dir)?;
+
+        let handle = root.resolve("b-file")?;
+        assert_eq!(handle.as_frozen_path()?, root_dir.join("b/c/file"));
+        assert_eq!(handle.as_ref().as_frozen_path()?, root_dir.join("b/c/file"));
+
+        // The path of an unlinked file has a " (deleted)" suffix.
+        fs::remove_file(root_dir.join("b/c/file"))?;
+        assert_eq!(
+            handle.as_frozen_path()?,
+            root_dir.join("b/c/file (deleted)"),
+            "unlinked file path should have (deleted) suffix"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn resolve() -> Result<(), Error> {
+        let root_dir = tests_common::create_basic_tree()?;
+        let root = Root::open(&root_dir)?;
+        let dir = root.resolve("b/c")?;
+
+        let file_ino = root.resolve("b/c/file")?.metadata()?.ino();
+        for path in [
+            "file",
+            "/file",
+            "../../file",
+            "d/../file",
+            "d/../../../file",
+        ] {
+            let handle = dir.resolve(path, ResolverFlags::empty())?;
+            assert_eq!(
+                handle.metadata()?.ino(),
+                file_ino,
+                "resolve({path:?}) should be scoped to the handle"
+            );
+        }
+
+        // Paths above the handle are not visible.
+        assert_eq!(
+            dir.resolve("../b-file", ResolverFlags::empty())
+                .map_err(|err| err.kind())
+                .err(),
+            Some(ErrorKind::OsError(Some(libc::ENOENT))),
+            "resolve should not be able to walk above the handle"
+        );
+
+        // Absolute symlinks are scoped to the handle too.
+        unix_fs::symlink("/file", root_dir.path().join("b/c/file-link"))?;
+        assert_eq!(
+            dir.resolve("file-link", ResolverFlags::empty())?
+                .metadata()?
+                .ino(),
+            file_ino,
+            "absolute symlinks should be resolved relative to the handle"
+        );
+        assert_eq!(
+            dir.resolve("file-link", ResolverFlags::NO_SYMLINKS)
+                .map_err(|err| err.kind())
+                .err(),
+            Some(ErrorKind::OsError(Some(libc::ELOOP))),
+            "resolve should use the provided resolver flags"
+        );
+
+        let file = root.resolve("b/c/file")?;
+        assert_eq!(
+            file.resolve("foo", ResolverFlags::empty())
+                .map_err(|err| err.kind())
+                .err(),
+            Some(ErrorKind::OsError(Some(libc::ENOTDIR))),
+            "resolve beneath a non-directory handle should fail"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn same_inode_as() -> Result<(), Error> {
+        let root_dir = tests_common::create_basic_tree()?;
+        let root = Root::open(&root_dir)?;
+
+        let file = root.resolve("b/c/file")?;
+        assert!(file.same_inode_as(&root.resolve("b-file")?)?);
+        assert!(file.same_inode_as(&file.try_clone()?)?);
+        assert!(!file.same_inode_as(&root.resolve_nofollow("b-file")?)?);
+        assert!(!file.same_inode_as(&root.resolve("b/c")?)?);
+
+        // Hardlinks reference the same inode.
+        fs::hard_link(
+            root_dir.path().join("b/c/file"),
+            root_dir.path().join("b/c/hardlink"),
+        )?;
+        assert!(file.same_inode_as(&root.resolve("b/c/hardlink")?)?);
+        Ok(())
+    }
+
+    #[test]
+    fn sync() -> Result<(), Error> {
+        let root_dir = tests_common::create_basic_tree()?;
+        let root = Root::open(&root_dir)?;
+
+        // O_PATH handles to files and directories are re-opened.
+        for path in ["b/c/file", "b/c"] {
+            let handle = root.resolve(path)?;
+            assert!(handle.is_o_path()?, "resolve({path:?}) should be O_PATH");
+            handle.sync_all()?;
+            handle.sync_data()?;
+        }
+
+        // Regular file descriptors are synced directly.
+        let handle = Handle::from_fd(root.open_subpath("b/c/file", OpenFlags::O_WRONLY)?);
+        handle.sync_all()?;
+        handle.sync_data()?;
+
+        // Symlinks cannot be re-opened, and so cannot be synced.
+        assert_eq!(
+            root.resolve_nofollow("b-file")?
+                .sync_all()
+                .map_err(|err| err.kind())
+                .err(),
+            Some(ErrorKind::OsError(Some(libc::ELOOP))),
+            "sync_all of a symlink handle should fail"
+        );
+
+        // FIFOs are rejected without being re-opened.
+        root.create("b/c/fifo", &InodeType::Fifo(Permissions::from_mode(0o644)))?;
+        assert_eq!(
+            root.resolve("b/c/fifo")?
+                .sync_all()
+                .map_err(|err| err.kind())
+                .err(),
+            Some(ErrorKind::OsError(Some(libc::EINVAL))),
+            "sync_all of a fifo handle should fail"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn xattr() -> Result<(), Error> {
+        let root_dir = tests_common::create_basic_tree()?;
+        let root = Root::open(&root_dir)?;
+
+        let handle = root.resolve("b/c/file")?;
+        assert_eq!(handle.get_xattr("user.pathrs")?, None, "unset xattr");
+        handle.set_xattr("user.pathrs", b"value1")?;
+        handle.as_ref().set_xattr("user.pathrs.other", b"")?;
+        assert_eq!(
+            handle.get_xattr("user.pathrs")?,
+            Some(b"value1".to_vec()),
+            "xattr set through handle"
+        );
+        assert_eq!(
+            handle.as_ref().get_xattr("user.pathrs.other")?,
+            Some(vec![]),
+            "empty xattr set through handle"
+        );
+        let mut names = handle.list_xattr()?;
+        names.retain(|name| name.to_string_lossy().starts_with("user."));
+        names.sort();
+        assert_eq!(names, ["user.pathrs", "user.pathrs.other"], "list xattrs");
+
+        // The xattrs are set on the resolved inode.
+        let other = root.resolve("b-file")?;
+        assert_eq!(
+            other.get_xattr("user.pathrs")?,
+            Some(b"value1".to_vec()),
+            "xattr of same inode through a different handle"
+        );
+
+        handle.remove_xattr("user.pathrs")?;
+        assert_eq!(handle.get_xattr("user.pathrs")?, None, "removed xattr");
+        assert_eq!(
+            handle.remove_xattr("user.pathrs").map_err(|err| err.kind()),
+            Err(ErrorKind::OsError(Some(libc::ENODATA))),
+            "remove unset xattr"
+        );
+
+        // Symlink handles operate on the symlink itself, which doesn't permit
+        // user.* xattrs. Older kernels don't support f*xattr(2) on O_PATH
+        // handles, in which case symlink handles are not supported at all.
+        let link = root.resolve_nofollow("b-file")?;
+        let link_xattr = link.get_xattr("user.pathrs.other");
+        if matches!(link_xattr, Err(ref err) if err.kind() == ErrorKind::NotSupported) {
+            for (op, res) in [
+                ("set", link.set_xattr("user.pathrs", b"value2")),
+                ("remove", link.remove_xattr("user.pathrs")),
+                ("list", link.list_xattr().map(|_| ())),
+            ] {
+                assert_eq!(
+                    res.map_err(|err| err.kind()),
+                    Err(ErrorKind::NotSupported),
+                    "{op} xattr of symlink should be consistently unsupported"
+                );
+            }
+            return Ok(());
+        }
+        assert_eq!(link_xattr?, None, "xattr of symlink");
+        assert_eq!(
+            link.set_xattr("user.pathrs", b"value2")
+                .map_err(|err| err.kind()),
+            Err(ErrorKind::OsError(Some(libc::EPERM))),
+            "user.* xattrs cannot be set on symlinks"
+        );
+        if syscalls::geteuid() == 0 {
+            link.set_xattr("trusted.pathrs", b"value3")?;
+            assert_eq!(
+                link.get_xattr("trusted.pathrs")?,
+                Some(b"value3".to_vec()),
+                "xattr set on symlink"
+            );
+            assert_eq!(
+                handle.get_xattr("trusted.pathrs")?,
+                None,
+                "xattr set on symlink should not be set on the target"
+            );
+            assert!(
+                link.list_xattr()?.contains(&"trusted.pathrs".into()),
+                "list symlink xattrs"
+            );
+            link.remove_xattr("trusted.pathrs")?;
+            assert_eq!(
+                link.get_xattr("trusted.pathrs")?,
+                None,
+                "removed symlink xattr"
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn reopen_invalid_flags() -> Result<(), Error> {
+        let root_dir = tests_common::create_basic_tree()?;
+        let root = Root::open(&root_dir)?;
+
+        for (path, flags, name) in [
+            (
+                "b/c/file",
+                OpenFlags::O_RDWR | OpenFlags::O_CREAT,
+                "O_CREAT",
+            ),
+            ("b/c/file", OpenFlags::O_RDWR | OpenFlags::O_EXCL, "O_EXCL"),
+            ("b/c", OpenFlags::O_RDWR | OpenFlags::O_TMPFILE, "O_TMPFILE"),
+            ("b/c/file", OpenFlags::O_PATH, "O_PATH"),
+            (
+                "b-file",
+                OpenFlags::O_PATH | OpenFlags::O_NOFOLLOW,
+                "O_PATH",
+            ),
+        ] {
+            let handle = root.resolve_nofollow(path)?;
+            let err = handle
+                .reopen(flags)
+                .expect_err("reopen with invalid flags should fail");
+            assert_eq!(
+                err.kind(),
+                ErrorKind::InvalidArgument,
+                "reopen({path:?}, {flags:?}) should fail with InvalidArgument: {err}"
+            );
+            assert!(
+                err.to_string().contains(name),
+                "reopen({path:?}, {flags:?}) error should mention {name}: {err}"
+            );
+        }
+
+        // O_DIRECTORY is part of O_TMPFILE, but is still permitted.
+        root.resolve("b/c")?.reopen(OpenFlags::O_DIRECTORY)?;
+        Ok(())
+    }
+
+    #[test]
+    fn revalidate() -> Result<(), Error> {
+        let root_dir = tests_common::create_basic_tree()?;
+        let root = Root::open(&root_dir)?;
+
+        let handle = root.resolve("b/c/file")?;
+        let token = handle.revalidation_token()?;
+        assert!(
+            handle.revalidate(&token)?,
+            "unmodified handle should revalidate"
+        );
+        assert!(
+            root.resolve("b/c/file")?.revalidate(&token)?,
+            "new handle to the same unmodified inode should revalidate"
+        );
+        assert!(
+            !root.resolve("b/c")?.revalidate(&token)?,
+            "handle to a different inode should not revalidate"
+        );
+
+        // ctime has coarse (jiffy) granularity on most filesystems, so make
+        // sure the modification happens in a later tick.
+        thread::sleep(Duration::from_millis(50));
+        fs::write(root_dir.path().join("b/c/file"), "modified")?;
+        assert!(
+            !handle.revalidate(&token)?,
+            "modified inode should not revalidate"
+        );
+        assert!(
+            handle.revalidate(&handle.revalidation_token()?)?,
+            "new token for modified inode should revalidate"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn io_read_write_seek() -> Result<(), Error> {
+        let root_dir = tests_common::create_basic_tree()?;
+        let root = Root::open(&root_dir)?;
+
+        let mut handle = root.resolve("b/c/file")?;
+        handle.write_all(b"hello world")?;
+        handle.flush()?;
+
+        handle.seek(SeekFrom::Start(6))?;
+        let mut contents = String::new();
+        handle.read_to_string(&mut contents)?;
+        assert_eq!(contents, "world", "read after seek should read the tail");
+
+        assert_eq!(
+            fs::read_to_string(root_dir.path().join("b/c/file"))?,
+            "hello world",
+            "writes through Handle should be visible in the file"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn io_read_only_file() -> Result<(), Error> {
+        let root_dir = tests_common::create_basic_tree()?;
+        let root = Root::open(&root_dir)?;
+
+        fs::write(root_dir.path().join("b/c/file"), "read only")?;
+        fs::set_permissions(
+            root_dir.path().join("b/c/file"),
+            fs::Permissions::from_mode(0o444),
+        )?;
+
+        // Reading must not require write access to the file.
+        let mut handle = root.resolve("b/c/file")?;
+        let mut contents = String::new();
+        handle.read_to_string(&mut contents)?;
+        assert_eq!(contents, "read only", "read from read-only file");
+        assert_eq!(
+            handle.io_file.as_ref().map(|(_, flags)| *flags),
+            Some(OpenFlags::O_RDONLY),
+            "reads should only re-open the handle with O_RDONLY"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn io_upgrade_keeps_offset() -> Result<(), Error> {
+        let root_dir = tests_common::create_basic_tree()?;
+        let root = Root::open(&root_dir)?;
+
+        fs::write(root_dir.path().join("b/c/file"), "hello world")?;
+
+        let mut handle = root.resolve("b/c/file")?;
+        let mut buf = [0u8; 6];
+        handle.read_exact(&mut buf)?;
+        assert_eq!(&buf, b"hello ", "read head of file");
+
+        // The write must upgrade the file to O_RDWR and continue from the
+        // current offset.
+        handle.write_all(b"WORLD")?;
+        assert_eq!(
+            handle.io_file.as_ref().map(|(_, flags)| *flags),
+            Some(OpenFlags::O_RDWR),
+            "write after read should upgrade to O_RDWR"
+        );
+        assert_eq!(
+            fs::read_to_string(root_dir.path().join("b/c/file"))?,
+            "hello WORLD",
+            "write after upgrade should use the previous offset"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn into_file() -> Result<(), Error> {
+        let root_dir = tests_common::create_basic_tree()?;
+        let root = Root::open(&root_dir)?;
+
+        let mut file = root.resolve("b/c/file")?.into_file(OpenFlags::O_RDWR)?;
+        file.write_all(b"hello")?;
+        assert_eq!(
+            fs::read_to_string(root_dir.path().join("b/c/file"))?,
+            "hello",
+            "writes through into_file should be visible in the file"
+        );
+
+        // Directories require O_DIRECTORY.
+        root.resolve("b/c")?.into_file(OpenFlags::O_DIRECTORY)?;
+        for (path, flags, desc) in [
+            ("b/c", OpenFlags::O_RDONLY, "directory"),
+            ("b/fifo", OpenFlags::O_RDONLY, "fifo"),
+            ("b/sock", OpenFlags::O_RDONLY, "socket"),
+        ] {
+            let err = root
+                .resolve(path)?
+                .into_file(flags)
+                .expect_err("into_file of non-regular file should fail");
+            assert_eq!(
+                err.kind(),
+                ErrorKind::InvalidArgument,
+                "into_file({path:?}, {flags:?}) should fail with InvalidArgument: {err}"
+            );
+            assert!(
+                err.to_string().contains(desc),
+                "into_file({path:?}, {flags:?}) error should mention {desc}: {err}"
+            );
+        }
+
+        let devnull = Handle::from_fd(fs::File::open("/dev/null")?);
+        assert_eq!(
+            devnull
+                .into_file(OpenFlags::O_RDONLY)
+                .map_err(|err| err.kind())
+                .err(),
+            Some(ErrorKind::InvalidArgument),
+            "into_file of a character device should fail"
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn into_tokio_file() -> Result<(), Error> {
+        use crate::error::ErrorKind;
+
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let root_dir = tests_common::create_basic_tree()?;
+        let root = Root::open(&root_dir)?;
+
+        let runtime = tokio::runtime::Builder::new_current_thread().build()?;
+        runtime.block_on(async {
+            let mut file = root
+                .resolve("b/c/file")?
+                .into_tokio_file(OpenFlags::O_RDWR)?;
+            file.write_all(b"hello tokio").await?;
+            file.flush().await?;
+            Ok::<_, Error>(())
+        })?;
+        assert_eq!(
+            fs::read_to_string(root_dir.path().join("b/c/file"))?,
+            "hello tokio",
+            "writes through tokio file should be visible in the file"
+        );
+
+        let contents = runtime.block_on(async {
+            let mut file = root
+                .resolve("b/c/file")?
+                .into_tokio_file(OpenFlags::O_RDONLY)?;
+            let mut contents = String::new();
+            file.read_to_string(&mut contents).await?;
+            Ok::<_, Error>(contents)
+        })?;
+        assert_eq!(contents, "hello tokio", "read through tokio file");
+
+        let err = root
+            .resolve("b/c/file")?
+            .into_tokio_file(OpenFlags::O_RDONLY | OpenFlags::O_NONBLOCK)
+            .expect_err("into_tokio_file with O_NONBLOCK should fail");
+        assert_eq!(
+            err.kind(),
+            ErrorKind::InvalidArgument,
+            "into_tokio_file with O_NONBLOCK should return InvalidArgument"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn io_read_symlink() -> Result<(), Error> {
+        let root_dir = tests_common::create_basic_tree()?;
+        let root = Root::open(&root_dir)?;
+
+        let mut handle = root.resolve_nofollow("b-file")?;
+        let err = handle
+            .read_to_end(&mut Vec::new())
+            .expect_err("reading from a symlink handle should fail");
+        assert_eq!(
+            err.raw_os_error(),
+            Some(libc::ELOOP),
+            "reading from a symlink handle should fail with ELOOP: {err}"
+        );
+        Ok(())
+    }
 }
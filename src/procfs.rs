@@ -30,25 +30,48 @@ use crate::{
 };
 
 use std::{
-    fs::File,
+    fs::{self, File},
     io::Error as IOError,
+    marker::PhantomData,
     os::unix::{
         fs::MetadataExt,
-        io::{AsFd, BorrowedFd, OwnedFd},
+        io::{AsFd, AsRawFd, BorrowedFd, OwnedFd},
     },
     path::{Path, PathBuf},
 };
 
-use once_cell::sync::Lazy;
+use once_cell::sync::{Lazy, OnceCell};
 use rustix::{
     fs::{self as rustix_fs, Access, AtFlags},
     mount::{FsMountFlags, FsOpenFlags, MountAttrFlags, OpenTreeFlags},
 };
 
+/// Storage for [`GLOBAL_PROCFS_HANDLE`]. Unlike [`Lazy`], failing to create
+/// the handle is not cached, so later callers will retry (in case `/proc` has
+/// since been mounted).
+///
+/// This handle is shared by all path operations in libpathrs. [`OnceCell`]
+/// guarantees that concurrent initialisation is synchronised (only one handle
+/// is ever stored), and the [`ProcfsHandle`] is never modified after it is
+/// created, so it can be used concurrently from any thread. Because the handle
+/// references the root of `/proc` (rather than `/proc/self` or
+/// `/proc/thread-self`), it is not tied to the thread that created it.
+// MSRV(1.70): Use OnceLock.
+static GLOBAL_PROCFS_HANDLE_CELL: OnceCell<ProcfsHandle> = OnceCell::new();
+
+/// Get the `procfs` handle used globally by libpathrs, returning an error if
+/// no usable `/proc` handle could be created.
+///
+/// Code which cannot operate safely without `/proc` should call this before
+/// using [`GLOBAL_PROCFS_HANDLE`] (which panics in that case).
+pub(crate) fn global_procfs_handle() -> Result<&'static ProcfsHandle, Error> {
+    GLOBAL_PROCFS_HANDLE_CELL.get_or_try_init(ProcfsHandle::new)
+}
+
 /// A `procfs` handle to which is used globally by libpathrs.
 // MSRV(1.80): Use LazyLock.
-pub(crate) static GLOBAL_PROCFS_HANDLE: Lazy<ProcfsHandle> =
-    Lazy::new(|| ProcfsHandle::new().expect("should be able to get some /proc handle"));
+pub(crate) static GLOBAL_PROCFS_HANDLE: Lazy<&'static ProcfsHandle> =
+    Lazy::new(|| global_procfs_handle().expect("should be able to get some /proc handle"));
 
 /// Indicate what base directory should be used when doing `/proc/...`
 /// operations with a [`ProcfsHandle`].
@@ -105,16 +128,18 @@ impl ProcfsBase {
             ]
             .into_iter()
             // Return the first option that exists in proc_root.
-            .find(|base| {
-                match proc_root {
-                    Some(root) => syscalls::fstatat(root, base),
-                    None => {
-                        syscalls::fstatat(syscalls::AT_FDCWD, PathBuf::from("/proc").join(base))
-                    }
-                }
-                .is_ok()
+            .find(|base| match proc_root {
+                Some(root) => syscalls::fstatat(root, base).is_ok(),
+                // NOTE: We cannot use syscalls::fstatat here, because this is
+                // used (via FdExt::as_unsafe_path_unchecked) when constructing
+                // syscalls::Error and so would recurse forever if /proc is not
+                // mounted.
+                None => fs::symlink_metadata(PathBuf::from("/proc").join(base)).is_ok(),
             })
-            .expect("at least one candidate /proc/thread-self path should work"),
+            // If none of the candidates exist (such as when /proc is not
+            // mounted), just return the last one and let the caller's
+            // operation fail.
+            .unwrap_or_else(|| "self".into()),
         }
     }
     // TODO: Add into_raw_path() that doesn't use symlinks?
@@ -302,6 +327,10 @@ impl ProcfsHandle {
     }
 
     fn open_base(&self, base: ProcfsBase) -> Result<OwnedFd, Error> {
+        // NOTE: The base directory must be resolved on every call rather than
+        // being cached in the handle, because /proc/thread-self (and its
+        // /proc/self/task/$tid fallback) refers to the calling thread and the
+        // handle may be used from any thread.
         let proc_rootfd = self.inner.as_fd();
         let fd = self.resolver.resolve(
             proc_rootfd,
@@ -363,7 +392,7 @@ impl ProcfsHandle {
         //       being a magic-link and then another thing being mounted on top.
         //       This is the same race as below.
         if self.readlink(base, subpath).is_err() {
-            return self.open(base, subpath, oflags).map(File::from);
+            return self.open(base, subpath, oflags);
         }
 
         // Get a no-follow handle to the parent of the magic-link.
@@ -497,6 +526,24 @@ impl ProcfsHandle {
         })
     }
 
+    /// Open a [`ProcfsFdDir`] for the current thread.
+    pub(crate) fn open_fd_dir(&self) -> Result<ProcfsFdDir, Error> {
+        let dir = self.open(
+            ProcfsBase::ProcThreadSelf,
+            "fd",
+            OpenFlags::O_PATH | OpenFlags::O_DIRECTORY,
+        )?;
+        // ProcfsHandle::open might have used a different procfs mount (see
+        // ProcfsHandle::open_follow), so use the mount ID of the directory we
+        // actually got.
+        let mnt_id = utils::fetch_mnt_id(&dir, "")?;
+        Ok(ProcfsFdDir {
+            dir,
+            mnt_id,
+            _not_send: PhantomData,
+        })
+    }
+
     fn verify_same_procfs_mnt<Fd: AsFd>(&self, fd: Fd) -> Result<(), Error> {
         // Detect if the file we landed on is from a bind-mount.
         verify_same_mnt(self.mnt_id, &fd, "")?;
@@ -552,6 +599,63 @@ impl ProcfsHandle {
     }
 }
 
+/// A cached handle to the current thread's `/proc/thread-self/fd` directory.
+///
+/// This is used to amortise the cost of reading many file descriptor
+/// magic-links (such as the safety checks done by the emulated resolver), by
+/// only doing the full [`ProcfsHandle::open`] lookup of `/proc/thread-self/fd`
+/// once. Because `/proc/thread-self` refers to the thread that opened it, the
+/// handle must only be used on the thread that created it (and so it is
+/// neither [`Send`] nor [`Sync`]).
+pub(crate) struct ProcfsFdDir {
+    dir: File,
+    mnt_id: Option<u64>,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl ProcfsFdDir {
+    /// Get the name of the `/proc/thread-self/fd/$n` magic-link for `fd`
+    /// inside this directory, after verifying that the magic-link has not been
+    /// bind-mounted over.
+    ///
+    /// The returned name is intended to be used (with this directory as the
+    /// dirfd) for `*at(2)` syscalls which need to operate on the target of the
+    /// magic-link. As with [`ProcfsHandle::open_follow`], this check is only
+    /// safe against races if there are no racing mounts.
+    pub(crate) fn verified_magiclink<Fd: AsFd>(&self, fd: Fd) -> Result<String, Error> {
+        let fd = fd.as_fd().as_raw_fd();
+        if fd < 0 {
+            Err(ErrorImpl::InvalidArgument {
+                name: "fd".into(),
+                description: "must be positive".into(),
+            })?
+        }
+        let name = fd.to_string();
+        // Detect if the magic-link has been bind-mounted over.
+        verify_same_mnt(self.mnt_id, &self.dir, &name)?;
+        Ok(name)
+    }
+
+    /// Read the `/proc/thread-self/fd/$n` magic-link for `fd`. This is
+    /// equivalent to [`FdExt::as_unsafe_path`] and so has the same caveats.
+    pub(crate) fn readlink_fd<Fd: AsFd>(&self, fd: Fd) -> Result<PathBuf, Error> {
+        let name = self.verified_magiclink(fd)?;
+        syscalls::readlinkat(&self.dir, name).map_err(|err| {
+            ErrorImpl::RawOsError {
+                operation: "read procfs magiclink".into(),
+                source: err,
+            }
+            .into()
+        })
+    }
+}
+
+impl AsFd for ProcfsFdDir {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.dir.as_fd()
+    }
+}
+
 pub(crate) fn verify_is_procfs<Fd: AsFd>(fd: Fd) -> Result<(), Error> {
     let fs_type = syscalls::fstatfs(fd)
         .map_err(|err| ErrorImpl::RawOsError {
@@ -641,4 +745,40 @@ mod tests {
             "new procfs handle should succeed, got {procfs:?}",
         );
     }
+
+    #[test]
+    fn fd_dir_readlink_fd() {
+        let procfs = ProcfsHandle::new().expect("new procfs handle");
+        let fd_dir = procfs.open_fd_dir().expect("open fd dir");
+        for path in ["/", "/tmp", "/proc/self"] {
+            let file = File::open(path).expect("open test path");
+            assert_eq!(
+                fd_dir.readlink_fd(&file).expect("readlink fd"),
+                file.as_unsafe_path(&procfs).expect("as_unsafe_path"),
+                "readlink_fd should match as_unsafe_path for {path:?}"
+            );
+        }
+        assert!(
+            fd_dir.readlink_fd(syscalls::BADFD).is_err(),
+            "readlink_fd should fail for bad file descriptor"
+        );
+    }
+
+    #[test]
+    fn fd_dir_verified_magiclink() {
+        let procfs = ProcfsHandle::new().expect("new procfs handle");
+        let fd_dir = procfs.open_fd_dir().expect("open fd dir");
+        let file = File::open("/").expect("open test path");
+        assert_eq!(
+            fd_dir
+                .verified_magiclink(&file)
+                .expect("verified magiclink"),
+            file.as_raw_fd().to_string(),
+            "verified_magiclink should return the fd number"
+        );
+        assert!(
+            fd_dir.verified_magiclink(syscalls::BADFD).is_err(),
+            "verified_magiclink should fail for bad file descriptor"
+        );
+    }
 }
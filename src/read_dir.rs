@@ -0,0 +1,557 @@
+/*
+ * libpathrs: safe path resolution on Linux
+ * Copyright (C) 2019-2021 Aleksa Sarai <cyphar@cyphar.com>
+ * Copyright (C) 2019-2021 SUSE LLC
+ *
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Lesser General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or (at your
+ * option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY
+ * or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License
+ * for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+#![forbid(unsafe_code)]
+
+use crate::{
+    error::{Error, ErrorExt, ErrorImpl, ErrorKind},
+    flags::OpenFlags,
+    syscalls, RootRef,
+};
+
+use std::{
+    collections::BTreeMap,
+    ffi::{OsStr, OsString},
+    fmt,
+    io::Error as IOError,
+    os::unix::{ffi::OsStrExt, io::OwnedFd},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use rustix::fs::{Dir, FileType as RustixFileType};
+
+/// The type of a directory entry returned by [`ReadDir`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum FileType {
+    /// A regular file.
+    File,
+    /// A directory.
+    Directory,
+    /// A symbolic link.
+    Symlink,
+    /// A named pipe (FIFO).
+    Fifo,
+    /// A Unix domain socket.
+    Socket,
+    /// A character device.
+    CharacterDevice,
+    /// A block device.
+    BlockDevice,
+    /// The kernel returned a file type unknown to libpathrs.
+    Unknown,
+}
+
+impl FileType {
+    /// Is this file type a regular file?
+    #[inline]
+    pub fn is_file(&self) -> bool {
+        *self == Self::File
+    }
+
+    /// Is this file type a directory?
+    #[inline]
+    pub fn is_dir(&self) -> bool {
+        *self == Self::Directory
+    }
+
+    /// Is this file type a symbolic link?
+    #[inline]
+    pub fn is_symlink(&self) -> bool {
+        *self == Self::Symlink
+    }
+}
+
+impl From<RustixFileType> for FileType {
+    fn from(file_type: RustixFileType) -> Self {
+        match file_type {
+            RustixFileType::RegularFile => Self::File,
+            RustixFileType::Directory => Self::Directory,
+            RustixFileType::Symlink => Self::Symlink,
+            RustixFileType::Fifo => Self::Fifo,
+            RustixFileType::Socket => Self::Socket,
+            RustixFileType::CharacterDevice => Self::CharacterDevice,
+            RustixFileType::BlockDevice => Self::BlockDevice,
+            RustixFileType::Unknown => Self::Unknown,
+        }
+    }
+}
+
+/// Iterator over the entries of a directory within a [`Root`].
+///
+/// This is returned by [`Root::read_dir`]. The iterator holds an
+/// `O_DIRECTORY` handle to the directory that was resolved, so the entries
+/// returned are always from that directory (even if the path is later swapped
+/// with a different directory). The `.` and `..` entries are not returned.
+///
+/// [`Root`]: crate::Root
+/// [`Root::read_dir`]: crate::Root::read_dir
+pub struct ReadDir {
+    dir: Arc<OwnedFd>,
+    iter: Dir,
+}
+
+impl ReadDir {
+    pub(crate) fn new(dir: OwnedFd) -> Result<Self, Error> {
+        let iter = Dir::read_from(&dir).map_err(|err| ErrorImpl::OsError {
+            operation: "create directory iterator".into(),
+            source: err.into(),
+        })?;
+        Ok(Self {
+            dir: Arc::new(dir),
+            iter,
+        })
+    }
+}
+
+impl fmt::Debug for ReadDir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReadDir").field("dir", &self.dir).finish()
+    }
+}
+
+impl Iterator for ReadDir {
+    type Item = Result<DirEntry, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let entry = match self.iter.next()? {
+                Ok(entry) => entry,
+                Err(err) => {
+                    return Some(Err(ErrorImpl::OsError {
+                        operation: "read directory entry".into(),
+                        source: err.into(),
+                    }
+                    .into()))
+                }
+            };
+            let name = entry.file_name().to_bytes();
+            if name == b"." || name == b".." {
+                continue;
+            }
+            return Some(Ok(DirEntry {
+                dir: Arc::clone(&self.dir),
+                name: OsStr::from_bytes(name).to_os_string(),
+                file_type: entry.file_type(),
+            }));
+        }
+    }
+}
+
+/// An entry returned by [`ReadDir`].
+#[derive(Clone, Debug)]
+pub struct DirEntry {
+    dir: Arc<OwnedFd>,
+    name: OsString,
+    file_type: RustixFileType,
+}
+
+impl DirEntry {
+    /// The name of the entry within its directory.
+    #[inline]
+    pub fn file_name(&self) -> OsString {
+        self.name.clone()
+    }
+
+    /// The type of the entry.
+    ///
+    /// This is usually taken from the `d_type` field returned by the kernel
+    /// and so does not require any syscalls. However, some filesystems do not
+    /// fill `d_type` (reporting `DT_UNKNOWN`), in which case the entry is
+    /// `stat`-ed (without following symlinks) relative to the directory handle
+    /// held by the [`ReadDir`]. If the entry was removed since the directory
+    /// was read, an error is returned in that case.
+    pub fn file_type(&self) -> Result<FileType, Error> {
+        match self.file_type {
+            RustixFileType::Unknown => {
+                let stat = syscalls::fstatat(&*self.dir, &self.name).map_err(|err| {
+                    ErrorImpl::RawOsError {
+                        operation: "stat directory entry to get file type".into(),
+                        source: err,
+                    }
+                })?;
+                Ok(RustixFileType::from_raw_mode(stat.st_mode).into())
+            }
+            file_type => Ok(file_type.into()),
+        }
+    }
+}
+
+/// Recursive iterator over the entries of a directory tree within a [`Root`].
+///
+/// This is returned by [`Root::walk`], and is the in-root equivalent of the
+/// [`walkdir`] crate. Entries are returned in depth-first order, with each
+/// directory being returned before its children. The starting directory itself
+/// is not returned.
+///
+/// Subdirectories are opened relative to their parent directory without
+/// following symlinks, so the walk never leaves the [`Root`]. Symlinks to
+/// directories are not walked into unless [`Walk::with_follow_links`] is
+/// enabled. Errors encountered while walking a subtree (such as a
+/// subdirectory that cannot be opened) are returned as `Err` items, and the
+/// walk continues with the rest of the tree.
+///
+/// [`Root`]: crate::Root
+/// [`Root::walk`]: crate::Root::walk
+/// [`walkdir`]: https://docs.rs/walkdir
+pub struct Walk<'fd> {
+    root: RootRef<'fd>,
+    stack: Vec<WalkLevel>,
+    pending_error: Option<Error>,
+    max_depth: usize,
+    follow_links: bool,
+    report_dev: bool,
+}
+
+struct WalkLevel {
+    iter: ReadDir,
+    path: PathBuf,
+    id: (u64, u64),
+}
+
+impl WalkLevel {
+    fn new(dir: OwnedFd, path: PathBuf) -> Result<Self, Error> {
+        let stat = syscalls::fstatat(&dir, "").map_err(|err| ErrorImpl::RawOsError {
+            operation: "stat directory to walk".into(),
+            source: err,
+        })?;
+        Ok(Self {
+            iter: ReadDir::new(dir)?,
+            path,
+            id: (stat.st_dev, stat.st_ino),
+        })
+    }
+}
+
+impl<'fd> Walk<'fd> {
+    pub(crate) fn new(root: RootRef<'fd>, dir: OwnedFd, path: PathBuf) -> Result<Self, Error> {
+        Ok(Self {
+            root,
+            stack: vec![WalkLevel::new(dir, path)?],
+            pending_error: None,
+            max_depth: usize::MAX,
+            follow_links: false,
+            report_dev: false,
+        })
+    }
+
+    /// Set the maximum depth of the walk.
+    ///
+    /// Entries of the starting directory have a depth of 1, so a maximum depth
+    /// of 1 is equivalent to [`Root::read_dir`]. By default there is no limit.
+    /// Bind-mount and symlink loops are always detected (see
+    /// [`Walk::with_follow_links`]), but a limit can still be useful to bound
+    /// the amount of work done on untrusted trees.
+    ///
+    /// [`Root::read_dir`]: crate::Root::read_dir
+    #[inline]
+    pub fn with_max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Set whether symlinks to directories should be walked into.
+    ///
+    /// By default, symlinks are returned as entries but are never walked into.
+    /// If enabled, symlinks are resolved within the [`Root`] (using
+    /// [`Root::resolve`] on the entry's path) and walked into if they resolve
+    /// to a directory. Entries within such directories have paths that go
+    /// through the symlink. The [`DirEntry::file_type`] of the symlink itself
+    /// is still [`FileType::Symlink`]. Dangling symlinks are silently skipped.
+    ///
+    /// If a directory being walked into is the same as one of its ancestors
+    /// (which can happen with symlinks or bind-mounts), an `ELOOP` error is
+    /// returned for that entry and it is not walked into.
+    ///
+    /// [`Root`]: crate::Root
+    /// [`Root::resolve`]: crate::Root::resolve
+    #[inline]
+    pub fn with_follow_links(mut self, follow: bool) -> Self {
+        self.follow_links = follow;
+        self
+    }
+
+    /// Set whether the device number (`st_dev`) of each entry should be
+    /// reported by [`WalkEntry::dev`].
+    ///
+    /// This is disabled by default because it requires an extra `fstatat(2)`
+    /// for every entry that is not a directory being walked into (directories
+    /// are already `stat`-ed to detect loops, so their device number is free).
+    /// Entries are `stat`-ed without following symlinks, so the device number
+    /// of a symlink is that of the filesystem containing the symlink itself.
+    #[inline]
+    pub fn with_dev(mut self, report: bool) -> Self {
+        self.report_dev = report;
+        self
+    }
+
+    /// Consume the walk and partition the returned entries by the filesystem
+    /// (device number) they are on.
+    ///
+    /// This implies [`Walk::with_dev`]. The entries for each device are in the
+    /// order they were returned by the walk. This is useful for operations
+    /// that need to treat each mount within a tree separately (such as
+    /// per-filesystem quota accounting).
+    ///
+    /// # Errors
+    ///
+    /// Unlike iterating over the [`Walk`] directly, the first error
+    /// encountered during the walk is returned and the walk is stopped.
+    pub fn group_by_filesystem(self) -> Result<BTreeMap<u64, Vec<WalkEntry>>, Error> {
+        let mut groups = BTreeMap::<u64, Vec<WalkEntry>>::new();
+        for entry in self.with_dev(true) {
+            let entry = entry?;
+            let dev = entry
+                .dev
+                .expect("walk with dev reporting enabled must set WalkEntry::dev");
+            groups.entry(dev).or_default().push(entry);
+        }
+        Ok(groups)
+    }
+
+    // Open the directory referenced by the entry, if we should walk into it.
+    fn open_subdir(&self, entry: &WalkEntry) -> Result<Option<OwnedFd>, Error> {
+        let dir = match entry.file_type()? {
+            FileType::Directory => syscalls::openat(
+                &*entry.entry.dir,
+                &entry.entry.name,
+                OpenFlags::O_DIRECTORY,
+                0,
+            )
+            .map_err(|err| {
+                ErrorImpl::RawOsError {
+                    operation: "open subdirectory to walk".into(),
+                    source: err,
+                }
+                .into()
+            }),
+            FileType::Symlink if self.follow_links => match self.root.resolve(&entry.path) {
+                Ok(handle) => {
+                    if !handle.metadata()?.is_dir() {
+                        return Ok(None);
+                    }
+                    handle
+                        .reopen(OpenFlags::O_DIRECTORY)
+                        .map(OwnedFd::from)
+                        .wrap("open symlinked directory to walk")
+                }
+                Err(err) => Err(err),
+            },
+            _ => return Ok(None),
+        };
+        match dir {
+            Ok(dir) => Ok(Some(dir)),
+            // The entry was removed since we read the directory (or it is a
+            // dangling symlink), so there is nothing to walk into.
+            Err(err) if err.kind() == ErrorKind::OsError(Some(libc::ENOENT)) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    // Returns the device number of the directory walked into, if any.
+    fn descend(&mut self, entry: &WalkEntry) -> Result<Option<u64>, Error> {
+        if let Some(dir) = self.open_subdir(entry)? {
+            let level = WalkLevel::new(dir, entry.path.clone())?;
+            if self.stack.iter().any(|ancestor| ancestor.id == level.id) {
+                Err(ErrorImpl::OsError {
+                    operation: "walk into directory".into(),
+                    source: IOError::from_raw_os_error(libc::ELOOP),
+                })
+                .with_wrap(|| {
+                    format!(
+                        "{:?} is the same directory as one of its ancestors",
+                        entry.path
+                    )
+                })?
+            }
+            let dev = level.id.0;
+            self.stack.push(level);
+            return Ok(Some(dev));
+        }
+        Ok(None)
+    }
+
+    fn entry_dev(&self, entry: &WalkEntry, walked_dev: Option<u64>) -> Result<u64, Error> {
+        // If we walked into a directory (rather than through a symlink) then
+        // we already have its device number.
+        if let (Some(dev), RustixFileType::Directory) = (walked_dev, entry.entry.file_type) {
+            return Ok(dev);
+        }
+        syscalls::fstatat(&*entry.entry.dir, &entry.entry.name)
+            .map(|stat| stat.st_dev)
+            .map_err(|err| {
+                ErrorImpl::RawOsError {
+                    operation: "stat entry to get device number".into(),
+                    source: err,
+                }
+                .into()
+            })
+    }
+}
+
+impl fmt::Debug for Walk<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Walk")
+            .field("root", &self.root)
+            .field("max_depth", &self.max_depth)
+            .field("follow_links", &self.follow_links)
+            .field("report_dev", &self.report_dev)
+            .finish()
+    }
+}
+
+impl Iterator for Walk<'_> {
+    type Item = Result<WalkEntry, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(err) = self.pending_error.take() {
+            return Some(Err(err));
+        }
+        loop {
+            let depth = self.stack.len();
+            let level = self.stack.last_mut()?;
+            let mut entry = match level.iter.next() {
+                None => {
+                    self.stack.pop();
+                    continue;
+                }
+                // Give up on this directory, since we probably can't make
+                // any more progress reading it.
+                Some(Err(err)) => {
+                    let path = level.path.clone();
+                    self.stack.pop();
+                    return Some(Err(err.wrap(format!("read directory {path:?}"))));
+                }
+                Some(Ok(entry)) => WalkEntry {
+                    path: level.path.join(&entry.name),
+                    depth,
+                    dev: None,
+                    entry,
+                },
+            };
+            let mut walked_dev = None;
+            if depth < self.max_depth {
+                match self.descend(&entry) {
+                    Ok(dev) => walked_dev = dev,
+                    Err(err) => self.pending_error = Some(err),
+                }
+            }
+            if self.report_dev {
+                match self.entry_dev(&entry, walked_dev) {
+                    Ok(dev) => entry.dev = Some(dev),
+                    Err(err) => return Some(Err(err.wrap(format!("walk entry {:?}", entry.path)))),
+                }
+            }
+            return Some(Ok(entry));
+        }
+    }
+}
+
+/// An entry returned by [`Walk`].
+#[derive(Clone, Debug)]
+pub struct WalkEntry {
+    path: PathBuf,
+    depth: usize,
+    dev: Option<u64>,
+    entry: DirEntry,
+}
+
+impl WalkEntry {
+    /// The path of the entry. This is the path passed to [`Root::walk`] joined
+    /// with the names of the entry and its parent directories.
+    ///
+    /// [`Root::walk`]: crate::Root::walk
+    #[inline]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The depth of the entry relative to the starting directory (entries in
+    /// the starting directory have a depth of 1).
+    #[inline]
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// The device number (`st_dev`) of the filesystem containing the entry.
+    ///
+    /// This is only reported if [`Walk::with_dev`] was enabled, otherwise
+    /// `None` is returned.
+    #[inline]
+    pub fn dev(&self) -> Option<u64> {
+        self.dev
+    }
+
+    /// The name of the entry within its directory.
+    #[inline]
+    pub fn file_name(&self) -> OsString {
+        self.entry.file_name()
+    }
+
+    /// The type of the entry. See [`DirEntry::file_type`] for more details.
+    #[inline]
+    pub fn file_type(&self) -> Result<FileType, Error> {
+        self.entry.file_type()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{error::ErrorKind, flags::OpenFlags, tests::common as tests_common, Root};
+
+    use anyhow::Error;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn file_type_unknown_fallback() -> Result<(), Error> {
+        let root_dir = tests_common::create_basic_tree()?;
+        let root = Root::open(&root_dir)?;
+        let dir: OwnedFd = root.resolve("b/c")?.reopen(OpenFlags::O_DIRECTORY)?.into();
+        let dir = Arc::new(dir);
+
+        for (name, expected) in [("file", FileType::File), ("d", FileType::Directory)] {
+            // Emulate a filesystem which returns DT_UNKNOWN.
+            let entry = DirEntry {
+                dir: Arc::clone(&dir),
+                name: name.into(),
+                file_type: RustixFileType::Unknown,
+            };
+            assert_eq!(
+                entry.file_type()?,
+                expected,
+                "DT_UNKNOWN entry {name:?} should fall back to stat"
+            );
+        }
+
+        let entry = DirEntry {
+            dir: Arc::clone(&dir),
+            name: "nonexistent".into(),
+            file_type: RustixFileType::Unknown,
+        };
+        assert_eq!(
+            entry.file_type().map_err(|err| err.kind()),
+            Err(ErrorKind::OsError(Some(libc::ENOENT))),
+            "DT_UNKNOWN entry which no longer exists should fail"
+        );
+        Ok(())
+    }
+}
@@ -22,11 +22,12 @@
 //! Resolver implementations for libpathrs.
 
 use crate::{
-    error::{Error, ErrorImpl, ErrorKind},
-    flags::{OpenFlags, ResolverFlags},
+    error::{Error, ErrorExt, ErrorImpl, ErrorKind},
+    flags::{AbsoluteSymlinkPolicy, OpenFlags, ReopenPolicy, ResolverFlags, SetidPolicy},
+    procfs::{global_procfs_handle, ProcfsFdDir, GLOBAL_PROCFS_HANDLE},
     syscalls,
-    utils::FdExt,
-    Handle,
+    utils::{self, FdExt, PathIterExt},
+    ComponentExplain, EscapeReason, Handle,
 };
 
 use std::{
@@ -35,9 +36,11 @@ use std::{
     os::unix::io::{AsFd, OwnedFd},
     path::{Path, PathBuf},
     rc::Rc,
+    time::{Duration, Instant},
 };
 
 use once_cell::sync::Lazy;
+use rustix::io::Errno;
 
 /// `O_PATH`-based userspace resolver.
 pub(crate) mod opath {
@@ -55,19 +58,29 @@ pub(crate) mod openat2;
 pub(crate) mod procfs;
 
 /// Maximum number of symlink traversals we will accept.
-const MAX_SYMLINK_TRAVERSALS: usize = 128;
+pub(crate) const MAX_SYMLINK_TRAVERSALS: usize = 128;
+
+/// Default maximum number of symlinks walked during a single [`Root`] path
+/// resolution. This matches the kernel's hardcoded `MAXSYMLINKS`.
+///
+/// [`Root`]: crate::Root
+const DEFAULT_MAX_SYMLINK_DEPTH: usize = 40;
 
 /// The backend used for path resolution within a [`Root`] to get a [`Handle`].
 ///
 /// We don't generally recommend specifying this, since libpathrs will
 /// automatically detect the best backend for your platform (which is the value
-/// returned by `Resolver::default`). However, this can be useful for testing.
+/// returned by [`ResolverBackend::default`]). However, this can be useful for
+/// testing, benchmarking and debugging (see [`Root::set_resolver_backend`] and
+/// [`Handle::backend`]).
 ///
 /// [`Root`]: crate::Root
+/// [`Root::set_resolver_backend`]: crate::Root::set_resolver_backend
 /// [`Handle`]: crate::Handle
+/// [`Handle::backend`]: crate::Handle::backend
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 #[non_exhaustive]
-pub(crate) enum ResolverBackend {
+pub enum ResolverBackend {
     /// Use the native `openat2(2)` backend (requires kernel support).
     KernelOpenat2,
     /// Use the userspace "emulated" backend.
@@ -94,8 +107,7 @@ impl Default for ResolverBackend {
 
 impl ResolverBackend {
     /// Checks if the resolver is supported on the current platform.
-    #[cfg(test)]
-    pub(crate) fn supported(self) -> bool {
+    pub fn supported(self) -> bool {
         match self {
             ResolverBackend::KernelOpenat2 => *syscalls::OPENAT2_IS_SUPPORTED,
             ResolverBackend::EmulatedOpath => true,
@@ -103,18 +115,166 @@ impl ResolverBackend {
     }
 }
 
+/// Returns whether the running kernel supports [`openat2(2)`], and thus
+/// whether [`ResolverBackend::KernelOpenat2`] will be used by default.
+///
+/// This is equivalent to [`ResolverBackend::supported`] for
+/// [`ResolverBackend::KernelOpenat2`]. The result is probed once and cached,
+/// and kernels without [`openat2(2)`] support (which return `ENOSYS`) simply
+/// result in `false`.
+///
+/// [`openat2(2)`]: https://man7.org/linux/man-pages/man2/openat2.2.html
+pub fn openat2_supported() -> bool {
+    *syscalls::OPENAT2_IS_SUPPORTED
+}
+
+/// Which [`openat2(2)`] features are supported by the running kernel, as
+/// returned by [`kernel_support`].
+///
+/// [`openat2(2)`]: https://man7.org/linux/man-pages/man2/openat2.2.html
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct KernelSupport {
+    /// Whether [`openat2(2)`] is supported at all (Linux 5.6). If this is
+    /// `false`, all of the other fields are also `false`.
+    ///
+    /// [`openat2(2)`]: https://man7.org/linux/man-pages/man2/openat2.2.html
+    pub openat2: bool,
+    /// Whether `RESOLVE_BENEATH` is supported.
+    pub resolve_beneath: bool,
+    /// Whether `RESOLVE_IN_ROOT` is supported.
+    pub resolve_in_root: bool,
+    /// Whether `RESOLVE_NO_MAGICLINKS` is supported.
+    pub resolve_no_magiclinks: bool,
+    /// Whether `RESOLVE_NO_SYMLINKS` is supported.
+    pub resolve_no_symlinks: bool,
+    /// Whether `RESOLVE_NO_XDEV` is supported.
+    pub resolve_no_xdev: bool,
+    /// Whether `RESOLVE_CACHED` is supported (Linux 5.12).
+    pub resolve_cached: bool,
+}
+
+// MSRV(1.80): Use LazyLock.
+static KERNEL_SUPPORT: Lazy<KernelSupport> = Lazy::new(|| {
+    // Probe relative to "/" rather than the current directory, since the
+    // current directory might have been deleted or be inaccessible (which
+    // would make every flag look unsupported).
+    let rootfd = syscalls::openat(
+        syscalls::AT_FDCWD,
+        "/",
+        OpenFlags::O_PATH | OpenFlags::O_DIRECTORY,
+        0,
+    )
+    .ok();
+    let openat2 = *syscalls::OPENAT2_IS_SUPPORTED;
+    let probe = |resolve: u64| {
+        let rootfd = match rootfd {
+            Some(ref rootfd) => rootfd,
+            None => return false,
+        };
+        let how = syscalls::OpenHow {
+            flags: (libc::O_PATH | libc::O_DIRECTORY) as u64,
+            resolve,
+            ..Default::default()
+        };
+        match syscalls::openat2(rootfd, ".", &how) {
+            Ok(_) => true,
+            // RESOLVE_CACHED returns EAGAIN if the lookup could not be done
+            // using only the dcache, which still means the flag is known.
+            Err(err) => err.errno() == Errno::AGAIN,
+        }
+    };
+    KernelSupport {
+        openat2,
+        resolve_beneath: openat2 && probe(libc::RESOLVE_BENEATH),
+        resolve_in_root: openat2 && probe(libc::RESOLVE_IN_ROOT),
+        resolve_no_magiclinks: openat2 && probe(libc::RESOLVE_NO_MAGICLINKS),
+        resolve_no_symlinks: openat2 && probe(libc::RESOLVE_NO_SYMLINKS),
+        resolve_no_xdev: openat2 && probe(libc::RESOLVE_NO_XDEV),
+        resolve_cached: openat2 && probe(libc::RESOLVE_CACHED),
+    }
+});
+
+/// Returns which [`openat2(2)`] features (including which `RESOLVE_*` flags)
+/// are supported by the running kernel.
+///
+/// This is primarily intended for logging which resolver backend will be used
+/// (see [`openat2_supported`]). The result is probed once and cached.
+///
+/// [`openat2(2)`]: https://man7.org/linux/man-pages/man2/openat2.2.html
+pub fn kernel_support() -> KernelSupport {
+    *KERNEL_SUPPORT
+}
+
+/// The outcome of [`Resolver::classify`].
+pub(crate) enum Classification {
+    /// The path resolved to this handle without escaping the root.
+    Inside(Handle),
+    /// Resolving the path would have escaped the root.
+    Escape {
+        component: PathBuf,
+        reason: EscapeReason,
+    },
+    /// The path does not exist.
+    NotFound,
+}
+
 /// Resolover backend and its associated flags.
 ///
 /// This is the primary structure used to configure how a given [`Root`] will
 /// conduct path resolutions.
 ///
 /// [`Root`]: crate::Root
-#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub struct Resolver {
-    /// Underlying resolution backend used.
-    pub(crate) backend: ResolverBackend,
+    /// Underlying resolution backend explicitly requested by the user. If
+    /// `None`, [`ResolverBackend::default`] is used.
+    pub(crate) backend: Option<ResolverBackend>,
     /// Flags to pass to the resolution backend.
     pub flags: ResolverFlags,
+    /// Maximum wall-clock time permitted for a single path resolution.
+    pub(crate) deadline: Option<Duration>,
+    /// How absolute symlink targets are handled.
+    pub(crate) absolute_symlinks: AbsoluteSymlinkPolicy,
+    /// If set, every component walked through during resolution must be owned
+    /// by this uid.
+    pub(crate) required_owner: Option<u32>,
+    /// Policy for re-opening handles produced by this resolver.
+    pub(crate) reopen_policy: ReopenPolicy,
+    /// How setuid and setgid bits are handled when creating regular files or
+    /// changing their mode.
+    pub(crate) setid_policy: SetidPolicy,
+    /// Maximum number of symlinks walked during a single resolution.
+    pub(crate) max_symlink_depth: usize,
+    /// If set, the path of the root must still be on the mount with this mount
+    /// id before every resolution.
+    pub(crate) expected_mnt_id: Option<u64>,
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self {
+            backend: None,
+            flags: Default::default(),
+            deadline: None,
+            absolute_symlinks: Default::default(),
+            required_owner: None,
+            reopen_policy: Default::default(),
+            setid_policy: Default::default(),
+            max_symlink_depth: DEFAULT_MAX_SYMLINK_DEPTH,
+            expected_mnt_id: None,
+        }
+    }
+}
+
+/// Return a [`ErrorKind::TimedOut`] error if `deadline` has passed.
+pub(crate) fn check_deadline(deadline: Option<Instant>) -> Result<(), Error> {
+    match deadline {
+        Some(deadline) if Instant::now() >= deadline => Err(ErrorImpl::TimedOut {
+            description: "path resolution exceeded the configured resolve deadline".into(),
+        })?,
+        _ => Ok(()),
+    }
 }
 
 /// Only used for internal resolver implementations.
@@ -206,6 +366,81 @@ impl From<PartialLookup<Rc<OwnedFd>>> for PartialLookup<Handle> {
 }
 
 impl Resolver {
+    /// Compute the deadline for a resolution starting now.
+    fn start_deadline(&self) -> Option<Instant> {
+        // If the deadline is so far in the future that it overflows Instant,
+        // it is effectively unlimited.
+        self.deadline
+            .and_then(|timeout| Instant::now().checked_add(timeout))
+    }
+
+    /// If mount id verification is enabled, make sure that the path of the root
+    /// is still on the same mount that the root handle is on. The mount id of
+    /// the root handle itself can never change, so we need to look at the path
+    /// to detect remounts and over-mounts of the root.
+    fn check_root_mnt_id<Fd: AsFd>(&self, root: Fd) -> Result<(), Error> {
+        let expected = match self.expected_mnt_id {
+            Some(mnt_id) => mnt_id,
+            None => return Ok(()),
+        };
+
+        // SAFETY: as_unsafe_path is safe here because we only use the path to
+        //         check whether the root's path is still on the same mount.
+        let root_path = root
+            .as_fd()
+            .as_unsafe_path(&GLOBAL_PROCFS_HANDLE)
+            .wrap("get root path to verify its mount id")?;
+        let mnt_id = utils::fetch_mnt_id(syscalls::AT_FDCWD, &root_path)
+            .wrap("get mount id of root path")?;
+        if mnt_id != Some(expected) {
+            Err(ErrorImpl::SafetyViolation {
+                description: format!(
+                    "root path {root_path:?} mount id changed (expected {expected}, got {mnt_id:?})"
+                )
+                .into(),
+            })?
+        }
+        Ok(())
+    }
+
+    /// Get the backend that will actually be used for resolution. Some
+    /// options cannot be implemented with openat2(2), in which case we need to
+    /// fall back to the emulated backend. If the openat2(2) backend was
+    /// explicitly requested, we return an error instead.
+    fn effective_backend(&self) -> Result<ResolverBackend, Error> {
+        let emulated_feature = if self.flags.contains(ResolverFlags::NO_AUTOMOUNT) {
+            Some("ResolverFlags::NO_AUTOMOUNT")
+        } else if self.absolute_symlinks != AbsoluteSymlinkPolicy::InRoot {
+            Some("an AbsoluteSymlinkPolicy other than InRoot")
+        } else if self.required_owner.is_some() {
+            Some("a required owner")
+        } else {
+            None
+        };
+        match (self.backend, emulated_feature) {
+            (Some(ResolverBackend::KernelOpenat2), Some(feature)) => {
+                Err(ErrorImpl::NotSupported {
+                    feature: format!("openat2 resolver backend with {feature}").into(),
+                })?
+            }
+            (_, Some(_)) => Ok(ResolverBackend::EmulatedOpath),
+            (backend, None) => Ok(backend.unwrap_or_default()),
+        }
+    }
+
+    /// Get the [`ResolverFlags`] to pass to the openat2(2) backend. The kernel
+    /// symlink limit cannot be changed, but a limit of 0 is equivalent to
+    /// [`ResolverFlags::NO_SYMLINKS`].
+    fn openat2_flags(&self) -> Result<ResolverFlags, Error> {
+        match self.max_symlink_depth {
+            DEFAULT_MAX_SYMLINK_DEPTH => Ok(self.flags),
+            0 => Ok(self.flags | ResolverFlags::NO_SYMLINKS),
+            depth => Err(ErrorImpl::NotSupported {
+                feature: format!("openat2 with a maximum symlink depth of {depth}").into(),
+            })?,
+        }
+    }
+
     pub(crate) fn open<Fd: AsFd, P: AsRef<Path>, F: Into<OpenFlags>>(
         &self,
         root: Fd,
@@ -213,6 +448,7 @@ impl Resolver {
         flags: F,
     ) -> Result<File, Error> {
         let flags = flags.into();
+        self.check_root_mnt_id(&root)?;
 
         // O_CREAT cannot be emulated by the O_PATH resolver (and in the
         // fallback case the flag gets silently ignored unless you also set
@@ -224,9 +460,15 @@ impl Resolver {
             })?
         }
 
-        match self.backend {
+        match self.effective_backend()? {
             // openat2 can do the lookup and open in one syscall.
-            ResolverBackend::KernelOpenat2 => openat2::open(root, path.as_ref(), self.flags, flags),
+            ResolverBackend::KernelOpenat2 => {
+                let deadline = self.start_deadline();
+                check_deadline(deadline)?;
+                let file = openat2::open(root, path.as_ref(), self.openat2_flags()?, flags)?;
+                check_deadline(deadline)?;
+                Ok(file)
+            }
 
             // For backends without an accelerated one-shot open()
             // implementation, we can just do the lookup+reopen thing in one go.
@@ -238,7 +480,7 @@ impl Resolver {
                 // FdExt::reopen, we have to support O_NOFOLLOW|O_PATH of
                 // symlinks, but that is easily emulated by returning the handle
                 // directly without a reopen.
-                if handle.metadata()?.is_symlink() {
+                if handle.as_fd().metadata()?.is_symlink() {
                     // If the user also asked for O_DIRECTORY, make sure we
                     // return the right error.
                     if flags.contains(OpenFlags::O_DIRECTORY) {
@@ -266,7 +508,12 @@ impl Resolver {
                     })?;
                 }
 
-                handle.reopen(flags)
+                // Handle::reopen rejects O_PATH, but emulating a one-shot
+                // open with O_PATH is perfectly reasonable.
+                handle
+                    .as_fd()
+                    .reopen(&GLOBAL_PROCFS_HANDLE, flags, self.reopen_policy)
+                    .map(File::from)
             }
         }
     }
@@ -278,14 +525,159 @@ impl Resolver {
         path: P,
         no_follow_trailing: bool,
     ) -> Result<Handle, Error> {
-        match self.backend {
+        self.resolve_with_fd_dir(root, path, no_follow_trailing, None)
+    }
+
+    /// Resolve each of `paths` (in order), amortising the setup cost of the
+    /// emulated backend's `/proc` checks across the whole batch. A failure to
+    /// resolve one path does not stop the others from being resolved.
+    pub(crate) fn resolve_all<Fd: AsFd, I, P>(
+        &self,
+        root: Fd,
+        paths: I,
+        no_follow_trailing: bool,
+    ) -> Vec<Result<Handle, Error>>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        let root = root.as_fd();
+        // Only the emulated backend needs /proc. If we cannot get a handle
+        // here, each resolution will try (and fail) to get its own and so the
+        // error will be reported for each path.
+        let fd_dir = match self.effective_backend() {
+            Ok(ResolverBackend::EmulatedOpath) => global_procfs_handle()
+                .and_then(|procfs| procfs.open_fd_dir())
+                .ok(),
+            // If the backend is not usable, each resolution will return the
+            // error.
+            Ok(ResolverBackend::KernelOpenat2) | Err(_) => None,
+        };
+        paths
+            .into_iter()
+            .map(|path| self.resolve_with_fd_dir(root, path, no_follow_trailing, fd_dir.as_ref()))
+            .collect()
+    }
+
+    fn resolve_with_fd_dir<Fd: AsFd, P: AsRef<Path>>(
+        &self,
+        root: Fd,
+        path: P,
+        no_follow_trailing: bool,
+        fd_dir: Option<&ProcfsFdDir>,
+    ) -> Result<Handle, Error> {
+        self.check_root_mnt_id(&root)?;
+        let backend = self.effective_backend()?;
+        let deadline = self.start_deadline();
+        match backend {
+            // openat2(2) cannot be interrupted part-way through a lookup, so we
+            // can only check the deadline before and after the syscall.
             ResolverBackend::KernelOpenat2 => {
-                openat2::resolve(root, path, self.flags, no_follow_trailing)
+                check_deadline(deadline)?;
+                let handle =
+                    openat2::resolve(root, path, self.openat2_flags()?, no_follow_trailing)?;
+                check_deadline(deadline)?;
+                Ok(handle)
             }
             ResolverBackend::EmulatedOpath => {
-                opath::resolve(root, path, self.flags, no_follow_trailing)
+                let ctx = opath::ResolveContext {
+                    deadline,
+                    fd_dir,
+                    ..Default::default()
+                };
+                opath::resolve(root, path, self, no_follow_trailing, ctx)
             }
         }
+        .map(|handle| {
+            handle
+                .with_backend(backend)
+                .with_reopen_policy(self.reopen_policy)
+        })
+    }
+
+    pub(crate) fn resolve_explain<Fd: AsFd, P: AsRef<Path>>(
+        &self,
+        root: Fd,
+        path: P,
+    ) -> (Result<Handle, Error>, Vec<ComponentExplain>) {
+        // Only the emulated backend can give us per-component information.
+        let mut trace = opath::ResolveTrace::default();
+        let res = self.check_root_mnt_id(&root).and_then(|_| {
+            let ctx = opath::ResolveContext {
+                deadline: self.start_deadline(),
+                trace: Some(&mut trace),
+                ..Default::default()
+            };
+            opath::resolve(root, path, self, false, ctx)
+        });
+        (
+            res.map(|handle| {
+                handle
+                    .with_backend(ResolverBackend::EmulatedOpath)
+                    .with_reopen_policy(self.reopen_policy)
+            }),
+            trace.components,
+        )
+    }
+
+    pub(crate) fn classify<Fd: AsFd, P: AsRef<Path>>(
+        &self,
+        root: Fd,
+        path: P,
+    ) -> Result<Classification, Error> {
+        let root = root.as_fd();
+        let path = path.as_ref();
+        self.check_root_mnt_id(root)?;
+
+        // Any attempt to escape the root (or cross a mount) needs to result in
+        // an error rather than being scoped to the root.
+        let mut strict = *self;
+        strict.flags |= ResolverFlags::NO_ESCAPE_BENEATH | ResolverFlags::NO_CROSS_MOUNT;
+
+        let mut escape = None;
+        let result = match strict.effective_backend()? {
+            // The emulated backend can tell us exactly which component
+            // tried to escape and why.
+            ResolverBackend::EmulatedOpath => {
+                let mut trace = opath::ResolveTrace::default();
+                let ctx = opath::ResolveContext {
+                    deadline: strict.start_deadline(),
+                    trace: Some(&mut trace),
+                    ..Default::default()
+                };
+                let result = opath::resolve(root, path, &strict, false, ctx);
+                escape = trace.escape;
+                result
+            }
+            // openat2(2) only gives us EXDEV, so the best we can do is find
+            // the first component of the path which cannot be resolved.
+            ResolverBackend::KernelOpenat2 => strict.resolve(root, path, false),
+        };
+
+        match result {
+            Ok(handle) => Ok(Classification::Inside(handle)),
+            Err(err) if err.kind() == ErrorKind::OsError(Some(libc::ENOENT)) => {
+                Ok(Classification::NotFound)
+            }
+            Err(err) if err.kind() == ErrorKind::OsError(Some(libc::EXDEV)) => {
+                let (component, reason) = match escape {
+                    Some(escape) => escape,
+                    None if path.is_absolute() => ("/".into(), EscapeReason::AbsolutePath),
+                    None => {
+                        let component = path
+                            .partial_ancestors()
+                            .find(|(ancestor, _)| strict.resolve(root, ancestor, false).is_ok())
+                            .and_then(|(_, remaining)| remaining)
+                            .and_then(|remaining| remaining.raw_components().next())
+                            .map(PathBuf::from)
+                            .unwrap_or_else(|| path.to_path_buf());
+                        (component, EscapeReason::Unknown)
+                    }
+                };
+                Ok(Classification::Escape { component, reason })
+            }
+            Err(err) => Err(err),
+        }
     }
 
     #[inline]
@@ -295,15 +687,44 @@ impl Resolver {
         path: P,
         no_follow_trailing: bool,
     ) -> Result<PartialLookup<Handle>, Error> {
-        match self.backend {
+        self.check_root_mnt_id(&root)?;
+        let backend = self.effective_backend()?;
+        let deadline = self.start_deadline();
+        match backend {
             ResolverBackend::KernelOpenat2 => {
-                openat2::resolve_partial(root, path.as_ref(), self.flags, no_follow_trailing)
+                check_deadline(deadline)?;
+                let lookup = openat2::resolve_partial(
+                    root,
+                    path.as_ref(),
+                    self.openat2_flags()?,
+                    no_follow_trailing,
+                )?;
+                check_deadline(deadline)?;
+                Ok(lookup)
             }
             ResolverBackend::EmulatedOpath => {
-                opath::resolve_partial(root, path.as_ref(), self.flags, no_follow_trailing)
+                opath::resolve_partial(root, path.as_ref(), self, no_follow_trailing, deadline)
                     // Rc<File> -> Handle
                     .map(Into::into)
             }
         }
+        .map(|lookup| match lookup {
+            PartialLookup::Complete(handle) => PartialLookup::Complete(
+                handle
+                    .with_backend(backend)
+                    .with_reopen_policy(self.reopen_policy),
+            ),
+            PartialLookup::Partial {
+                handle,
+                remaining,
+                last_error,
+            } => PartialLookup::Partial {
+                handle: handle
+                    .with_backend(backend)
+                    .with_reopen_policy(self.reopen_policy),
+                remaining,
+                last_error,
+            },
+        })
     }
 }
@@ -38,17 +38,18 @@
 
 use crate::{
     error::{Error, ErrorExt, ErrorImpl},
-    flags::{OpenFlags, ResolverFlags},
-    procfs::GLOBAL_PROCFS_HANDLE,
-    resolvers::{opath::SymlinkStack, PartialLookup, MAX_SYMLINK_TRAVERSALS},
+    flags::{AbsoluteSymlinkPolicy, OpenFlags, ResolverFlags},
+    procfs::{self, ProcfsFdDir, ProcfsHandle, GLOBAL_PROCFS_HANDLE},
+    resolvers::{self, opath::SymlinkStack, PartialLookup, Resolver},
     syscalls,
     utils::{self, FdExt, PathIterExt},
-    Handle,
+    ComponentExplain, EscapeReason, Handle,
 };
 
 use std::{
-    collections::VecDeque,
+    collections::{hash_map::DefaultHasher, VecDeque},
     ffi::{OsStr, OsString},
+    hash::{Hash, Hasher},
     io::Error as IOError,
     iter,
     os::unix::{
@@ -58,13 +59,16 @@ use std::{
     },
     path::{Path, PathBuf},
     rc::Rc,
+    time::Instant,
 };
 
 use itertools::Itertools;
-use once_cell::sync::Lazy;
+use once_cell::{sync::Lazy, unsync::OnceCell};
+use rustix::{fs::Access, io::Errno};
 
 /// Ensure that the expected path within the root matches the current fd.
 fn check_current<RootFd: AsFd, Fd: AsFd, P: AsRef<Path>>(
+    fd_dir: &ProcfsFdDir,
     current: Fd,
     root: RootFd,
     expected: P,
@@ -73,8 +77,8 @@ fn check_current<RootFd: AsFd, Fd: AsFd, P: AsRef<Path>>(
     //         for a string-based check as part of a larger safety setup. This
     //         path will be re-checked after the unsafe "current_path" is
     //         generated.
-    let root_path = root
-        .as_unsafe_path(&GLOBAL_PROCFS_HANDLE)
+    let root_path = fd_dir
+        .readlink_fd(&root)
         .wrap("get root path to construct expected path")?;
 
     // Combine the root path and our expected_path to get the full path to
@@ -98,8 +102,8 @@ fn check_current<RootFd: AsFd, Fd: AsFd, P: AsRef<Path>>(
     // at least one point in time.
     // SAFETY: as_unsafe_path is safe here since we're explicitly doing a
     //         string-based check to see whether the path we want is correct.
-    let current_path = current
-        .as_unsafe_path(&GLOBAL_PROCFS_HANDLE)
+    let current_path = fd_dir
+        .readlink_fd(&current)
         .wrap("check fd against expected path")?;
 
     // The paths should be identical.
@@ -119,8 +123,8 @@ fn check_current<RootFd: AsFd, Fd: AsFd, P: AsRef<Path>>(
     // that allowing roots to be moved by an attacker is a very bad idea.
     // SAFETY: as_unsafe_path path is safe here because it's just used in a
     //         string check -- and it's known that this check isn't perfect.
-    let new_root_path = root
-        .as_unsafe_path(&GLOBAL_PROCFS_HANDLE)
+    let new_root_path = fd_dir
+        .readlink_fd(&root)
         .wrap("get root path to double-check it hasn't moved")?;
     if root_path != new_root_path {
         Err(ErrorImpl::SafetyViolation {
@@ -131,6 +135,26 @@ fn check_current<RootFd: AsFd, Fd: AsFd, P: AsRef<Path>>(
     Ok(())
 }
 
+/// Ensure that a usable `/proc` handle is available.
+///
+/// The safety checks done by the emulated resolver (most notably
+/// [`check_current`]) depend on being able to look at `/proc/self/fd`. If we
+/// cannot get a handle to `/proc` (because it isn't mounted and we cannot
+/// create a new procfs instance) we need to error out rather than silently
+/// doing an unsafe resolution.
+pub(crate) fn require_procfs<P>(procfs: Result<P, Error>) -> Result<P, Error> {
+    procfs.map_err(|err| {
+        Error::from(ErrorImpl::NotSupported {
+            feature: "emulated resolver requires /proc".into(),
+        })
+        .with_wrap(|| {
+            format!(
+                "emulated resolver could not get a /proc handle ({err}), consider using the openat2 resolver backend (Linux 5.6 or later) which does not require /proc"
+            )
+        })
+    })
+}
+
 /// Cached copy of `fs.protected_symlinks` sysctl.
 // TODO: In theory this value could change during the lifetime of the
 // program, but there's no nice way of detecting that, and the overhead of
@@ -172,17 +196,142 @@ fn may_follow_link<DirFd: AsFd, Fd: AsFd>(dir: DirFd, link: Fd) -> Result<(), Er
     }
 }
 
+/// Reconstruct the remaining path of a lookup from the current component and
+/// the set of components left to walk. We can't just use ::collect<PathBuf>
+/// because we might have "" components, which std::path::PathBuf don't like.
+fn remaining_path(part: &OsStr, remaining_components: &VecDeque<OsString>) -> PathBuf {
+    Itertools::intersperse(
+        iter::once(part).chain(remaining_components.iter().map(OsString::as_os_str)),
+        OsStr::new("/"),
+    )
+    .collect::<OsString>()
+    .into()
+}
+
+/// Compute a fingerprint of the state of a lookup when walking into the symlink
+/// described by `link_meta`, used to detect symlink cycles.
+fn symlink_state(
+    link_meta: &utils::Metadata,
+    expected_path: &Path,
+    remaining_components: &VecDeque<OsString>,
+) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (link_meta.dev(), link_meta.ino()).hash(&mut hasher);
+    expected_path.hash(&mut hasher);
+    remaining_components.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Collect the diagnostic information about a component for
+/// `Root::resolve_explain`.
+fn explain_component<Fd: AsFd>(
+    dirfd: Fd,
+    part: &OsStr,
+    path: &Path,
+    meta: &utils::Metadata,
+) -> ComponentExplain {
+    // NOTE: This check is done by name (faccessat2(2) doesn't support
+    // AT_EMPTY_PATH) and so could race against a rename. This is fine because
+    // the result is only used for diagnostics, and it will never follow
+    // symlinks.
+    let search_permitted = if meta.mode() & libc::S_IFMT == libc::S_IFDIR {
+        match syscalls::faccessat(dirfd, part, Access::EXEC_OK) {
+            Ok(()) => Some(true),
+            Err(err) if err.errno() == Errno::ACCESS => Some(false),
+            // faccessat2(2) is not supported (or some other error occurred),
+            // so we can't give an answer.
+            Err(_) => None,
+        }
+    } else {
+        None
+    };
+
+    ComponentExplain {
+        name: part.to_os_string(),
+        path: path.to_path_buf(),
+        mode: meta.mode(),
+        uid: meta.uid(),
+        gid: meta.gid(),
+        search_permitted,
+    }
+}
+
+/// Diagnostic information collected during resolution for
+/// `Root::resolve_explain` and `Root::classify`.
+#[derive(Debug, Default)]
+pub(crate) struct ResolveTrace {
+    /// Every component walked through, in order.
+    pub(crate) components: Vec<ComponentExplain>,
+    /// The component (and the reason) that caused resolution to fail because
+    /// it would have escaped the root (with `NO_ESCAPE_BENEATH`) or crossed a
+    /// mount (with `NO_CROSS_MOUNT`).
+    pub(crate) escape: Option<(PathBuf, EscapeReason)>,
+}
+
+fn record_escape<C: Into<PathBuf>>(
+    trace: &mut Option<&mut ResolveTrace>,
+    component: C,
+    reason: EscapeReason,
+) {
+    if let Some(trace) = trace {
+        trace.escape = Some((component.into(), reason));
+    }
+}
+
+/// Per-lookup state for the emulated resolver, other than the path and the
+/// [`Resolver`] configuration.
+#[derive(Default)]
+pub(crate) struct ResolveContext<'a> {
+    /// The deadline for the whole lookup (see `Resolver::start_deadline`).
+    pub(crate) deadline: Option<Instant>,
+    /// Where to record diagnostic information about the lookup.
+    pub(crate) trace: Option<&'a mut ResolveTrace>,
+    /// A `/proc/thread-self/fd` handle shared by a batch of lookups. If unset,
+    /// a handle is opened for this lookup the first time it is needed.
+    pub(crate) fd_dir: Option<&'a ProcfsFdDir>,
+}
+
+/// Get the `/proc/thread-self/fd` handle for a lookup, either the one provided
+/// by the caller or one opened (only once) on first use.
+fn lazy_fd_dir<'a>(
+    procfs: &ProcfsHandle,
+    fd_dir: Option<&'a ProcfsFdDir>,
+    owned_fd_dir: &'a OnceCell<ProcfsFdDir>,
+) -> Result<&'a ProcfsFdDir, Error> {
+    match fd_dir {
+        Some(fd_dir) => Ok(fd_dir),
+        None => owned_fd_dir.get_or_try_init(|| {
+            procfs
+                .open_fd_dir()
+                .wrap("open /proc/thread-self/fd for resolution")
+        }),
+    }
+}
+
 /// Common implementation used by `resolve_partial()` and `resolve()`. The main
 /// difference is that if `symlink_stack` is `true`, the returned paths
-// TODO: Make (flags, no_follow_trailing, symlink_stack) a single struct to
-//       avoid possible issues with passing a bool to the wrong argument.
+// TODO: Make (no_follow_trailing, symlink_stack) a single struct to avoid
+//       possible issues with passing a bool to the wrong argument.
 fn do_resolve<Fd: AsFd, P: AsRef<Path>>(
     root: Fd,
     path: P,
-    flags: ResolverFlags,
+    resolver: &Resolver,
     no_follow_trailing: bool,
     mut symlink_stack: Option<&mut SymlinkStack<OwnedFd>>,
+    ctx: ResolveContext<'_>,
 ) -> Result<PartialLookup<Rc<OwnedFd>>, Error> {
+    let ResolveContext {
+        deadline,
+        mut trace,
+        fd_dir,
+    } = ctx;
+    let procfs = require_procfs(procfs::global_procfs_handle())?;
+
+    // Reuse the caller's /proc/thread-self/fd handle (for batched lookups) if
+    // we were given one. Otherwise we only open one when we first need it, so
+    // that lookups which fail early don't pay for it.
+    let owned_fd_dir = OnceCell::new();
+
     // What is the final path we expect to get after we do the final open? This
     // allows us to track any attacker moving path components around and we can
     // sanity-check at the very end. This does not include rootpath.
@@ -209,19 +358,49 @@ fn do_resolve<Fd: AsFd, P: AsRef<Path>>(
         .map(|p| p.to_os_string())
         .collect::<VecDeque<_>>();
 
+    // With NO_ESCAPE_BENEATH, absolute paths are rejected outright (like
+    // RESOLVE_BENEATH) rather than being resolved relative to the root.
+    let beneath = resolver.flags.contains(ResolverFlags::NO_ESCAPE_BENEATH);
+    if beneath && path.as_ref().is_absolute() {
+        record_escape(&mut trace, "/", EscapeReason::AbsolutePath);
+        return Ok(PartialLookup::Partial {
+            handle: current,
+            remaining: path.as_ref().to_path_buf(),
+            // Construct a fake OS error containing EXDEV.
+            last_error: ErrorImpl::OsError {
+                operation: "emulated RESOLVE_BENEATH".into(),
+                source: IOError::from_raw_os_error(libc::EXDEV),
+            }
+            .wrap("absolute paths cannot be resolved with NO_ESCAPE_BENEATH")
+            .into(),
+        });
+    }
+
+    // With NO_CROSS_MOUNT, every component must be on the same device as the
+    // root. We cannot see mount boundaries from userspace, so st_dev is the
+    // best approximation we have.
+    let root_dev = if resolver.flags.contains(ResolverFlags::NO_CROSS_MOUNT) {
+        Some(root.as_fd().metadata().wrap("fstat root handle")?.dev())
+    } else {
+        None
+    };
+
+    let no_automount = resolver.flags.contains(ResolverFlags::NO_AUTOMOUNT);
+
+    let max_symlink_depth = resolver.max_symlink_depth;
     let mut symlink_traversals = 0;
+    let mut symlink_states = Vec::new();
+    let mut symlink_cycle = false;
     while let Some(part) = remaining_components.pop_front() {
-        // Stash a copy of the real remaining path. We can't just use
-        // ::collect<PathBuf> because we might have "" components, which
-        // std::path::PathBuf don't like.
-        let remaining: PathBuf = Itertools::intersperse(
-            iter::once(&part)
-                .chain(remaining_components.iter())
-                .map(OsString::as_os_str),
-            OsStr::new("/"),
-        )
-        .collect::<OsString>()
-        .into();
+        // We can't interrupt individual syscalls, so we check the deadline
+        // between each component.
+        resolvers::check_deadline(deadline)?;
+
+        // Stash a copy of the real component, so that we can reconstruct the
+        // real remaining path if we need it. We don't construct the remaining
+        // path here because that would make the walk quadratic in the number
+        // of components.
+        let raw_part = part.clone();
 
         let part = match part.as_bytes() {
             // If we hit an empty component, we need to treat it as though it is
@@ -237,6 +416,22 @@ fn do_resolve<Fd: AsFd, P: AsRef<Path>>(
                 // lexically. If pop() fails, then we are at the root.
                 // should .
                 if !expected_path.pop() {
+                    // With NO_ESCAPE_BENEATH, walking above the root is an
+                    // error rather than being clamped to the root.
+                    if beneath {
+                        record_escape(&mut trace, &part, EscapeReason::DotDot);
+                        return Ok(PartialLookup::Partial {
+                            handle: current,
+                            remaining: remaining_path(&raw_part, &remaining_components),
+                            // Construct a fake OS error containing EXDEV.
+                            last_error: ErrorImpl::OsError {
+                                operation: "emulated RESOLVE_BENEATH".into(),
+                                source: IOError::from_raw_os_error(libc::EXDEV),
+                            }
+                            .wrap("'..' component would escape the root")
+                            .into(),
+                        });
+                    }
                     // If we hit ".." due to the symlink we need to drop it from
                     // the stack like we would if we walked into a real
                     // component. Otherwise walking into ".." will result in a
@@ -281,16 +476,24 @@ fn do_resolve<Fd: AsFd, P: AsRef<Path>>(
             0,
         )
         .map_err(|err| {
-            ErrorImpl::RawOsError {
+            let too_long = err.errno() == Errno::NAMETOOLONG;
+            let err: Error = ErrorImpl::RawOsError {
                 operation: "open next component of resolution".into(),
                 source: err,
             }
-            .into()
+            .into();
+            if too_long {
+                err.wrap(format!(
+                    "component {part:?} is longer than the maximum filename length (NAME_MAX)",
+                ))
+            } else {
+                err
+            }
         }) {
             Err(err) => {
                 return Ok(PartialLookup::Partial {
                     handle: current,
-                    remaining,
+                    remaining: remaining_path(&raw_part, &remaining_components),
                     last_error: err,
                 });
             }
@@ -307,18 +510,85 @@ fn do_resolve<Fd: AsFd, P: AsRef<Path>>(
                 // was a racing rename -- we have to do it every time.
                 if part.as_bytes() == b".." {
                     // MSRV(1.69): Remove &*.
-                    check_current(&next, &*root, &expected_path)
-                        .wrap("check next '..' component didn't escape")?;
+                    check_current(
+                        lazy_fd_dir(procfs, fd_dir, &owned_fd_dir)?,
+                        &next,
+                        &*root,
+                        &expected_path,
+                    )
+                    .wrap("check next '..' component didn't escape")?;
+                }
+
+                let next_meta = next.metadata().wrap("fstat of next component")?;
+
+                // Emulate RESOLVE_NO_XDEV by rejecting components which are on a
+                // different device to the root.
+                if let Some(root_dev) = root_dev {
+                    if next_meta.dev() != root_dev {
+                        record_escape(&mut trace, &part, EscapeReason::MountCrossing);
+                        return Ok(PartialLookup::Partial {
+                            handle: current,
+                            remaining: remaining_path(&raw_part, &remaining_components),
+                            // Construct a fake OS error containing EXDEV.
+                            last_error: ErrorImpl::OsError {
+                                operation: "emulated RESOLVE_NO_XDEV".into(),
+                                source: IOError::from_raw_os_error(libc::EXDEV),
+                            }
+                            .wrap(format!(
+                                "component {part:?} is on a different mount to the root",
+                            ))
+                            .into(),
+                        });
+                    }
+                }
+
+                // Emulate "RESOLVE_NO_AUTOMOUNT". We open each component with
+                // O_PATH (and without O_DIRECTORY) so opening the automount
+                // point itself does not trigger it, but walking into it (or
+                // re-opening the returned handle) would.
+                if no_automount && next_meta.is_dir() && utils::is_automount_point(&next)? {
+                    record_escape(&mut trace, &part, EscapeReason::MountCrossing);
+                    return Ok(PartialLookup::Partial {
+                        handle: current,
+                        remaining: remaining_path(&raw_part, &remaining_components),
+                        // Construct a fake OS error containing EXDEV.
+                        last_error: ErrorImpl::OsError {
+                            operation: "emulated RESOLVE_NO_AUTOMOUNT".into(),
+                            source: IOError::from_raw_os_error(libc::EXDEV),
+                        }
+                        .wrap(format!("component {part:?} is an automount point"))
+                        .into(),
+                    });
+                }
+
+                if let Some(ref mut trace) = trace {
+                    // MSRV(1.69): Remove &*.
+                    trace.components.push(explain_component(
+                        &*current,
+                        &part,
+                        &expected_path,
+                        &next_meta,
+                    ));
                 }
 
                 // Is the next dirfd a symlink or an ordinary path? If we're an
                 // ordinary dirent, we just update current and move on to the
                 // next component. Nothing special here.
-                if !next
-                    .metadata()
-                    .wrap("fstat of next component")?
-                    .is_symlink()
-                {
+                if !next_meta.is_symlink() {
+                    // If the user requires every component we walk through to
+                    // be owned by a specific user, bail if this one isn't.
+                    if let Some(uid) = resolver.required_owner {
+                        if next_meta.uid() != uid {
+                            Err(ErrorImpl::SafetyViolation {
+                                description: format!(
+                                    "component {part:?} is owned by uid {} rather than the required owner uid {uid}",
+                                    next_meta.uid(),
+                                )
+                                .into(),
+                            })?
+                        }
+                    }
+
                     // We hit a non-symlink component, so clear it from the
                     // symlink stack.
                     if let Some(ref mut stack) = symlink_stack {
@@ -340,18 +610,20 @@ fn do_resolve<Fd: AsFd, P: AsRef<Path>>(
                         break;
                     }
 
-                    // Don't continue walking if user asked for no symlinks.
-                    if flags.contains(ResolverFlags::NO_SYMLINKS) {
+                    // Don't continue walking if user asked for no symlinks. A
+                    // maximum symlink depth of 0 is equivalent.
+                    if resolver.flags.contains(ResolverFlags::NO_SYMLINKS) || max_symlink_depth == 0
+                    {
                         return Ok(PartialLookup::Partial {
                             handle: current,
-                            remaining,
+                            remaining: remaining_path(&raw_part, &remaining_components),
                             // Construct a fake OS error containing ELOOP.
                             last_error: ErrorImpl::OsError {
                                 operation: "emulated symlink resolution".into(),
                                 source: IOError::from_raw_os_error(libc::ELOOP),
                             }
                             .wrap(format!(
-                                "component {part:?} is a symlink but symlink resolution is disabled",
+                                "component {part:?} (at {expected_path:?} in root) is a symlink but symlink resolution is disabled",
                             ))
                             .into(),
                         });
@@ -365,19 +637,32 @@ fn do_resolve<Fd: AsFd, P: AsRef<Path>>(
                         )
                     })?;
 
+                    // Keep track of the state of the lookup each time we walk
+                    // into a symlink. Resolution is deterministic (modulo
+                    // racing filesystem changes), so if we ever see the same
+                    // state twice we are in a symlink cycle. This is only used
+                    // to give a better error if we hit the symlink limit.
+                    let state = symlink_state(&next_meta, &expected_path, &remaining_components);
+                    symlink_cycle |= symlink_states.contains(&state);
+                    symlink_states.push(state);
+
                     // We need a limit on the number of symlinks we traverse to
                     // avoid hitting filesystem loops and DoSing.
                     symlink_traversals += 1;
-                    if symlink_traversals >= MAX_SYMLINK_TRAVERSALS {
+                    if symlink_traversals > max_symlink_depth {
                         return Ok(PartialLookup::Partial {
                             handle: current,
-                            remaining,
+                            remaining: remaining_path(&raw_part, &remaining_components),
                             // Construct a fake OS error containing ELOOP.
                             last_error: ErrorImpl::OsError {
                                 operation: "emulated symlink resolution".into(),
                                 source: IOError::from_raw_os_error(libc::ELOOP),
                             }
-                            .wrap("exceeded symlink limit")
+                            .wrap(if symlink_cycle {
+                                format!("symlink cycle detected (walking into component {part:?})")
+                            } else {
+                                format!("exceeded symlink limit ({max_symlink_depth} symlinks)")
+                            })
                             .into(),
                         });
                     }
@@ -388,6 +673,68 @@ fn do_resolve<Fd: AsFd, P: AsRef<Path>>(
                             source: err,
                         })?;
 
+                    // With NO_MAGICLINKS, refuse to walk into anything that
+                    // looks like a magic-link at all (rather than only
+                    // refusing the absolute ones below). In procfs, the
+                    // readlink() of a magic-link is either an absolute path
+                    // (from d_path()) or a "type:[id]"-style dummy path (for
+                    // pipes, sockets, namespaces and so on), while the
+                    // pseudo-magic-links like /proc/self only contain plain
+                    // relative paths. This is only a best-effort heuristic.
+                    if resolver.flags.contains(ResolverFlags::NO_MAGICLINKS)
+                        && (link_target.is_absolute()
+                            || link_target.as_os_str().as_bytes().contains(&b':'))
+                        && next
+                            .is_magiclink_filesystem()
+                            .wrap("check if next is on a dangerous filesystem")?
+                    {
+                        Err(ErrorImpl::SafetyViolation {
+                            description: format!(
+                                "component {part:?} is a potential magic-link (to {link_target:?}) but magic-link resolution is disabled",
+                            )
+                            .into(),
+                        })?
+                    }
+
+                    // Don't continue walking if the user asked for absolute
+                    // symlinks to be rejected.
+                    if link_target.is_absolute()
+                        && resolver.absolute_symlinks == AbsoluteSymlinkPolicy::Error
+                    {
+                        return Ok(PartialLookup::Partial {
+                            handle: current,
+                            remaining: remaining_path(&raw_part, &remaining_components),
+                            // Construct a fake OS error containing ELOOP.
+                            last_error: ErrorImpl::OsError {
+                                operation: "emulated symlink resolution".into(),
+                                source: IOError::from_raw_os_error(libc::ELOOP),
+                            }
+                            .wrap(format!(
+                                "component {part:?} is an absolute symlink but absolute symlinks are disallowed",
+                            ))
+                            .into(),
+                        });
+                    }
+
+                    // Absolute symlinks always jump to the root, which is not
+                    // permitted with NO_ESCAPE_BENEATH.
+                    if link_target.is_absolute() && beneath {
+                        record_escape(&mut trace, &part, EscapeReason::AbsoluteSymlink);
+                        return Ok(PartialLookup::Partial {
+                            handle: current,
+                            remaining: remaining_path(&raw_part, &remaining_components),
+                            // Construct a fake OS error containing EXDEV.
+                            last_error: ErrorImpl::OsError {
+                                operation: "emulated RESOLVE_BENEATH".into(),
+                                source: IOError::from_raw_os_error(libc::EXDEV),
+                            }
+                            .wrap(format!(
+                                "component {part:?} is an absolute symlink which cannot be resolved with NO_ESCAPE_BENEATH",
+                            ))
+                            .into(),
+                        });
+                    }
+
                     // Check if it's a good idea to walk this symlink. If we are on
                     // a filesystem that supports magic-links and we've hit an
                     // absolute symlink, it is incredibly likely that this component
@@ -422,7 +769,11 @@ fn do_resolve<Fd: AsFd, P: AsRef<Path>>(
                     // a new entry for the link target.
                     if let Some(ref mut stack) = symlink_stack {
                         stack
-                            .swap_link(&part, (&current, remaining), link_target.clone())
+                            .swap_link(
+                                &part,
+                                (&current, remaining_path(&raw_part, &remaining_components)),
+                                link_target.clone(),
+                            )
                             .map_err(|err| ErrorImpl::BadSymlinkStackError {
                                 description: "walking into symlink".into(),
                                 source: err,
@@ -450,7 +801,13 @@ fn do_resolve<Fd: AsFd, P: AsRef<Path>>(
 
     // Make sure that the path is what we expect...
     // MSRV(1.69): Remove &*.
-    check_current(&*current, &*root, &expected_path).wrap("check final handle didn't escape")?;
+    check_current(
+        lazy_fd_dir(procfs, fd_dir, &owned_fd_dir)?,
+        &*current,
+        &*root,
+        &expected_path,
+    )
+    .wrap("check final handle didn't escape")?;
 
     // We finished the lookup with no remaining components.
     Ok(PartialLookup::Complete(current))
@@ -461,8 +818,9 @@ fn do_resolve<Fd: AsFd, P: AsRef<Path>>(
 pub(crate) fn resolve_partial<Fd: AsFd, P: AsRef<Path>>(
     root: Fd,
     path: P,
-    flags: ResolverFlags,
+    resolver: &Resolver,
     no_follow_trailing: bool,
+    deadline: Option<Instant>,
 ) -> Result<PartialLookup<Rc<OwnedFd>>, Error> {
     // For partial lookups, we need to use a SymlinkStack to match openat2.
     let mut symlink_stack = SymlinkStack::new();
@@ -470,9 +828,13 @@ pub(crate) fn resolve_partial<Fd: AsFd, P: AsRef<Path>>(
     match do_resolve(
         root,
         path,
-        flags,
+        resolver,
         no_follow_trailing,
         Some(&mut symlink_stack),
+        ResolveContext {
+            deadline,
+            ..Default::default()
+        },
     ) {
         // For complete and error paths, just return what we got.
         ret @ Ok(PartialLookup::Complete(_)) => ret,
@@ -507,8 +869,9 @@ pub(crate) fn resolve_partial<Fd: AsFd, P: AsRef<Path>>(
 pub(crate) fn resolve<Fd: AsFd, P: AsRef<Path>>(
     root: Fd,
     path: P,
-    flags: ResolverFlags,
+    resolver: &Resolver,
     no_follow_trailing: bool,
+    ctx: ResolveContext<'_>,
 ) -> Result<Handle, Error> {
-    do_resolve(root, path, flags, no_follow_trailing, None).and_then(TryInto::try_into)
+    do_resolve(root, path, resolver, no_follow_trailing, None, ctx).and_then(TryInto::try_into)
 }
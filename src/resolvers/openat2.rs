@@ -18,7 +18,7 @@
  */
 
 use crate::{
-    error::{Error, ErrorImpl},
+    error::{Error, ErrorExt, ErrorImpl},
     flags::{OpenFlags, ResolverFlags},
     resolvers::PartialLookup,
     syscalls::{self, OpenHow},
@@ -32,6 +32,42 @@ use std::{
     path::{Path, PathBuf},
 };
 
+/// Convert an `openat2(2)` failure into an [`Error`].
+///
+/// The [`OpenHow`] is included (rendered symbolically) in the operation so that
+/// the exact arguments are visible even if the caller only prints the top-level
+/// error, which makes failures much easier to reproduce.
+fn openat2_error(operation: &str, how: &OpenHow, err: syscalls::Error) -> Error {
+    let is_einval = err.root_cause().raw_os_error() == Some(libc::EINVAL);
+    let err: Error = ErrorImpl::RawOsError {
+        operation: format!("{operation} {how}").into(),
+        source: err,
+    }
+    .into();
+    if is_einval {
+        // The most likely cause of EINVAL is that we passed a flag that this
+        // kernel doesn't know about.
+        err.wrap("openat2 rejected arguments (a RESOLVE_* or O_* flag may be unsupported by this kernel)")
+    } else {
+        err
+    }
+}
+
+/// Compute the `RESOLVE_*` flags to pass to `openat2(2)` for `rflags`.
+fn resolve_flags(rflags: ResolverFlags) -> u64 {
+    // RESOLVE_IN_ROOT and RESOLVE_BENEATH cannot be used together, so
+    // NO_ESCAPE_BENEATH replaces the default RESOLVE_IN_ROOT scoping.
+    let scope = if rflags.contains(ResolverFlags::NO_ESCAPE_BENEATH) {
+        0
+    } else {
+        libc::RESOLVE_IN_ROOT
+    };
+    // NO_AUTOMOUNT is not a RESOLVE_* flag (and resolutions using it never
+    // use openat2), so make sure it never gets passed to the kernel.
+    let rflags = rflags - ResolverFlags::NO_AUTOMOUNT;
+    scope | libc::RESOLVE_NO_MAGICLINKS | rflags.bits()
+}
+
 /// Open `path` within `root` through `openat(2)`.
 ///
 /// This is an optimised version of `resolve(root, path, ...)?.reopen(flags)`.
@@ -47,7 +83,7 @@ pub(crate) fn open<Fd: AsFd, P: AsRef<Path>>(
         })?
     }
 
-    let rflags = libc::RESOLVE_IN_ROOT | libc::RESOLVE_NO_MAGICLINKS | rflags.bits();
+    let rflags = resolve_flags(rflags);
     let how = OpenHow {
         flags: oflags.bits() as u64,
         resolve: rflags,
@@ -56,13 +92,7 @@ pub(crate) fn open<Fd: AsFd, P: AsRef<Path>>(
 
     syscalls::openat2(&root, path.as_ref(), &how)
         .map(File::from)
-        .map_err(|err| {
-            ErrorImpl::RawOsError {
-                operation: "openat2 one-shot open".into(),
-                source: err,
-            }
-            .into()
-        })
+        .map_err(|err| openat2_error("openat2 one-shot open", &how, err))
 }
 
 /// Resolve `path` within `root` through `openat2(2)`.
@@ -83,7 +113,7 @@ pub(crate) fn resolve<Fd: AsFd, P: AsRef<Path>>(
     if no_follow_trailing {
         oflags.insert(OpenFlags::O_NOFOLLOW);
     }
-    let rflags = libc::RESOLVE_IN_ROOT | libc::RESOLVE_NO_MAGICLINKS | rflags.bits();
+    let rflags = resolve_flags(rflags);
 
     let how = OpenHow {
         flags: oflags.bits() as u64,
@@ -108,10 +138,7 @@ pub(crate) fn resolve<Fd: AsFd, P: AsRef<Path>>(
                 Some(libc::EAGAIN) => continue,
                 // TODO: Add wrapper for known-bad openat2 return codes.
                 //Some(libc::EXDEV) | Some(libc::ELOOP) => { ... }
-                _ => Err(ErrorImpl::RawOsError {
-                    operation: "openat2 subpath".into(),
-                    source: err,
-                })?,
+                _ => Err(openat2_error("openat2 subpath", &how, err))?,
             },
         }
     }
@@ -161,3 +188,111 @@ pub(crate) fn resolve_partial<Fd: AsFd>(
 
     unreachable!("partial_ancestors should include root path which must be resolvable");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+
+    use anyhow::Error;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn openat2_error_how() -> Result<(), Error> {
+        if !*syscalls::OPENAT2_IS_SUPPORTED {
+            return Ok(());
+        }
+
+        let root = File::open(".")?;
+        let err = resolve(&root, "nonexistent", ResolverFlags::NO_SYMLINKS, true)
+            .expect_err("resolving a non-existent path should fail");
+        assert_eq!(
+            err.kind(),
+            ErrorKind::OsError(Some(libc::ENOENT)),
+            "unexpected error kind for {err:?}",
+        );
+        let msg = err.to_string();
+        for needle in [
+            "O_PATH",
+            "O_NOFOLLOW",
+            "RESOLVE_IN_ROOT",
+            "RESOLVE_NO_MAGICLINKS",
+            "RESOLVE_NO_SYMLINKS",
+        ] {
+            assert!(
+                msg.contains(needle),
+                "openat2 error should contain {needle:?}: {msg:?}"
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn openat2_error_unknown_flags() -> Result<(), Error> {
+        if !*syscalls::OPENAT2_IS_SUPPORTED {
+            return Ok(());
+        }
+
+        let root = File::open(".")?;
+        let err = resolve(&root, ".", ResolverFlags::from_bits_retain(1 << 40), false)
+            .expect_err("unknown RESOLVE_* flags should be rejected");
+        assert_eq!(
+            err.kind(),
+            ErrorKind::OsError(Some(libc::EINVAL)),
+            "unexpected error kind for {err:?}",
+        );
+        assert!(
+            err.to_string()
+                .contains("may be unsupported by this kernel"),
+            "openat2 EINVAL error should mention unsupported flags: {err}"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn kernel_support() {
+        let support = crate::kernel_support();
+        assert_eq!(
+            support.openat2,
+            crate::openat2_supported(),
+            "kernel_support().openat2 should match openat2_supported()"
+        );
+        if support.openat2 {
+            // These flags were all added along with openat2(2) itself.
+            assert!(
+                support.resolve_beneath,
+                "RESOLVE_BENEATH should be supported"
+            );
+            assert!(
+                support.resolve_in_root,
+                "RESOLVE_IN_ROOT should be supported"
+            );
+            assert!(
+                support.resolve_no_magiclinks,
+                "RESOLVE_NO_MAGICLINKS should be supported"
+            );
+            assert!(
+                support.resolve_no_symlinks,
+                "RESOLVE_NO_SYMLINKS should be supported"
+            );
+            assert!(
+                support.resolve_no_xdev,
+                "RESOLVE_NO_XDEV should be supported"
+            );
+        } else {
+            assert_eq!(
+                support,
+                crate::KernelSupport {
+                    openat2: false,
+                    resolve_beneath: false,
+                    resolve_in_root: false,
+                    resolve_no_magiclinks: false,
+                    resolve_no_symlinks: false,
+                    resolve_no_xdev: false,
+                    resolve_cached: false,
+                },
+                "no RESOLVE_* flags should be supported without openat2"
+            );
+        }
+    }
+}
@@ -20,28 +20,43 @@
 #![forbid(unsafe_code)]
 
 use crate::{
-    error::{Error, ErrorExt, ErrorImpl},
-    flags::{OpenFlags, RenameFlags, ResolverFlags},
-    resolvers::Resolver,
+    error::{Error, ErrorExt, ErrorImpl, ErrorKind},
+    flags::{
+        AbsoluteSymlinkPolicy, AccessMode, OpenFlags, RenameFlags, ReopenPolicy, ResolverFlags,
+        SetidPolicy, StatxMask,
+    },
+    procfs::GLOBAL_PROCFS_HANDLE,
+    resolvers::{Classification, Resolver, ResolverBackend, MAX_SYMLINK_TRAVERSALS},
     syscalls::{self, FrozenFd},
-    utils::{self, PathIterExt},
-    Handle,
+    utils::{self, FdExt, PathIterExt},
+    Component, Handle, ReadDir, Walk,
 };
 
 use std::{
-    fs::{File, Permissions},
-    io::Error as IOError,
+    collections::VecDeque,
+    ffi::{OsStr, OsString},
+    fs::{File, Metadata, Permissions},
+    io::{self, Error as IOError, ErrorKind as IOErrorKind, Read, Write},
+    ops::ControlFlow,
     os::unix::{
         ffi::OsStrExt,
-        fs::PermissionsExt,
+        fs::{MetadataExt, PermissionsExt},
         io::{AsFd, BorrowedFd, OwnedFd},
     },
-    path::{Path, PathBuf},
+    path::{Component as PathComponent, Path, PathBuf},
+    process,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use rustix::{
-    fs::{self as rustix_fs, AtFlags},
+    fs::{
+        self as rustix_fs, Access, AtFlags, FallocateFlags, FlockOperation, SeekFrom,
+        StatVfsMountFlags, Timespec, Timestamps,
+    },
     io::Errno,
+    mount::OpenTreeFlags,
+    thread::CapabilityFlags,
 };
 
 /// An inode type to be created with [`Root::create`].
@@ -94,11 +109,58 @@ pub enum InodeType {
     ///
     /// [`mknod(2)`]: http://man7.org/linux/man-pages/man2/mknod.2.html
     BlockDevice(Permissions, rustix_fs::Dev),
-    // XXX: Does this really make sense?
-    //// "Detached" unix socket, as in [`mknod(2)`] with `S_IFSOCK`.
-    ////
-    //// [`mknod(2)`]: http://man7.org/linux/man-pages/man2/mknod.2.html
-    //DetachedSocket(),
+
+    /// "Detached" unix socket, as in [`mknod(2)`] with `S_IFSOCK`.
+    ///
+    /// The created inode is not bound to any socket, but a process can later
+    /// `bind(2)` a socket to it (after the inode has been removed). This is
+    /// mostly useful for pre-creating socket inodes (with the right mode)
+    /// inside container root filesystems.
+    ///
+    /// [`mknod(2)`]: http://man7.org/linux/man-pages/man2/mknod.2.html
+    Socket(Permissions),
+}
+
+impl InodeType {
+    /// Get the [`InodeType`] that would re-create an inode with the same type,
+    /// mode and device number as the inode described by `meta`.
+    ///
+    /// This is intended for copying trees, where the metadata of an existing
+    /// inode (from [`Root::metadata_nofollow`] or [`std::fs::symlink_metadata`])
+    /// is used to drive [`Root::create`]. All of the permission bits of the
+    /// mode (including the setuid, setgid and sticky bits) are carried through
+    /// unchanged, though note that the mode of the created inode is still
+    /// subject to the umask.
+    ///
+    /// Returns `None` for symlinks (the target needs to be read separately
+    /// with [`Root::readlink`] to create an [`InodeType::Symlink`]) and for
+    /// unknown inode types. [`InodeType::Hardlink`] is never returned.
+    pub fn from_metadata(meta: &Metadata) -> Option<InodeType> {
+        let perm = Permissions::from_mode(meta.mode() & !libc::S_IFMT);
+        match meta.mode() & libc::S_IFMT {
+            libc::S_IFREG => Some(InodeType::File(perm)),
+            libc::S_IFDIR => Some(InodeType::Directory(perm)),
+            libc::S_IFIFO => Some(InodeType::Fifo(perm)),
+            libc::S_IFSOCK => Some(InodeType::Socket(perm)),
+            libc::S_IFCHR => Some(InodeType::CharacterDevice(perm, meta.rdev())),
+            libc::S_IFBLK => Some(InodeType::BlockDevice(perm, meta.rdev())),
+            _ => None,
+        }
+    }
+}
+
+/// Check whether the current thread appears to be able to create device inodes
+/// (such as [`InodeType::CharacterDevice`] and [`InodeType::BlockDevice`]).
+///
+/// This checks whether `CAP_MKNOD` is in the effective capability set of the
+/// calling thread, which is commonly dropped inside containers and other
+/// sandboxes. Note that even with `CAP_MKNOD`, creating device inodes can still
+/// fail (such as inside a user namespace, or due to device cgroup
+/// restrictions), so this should only be treated as a hint.
+pub fn can_mknod() -> bool {
+    syscalls::capget()
+        .map(|caps| caps.effective.contains(CapabilityFlags::MKNOD))
+        .unwrap_or(false)
 }
 
 /// The inode type for [`RootRef::remove_inode`]. This only used internally
@@ -131,6 +193,31 @@ enum RemoveInodeType {
 /// will fail with a `SafetyViolation` error since it's not obvious
 /// whether there is an attacker or if the path was moved innocently. This
 /// restriction might be relaxed in the future.
+///
+/// # Thread Safety
+///
+/// [`Root`] and [`RootRef`] are both [`Send`] and [`Sync`], and it is safe to
+/// call methods that take `&self` (such as [`Root::resolve`]) on the same
+/// [`Root`] from multiple threads concurrently (such as by sharing the [`Root`]
+/// with an [`Arc`][std::sync::Arc]). Each operation only uses file descriptors
+/// it owns (or the immutable [`Root`] file descriptor). The global state used
+/// by libpathrs is all either initialised exactly once and never modified
+/// afterwards, or is internally synchronised:
+///
+/// * The internal `/proc` handle.
+/// * Lazily-computed probes of the running kernel (the supported resolver
+///   backends and `renameat2(2)` flags, and the `fs.protected_symlinks`
+///   sysctl).
+/// * An atomic counter used to generate names for temporary files.
+/// * The error table used by the C API (protected by a mutex).
+///
+/// All `/proc/self/fd`-style operations are done through `/proc/thread-self`,
+/// so they are correct even if threads have different file descriptor tables.
+///
+/// The configuration of a [`Root`] is not shared with [`RootRef`]s created
+/// from it, so it can only be changed with `&mut self`. The one exception to
+/// all of this is [`Root::enter`], which modifies process-wide state (see its
+/// documentation for more details).
 // TODO: Fix the SafetyViolation link once we expose ErrorKind.
 #[derive(Debug)]
 pub struct Root {
@@ -143,6 +230,12 @@ pub struct Root {
     ///
     /// [`resolve`]: Self::resolve
     resolver: Resolver,
+
+    /// The maximum number of bytes [`read`] will read from a file (`None`
+    /// means there is no limit).
+    ///
+    /// [`read`]: Self::read
+    read_limit: Option<u64>,
 }
 
 impl Root {
@@ -151,26 +244,170 @@ impl Root {
     /// The resolver backend used by this handle is chosen at runtime based on
     /// which resolvers are supported by the running kernel.
     ///
+    /// The final component of `path` is never followed if it is a symlink
+    /// (even one pointing to a directory), as a symlinked root location is a
+    /// common source of subtle scoping bugs. Callers must pass the real
+    /// directory, or explicitly opt into following symlinks by using
+    /// [`Root::open_resolving`].
+    ///
     /// # Errors
     ///
     /// `path` must be an existing directory, and must (at the moment) be a
     /// fully-resolved pathname with no symlink components. This restriction
-    /// might be relaxed in the future.
+    /// might be relaxed in the future. If you need to open a root directory
+    /// whose path contains symlinks, use [`Root::open_resolving`].
+    ///
+    /// If the final component of `path` is a symlink, `ELOOP` is returned
+    /// (mirroring `O_NOFOLLOW`). If `path` is not a directory, `ENOTDIR` is
+    /// returned.
     #[doc(alias = "pathrs_open_root")]
+    #[doc(alias = "open_nofollow")]
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
-        let file = syscalls::openat(
-            syscalls::AT_FDCWD,
-            path,
-            OpenFlags::O_PATH | OpenFlags::O_DIRECTORY,
-            0,
-        )
-        .map_err(|err| ErrorImpl::RawOsError {
-            operation: "open root handle".into(),
-            source: err,
-        })?;
+        // We cannot use O_DIRECTORY here, because O_PATH|O_NOFOLLOW on a
+        // symlink will give us a handle to the symlink itself (which would
+        // result in an unhelpful ENOTDIR rather than ELOOP).
+        let file =
+            syscalls::openat(syscalls::AT_FDCWD, path, OpenFlags::O_PATH, 0).map_err(|err| {
+                ErrorImpl::RawOsError {
+                    operation: "open root handle".into(),
+                    source: err,
+                }
+            })?;
+
+        let meta = file.metadata()?;
+        if meta.is_symlink() {
+            Err(ErrorImpl::OsError {
+                operation: "open root handle with trailing symlink".into(),
+                source: IOError::from_raw_os_error(libc::ELOOP),
+            })?
+        }
+        if !meta.is_dir() {
+            Err(ErrorImpl::OsError {
+                operation: "open root handle".into(),
+                source: IOError::from_raw_os_error(libc::ENOTDIR),
+            })?
+        }
         Ok(Self::from_fd(file))
     }
 
+    /// Open a [`Root`] handle, following any symlink components in `path`.
+    ///
+    /// Unlike [`Root::open`], `path` may contain symlink components (including
+    /// a trailing symlink). The path is walked component-by-component with
+    /// `O_PATH|O_NOFOLLOW`, and any symlinks are followed in the *host* mount
+    /// namespace (absolute symlinks are resolved relative to the host `/` and
+    /// `..` components are permitted to move above any directory).
+    ///
+    /// **This is not a scoped lookup**, and so `path` (and every symlink
+    /// within it) must be trusted. The scoped resolver only applies to
+    /// operations done on the returned [`Root`], once the root directory has
+    /// been opened.
+    ///
+    /// The resolver backend used by this handle is chosen at runtime based on
+    /// which resolvers are supported by the running kernel.
+    ///
+    /// # Errors
+    ///
+    /// `path` must resolve to an existing directory. If the final component is
+    /// not a directory, `ENOTDIR` is returned. If too many symlinks are
+    /// encountered during the walk, `ELOOP` is returned.
+    pub fn open_resolving<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        if path.as_os_str().is_empty() {
+            Err(ErrorImpl::InvalidArgument {
+                name: "path".into(),
+                description: "root path cannot be empty".into(),
+            })?
+        }
+
+        let open_dir = |dir: &str| -> Result<OwnedFd, Error> {
+            syscalls::openat(
+                syscalls::AT_FDCWD,
+                dir,
+                OpenFlags::O_PATH | OpenFlags::O_DIRECTORY,
+                0,
+            )
+            .map_err(|err| {
+                ErrorImpl::RawOsError {
+                    operation: "open starting point of root path walk".into(),
+                    source: err,
+                }
+                .into()
+            })
+        };
+
+        let mut current = open_dir(if path.is_absolute() { "/" } else { "." })?;
+        let mut remaining_components = path
+            .raw_components()
+            .map(|p| p.to_os_string())
+            .collect::<VecDeque<_>>();
+
+        let mut symlink_traversals = 0;
+        while let Some(part) = remaining_components
+            .pop_front()
+            // Empty components (from leading, trailing or repeated "/"s) are
+            // equivalent to ".".
+            .map(|part| if part.is_empty() { ".".into() } else { part })
+        {
+            let next = syscalls::openat(
+                &current,
+                &part,
+                OpenFlags::O_PATH | OpenFlags::O_NOFOLLOW,
+                0,
+            )
+            .map_err(|err| ErrorImpl::RawOsError {
+                operation: format!("open root path component {part:?}").into(),
+                source: err,
+            })?;
+
+            let next_meta = next.metadata().wrap("fstat of next component")?;
+            if !next_meta.is_symlink() {
+                current = next;
+                continue;
+            }
+
+            // We need a limit on the number of symlinks we traverse to avoid
+            // hitting filesystem loops.
+            symlink_traversals += 1;
+            if symlink_traversals > MAX_SYMLINK_TRAVERSALS {
+                Err(ErrorImpl::OsError {
+                    operation: "follow root path symlinks".into(),
+                    source: IOError::from_raw_os_error(libc::ELOOP),
+                })
+                .wrap("exceeded symlink limit")?
+            }
+
+            let link_target =
+                syscalls::readlinkat(&next, "").map_err(|err| ErrorImpl::RawOsError {
+                    operation: format!("readlink root path component {part:?}").into(),
+                    source: err,
+                })?;
+
+            // Absolute symlinks are resolved relative to the host root, since
+            // this is a trusted lookup.
+            if link_target.is_absolute() {
+                current = open_dir("/")?;
+            }
+            link_target
+                .raw_components()
+                .prepend(&mut remaining_components);
+        }
+
+        if !current
+            .metadata()
+            .wrap("fstat of final component")?
+            .is_dir()
+        {
+            Err(ErrorImpl::OsError {
+                operation: "open root handle".into(),
+                source: IOError::from_raw_os_error(libc::ENOTDIR),
+            })
+            .with_wrap(|| format!("root path {path:?} is not a directory"))?
+        }
+
+        Ok(Self::from_fd(current))
+    }
+
     /// Wrap an [`OwnedFd`] into a [`Root`].
     ///
     /// The [`OwnedFd`] should be a file descriptor referencing a directory,
@@ -183,9 +420,18 @@ impl Root {
         Self {
             inner: fd.into(),
             resolver: Default::default(),
+            read_limit: None,
         }
     }
 
+    /// Create a [`RootBuilder`] to configure a [`Root`] before opening it.
+    ///
+    /// See [`RootBuilder`] for more details.
+    #[inline]
+    pub fn builder() -> RootBuilder {
+        RootBuilder::new()
+    }
+
     /// Borrow this [`Root`] as a [`RootRef`].
     ///
     /// The [`ResolverFlags`] of the [`Root`] are inherited by the [`RootRef`]
@@ -201,6 +447,7 @@ impl Root {
         RootRef {
             inner: self.as_fd(),
             resolver: self.resolver,
+            read_limit: self.read_limit,
         }
     }
 
@@ -246,6 +493,339 @@ impl Root {
         self
     }
 
+    /// Get the current resolution deadline for this [`Root`].
+    #[inline]
+    pub fn resolve_deadline(&self) -> Option<Duration> {
+        self.resolver.deadline
+    }
+
+    /// Set the maximum wall-clock time permitted for each path resolution
+    /// done by this [`Root`] (`None` means there is no limit).
+    ///
+    /// If a path resolution takes longer than this, the operation is aborted
+    /// with [`ErrorKind::TimedOut`]. This is intended to protect services with
+    /// latency requirements against pathological inputs (such as paths with
+    /// very large numbers of components or symlinks) on slow storage.
+    ///
+    /// The check is coarse: the emulated resolver checks the deadline between
+    /// each path component, while the `openat2(2)` resolver can only check the
+    /// deadline before and after the (uninterruptible) `openat2(2)` syscall.
+    /// Individual syscalls are never interrupted, so the deadline may be
+    /// overrun by the duration of a single (possibly slow) syscall.
+    ///
+    /// As with [`Root::set_resolver_flags`], this only affects this instance of
+    /// [`Root`] and not any existing [`RootRef`]s.
+    ///
+    /// [`ErrorKind::TimedOut`]: crate::error::ErrorKind::TimedOut
+    #[inline]
+    pub fn set_resolve_deadline(&mut self, deadline: Option<Duration>) -> &mut Self {
+        self.resolver.deadline = deadline;
+        self
+    }
+
+    /// Set the maximum wall-clock time permitted for each path resolution
+    /// done by this [`Root`].
+    ///
+    /// This is identical to [`Root::set_resolve_deadline`] except that it can
+    /// more easily be used with chaining.
+    #[inline]
+    pub fn with_resolve_deadline(mut self, deadline: Option<Duration>) -> Self {
+        self.set_resolve_deadline(deadline);
+        self
+    }
+
+    /// Get the current maximum symlink depth for this [`Root`].
+    #[inline]
+    pub fn max_symlink_depth(&self) -> usize {
+        self.resolver.max_symlink_depth
+    }
+
+    /// Set the maximum number of symlinks that can be walked during each path
+    /// resolution done by this [`Root`].
+    ///
+    /// Walking into more symlinks than this during a single path resolution
+    /// results in an `ELOOP` error. The default is 40, which matches the
+    /// (hardcoded) limit used by the kernel. A limit of 0 is equivalent to
+    /// [`ResolverFlags::NO_SYMLINKS`].
+    ///
+    /// The limit used by `openat2(2)` cannot be changed, so setting any limit
+    /// other than the default or 0 requires the [`ResolverBackend::EmulatedOpath`]
+    /// backend. Operations using [`ResolverBackend::KernelOpenat2`] with such a
+    /// limit will fail with [`ErrorKind::NotSupported`].
+    ///
+    /// As with [`Root::set_resolver_flags`], this only affects this instance of
+    /// [`Root`] and not any existing [`RootRef`]s.
+    ///
+    /// [`ErrorKind::NotSupported`]: crate::error::ErrorKind::NotSupported
+    #[inline]
+    pub fn set_max_symlink_depth(&mut self, depth: usize) -> &mut Self {
+        self.resolver.max_symlink_depth = depth;
+        self
+    }
+
+    /// Set the maximum number of symlinks that can be walked during each path
+    /// resolution done by this [`Root`].
+    ///
+    /// This is identical to [`Root::set_max_symlink_depth`] except that it can
+    /// more easily be used with chaining.
+    #[inline]
+    pub fn with_max_symlink_depth(mut self, depth: usize) -> Self {
+        self.set_max_symlink_depth(depth);
+        self
+    }
+
+    /// Get the current [`AbsoluteSymlinkPolicy`] for this [`Root`].
+    #[inline]
+    pub fn absolute_symlink_policy(&self) -> AbsoluteSymlinkPolicy {
+        self.resolver.absolute_symlinks
+    }
+
+    /// Set the [`AbsoluteSymlinkPolicy`] for all operations in this [`Root`].
+    ///
+    /// The default is [`AbsoluteSymlinkPolicy::InRoot`]. Note that some
+    /// policies are not supported by all resolver backends, in which case a
+    /// different resolver backend will be used (see [`AbsoluteSymlinkPolicy`]
+    /// for more details).
+    ///
+    /// As with [`Root::set_resolver_flags`], this only affects this instance of
+    /// [`Root`] and not any existing [`RootRef`]s.
+    #[inline]
+    pub fn set_absolute_symlink_policy(&mut self, policy: AbsoluteSymlinkPolicy) -> &mut Self {
+        self.resolver.absolute_symlinks = policy;
+        self
+    }
+
+    /// Set the [`AbsoluteSymlinkPolicy`] for all operations in this [`Root`].
+    ///
+    /// This is identical to [`Root::set_absolute_symlink_policy`] except that
+    /// it can more easily be used with chaining.
+    #[inline]
+    pub fn with_absolute_symlink_policy(mut self, policy: AbsoluteSymlinkPolicy) -> Self {
+        self.set_absolute_symlink_policy(policy);
+        self
+    }
+
+    /// Get the current [`ReopenPolicy`] for this [`Root`].
+    #[inline]
+    pub fn reopen_policy(&self) -> ReopenPolicy {
+        self.resolver.reopen_policy
+    }
+
+    /// Set the [`ReopenPolicy`] for [`Handle`]s produced by this [`Root`].
+    ///
+    /// All [`Handle`]s returned by path resolution within this [`Root`] (such
+    /// as from [`Root::resolve`]) will use this policy for [`Handle::reopen`],
+    /// as will [`Root::open_subpath`] when using the emulated resolver backend
+    /// (the `openat2(2)` resolver backend opens files directly and does not
+    /// need to re-open a handle). The default policy matches the historical
+    /// behaviour of [`Handle::reopen`].
+    ///
+    /// As with [`Root::set_resolver_flags`], this only affects this instance of
+    /// [`Root`] and not any existing [`RootRef`]s.
+    #[inline]
+    pub fn set_reopen_policy(&mut self, policy: ReopenPolicy) -> &mut Self {
+        self.resolver.reopen_policy = policy;
+        self
+    }
+
+    /// Set the [`ReopenPolicy`] for [`Handle`]s produced by this [`Root`].
+    ///
+    /// This is identical to [`Root::set_reopen_policy`] except that it can
+    /// more easily be used with chaining.
+    #[inline]
+    pub fn with_reopen_policy(mut self, policy: ReopenPolicy) -> Self {
+        self.set_reopen_policy(policy);
+        self
+    }
+
+    /// Get the current [`SetidPolicy`] for this [`Root`].
+    #[inline]
+    pub fn setid_policy(&self) -> SetidPolicy {
+        self.resolver.setid_policy
+    }
+
+    /// Set the [`SetidPolicy`] for regular files created (or whose mode is
+    /// changed) by this [`Root`].
+    ///
+    /// The default is [`SetidPolicy::Allow`], so that modes are applied
+    /// faithfully (as is necessary when extracting trusted archives). Programs
+    /// handling untrusted input can instead use [`SetidPolicy::Strip`] or
+    /// [`SetidPolicy::Error`] to make sure no setuid or setgid files are
+    /// created, without having to check the mode passed to every operation.
+    /// See [`SetidPolicy`] for the list of affected operations, and how this
+    /// interacts with [`Root::mkdir_all`].
+    ///
+    /// As with [`Root::set_resolver_flags`], this only affects this instance of
+    /// [`Root`] and not any existing [`RootRef`]s.
+    #[inline]
+    pub fn set_setid_policy(&mut self, policy: SetidPolicy) -> &mut Self {
+        self.resolver.setid_policy = policy;
+        self
+    }
+
+    /// Set the [`SetidPolicy`] for regular files created (or whose mode is
+    /// changed) by this [`Root`].
+    ///
+    /// This is identical to [`Root::set_setid_policy`] except that it can
+    /// more easily be used with chaining.
+    #[inline]
+    pub fn with_setid_policy(mut self, policy: SetidPolicy) -> Self {
+        self.set_setid_policy(policy);
+        self
+    }
+
+    /// Forbid (or permit) the creation of setuid and setgid files by this
+    /// [`Root`].
+    ///
+    /// This is shorthand for [`Root::set_setid_policy`] with
+    /// [`SetidPolicy::Error`] (if `forbid` is `true`) or
+    /// [`SetidPolicy::Allow`] (if `forbid` is `false`). Use
+    /// [`SetidPolicy::Strip`] to silently clear the bits instead.
+    #[inline]
+    pub fn forbid_suid_sgid(&mut self, forbid: bool) -> &mut Self {
+        self.set_setid_policy(if forbid {
+            SetidPolicy::Error
+        } else {
+            SetidPolicy::Allow
+        })
+    }
+
+    /// Get the current read limit for this [`Root`].
+    #[inline]
+    pub fn read_limit(&self) -> Option<u64> {
+        self.read_limit
+    }
+
+    /// Set the maximum number of bytes that [`Root::read`] and
+    /// [`Root::read_to_string`] will read from a file (`None` means there is
+    /// no limit).
+    ///
+    /// If a file contains more data than the limit, the read is aborted with
+    /// [`ErrorKind::LimitExceeded`]. The limit is checked while reading (not
+    /// against the file size reported by `fstat(2)`), so it also applies to
+    /// pseudo-filesystem files, FIFOs and files which are being appended to
+    /// concurrently. This is intended to protect services reading files from
+    /// untrusted directories against being made to allocate unbounded amounts
+    /// of memory.
+    ///
+    /// As with [`Root::set_resolver_flags`], this only affects this instance of
+    /// [`Root`] and not any existing [`RootRef`]s.
+    ///
+    /// [`ErrorKind::LimitExceeded`]: crate::error::ErrorKind::LimitExceeded
+    #[inline]
+    pub fn set_read_limit(&mut self, limit: Option<u64>) -> &mut Self {
+        self.read_limit = limit;
+        self
+    }
+
+    /// Set the maximum number of bytes that [`Root::read`] and
+    /// [`Root::read_to_string`] will read from a file.
+    ///
+    /// This is identical to [`Root::set_read_limit`] except that it can more
+    /// easily be used with chaining.
+    #[inline]
+    pub fn with_read_limit(mut self, limit: Option<u64>) -> Self {
+        self.set_read_limit(limit);
+        self
+    }
+
+    /// Get the [`ResolverBackend`] used by this [`Root`] for path resolution.
+    #[inline]
+    pub fn resolver_backend(&self) -> ResolverBackend {
+        self.resolver.backend.unwrap_or_default()
+    }
+
+    /// Set the [`ResolverBackend`] used by this [`Root`] for path resolution.
+    ///
+    /// By default, the best backend supported by the running kernel is used
+    /// (see [`ResolverBackend::default`]) and it is generally not necessary to
+    /// change this. However, forcing a particular backend can be useful for
+    /// benchmarking, testing, or working around kernel bugs.
+    ///
+    /// Note that no fallback is done if the requested backend is not supported
+    /// by the running kernel (see [`ResolverBackend::supported`]) --
+    /// operations which need to resolve paths will instead return an error of
+    /// kind [`ErrorKind::NotSupported`].
+    ///
+    /// Some options can only be implemented by
+    /// [`ResolverBackend::EmulatedOpath`] ([`ResolverFlags::NO_AUTOMOUNT`],
+    /// [`AbsoluteSymlinkPolicy`]s other than [`AbsoluteSymlinkPolicy::InRoot`]
+    /// and [`Root::resolve_require_owner`]). If no backend has been set, these
+    /// options cause the emulated backend to be used. However, if
+    /// [`ResolverBackend::KernelOpenat2`] was explicitly requested, operations
+    /// using these options also return an error of kind
+    /// [`ErrorKind::NotSupported`] rather than silently using a different
+    /// backend.
+    #[inline]
+    pub fn set_resolver_backend(&mut self, backend: ResolverBackend) -> &mut Self {
+        self.resolver.backend = Some(backend);
+        self
+    }
+
+    /// Set the [`ResolverBackend`] used by this [`Root`] for path resolution.
+    ///
+    /// This is identical to [`Root::set_resolver_backend`] except that it can
+    /// more easily be used with chaining.
+    #[inline]
+    pub fn with_resolver_backend(mut self, backend: ResolverBackend) -> Self {
+        self.set_resolver_backend(backend);
+        self
+    }
+
+    /// Get whether this [`Root`] verifies the mount id of the root directory's
+    /// path before each operation.
+    #[inline]
+    pub fn verify_mount_id(&self) -> bool {
+        self.resolver.expected_mnt_id.is_some()
+    }
+
+    /// Set whether every operation done by this [`Root`] should verify that
+    /// the path of the root directory is still on the same mount as the
+    /// [`Root`] handle.
+    ///
+    /// A [`Root`] handle always references the mount it was opened on (see
+    /// [`Root::mount_id`]), which means that if the root directory is
+    /// remounted or over-mounted (for instance, when a network filesystem is
+    /// remounted) operations will silently continue to use the old mount. When
+    /// this verification is enabled, each operation first looks up the mount
+    /// id of the root directory's current path and returns an
+    /// [`ErrorKind::SafetyViolation`] if it doesn't match [`Root::mount_id`].
+    /// This is stricter than the existing detection of the root being moved
+    /// during a lookup, because it also detects remounts where the root
+    /// directory inode stays the same.
+    ///
+    /// This check requires an extra `readlink(2)` of `/proc/self/fd/$n` and a
+    /// `statx(2)` for every operation, so it is disabled by default. Like the
+    /// existing "root moved" check, an attacker that can rename the root
+    /// directory could (in theory) race with this check.
+    ///
+    /// # Errors
+    ///
+    /// Enabling verification requires `STATX_MNT_ID` support (Linux 5.8 or
+    /// later). If it is not supported, an [`ErrorKind::NotSupported`] error is
+    /// returned and the configuration is left unchanged.
+    ///
+    /// As with [`Root::set_resolver_flags`], this only affects this instance of
+    /// [`Root`] and not any existing [`RootRef`]s.
+    ///
+    /// [`ErrorKind::SafetyViolation`]: crate::error::ErrorKind::SafetyViolation
+    /// [`ErrorKind::NotSupported`]: crate::error::ErrorKind::NotSupported
+    pub fn set_verify_mount_id(&mut self, verify: bool) -> Result<&mut Self, Error> {
+        self.resolver.expected_mnt_id = if verify { Some(self.mount_id()?) } else { None };
+        Ok(self)
+    }
+
+    /// Set whether every operation done by this [`Root`] should verify that
+    /// the path of the root directory is still on the same mount.
+    ///
+    /// This is identical to [`Root::set_verify_mount_id`] except that it can
+    /// more easily be used with chaining.
+    #[inline]
+    pub fn with_verify_mount_id(mut self, verify: bool) -> Result<Self, Error> {
+        self.set_verify_mount_id(verify)?;
+        Ok(self)
+    }
+
     /// Create a copy of an existing [`Root`].
     ///
     /// The new handle is completely independent from the original, but
@@ -255,6 +835,76 @@ impl Root {
         self.as_ref().try_clone()
     }
 
+    /// Check whether this [`Root`] and `other` reference the same directory.
+    ///
+    /// See [`RootRef::same_inode_as`] for more details.
+    #[inline]
+    pub fn same_inode_as(&self, other: &Root) -> Result<bool, Error> {
+        self.as_ref().same_inode_as(&other.as_ref())
+    }
+
+    /// Get the current path of the [`Root`] directory, for diagnostic purposes
+    /// only.
+    ///
+    /// See [`RootRef::as_frozen_path`] for more details.
+    #[inline]
+    pub fn as_frozen_path(&self) -> Result<PathBuf, Error> {
+        self.as_ref().as_frozen_path()
+    }
+
+    /// Create a new [`Root`] on a detached copy of this [`Root`]'s mount
+    /// tree.
+    ///
+    /// See [`RootRef::pin_mount`] for more details.
+    #[inline]
+    pub fn pin_mount(&self) -> Result<Root, Error> {
+        self.as_ref().pin_mount()
+    }
+
+    /// Resolve the directory `path` within the [`Root`]'s tree and return a
+    /// new [`Root`] rooted at that directory.
+    ///
+    /// See [`RootRef::sub_root`] for more details.
+    #[inline]
+    pub fn sub_root<P: AsRef<Path>>(&self, path: P) -> Result<Root, Error> {
+        self.as_ref().sub_root(path)
+    }
+
+    /// Get a [`Handle`] to the root directory itself.
+    ///
+    /// See [`RootRef::root_handle`] for more details.
+    #[inline]
+    pub fn root_handle(&self) -> Result<Handle, Error> {
+        self.as_ref().root_handle()
+    }
+
+    /// Change the current working directory of the process to the [`Root`],
+    /// returning a [`RootGuard`] which will restore the previous working
+    /// directory when dropped.
+    ///
+    /// See [`RootRef::enter`] for more details.
+    #[inline]
+    pub fn enter(&self) -> Result<RootGuard, Error> {
+        self.as_ref().enter()
+    }
+
+    /// Check whether the mount containing the [`Root`] is currently mounted
+    /// read-only.
+    ///
+    /// See [`RootRef::mount_is_readonly`] for more details.
+    #[inline]
+    pub fn mount_is_readonly(&self) -> Result<bool, Error> {
+        self.as_ref().mount_is_readonly()
+    }
+
+    /// Get the mount id of the mount containing the [`Root`].
+    ///
+    /// See [`RootRef::mount_id`] for more details.
+    #[inline]
+    pub fn mount_id(&self) -> Result<u64, Error> {
+        self.as_ref().mount_id()
+    }
+
     /// Within the given [`Root`]'s tree, resolve `path` and return a
     /// [`Handle`].
     ///
@@ -292,14 +942,127 @@ impl Root {
         self.as_ref().resolve_nofollow(path)
     }
 
-    /// Open a path without creating an intermediate [`Handle`] object.
-    ///
-    /// This is effectively just shorthand for [`resolve`] followed by
-    /// [`Handle::reopen`]. However, some resolvers (such as the `openat2`
-    /// resolver) can implement [`open_subpath`] slightly more efficiently than
-    /// naively doing a two-step open operation with [`Handle::reopen`]. If you
-    /// wish to create an [`OpenFlags::O_PATH`] file handle, it probably makes
-    /// more sense to use [`resolve`] or [`resolve_nofollow`].
+    /// Within the given [`Root`]'s tree, resolve each of `paths` and return
+    /// the results in the same order.
+    ///
+    /// See [`RootRef::resolve_all`] for more details.
+    #[inline]
+    pub fn resolve_all<I, P>(&self, paths: I) -> Vec<Result<Handle, Error>>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        self.as_ref().resolve_all(paths)
+    }
+
+    /// Resolve `path` both with and without following a trailing symlink.
+    ///
+    /// See [`RootRef::resolve_both`] for more details.
+    #[inline]
+    pub fn resolve_both<P: AsRef<Path>>(&self, path: P) -> Result<(Handle, Option<Handle>), Error> {
+        self.as_ref().resolve_both(path)
+    }
+
+    /// Within the given [`Root`]'s tree, resolve `path` and return a
+    /// [`Handle`], requiring every component to be owned by `uid`.
+    ///
+    /// See [`RootRef::resolve_require_owner`] for more details.
+    #[inline]
+    pub fn resolve_require_owner<P: AsRef<Path>>(
+        &self,
+        path: P,
+        uid: u32,
+    ) -> Result<Handle, Error> {
+        self.as_ref().resolve_require_owner(path, uid)
+    }
+
+    /// Within the given [`Root`]'s tree, resolve `path` and return a
+    /// [`Handle`], refusing to return setuid or setgid files.
+    ///
+    /// See [`RootRef::resolve_denying_setid`] for more details.
+    #[inline]
+    pub fn resolve_denying_setid<P: AsRef<Path>>(&self, path: P) -> Result<Handle, Error> {
+        self.as_ref().resolve_denying_setid(path)
+    }
+
+    /// Within the given [`Root`]'s tree, clear the setuid and setgid bits of
+    /// the file at `path`.
+    ///
+    /// See [`RootRef::strip_setid`] for more details.
+    #[inline]
+    pub fn strip_setid<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        self.as_ref().strip_setid(path)
+    }
+
+    /// Within the given [`Root`]'s tree, resolve `path` and return a
+    /// [`Handle`] as well as diagnostic information about every component
+    /// walked through.
+    ///
+    /// See [`RootRef::resolve_explain`] for more details.
+    #[inline]
+    pub fn resolve_explain<P: AsRef<Path>>(
+        &self,
+        path: P,
+    ) -> (Result<Handle, Error>, Vec<ComponentExplain>) {
+        self.as_ref().resolve_explain(path)
+    }
+
+    /// Within the given [`Root`]'s tree, get the canonical root-relative path
+    /// of `path`.
+    ///
+    /// See [`RootRef::canonicalize`] for more details.
+    #[inline]
+    pub fn canonicalize<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf, Error> {
+        self.as_ref().canonicalize(path)
+    }
+
+    /// Get the path of `handle` relative to the [`Root`].
+    ///
+    /// See [`RootRef::relative_path_of`] for more details.
+    #[inline]
+    pub fn relative_path_of(&self, handle: &Handle) -> Result<PathBuf, Error> {
+        self.as_ref().relative_path_of(handle)
+    }
+
+    /// Within the given [`Root`]'s tree, check whether resolving `path` would
+    /// attempt to escape the root.
+    ///
+    /// See [`RootRef::classify`] for more details.
+    #[inline]
+    pub fn classify<P: AsRef<Path>>(&self, path: P) -> Result<Resolution, Error> {
+        self.as_ref().classify(path)
+    }
+
+    /// Get a [`Handle`] to the deepest existing ancestor of `path`.
+    ///
+    /// See [`RootRef::open_nearest_ancestor`] for more details.
+    #[inline]
+    pub fn open_nearest_ancestor<P: AsRef<Path>>(&self, path: P) -> Result<(Handle, usize), Error> {
+        self.as_ref().open_nearest_ancestor(path)
+    }
+
+    /// Within the given [`Root`]'s tree, resolve as much of `path` as
+    /// possible, returning a [`Handle`] to the deepest existing component and
+    /// the remaining (non-existent) suffix of `path`.
+    ///
+    /// See [`RootRef::resolve_partial`] for more details.
+    #[inline]
+    pub fn resolve_partial<P: AsRef<Path>>(
+        &self,
+        path: P,
+        no_follow_trailing: bool,
+    ) -> Result<(Handle, PathBuf), Error> {
+        self.as_ref().resolve_partial(path, no_follow_trailing)
+    }
+
+    /// Open a path without creating an intermediate [`Handle`] object.
+    ///
+    /// This is effectively just shorthand for [`resolve`] followed by
+    /// [`Handle::reopen`]. However, some resolvers (such as the `openat2`
+    /// resolver) can implement [`open_subpath`] slightly more efficiently than
+    /// naively doing a two-step open operation with [`Handle::reopen`]. If you
+    /// wish to create an [`OpenFlags::O_PATH`] file handle, it probably makes
+    /// more sense to use [`resolve`] or [`resolve_nofollow`].
     ///
     /// If `flags` contains [`OpenFlags::O_NOFOLLOW`] and the path refers to a
     /// symlink then this method will match the behaviour of [`openat2`] (this
@@ -317,6 +1080,7 @@ impl Root {
     /// [`resolve`]: Self::resolve
     /// [`resolve_nofollow`]: Self::resolve_nofollow
     #[doc(alias = "pathrs_inroot_open")]
+    #[doc(alias = "open_beneath")]
     #[inline]
     pub fn open_subpath<P: AsRef<Path>, F: Into<OpenFlags>>(
         &self,
@@ -326,6 +1090,149 @@ impl Root {
         self.as_ref().open_subpath(path, flags)
     }
 
+    /// Get the metadata of `path` within the [`Root`]'s tree, following
+    /// trailing symlinks.
+    ///
+    /// See [`RootRef::metadata`] for more details.
+    #[inline]
+    pub fn metadata<P: AsRef<Path>>(&self, path: P) -> Result<Metadata, Error> {
+        self.as_ref().metadata(path)
+    }
+
+    /// Get the metadata of `path` within the [`Root`]'s tree, without following
+    /// trailing symlinks.
+    ///
+    /// See [`RootRef::metadata_nofollow`] for more details.
+    #[inline]
+    pub fn metadata_nofollow<P: AsRef<Path>>(&self, path: P) -> Result<Metadata, Error> {
+        self.as_ref().metadata_nofollow(path)
+    }
+
+    /// Get the extended metadata of `path` within the [`Root`]'s tree,
+    /// following trailing symlinks.
+    ///
+    /// See [`RootRef::statx`] for more details.
+    #[inline]
+    pub fn statx<P: AsRef<Path>>(&self, path: P, mask: StatxMask) -> Result<Statx, Error> {
+        self.as_ref().statx(path, mask)
+    }
+
+    /// Get the birth (creation) time of `path` within the [`Root`]'s tree,
+    /// following trailing symlinks.
+    ///
+    /// See [`RootRef::created`] for more details.
+    #[doc(alias = "btime")]
+    #[inline]
+    pub fn created<P: AsRef<Path>>(&self, path: P) -> Result<Option<SystemTime>, Error> {
+        self.as_ref().created(path)
+    }
+
+    /// Get the birth (creation) time of `path` within the [`Root`]'s tree,
+    /// without following trailing symlinks.
+    ///
+    /// See [`RootRef::created_nofollow`] for more details.
+    #[inline]
+    pub fn created_nofollow<P: AsRef<Path>>(&self, path: P) -> Result<Option<SystemTime>, Error> {
+        self.as_ref().created_nofollow(path)
+    }
+
+    /// Check whether `path` exists within the [`Root`]'s tree, following
+    /// trailing symlinks.
+    ///
+    /// See [`RootRef::exists`] for more details.
+    #[inline]
+    pub fn exists<P: AsRef<Path>>(&self, path: P) -> Result<bool, Error> {
+        self.as_ref().exists(path)
+    }
+
+    /// Check whether `path` exists within the [`Root`]'s tree, without
+    /// following trailing symlinks.
+    ///
+    /// See [`RootRef::exists_nofollow`] for more details.
+    #[inline]
+    pub fn exists_nofollow<P: AsRef<Path>>(&self, path: P) -> Result<bool, Error> {
+        self.as_ref().exists_nofollow(path)
+    }
+
+    /// Iterate over the entries of the directory at `path` within the
+    /// [`Root`]'s tree.
+    ///
+    /// See [`RootRef::read_dir`] for more details.
+    #[inline]
+    pub fn read_dir<P: AsRef<Path>>(&self, path: P) -> Result<ReadDir, Error> {
+        self.as_ref().read_dir(path)
+    }
+
+    /// Recursively iterate over the entries of the directory tree at `path`
+    /// within the [`Root`]'s tree.
+    ///
+    /// See [`RootRef::walk`] for more details.
+    #[inline]
+    pub fn walk<P: AsRef<Path>>(&self, path: P) -> Result<Walk<'_>, Error> {
+        self.as_ref().walk(path)
+    }
+
+    /// Change the permissions of `path` within the [`Root`]'s tree, following
+    /// trailing symlinks.
+    ///
+    /// See [`RootRef::chmod`] for more details.
+    #[inline]
+    pub fn chmod<P: AsRef<Path>>(&self, path: P, perm: &Permissions) -> Result<(), Error> {
+        self.as_ref().chmod(path, perm)
+    }
+
+    /// Change the permissions of `path` within the [`Root`]'s tree, without
+    /// following trailing symlinks.
+    ///
+    /// See [`RootRef::chmod_nofollow`] for more details.
+    #[inline]
+    pub fn chmod_nofollow<P: AsRef<Path>>(&self, path: P, perm: &Permissions) -> Result<(), Error> {
+        self.as_ref().chmod_nofollow(path, perm)
+    }
+
+    /// Change the owner of `path` within the [`Root`]'s tree, following
+    /// trailing symlinks.
+    ///
+    /// See [`RootRef::chown`] for more details.
+    #[inline]
+    pub fn chown<P: AsRef<Path>>(
+        &self,
+        path: P,
+        uid: Option<u32>,
+        gid: Option<u32>,
+    ) -> Result<(), Error> {
+        self.as_ref().chown(path, uid, gid)
+    }
+
+    /// Change the owner of `path` within the [`Root`]'s tree, without
+    /// following trailing symlinks.
+    ///
+    /// See [`RootRef::chown_nofollow`] for more details.
+    #[inline]
+    pub fn chown_nofollow<P: AsRef<Path>>(
+        &self,
+        path: P,
+        uid: Option<u32>,
+        gid: Option<u32>,
+    ) -> Result<(), Error> {
+        self.as_ref().chown_nofollow(path, uid, gid)
+    }
+
+    /// Change the access and modification times of `path` within the
+    /// [`Root`]'s tree.
+    ///
+    /// See [`RootRef::set_times`] for more details.
+    #[inline]
+    pub fn set_times<P: AsRef<Path>>(
+        &self,
+        path: P,
+        atime: Option<SystemTime>,
+        mtime: Option<SystemTime>,
+        follow: bool,
+    ) -> Result<(), Error> {
+        self.as_ref().set_times(path, atime, mtime, follow)
+    }
+
     /// Get the target of a symlink within a [`Root`].
     ///
     /// **NOTE**: The returned path is not modified to be "safe" outside of the
@@ -343,6 +1250,68 @@ impl Root {
         self.as_ref().readlink(path)
     }
 
+    /// Read the entire contents of a file within the [`Root`].
+    ///
+    /// This method is just shorthand for calling [`open_subpath`] with
+    /// [`OpenFlags::O_RDONLY`] and reading the file until EOF. See
+    /// [`RootRef::read`] for more details.
+    ///
+    /// [`open_subpath`]: Self::open_subpath
+    #[inline]
+    pub fn read<P: AsRef<Path>>(&self, path: P) -> Result<Vec<u8>, Error> {
+        self.as_ref().read(path)
+    }
+
+    /// Read the entire contents of a file within the [`Root`] into a
+    /// [`String`].
+    ///
+    /// See [`RootRef::read_to_string`] for more details.
+    #[inline]
+    pub fn read_to_string<P: AsRef<Path>>(&self, path: P) -> Result<String, Error> {
+        self.as_ref().read_to_string(path)
+    }
+
+    /// Within the [`Root`]'s tree, write `data` to the file at `path`,
+    /// creating it if it doesn't exist and truncating it if it does.
+    ///
+    /// See [`RootRef::write`] for more details.
+    #[inline]
+    pub fn write<P: AsRef<Path>>(
+        &self,
+        path: P,
+        perm: &Permissions,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        self.as_ref().write(path, perm, data)
+    }
+
+    /// Within the [`Root`]'s tree, truncate (or extend) the file at `path` to
+    /// `length` bytes.
+    ///
+    /// See [`RootRef::truncate`] for more details.
+    #[inline]
+    pub fn truncate<P: AsRef<Path>>(&self, path: P, length: u64) -> Result<(), Error> {
+        self.as_ref().truncate(path, length)
+    }
+
+    /// Within the [`Root`]'s tree, synchronise the directory containing `path`
+    /// to disk.
+    ///
+    /// See [`RootRef::sync_parent`] for more details.
+    #[inline]
+    pub fn sync_parent<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        self.as_ref().sync_parent(path)
+    }
+
+    /// Open a file within the [`Root`] so that it can be executed with
+    /// `fexecve(3)` or `execveat(2)` with `AT_EMPTY_PATH`.
+    ///
+    /// See [`RootRef::open_exec`] for more details.
+    #[inline]
+    pub fn open_exec<P: AsRef<Path>>(&self, path: P) -> Result<File, Error> {
+        self.as_ref().open_exec(path)
+    }
+
     /// Within the [`Root`]'s tree, create an inode at `path` as specified by
     /// `inode_type`.
     ///
@@ -359,6 +1328,33 @@ impl Root {
         self.as_ref().create(path, inode_type)
     }
 
+    /// Within the [`Root`]'s tree, create a symlink at `path` pointing to
+    /// `target`, after checking that `target` resolves within the root.
+    ///
+    /// See [`RootRef::symlink_checked`] for more details.
+    #[inline]
+    pub fn symlink_checked<P: AsRef<Path>, T: AsRef<Path>>(
+        &self,
+        path: P,
+        target: T,
+    ) -> Result<(), Error> {
+        self.as_ref().symlink_checked(path, target)
+    }
+
+    /// Create an inode named `name` inside the already-resolved directory
+    /// `dir`, as specified by `inode_type`.
+    ///
+    /// See [`RootRef::create_in`] for more details.
+    #[inline]
+    pub fn create_in<N: AsRef<OsStr>>(
+        &self,
+        dir: &Handle,
+        name: N,
+        inode_type: &InodeType,
+    ) -> Result<(), Error> {
+        self.as_ref().create_in(dir, name, inode_type)
+    }
+
     /// Create an [`InodeType::File`] within the [`Root`]'s tree at `path` with
     /// the mode given by `perm`, and return a [`Handle`] to the newly-created
     /// file.
@@ -376,12 +1372,21 @@ impl Root {
     /// for ordinary files because there is no [`O_CREAT`]-equivalent for other
     /// inode types.
     ///
+    /// `O_CREAT` is added to `flags` automatically, and all other flags are
+    /// passed through unchanged. In particular, if `flags` contains `O_TRUNC`
+    /// (but not `O_EXCL`) and the file already exists, it is atomically
+    /// truncated when it is opened. Similarly, `O_SYNC` or `O_DSYNC` can be
+    /// used to make writes to the returned file synchronous -- but note that
+    /// making the creation of the file itself durable requires syncing its
+    /// parent directory with [`sync_parent`].
+    ///
     /// # Errors
     ///
     /// Identical to [`create`].
     ///
     /// [`create`]: Self::create
     /// [`create_file`]: Self::create_file
+    /// [`sync_parent`]: Self::sync_parent
     /// [`O_CREAT`]: http://man7.org/linux/man-pages/man2/open.2.html
     #[doc(alias = "pathrs_inroot_creat")]
     #[doc(alias = "pathrs_inroot_create")]
@@ -395,6 +1400,107 @@ impl Root {
         self.as_ref().create_file(path, flags, perm)
     }
 
+    /// Within the [`Root`]'s tree, create a file with [`create_file`] and set
+    /// its owner to `uid` and `gid`.
+    ///
+    /// See [`RootRef::create_file_owned`] for more details.
+    ///
+    /// [`create_file`]: Self::create_file
+    #[doc(alias = "create_file_with_ownership")]
+    #[inline]
+    pub fn create_file_owned<P: AsRef<Path>>(
+        &self,
+        path: P,
+        flags: OpenFlags,
+        perm: &Permissions,
+        uid: u32,
+        gid: u32,
+    ) -> Result<File, Error> {
+        self.as_ref().create_file_owned(path, flags, perm, uid, gid)
+    }
+
+    /// Within the [`Root`]'s tree, create an anonymous (unnamed) file inside
+    /// the directory `dir` with `O_TMPFILE`.
+    ///
+    /// See [`RootRef::create_tmpfile`] for more details.
+    #[inline]
+    pub fn create_tmpfile<P: AsRef<Path>>(
+        &self,
+        dir: P,
+        flags: OpenFlags,
+        perm: &Permissions,
+    ) -> Result<File, Error> {
+        self.as_ref().create_tmpfile(dir, flags, perm)
+    }
+
+    /// Within the [`Root`]'s tree, give an anonymous file created by
+    /// [`Root::create_tmpfile`] the name `path`.
+    ///
+    /// See [`RootRef::link_tmpfile`] for more details.
+    #[inline]
+    pub fn link_tmpfile<Fd: AsFd, P: AsRef<Path>>(&self, file: Fd, path: P) -> Result<(), Error> {
+        self.as_ref().link_tmpfile(file, path)
+    }
+
+    /// Within the [`Root`]'s tree, exclusively create a lock file at `path`
+    /// and take an exclusive `flock(2)` lock on it.
+    ///
+    /// See [`RootRef::create_lockfile`] for more details.
+    #[inline]
+    pub fn create_lockfile<P: AsRef<Path>>(
+        &self,
+        path: P,
+        perm: &Permissions,
+    ) -> Result<LockFile, Error> {
+        self.as_ref().create_lockfile(path, perm)
+    }
+
+    /// Within the [`Root`]'s tree, create a symlink at `path` pointing to
+    /// `target`, and return an `O_PATH` [`Handle`] to the symlink itself.
+    ///
+    /// See [`RootRef::symlink`] for more details.
+    #[inline]
+    pub fn symlink<P: AsRef<Path>, T: AsRef<Path>>(
+        &self,
+        path: P,
+        target: T,
+    ) -> Result<Handle, Error> {
+        self.as_ref().symlink(path, target)
+    }
+
+    /// Within the [`Root`]'s tree, copy the contents of the regular file at
+    /// `source` to a newly-created file at `destination`.
+    ///
+    /// See [`RootRef::copy`] for more details.
+    #[inline]
+    pub fn copy<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        source: P,
+        destination: Q,
+        flags: OpenFlags,
+        perm: Option<&Permissions>,
+    ) -> Result<File, Error> {
+        self.as_ref().copy(source, destination, flags, perm)
+    }
+
+    /// Within the [`Root`]'s tree, create a new file at `destination` that
+    /// shares the data of the regular file at `source` using the `FICLONE`
+    /// ioctl.
+    ///
+    /// See [`RootRef::reflink`] for more details.
+    #[doc(alias = "FICLONE")]
+    #[inline]
+    pub fn reflink<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        source: P,
+        destination: Q,
+        perm: &Permissions,
+        copy_fallback: bool,
+    ) -> Result<File, Error> {
+        self.as_ref()
+            .reflink(source, destination, perm, copy_fallback)
+    }
+
     /// Within the [`Root`]'s tree, create a directory and any of its parent
     /// component if they are missing. This is effectively equivalent to
     /// [`std::fs::create_dir_all`], Go's [`os.MkdirAll`], or Unix's `mkdir -p`.
@@ -427,6 +1533,36 @@ impl Root {
         self.as_ref().mkdir_all(path, perm)
     }
 
+    /// Within the [`Root`]'s tree, create a directory and any of its parent
+    /// component if they are missing, setting the owner of any newly-created
+    /// directories to `uid` and `gid`.
+    ///
+    /// See [`RootRef::mkdir_all_owned`] for more details.
+    #[inline]
+    pub fn mkdir_all_owned<P: AsRef<Path>>(
+        &self,
+        path: P,
+        perm: &Permissions,
+        uid: u32,
+        gid: u32,
+    ) -> Result<Handle, Error> {
+        self.as_ref().mkdir_all_owned(path, perm, uid, gid)
+    }
+
+    /// Within the [`Root`]'s tree, create a directory and any of its parent
+    /// component if they are missing, with the mode and owner of each created
+    /// directory chosen by `per_component`.
+    ///
+    /// See [`RootRef::mkdir_all_with`] for more details.
+    #[inline]
+    pub fn mkdir_all_with<P, F>(&self, path: P, per_component: F) -> Result<Handle, Error>
+    where
+        P: AsRef<Path>,
+        F: FnMut(&Path) -> (Permissions, Option<(u32, u32)>),
+    {
+        self.as_ref().mkdir_all_with(path, per_component)
+    }
+
     /// Within the [`Root`]'s tree, remove the empty directory at `path`.
     ///
     /// Any existing [`Handle`]s to `path` will continue to work as before,
@@ -487,6 +1623,19 @@ impl Root {
         self.as_ref().remove_all(path)
     }
 
+    /// Within the [`Root`]'s tree, recursively delete the provided `path`,
+    /// calling `on_error` for every entry that could not be deleted.
+    ///
+    /// See [`RootRef::remove_all_with`] for more details.
+    #[inline]
+    pub fn remove_all_with<P, F>(&self, path: P, on_error: F) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+        F: FnMut(&Path, &Error) -> ControlFlow<()>,
+    {
+        self.as_ref().remove_all_with(path, on_error)
+    }
+
     /// Within the [`Root`]'s tree, perform a rename with the given `source` and
     /// `directory`. The `flags` argument is passed directly to
     /// [`renameat2(2)`].
@@ -505,20 +1654,104 @@ impl Root {
     ) -> Result<(), Error> {
         self.as_ref().rename(source, destination, rflags)
     }
-}
 
-impl From<OwnedFd> for Root {
-    /// Shorthand for [`Root::from_fd`].
-    fn from(fd: OwnedFd) -> Self {
-        Self::from_fd(fd)
+    /// Within the [`Root`]'s tree, perform a rename with the given `source` and
+    /// `destination`, and return handles to the renamed inodes.
+    ///
+    /// See [`RootRef::rename_at`] for more details.
+    #[inline]
+    pub fn rename_at<P: AsRef<Path>>(
+        &self,
+        source: P,
+        destination: P,
+        rflags: RenameFlags,
+    ) -> Result<(Option<Handle>, Handle), Error> {
+        self.as_ref().rename_at(source, destination, rflags)
     }
-}
 
-impl From<Root> for OwnedFd {
-    /// Unwrap a [`Root`] to reveal the underlying [`OwnedFd`].
+    /// Find all paths within `search_root` in the [`Root`]'s tree which are
+    /// hardlinks to the file at `path`.
     ///
-    /// **Note**: This method is primarily intended to allow for file descriptor
-    /// passing or otherwise transmitting file descriptor information. It is not
+    /// See [`RootRef::find_hardlinks`] for more details.
+    #[inline]
+    pub fn find_hardlinks<P: AsRef<Path>, P2: AsRef<Path>>(
+        &self,
+        path: P,
+        search_root: P2,
+    ) -> Result<Vec<PathBuf>, Error> {
+        self.as_ref().find_hardlinks(path, search_root)
+    }
+
+    /// Check whether `path` within the [`Root`]'s tree is a directory that
+    /// can be searched by the current process.
+    ///
+    /// See [`RootRef::probe_dir`] for more details.
+    #[inline]
+    pub fn probe_dir<P: AsRef<Path>>(&self, path: P) -> Result<DirProbe, Error> {
+        self.as_ref().probe_dir(path)
+    }
+
+    /// Check whether the current process can access `path` within the
+    /// [`Root`]'s tree with the given `mode`, in the same manner as
+    /// [`access(2)`].
+    ///
+    /// See [`RootRef::access`] for more details.
+    ///
+    /// [`access(2)`]: http://man7.org/linux/man-pages/man2/access.2.html
+    #[doc(alias = "faccessat")]
+    #[inline]
+    pub fn access<P: AsRef<Path>>(
+        &self,
+        path: P,
+        mode: AccessMode,
+        follow: bool,
+    ) -> Result<bool, Error> {
+        self.as_ref().access(path, mode, follow)
+    }
+
+    /// Check whether a process with the given credentials would be permitted
+    /// to access `path` within the [`Root`]'s tree with the given `mode`.
+    ///
+    /// See [`RootRef::access_as`] for more details.
+    #[inline]
+    pub fn access_as<P: AsRef<Path>>(
+        &self,
+        path: P,
+        uid: u32,
+        gid: u32,
+        groups: &[u32],
+        mode: AccessMode,
+    ) -> Result<bool, Error> {
+        self.as_ref().access_as(path, uid, gid, groups, mode)
+    }
+
+    /// Within the [`Root`]'s tree, rewrite the absolute symlink at `path` so
+    /// that its target starts with `new_prefix` rather than `old_prefix`.
+    ///
+    /// See [`RootRef::rebase_symlink`] for more details.
+    #[inline]
+    pub fn rebase_symlink<P: AsRef<Path>, P1: AsRef<Path>, P2: AsRef<Path>>(
+        &self,
+        path: P,
+        old_prefix: P1,
+        new_prefix: P2,
+    ) -> Result<Option<PathBuf>, Error> {
+        self.as_ref().rebase_symlink(path, old_prefix, new_prefix)
+    }
+}
+
+impl From<OwnedFd> for Root {
+    /// Shorthand for [`Root::from_fd`].
+    fn from(fd: OwnedFd) -> Self {
+        Self::from_fd(fd)
+    }
+}
+
+impl From<Root> for OwnedFd {
+    /// Unwrap a [`Root`] to reveal the underlying [`OwnedFd`].
+    ///
+    /// **Note**: This method is primarily intended to allow for file descriptor
+    /// passing or otherwise transmitting file descriptor information. It is not
     /// safe to use this [`OwnedFd`] directly to do filesystem operations.
     /// Please use the provided [`Root`] methods.
     fn from(root: Root) -> Self {
@@ -540,6 +1773,161 @@ impl AsFd for Root {
     }
 }
 
+/// Builder for a fully-configured [`Root`].
+///
+/// Configuring a [`Root`] with methods like [`Root::set_resolver_flags`] after
+/// it has been opened is error-prone, because the configuration is copied
+/// (rather than shared) when creating a [`RootRef`] with [`Root::as_ref`]. A
+/// [`RootBuilder`] accumulates the configuration first and then produces a
+/// [`Root`] with [`RootBuilder::open`] or [`RootBuilder::from_fd`], so the
+/// configuration is always set before the [`Root`] is used:
+///
+/// ```rust
+/// # use pathrs::{Root, ResolverBackend, flags::ResolverFlags};
+/// # let tmpdir = tempfile::TempDir::new()?;
+/// # let rootdir = &tmpdir;
+/// let root = Root::builder()
+///     .resolver_flags(ResolverFlags::NO_SYMLINKS)
+///     .resolver_backend(ResolverBackend::EmulatedOpath)
+///     .max_symlink_depth(10)
+///     .open(rootdir)?;
+/// // Continue to use root.
+/// # let _ = tmpdir; // make sure it is not dropped early
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+///
+/// Any configuration not set with the builder uses the same defaults as
+/// [`Root::open`].
+#[derive(Clone, Debug, Default)]
+pub struct RootBuilder {
+    resolver: Resolver,
+    read_limit: Option<u64>,
+    verify_mount_id: bool,
+}
+
+impl RootBuilder {
+    /// Create a new [`RootBuilder`] with the default configuration.
+    ///
+    /// This is identical to [`Root::builder`].
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the [`ResolverFlags`] of the [`Root`].
+    ///
+    /// See [`Root::set_resolver_flags`] for more details.
+    #[inline]
+    pub fn resolver_flags(mut self, flags: ResolverFlags) -> Self {
+        self.resolver.flags = flags;
+        self
+    }
+
+    /// Set the [`ResolverBackend`] of the [`Root`].
+    ///
+    /// See [`Root::set_resolver_backend`] for more details.
+    #[inline]
+    pub fn resolver_backend(mut self, backend: ResolverBackend) -> Self {
+        self.resolver.backend = Some(backend);
+        self
+    }
+
+    /// Set the maximum number of symlinks that can be walked during each path
+    /// resolution done by the [`Root`].
+    ///
+    /// See [`Root::set_max_symlink_depth`] for more details.
+    #[inline]
+    pub fn max_symlink_depth(mut self, depth: usize) -> Self {
+        self.resolver.max_symlink_depth = depth;
+        self
+    }
+
+    /// Set the maximum wall-clock time permitted for each path resolution
+    /// done by the [`Root`].
+    ///
+    /// See [`Root::set_resolve_deadline`] for more details.
+    #[inline]
+    pub fn resolve_deadline(mut self, deadline: Option<Duration>) -> Self {
+        self.resolver.deadline = deadline;
+        self
+    }
+
+    /// Set the [`AbsoluteSymlinkPolicy`] of the [`Root`].
+    ///
+    /// See [`Root::set_absolute_symlink_policy`] for more details.
+    #[inline]
+    pub fn absolute_symlink_policy(mut self, policy: AbsoluteSymlinkPolicy) -> Self {
+        self.resolver.absolute_symlinks = policy;
+        self
+    }
+
+    /// Set the [`ReopenPolicy`] of the [`Root`].
+    ///
+    /// See [`Root::set_reopen_policy`] for more details.
+    #[inline]
+    pub fn reopen_policy(mut self, policy: ReopenPolicy) -> Self {
+        self.resolver.reopen_policy = policy;
+        self
+    }
+
+    /// Set the [`SetidPolicy`] of the [`Root`].
+    ///
+    /// See [`Root::set_setid_policy`] for more details.
+    #[inline]
+    pub fn setid_policy(mut self, policy: SetidPolicy) -> Self {
+        self.resolver.setid_policy = policy;
+        self
+    }
+
+    /// Set the maximum number of bytes that [`Root::read`] and
+    /// [`Root::read_to_string`] will read from a file.
+    ///
+    /// See [`Root::set_read_limit`] for more details.
+    #[inline]
+    pub fn read_limit(mut self, limit: Option<u64>) -> Self {
+        self.read_limit = limit;
+        self
+    }
+
+    /// Set whether every operation done by the [`Root`] should verify that the
+    /// path of the root directory is still on the same mount.
+    ///
+    /// The mount id is only looked up when the [`Root`] is created with
+    /// [`RootBuilder::open`] or [`RootBuilder::from_fd`], which return an
+    /// error if it cannot be determined. See [`Root::set_verify_mount_id`] for
+    /// more details.
+    #[inline]
+    pub fn verify_mount_id(mut self, verify: bool) -> Self {
+        self.verify_mount_id = verify;
+        self
+    }
+
+    /// Open a [`Root`] handle with this configuration.
+    ///
+    /// See [`Root::open`] for more details.
+    pub fn open<P: AsRef<Path>>(self, path: P) -> Result<Root, Error> {
+        self.from_fd(Root::open(path)?)
+    }
+
+    /// Wrap an [`OwnedFd`] into a [`Root`] with this configuration.
+    ///
+    /// See [`Root::from_fd`] for more details.
+    ///
+    /// # Errors
+    ///
+    /// If [`RootBuilder::verify_mount_id`] was enabled and the mount id of
+    /// `fd` cannot be determined, an error is returned (see
+    /// [`Root::set_verify_mount_id`]).
+    pub fn from_fd<Fd: Into<OwnedFd>>(self, fd: Fd) -> Result<Root, Error> {
+        Root {
+            inner: fd.into(),
+            resolver: self.resolver,
+            read_limit: self.read_limit,
+        }
+        .with_verify_mount_id(self.verify_mount_id)
+    }
+}
+
 /// Borrowed version of [`Root`].
 ///
 /// Unlike [`Root`], when [`RootRef`] is dropped the underlying file descriptor
@@ -547,6 +1935,9 @@ impl AsFd for Root {
 /// to do operations on [`&File`][File]s and [`BorrowedFd`]s passed from
 /// elsewhere.
 ///
+/// As with [`Root`], [`RootRef`] is [`Send`] and [`Sync`] (see [the
+/// documentation for `Root`](Root#thread-safety) for more details).
+///
 /// [File]: std::fs::File
 // TODO: Is there any way we can restructure this to use Deref so that we don't
 //       need to copy all of the methods into Handle? Probably not... Maybe GATs
@@ -554,11 +1945,11 @@ impl AsFd for Root {
 #[derive(Copy, Clone, Debug)]
 pub struct RootRef<'fd> {
     inner: BorrowedFd<'fd>,
-    // TODO: Drop this and switch to builder-pattern.
     resolver: Resolver,
+    read_limit: Option<u64>,
 }
 
-impl RootRef<'_> {
+impl<'fd> RootRef<'fd> {
     /// Wrap a [`BorrowedFd`] into a [`RootRef`].
     ///
     /// The [`BorrowedFd`] should be a file descriptor referencing a directory,
@@ -570,6 +1961,7 @@ impl RootRef<'_> {
         RootRef {
             inner,
             resolver: Default::default(),
+            read_limit: None,
         }
     }
 
@@ -655,6 +2047,270 @@ impl RootRef<'_> {
         self
     }
 
+    /// Get the current resolution deadline for this [`RootRef`].
+    #[inline]
+    pub fn resolve_deadline(&self) -> Option<Duration> {
+        self.resolver.deadline
+    }
+
+    /// Set the maximum wall-clock time permitted for each path resolution
+    /// done by this [`RootRef`] (`None` means there is no limit).
+    ///
+    /// See [`Root::set_resolve_deadline`] for more details.
+    #[inline]
+    pub fn set_resolve_deadline(&mut self, deadline: Option<Duration>) -> &mut Self {
+        self.resolver.deadline = deadline;
+        self
+    }
+
+    /// Set the maximum wall-clock time permitted for each path resolution
+    /// done by this [`RootRef`].
+    ///
+    /// This is identical to [`RootRef::set_resolve_deadline`] except that it
+    /// can more easily be used with chaining.
+    #[inline]
+    pub fn with_resolve_deadline(mut self, deadline: Option<Duration>) -> Self {
+        self.set_resolve_deadline(deadline);
+        self
+    }
+
+    /// Get the current maximum symlink depth for this [`RootRef`].
+    #[inline]
+    pub fn max_symlink_depth(&self) -> usize {
+        self.resolver.max_symlink_depth
+    }
+
+    /// Set the maximum number of symlinks that can be walked during each path
+    /// resolution done by this [`RootRef`].
+    ///
+    /// See [`Root::set_max_symlink_depth`] for more details.
+    #[inline]
+    pub fn set_max_symlink_depth(&mut self, depth: usize) -> &mut Self {
+        self.resolver.max_symlink_depth = depth;
+        self
+    }
+
+    /// Set the maximum number of symlinks that can be walked during each path
+    /// resolution done by this [`RootRef`].
+    ///
+    /// This is identical to [`RootRef::set_max_symlink_depth`] except that it
+    /// can more easily be used with chaining.
+    #[inline]
+    pub fn with_max_symlink_depth(mut self, depth: usize) -> Self {
+        self.set_max_symlink_depth(depth);
+        self
+    }
+
+    /// Get the current [`AbsoluteSymlinkPolicy`] for this [`RootRef`].
+    #[inline]
+    pub fn absolute_symlink_policy(&self) -> AbsoluteSymlinkPolicy {
+        self.resolver.absolute_symlinks
+    }
+
+    /// Set the [`AbsoluteSymlinkPolicy`] for all operations in this
+    /// [`RootRef`].
+    ///
+    /// See [`Root::set_absolute_symlink_policy`] for more details.
+    #[inline]
+    pub fn set_absolute_symlink_policy(&mut self, policy: AbsoluteSymlinkPolicy) -> &mut Self {
+        self.resolver.absolute_symlinks = policy;
+        self
+    }
+
+    /// Set the [`AbsoluteSymlinkPolicy`] for all operations in this
+    /// [`RootRef`].
+    ///
+    /// This is identical to [`RootRef::set_absolute_symlink_policy`] except
+    /// that it can more easily be used with chaining.
+    #[inline]
+    pub fn with_absolute_symlink_policy(mut self, policy: AbsoluteSymlinkPolicy) -> Self {
+        self.set_absolute_symlink_policy(policy);
+        self
+    }
+
+    /// Get the current [`ReopenPolicy`] for this [`RootRef`].
+    #[inline]
+    pub fn reopen_policy(&self) -> ReopenPolicy {
+        self.resolver.reopen_policy
+    }
+
+    /// Set the [`ReopenPolicy`] for [`Handle`]s produced by this [`RootRef`].
+    ///
+    /// See [`Root::set_reopen_policy`] for more details.
+    #[inline]
+    pub fn set_reopen_policy(&mut self, policy: ReopenPolicy) -> &mut Self {
+        self.resolver.reopen_policy = policy;
+        self
+    }
+
+    /// Set the [`ReopenPolicy`] for [`Handle`]s produced by this [`RootRef`].
+    ///
+    /// This is identical to [`RootRef::set_reopen_policy`] except that it can
+    /// more easily be used with chaining.
+    #[inline]
+    pub fn with_reopen_policy(mut self, policy: ReopenPolicy) -> Self {
+        self.set_reopen_policy(policy);
+        self
+    }
+
+    /// Get the current [`SetidPolicy`] for this [`RootRef`].
+    #[inline]
+    pub fn setid_policy(&self) -> SetidPolicy {
+        self.resolver.setid_policy
+    }
+
+    /// Set the [`SetidPolicy`] for regular files created (or whose mode is
+    /// changed) by this [`RootRef`].
+    ///
+    /// See [`Root::set_setid_policy`] for more details.
+    #[inline]
+    pub fn set_setid_policy(&mut self, policy: SetidPolicy) -> &mut Self {
+        self.resolver.setid_policy = policy;
+        self
+    }
+
+    /// Set the [`SetidPolicy`] for regular files created (or whose mode is
+    /// changed) by this [`RootRef`].
+    ///
+    /// This is identical to [`RootRef::set_setid_policy`] except that it can
+    /// more easily be used with chaining.
+    #[inline]
+    pub fn with_setid_policy(mut self, policy: SetidPolicy) -> Self {
+        self.set_setid_policy(policy);
+        self
+    }
+
+    /// Forbid (or permit) the creation of setuid and setgid files by this
+    /// [`RootRef`].
+    ///
+    /// See [`Root::forbid_suid_sgid`] for more details.
+    #[inline]
+    pub fn forbid_suid_sgid(&mut self, forbid: bool) -> &mut Self {
+        self.set_setid_policy(if forbid {
+            SetidPolicy::Error
+        } else {
+            SetidPolicy::Allow
+        })
+    }
+
+    // Apply the SetidPolicy to the mode of a regular file that is being
+    // created (or having its mode changed).
+    fn setid_checked_mode(&self, mode: u32) -> Result<u32, Error> {
+        const SETID_BITS: u32 = libc::S_ISUID | libc::S_ISGID;
+        if mode & SETID_BITS == 0 {
+            return Ok(mode);
+        }
+        match self.resolver.setid_policy {
+            SetidPolicy::Allow => Ok(mode),
+            SetidPolicy::Strip => Ok(mode & !SETID_BITS),
+            SetidPolicy::Error => Err(ErrorImpl::SafetyViolation {
+                description: format!(
+                    "mode 0o{mode:o} contains setuid or setgid bits forbidden by the setid policy"
+                )
+                .into(),
+            })?,
+        }
+    }
+
+    /// Get the current read limit for this [`RootRef`].
+    #[inline]
+    pub fn read_limit(&self) -> Option<u64> {
+        self.read_limit
+    }
+
+    /// Set the maximum number of bytes that [`RootRef::read`] and
+    /// [`RootRef::read_to_string`] will read from a file (`None` means there
+    /// is no limit).
+    ///
+    /// See [`Root::set_read_limit`] for more details.
+    #[inline]
+    pub fn set_read_limit(&mut self, limit: Option<u64>) -> &mut Self {
+        self.read_limit = limit;
+        self
+    }
+
+    /// Set the maximum number of bytes that [`RootRef::read`] and
+    /// [`RootRef::read_to_string`] will read from a file.
+    ///
+    /// This is identical to [`RootRef::set_read_limit`] except that it can
+    /// more easily be used with chaining.
+    #[inline]
+    pub fn with_read_limit(mut self, limit: Option<u64>) -> Self {
+        self.set_read_limit(limit);
+        self
+    }
+
+    /// Get the [`ResolverBackend`] used by this [`RootRef`] for path resolution.
+    #[inline]
+    pub fn resolver_backend(&self) -> ResolverBackend {
+        self.resolver.backend.unwrap_or_default()
+    }
+
+    /// Set the [`ResolverBackend`] used by this [`RootRef`] for path resolution.
+    ///
+    /// By default, the best backend supported by the running kernel is used
+    /// (see [`ResolverBackend::default`]) and it is generally not necessary to
+    /// change this. However, forcing a particular backend can be useful for
+    /// benchmarking, testing, or working around kernel bugs.
+    ///
+    /// Note that no fallback is done if the requested backend is not supported
+    /// by the running kernel (see [`ResolverBackend::supported`]) --
+    /// operations which need to resolve paths will instead return an error of
+    /// kind [`ErrorKind::NotSupported`].
+    ///
+    /// Some options can only be implemented by
+    /// [`ResolverBackend::EmulatedOpath`] ([`ResolverFlags::NO_AUTOMOUNT`],
+    /// [`AbsoluteSymlinkPolicy`]s other than [`AbsoluteSymlinkPolicy::InRoot`]
+    /// and [`RootRef::resolve_require_owner`]). If no backend has been set, these
+    /// options cause the emulated backend to be used. However, if
+    /// [`ResolverBackend::KernelOpenat2`] was explicitly requested, operations
+    /// using these options also return an error of kind
+    /// [`ErrorKind::NotSupported`] rather than silently using a different
+    /// backend.
+    #[inline]
+    pub fn set_resolver_backend(&mut self, backend: ResolverBackend) -> &mut Self {
+        self.resolver.backend = Some(backend);
+        self
+    }
+
+    /// Set the [`ResolverBackend`] used by this [`RootRef`] for path resolution.
+    ///
+    /// This is identical to [`RootRef::set_resolver_backend`] except that it can
+    /// more easily be used with chaining.
+    #[inline]
+    pub fn with_resolver_backend(mut self, backend: ResolverBackend) -> Self {
+        self.set_resolver_backend(backend);
+        self
+    }
+
+    /// Get whether this [`RootRef`] verifies the mount id of the root directory's
+    /// path before each operation.
+    #[inline]
+    pub fn verify_mount_id(&self) -> bool {
+        self.resolver.expected_mnt_id.is_some()
+    }
+
+    /// Set whether every operation done by this [`RootRef`] should verify
+    /// that the path of the root directory is still on the same mount as the
+    /// [`RootRef`] handle.
+    ///
+    /// See [`Root::set_verify_mount_id`] for more details.
+    pub fn set_verify_mount_id(&mut self, verify: bool) -> Result<&mut Self, Error> {
+        self.resolver.expected_mnt_id = if verify { Some(self.mount_id()?) } else { None };
+        Ok(self)
+    }
+
+    /// Set whether every operation done by this [`RootRef`] should verify that
+    /// the path of the root directory is still on the same mount.
+    ///
+    /// This is identical to [`RootRef::set_verify_mount_id`] except that it can
+    /// more easily be used with chaining.
+    #[inline]
+    pub fn with_verify_mount_id(mut self, verify: bool) -> Result<Self, Error> {
+        self.set_verify_mount_id(verify)?;
+        Ok(self)
+    }
+
     /// Create a copy of a [`RootRef`].
     ///
     /// Note that (unlike [`BorrowedFd::clone`]) this method creates a full copy
@@ -676,44 +2332,725 @@ impl RootRef<'_> {
                     source: err,
                 })?,
             resolver: self.resolver,
+            read_limit: self.read_limit,
         })
     }
 
-    /// Within the given [`RootRef`]'s tree, resolve `path` and return a
-    /// [`Handle`].
+    /// Check whether this [`RootRef`] and `other` reference the same directory.
     ///
-    /// All symlink path components are scoped to [`RootRef`]. Trailing symlinks
-    /// *are* followed, if you want to get a handle to a symlink use
-    /// [`resolve_nofollow`].
+    /// This compares the device and inode numbers of the root directories
+    /// (see [`HandleRef::same_inode_as`]), and does not take into account the
+    /// configuration of either root. As with handles, the same directory may
+    /// be reachable through different paths (such as with bind-mounts), and
+    /// opening the same path twice does not guarantee that the same directory
+    /// was opened.
     ///
-    /// # Errors
+    /// [`HandleRef::same_inode_as`]: crate::HandleRef::same_inode_as
+    pub fn same_inode_as(&self, other: &RootRef<'_>) -> Result<bool, Error> {
+        self.inner
+            .is_same_inode(other.inner)
+            .wrap("compare root inodes")
+    }
+
+    /// Get the current path of the [`RootRef`] directory, for diagnostic
+    /// purposes only.
     ///
-    /// If `path` doesn't exist, or an attack was detected during resolution, a
-    /// corresponding [`Error`] will be returned. If no error is returned, then
-    /// the path is guaranteed to have been reachable from the root of the
-    /// directory tree and thus have been inside the root at one point in the
-    /// resolution.
+    /// **This path must only be used for diagnostic purposes** (such as
+    /// logging) and must never be used to re-open the root directory, since
+    /// the directory may have been moved in the meantime. If the directory has
+    /// been deleted, the path has a `" (deleted)"` suffix. See
+    /// [`HandleRef::as_frozen_path`] for more details.
     ///
-    /// [`resolve_nofollow`]: Self::resolve_nofollow
-    #[doc(alias = "pathrs_inroot_resolve")]
-    #[inline]
-    pub fn resolve<P: AsRef<Path>>(&self, path: P) -> Result<Handle, Error> {
-        self.resolver.resolve(self, path, false)
+    /// [`HandleRef::as_frozen_path`]: crate::HandleRef::as_frozen_path
+    pub fn as_frozen_path(&self) -> Result<PathBuf, Error> {
+        self.inner
+            .as_unsafe_path(&GLOBAL_PROCFS_HANDLE)
+            .wrap("get root path from procfs")
     }
 
-    /// Identical to [`resolve`], except that *trailing* symlinks are *not*
-    /// followed.
+    /// Get a [`Handle`] to the root directory itself.
     ///
-    /// If the trailing component is a symlink [`resolve_nofollow`] will return
-    /// a handle to the symlink itself. This is effectively equivalent to
-    /// `O_NOFOLLOW`.
+    /// This is equivalent to resolving `.` (but without doing a lookup), and
+    /// is useful for generic code which operates on [`Handle`]s so that the
+    /// root directory can be used without any special-casing. The returned
+    /// [`Handle`] contains a copy of the underlying file descriptor (so it is
+    /// independent of this [`RootRef`]) and is tagged with the resolver backend
+    /// configured for this [`RootRef`], as with any other [`Handle`] returned
+    /// by [`resolve`].
     ///
     /// [`resolve`]: Self::resolve
-    /// [`resolve_nofollow`]: Self::resolve_nofollow
-    #[doc(alias = "pathrs_inroot_resolve_nofollow")]
-    #[inline]
-    pub fn resolve_nofollow<P: AsRef<Path>>(&self, path: P) -> Result<Handle, Error> {
-        self.resolver.resolve(self, path, true)
+    pub fn root_handle(&self) -> Result<Handle, Error> {
+        let fd = self
+            .as_fd()
+            .try_clone_to_owned()
+            .map_err(|err| ErrorImpl::OsError {
+                operation: "clone underlying root file".into(),
+                source: err,
+            })?;
+        Ok(Handle::from_fd(fd)
+            .with_backend(self.resolver_backend())
+            .with_reopen_policy(self.resolver.reopen_policy))
+    }
+
+    /// Create a new [`Root`] on a detached copy of this [`RootRef`]'s mount
+    /// tree.
+    ///
+    /// This uses `open_tree(2)` with `OPEN_TREE_CLONE` to create a detached
+    /// recursive bind-mount of the root directory, which is not attached to
+    /// any mount namespace. Any operations done on the returned [`Root`] are
+    /// immune to other processes changing the mount topology (mounting on top
+    /// of, unmounting or moving mounts within the tree) after this method
+    /// returns, which makes this a useful hardening measure when doing a long
+    /// series of operations within a [`Root`].
+    ///
+    /// Note that only the mount topology is frozen -- the contents of the
+    /// filesystems are still live, so files and directories can still be
+    /// modified, renamed or swapped by other processes.
+    ///
+    /// The new [`Root`] has the same configuration as this [`RootRef`].
+    ///
+    /// # Errors
+    ///
+    /// This requires `open_tree(2)` (Linux 5.2) and `CAP_SYS_ADMIN` in the
+    /// user namespace that owns the mount namespace, so it will fail for most
+    /// unprivileged programs.
+    pub fn pin_mount(&self) -> Result<Root, Error> {
+        let inner = syscalls::open_tree(
+            self,
+            "",
+            OpenTreeFlags::OPEN_TREE_CLONE
+                | OpenTreeFlags::OPEN_TREE_CLOEXEC
+                | OpenTreeFlags::AT_RECURSIVE
+                | OpenTreeFlags::AT_EMPTY_PATH,
+        )
+        .map_err(|err| ErrorImpl::RawOsError {
+            operation: "create detached bind-mount of root".into(),
+            source: err,
+        })?;
+        Ok(Root {
+            inner,
+            resolver: self.resolver,
+            read_limit: self.read_limit,
+        })
+    }
+
+    /// Resolve the directory `path` within the [`RootRef`]'s tree and return a
+    /// new [`Root`] rooted at that directory.
+    ///
+    /// This is useful when doing many operations within a fixed subtree of a
+    /// [`RootRef`]. Because `path` is resolved safely within this [`RootRef`],
+    /// and all operations on the returned [`Root`] are scoped to it, paths used
+    /// with the returned [`Root`] can never escape the subdirectory (and thus
+    /// can also never escape this [`RootRef`]). Note that this means that
+    /// `..` and absolute symlinks within the subtree are resolved relative to
+    /// the subdirectory, not this [`RootRef`].
+    ///
+    /// The returned [`Root`] is a completely ordinary [`Root`], so moving the
+    /// subdirectory during a lookup is detected in the same way as for any
+    /// other [`Root`]. It has the same configuration as this [`RootRef`], except
+    /// that if [mount id verification] is enabled then it verifies the mount
+    /// id of the subdirectory.
+    ///
+    /// # Errors
+    ///
+    /// If `path` does not resolve to a directory, an `ENOTDIR` error is
+    /// returned.
+    ///
+    /// [mount id verification]: Self::set_verify_mount_id
+    pub fn sub_root<P: AsRef<Path>>(&self, path: P) -> Result<Root, Error> {
+        let handle = self.resolve(path)?;
+        if !handle.metadata()?.is_dir() {
+            Err(ErrorImpl::OsError {
+                operation: "open sub-root".into(),
+                source: IOError::from_raw_os_error(libc::ENOTDIR),
+            })?
+        }
+
+        let mut root = Root {
+            inner: handle.into(),
+            resolver: self.resolver,
+            read_limit: self.read_limit,
+        };
+        if root.verify_mount_id() {
+            root.set_verify_mount_id(true)?;
+        }
+        Ok(root)
+    }
+
+    /// Change the current working directory of the process to the
+    /// [`RootRef`], returning a [`RootGuard`] which will restore the previous
+    /// working directory when dropped.
+    ///
+    /// This is intended for running legacy code which operates on paths
+    /// relative to the current working directory. The previous working
+    /// directory is saved as an `O_PATH` file descriptor (rather than as a
+    /// path) so that restoring it is not affected by concurrent renames.
+    ///
+    /// **NOTE**: Paths used by such code are resolved by the kernel in the
+    /// usual way and are *not* scoped to the root -- a `..` component or an
+    /// absolute symlink can trivially escape it. You should only use this for
+    /// code which you trust to only use simple relative paths.
+    ///
+    /// # Thread Safety
+    ///
+    /// The current working directory is shared by all threads in the process
+    /// (unless a thread has called `unshare(CLONE_FS)`), it is not a property
+    /// of the calling thread. This means that any other threads using relative
+    /// paths will also be affected while the
+    /// [`RootGuard`] is alive, and if multiple threads call [`enter`] at the
+    /// same time the restored working directory depends on the order in which
+    /// the guards are dropped. If you need to isolate the working directory
+    /// change to a single thread, call `unshare(CLONE_FS)` in that thread
+    /// first.
+    ///
+    /// [`enter`]: Self::enter
+    pub fn enter(&self) -> Result<RootGuard, Error> {
+        let old_cwd = syscalls::openat(
+            syscalls::AT_FDCWD,
+            ".",
+            OpenFlags::O_PATH | OpenFlags::O_DIRECTORY,
+            0,
+        )
+        .map_err(|err| ErrorImpl::RawOsError {
+            operation: "save current working directory".into(),
+            source: err,
+        })?;
+
+        syscalls::fchdir(self).map_err(|err| ErrorImpl::RawOsError {
+            operation: "change working directory to root".into(),
+            source: err,
+        })?;
+
+        Ok(RootGuard { old_cwd })
+    }
+
+    /// Check whether the mount containing the [`RootRef`] is currently mounted
+    /// read-only.
+    ///
+    /// This is a point-in-time check using `fstatvfs(3)`, intended for
+    /// long-running programs that hold a [`RootRef`] while the underlying
+    /// mount may be remounted. Note that mounts inside the [`RootRef`]'s tree
+    /// can have different mount flags, and the result may be out of date by
+    /// the time you act on it.
+    ///
+    /// Write operations which fail with `EROFS` will also return an error
+    /// which indicates that the mount is read-only, so this check is not
+    /// necessary to get a clear error.
+    pub fn mount_is_readonly(&self) -> Result<bool, Error> {
+        let statvfs = syscalls::fstatvfs(self).map_err(|err| ErrorImpl::RawOsError {
+            operation: "get root mount flags".into(),
+            source: err,
+        })?;
+        Ok(statvfs.f_flag.contains(StatVfsMountFlags::RDONLY))
+    }
+
+    /// Get the mount id of the mount containing the [`RootRef`].
+    ///
+    /// This is the mount the [`RootRef`] was opened on, and it does not change
+    /// even if the root directory is later remounted or over-mounted. The
+    /// unique (non-recycled) mount id is returned if the kernel supports
+    /// `STATX_MNT_ID_UNIQUE` (Linux 6.8 or later). To detect the root directory
+    /// changing mounts, see [`RootRef::set_verify_mount_id`].
+    ///
+    /// # Errors
+    ///
+    /// If the kernel does not support `STATX_MNT_ID` (Linux 5.8 or later), an
+    /// [`ErrorKind::NotSupported`] error is returned.
+    ///
+    /// [`ErrorKind::NotSupported`]: crate::error::ErrorKind::NotSupported
+    pub fn mount_id(&self) -> Result<u64, Error> {
+        match utils::fetch_mnt_id(self, "")? {
+            Some(mnt_id) => Ok(mnt_id),
+            None => Err(ErrorImpl::NotSupported {
+                feature: "STATX_MNT_ID".into(),
+            })?,
+        }
+    }
+
+    /// Within the given [`RootRef`]'s tree, resolve `path` and return a
+    /// [`Handle`].
+    ///
+    /// All symlink path components are scoped to [`RootRef`]. Trailing symlinks
+    /// *are* followed, if you want to get a handle to a symlink use
+    /// [`resolve_nofollow`].
+    ///
+    /// # Errors
+    ///
+    /// If `path` doesn't exist, or an attack was detected during resolution, a
+    /// corresponding [`Error`] will be returned. If no error is returned, then
+    /// the path is guaranteed to have been reachable from the root of the
+    /// directory tree and thus have been inside the root at one point in the
+    /// resolution.
+    ///
+    /// [`resolve_nofollow`]: Self::resolve_nofollow
+    #[doc(alias = "pathrs_inroot_resolve")]
+    #[inline]
+    pub fn resolve<P: AsRef<Path>>(&self, path: P) -> Result<Handle, Error> {
+        self.resolver.resolve(self, path, false)
+    }
+
+    /// Identical to [`resolve`], except that *trailing* symlinks are *not*
+    /// followed.
+    ///
+    /// If the trailing component is a symlink [`resolve_nofollow`] will return
+    /// a handle to the symlink itself. This is effectively equivalent to
+    /// `O_NOFOLLOW`.
+    ///
+    /// [`resolve`]: Self::resolve
+    /// [`resolve_nofollow`]: Self::resolve_nofollow
+    #[doc(alias = "pathrs_inroot_resolve_nofollow")]
+    #[inline]
+    pub fn resolve_nofollow<P: AsRef<Path>>(&self, path: P) -> Result<Handle, Error> {
+        self.resolver.resolve(self, path, true)
+    }
+
+    /// Within the given [`RootRef`]'s tree, resolve each of `paths` and
+    /// return the results in the same order.
+    ///
+    /// Each path is resolved in the same way as [`resolve`] (following
+    /// trailing symlinks), and a failure to resolve one path does not stop the
+    /// remaining paths from being resolved. This is more efficient than
+    /// calling [`resolve`] in a loop when using the emulated resolver backend,
+    /// as the handle to `/proc/thread-self/fd` used for its safety checks is
+    /// only opened once for the whole batch.
+    ///
+    /// [`resolve`]: Self::resolve
+    pub fn resolve_all<I, P>(&self, paths: I) -> Vec<Result<Handle, Error>>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        self.resolver.resolve_all(self, paths, false)
+    }
+
+    /// Resolve `path` both with and without following a trailing symlink.
+    ///
+    /// The first [`Handle`] is equivalent to [`resolve_nofollow`]. If the
+    /// trailing component of `path` is a symlink, the second [`Handle`] is the
+    /// target of the symlink (equivalent to [`resolve`]), otherwise it is
+    /// `None`. This is useful for programs (such as archivers) which need to
+    /// record information about both a symlink and its target.
+    ///
+    /// The parent directory of `path` is only resolved once, and both lookups
+    /// of the trailing component are done relative to it. If the symlink
+    /// target leaves the parent directory (such as with an absolute symlink or
+    /// `..` components), the target is instead resolved from the root. Note
+    /// that the two lookups are not atomic with respect to each other, so if
+    /// the symlink is being concurrently modified the target [`Handle`] may
+    /// not match the symlink [`Handle`].
+    ///
+    /// [`resolve`]: Self::resolve
+    /// [`resolve_nofollow`]: Self::resolve_nofollow
+    pub fn resolve_both<P: AsRef<Path>>(&self, path: P) -> Result<(Handle, Option<Handle>), Error> {
+        let path = path.as_ref();
+
+        let (parent, name) = self.resolve_parent(path)?;
+        // A trailing "." or ".." (or a trailing slash) cannot be looked up
+        // relative to the parent using it as the root of the lookup, but they
+        // also cannot refer to a symlink.
+        let name = match name {
+            Some(name) if name != Path::new(".") && name != Path::new("..") => name,
+            _ => return Ok((self.resolve_nofollow(path)?, None)),
+        };
+
+        // The trailing component cannot contain any '/', so it is safe to use
+        // the parent as the root for a lookup which doesn't follow symlinks.
+        // The mount id of the root was already verified by resolve_parent.
+        let mut scoped = self.resolver;
+        scoped.expected_mnt_id = None;
+        let link = scoped.resolve(&parent, name, true)?;
+        if !link.as_fd().metadata()?.is_symlink() {
+            return Ok((link, None));
+        }
+
+        // Follow the symlink while scoped beneath the parent. If the target
+        // would leave the parent directory this fails, and we do a full lookup
+        // from the root instead (which also gives us the right error if the
+        // target cannot be resolved at all).
+        let mut beneath = scoped;
+        beneath.flags |= ResolverFlags::NO_ESCAPE_BENEATH;
+        let target = match beneath.resolve(&parent, name, false) {
+            Ok(target) => target,
+            Err(_) => self.resolve(path)?,
+        };
+        Ok((link, Some(target)))
+    }
+
+    /// Within the given [`RootRef`]'s tree, resolve `path` and return a
+    /// [`Handle`], requiring every component to be owned by `uid`.
+    ///
+    /// This is identical to [`resolve`], except that the owner of every
+    /// component walked through during resolution (including the final
+    /// component, but not the root itself or any symlinks) is checked. If any
+    /// component is not owned by `uid`, resolution fails with an
+    /// [`ErrorKind::SafetyViolation`] error. This allows privileged programs to
+    /// avoid walking through intermediate directories that could be modified
+    /// by other users.
+    ///
+    /// This check is not supported by `openat2(2)`, so this method always
+    /// uses the emulated resolver backend. If [`ResolverBackend::KernelOpenat2`]
+    /// was explicitly requested with [`RootRef::set_resolver_backend`], an
+    /// error of kind [`ErrorKind::NotSupported`] is returned instead.
+    ///
+    /// [`resolve`]: Self::resolve
+    /// [`ErrorKind::SafetyViolation`]: crate::error::ErrorKind::SafetyViolation
+    pub fn resolve_require_owner<P: AsRef<Path>>(
+        &self,
+        path: P,
+        uid: u32,
+    ) -> Result<Handle, Error> {
+        Resolver {
+            required_owner: Some(uid),
+            ..self.resolver
+        }
+        .resolve(self, path, false)
+    }
+
+    /// Within the given [`RootRef`]'s tree, resolve `path` and return a
+    /// [`Handle`], refusing to return setuid or setgid files.
+    ///
+    /// This is identical to [`resolve`], except that if the resolved path is a
+    /// regular file with the `S_ISUID` or `S_ISGID` bits set, an error is
+    /// returned instead. Because the check is done on the resolved [`Handle`],
+    /// the returned [`Handle`] is guaranteed to refer to an inode which did not
+    /// have those bits set at the time of the check (though the bits could be
+    /// added later by someone with write access to the inode).
+    ///
+    /// Only regular files are checked, as the setuid and setgid bits do not
+    /// grant privileges for other inode types (in particular, the setgid bit
+    /// on directories only controls group inheritance and is very common).
+    ///
+    /// # Errors
+    ///
+    /// If the resolved file is setuid or setgid, an error of kind
+    /// [`ErrorKind::SafetyViolation`] is returned.
+    ///
+    /// [`resolve`]: Self::resolve
+    pub fn resolve_denying_setid<P: AsRef<Path>>(&self, path: P) -> Result<Handle, Error> {
+        let path = path.as_ref();
+        let handle = self.resolve(path)?;
+        let mode = handle
+            .as_fd()
+            .metadata()
+            .wrap("fstat resolved handle")?
+            .mode();
+        if mode & libc::S_IFMT == libc::S_IFREG && mode & (libc::S_ISUID | libc::S_ISGID) != 0 {
+            Err(ErrorImpl::SafetyViolation {
+                description: format!("{path:?} is a setuid or setgid file (mode 0o{mode:o})")
+                    .into(),
+            })?
+        }
+        Ok(handle)
+    }
+
+    /// Within the given [`RootRef`]'s tree, clear the setuid and setgid bits of
+    /// the file at `path`.
+    ///
+    /// The change is done using the file descriptor of the resolved file, so
+    /// it cannot be redirected to a different inode by a concurrent attacker
+    /// swapping path components. As with [`resolve_denying_setid`], only
+    /// regular files are modified -- if `path` is not a regular file (or does
+    /// not have either bit set) this is a no-op.
+    ///
+    /// [`resolve_denying_setid`]: Self::resolve_denying_setid
+    pub fn strip_setid<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let handle = self.resolve(path)?;
+        let mode = handle
+            .as_fd()
+            .metadata()
+            .wrap("fstat resolved handle")?
+            .mode();
+        if mode & libc::S_IFMT != libc::S_IFREG || mode & (libc::S_ISUID | libc::S_ISGID) == 0 {
+            return Ok(());
+        }
+
+        utils::fchmod_procfs(
+            &GLOBAL_PROCFS_HANDLE,
+            &handle,
+            mode & !(libc::S_IFMT | libc::S_ISUID | libc::S_ISGID),
+        )
+        .map_err(readonly_mount_context)
+        .wrap("clear setuid and setgid bits")
+    }
+
+    /// Within the given [`RootRef`]'s tree, resolve `path` and return a
+    /// [`Handle`] as well as diagnostic information about every component
+    /// walked through.
+    ///
+    /// This is identical to [`resolve`], except that a [`ComponentExplain`] is
+    /// returned for every component (including symlinks and the components of
+    /// their targets, but not the root itself) in the order they were walked
+    /// through. Each entry contains the mode and owner of the component, as
+    /// well as whether the current process has search permission on it. This
+    /// is useful for figuring out exactly why a path is not accessible.
+    ///
+    /// This information is purely diagnostic -- the permission checks are done
+    /// separately to the actual resolution and so may not match the result of
+    /// later operations if the filesystem or process credentials change. This
+    /// method always uses the emulated resolver backend, since `openat2(2)`
+    /// cannot provide per-component information.
+    ///
+    /// The components are returned even if the resolution fails, in which
+    /// case they contain every component that was walked through before the
+    /// failure (which is usually the most useful information for figuring out
+    /// why the resolution failed).
+    ///
+    /// [`resolve`]: Self::resolve
+    pub fn resolve_explain<P: AsRef<Path>>(
+        &self,
+        path: P,
+    ) -> (Result<Handle, Error>, Vec<ComponentExplain>) {
+        self.resolver.resolve_explain(self, path)
+    }
+
+    /// Within the given [`RootRef`]'s tree, get the canonical path of `path`
+    /// relative to the root.
+    ///
+    /// This is the in-root equivalent of [`std::fs::canonicalize`] (or
+    /// `realpath(3)`). `path` is resolved (following trailing symlinks, as
+    /// with [`resolve`]) and the returned path has all symlinks, `.` and `..`
+    /// components removed. For instance, if `a/c/link` is a symlink to
+    /// `target`, then `a/b/../c/link` is canonicalised to `a/c/target`. The
+    /// root itself is canonicalised to `.`.
+    ///
+    /// The returned path is always relative to the root and can be passed to
+    /// [`resolve`] (or any other [`RootRef`] method) to get the same file,
+    /// making it useful for de-duplicating paths or presenting canonical
+    /// paths to users.
+    ///
+    /// The path is computed from the `/proc/thread-self/fd/$n` paths of the
+    /// resolved handle and the root (just like the final check done by the
+    /// emulated resolver) and then verified by resolving the canonical path
+    /// (without following symlinks) and checking that it references the same
+    /// inode. Note that the canonical path is only correct at the time it was
+    /// computed -- if the tree is modified, it may no longer reference the
+    /// same file.
+    ///
+    /// # Errors
+    ///
+    /// The error rules are identical to [`resolve`]. If the resolved file is
+    /// moved (or the tree is otherwise modified) while the canonical path is
+    /// being computed, an [`ErrorKind::SafetyViolation`] error is returned.
+    ///
+    /// [`resolve`]: Self::resolve
+    /// [`ErrorKind::SafetyViolation`]: crate::error::ErrorKind::SafetyViolation
+    pub fn canonicalize<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf, Error> {
+        let handle = self.resolve(path).wrap("resolve path to canonicalise")?;
+        self.relative_path_of(&handle)
+    }
+
+    /// Get the path of `handle` relative to the [`RootRef`].
+    ///
+    /// This computes the canonical root-relative path of a [`Handle`] that was
+    /// previously resolved within the root (such as with [`resolve`]), which is
+    /// useful for logging or for storing paths in a manifest. The path is
+    /// computed in the same way as [`canonicalize`] (from the
+    /// `/proc/thread-self/fd/$n` paths of the handle and the root) and is then
+    /// verified by re-resolving it within the root and checking that it
+    /// references the same inode as `handle`.
+    ///
+    /// This method is purely diagnostic. The returned path is only correct at
+    /// the time it was computed and **must not** be used as a substitute for
+    /// operating on `handle` directly -- if you need to do something with the
+    /// file, use `handle` (or re-resolve the path with [`resolve`], accepting
+    /// that it might now refer to a different file).
+    ///
+    /// # Errors
+    ///
+    /// If the file referenced by `handle` has been deleted, an `ENOENT` error
+    /// is returned. If `handle` is not beneath the root (for instance, because
+    /// it was resolved within a different root) or the tree is modified while
+    /// the path is being computed, an [`ErrorKind::SafetyViolation`] error is
+    /// returned.
+    ///
+    /// [`resolve`]: Self::resolve
+    /// [`canonicalize`]: Self::canonicalize
+    pub fn relative_path_of(&self, handle: &Handle) -> Result<PathBuf, Error> {
+        // Unlinked files have a " (deleted)" suffix in procfs, which we could
+        // confuse with a real file with that name.
+        let handle_meta = handle.metadata().wrap("fstat handle")?;
+        if handle_meta.nlink() == 0 {
+            Err(ErrorImpl::OsError {
+                operation: "get root-relative path of handle".into(),
+                source: IOError::from_raw_os_error(libc::ENOENT),
+            })
+            .wrap("handle references a deleted file")?
+        }
+
+        let canonical = self.procfs_relative_path_of(handle)?;
+
+        // Make sure the canonical path actually refers to the file we
+        // resolved, to detect any racing renames (or unlinks).
+        let check_meta = self
+            .resolve_nofollow(&canonical)
+            .wrap("re-resolve canonical path")?
+            .metadata()
+            .wrap("fstat re-resolved handle")?;
+        if (handle_meta.dev(), handle_meta.ino()) != (check_meta.dev(), check_meta.ino()) {
+            Err(ErrorImpl::SafetyViolation {
+                description: format!(
+                    "canonical path {canonical:?} does not reference the resolved file (was the tree modified?)"
+                )
+                .into(),
+            })?
+        }
+
+        Ok(canonical)
+    }
+
+    // Get the root-relative path of handle based only on the procfs paths of
+    // the handle and the root, returning an error if handle is not beneath
+    // the root. The result is not verified, see relative_path_of.
+    fn procfs_relative_path_of<Fd: AsFd>(&self, handle: Fd) -> Result<PathBuf, Error> {
+        let root_path = self
+            .inner
+            .as_unsafe_path(&GLOBAL_PROCFS_HANDLE)
+            .wrap("get root path from procfs")?;
+        let handle_path = handle
+            .as_fd()
+            .as_unsafe_path(&GLOBAL_PROCFS_HANDLE)
+            .wrap("get handle path from procfs")?;
+        match handle_path.strip_prefix(&root_path) {
+            Ok(path) if path.as_os_str().is_empty() => Ok(PathBuf::from(".")),
+            Ok(path) => Ok(path.to_path_buf()),
+            Err(_) => Err(ErrorImpl::SafetyViolation {
+                description: format!(
+                    "handle path {handle_path:?} is not inside the root {root_path:?}"
+                )
+                .into(),
+            })?,
+        }
+    }
+
+    /// Within the given [`RootRef`]'s tree, check whether resolving `path`
+    /// would attempt to escape the root, without opening the file.
+    ///
+    /// Ordinarily, [`resolve`] scopes every path to the root -- `..`
+    /// components cannot walk above the root and absolute symlinks are
+    /// resolved relative to the root. This method instead resolves `path` as
+    /// though [`ResolverFlags::NO_ESCAPE_BENEATH`] and
+    /// [`ResolverFlags::NO_CROSS_MOUNT`] were set, and reports whether (and
+    /// why) the path would have left the root. This is intended for auditing
+    /// and fuzzing untrusted paths -- there is no need to call this before
+    /// using a path with any other [`RootRef`] method.
+    ///
+    /// If `path` resolves (following trailing symlinks) without escaping,
+    /// [`Resolution::InsideRoot`] is returned with the canonical root-relative
+    /// path of the file (see [`canonicalize`]). If some component of `path`
+    /// does not exist, [`Resolution::NotFound`] is returned.
+    ///
+    /// With [`ResolverBackend::EmulatedOpath`], the exact component and
+    /// [`EscapeReason`] of the escape attempt are reported. `openat2(2)` only
+    /// tells us that an escape was attempted, so with
+    /// [`ResolverBackend::KernelOpenat2`] the reason is
+    /// [`EscapeReason::Unknown`] (unless `path` itself is absolute) and the
+    /// component is the first component of `path` that could not be resolved.
+    ///
+    /// # Errors
+    ///
+    /// Any error other than an escape attempt or a missing component (such as
+    /// `ENOTDIR` or `ELOOP`) is returned as an error.
+    ///
+    /// [`resolve`]: Self::resolve
+    /// [`canonicalize`]: Self::canonicalize
+    pub fn classify<P: AsRef<Path>>(&self, path: P) -> Result<Resolution, Error> {
+        match self.resolver.classify(self, path)? {
+            Classification::Inside(handle) => {
+                Ok(Resolution::InsideRoot(self.relative_path_of(&handle)?))
+            }
+            Classification::Escape { component, reason } => {
+                Ok(Resolution::EscapeAttempt { component, reason })
+            }
+            Classification::NotFound => Ok(Resolution::NotFound),
+        }
+    }
+
+    /// Get a [`Handle`] to the deepest existing ancestor of `path`.
+    ///
+    /// `path` is resolved as far as possible, and a [`Handle`] to the last
+    /// directory that could be resolved is returned, along with the number of
+    /// leading components of `path` that the [`Handle`] corresponds to. Empty
+    /// components (from repeated or trailing `/`s) are not counted. If all of
+    /// `path` exists, the [`Handle`] refers to `path` itself (which might not
+    /// be a directory) and the number of components in `path` is returned.
+    ///
+    /// This is useful for code which needs to create the missing components
+    /// of a path itself, or for reporting where a path stops existing.
+    ///
+    /// Note that if resolution fails part-way through a symlink, the returned
+    /// [`Handle`] is the directory containing the symlink.
+    ///
+    /// # Errors
+    ///
+    /// If resolution fails for any reason other than a component not
+    /// existing (`ENOENT`), an error is returned.
+    pub fn open_nearest_ancestor<P: AsRef<Path>>(&self, path: P) -> Result<(Handle, usize), Error> {
+        let path = path.as_ref();
+        let count_components = |path: &Path| {
+            path.raw_components()
+                .filter(|part| !part.is_empty())
+                .count()
+        };
+
+        let (handle, remaining) = self
+            .resolver
+            .resolve_partial(self, path, false)
+            .and_then(TryInto::try_into)?;
+
+        // The remaining path is always a suffix of the original path, so we
+        // can figure out how many components were resolved by subtraction.
+        let resolved = count_components(path)
+            - remaining
+                .as_deref()
+                .map(count_components)
+                .unwrap_or_default();
+        Ok((handle, resolved))
+    }
+
+    /// Within the given [`RootRef`]'s tree, resolve as much of `path` as
+    /// possible, returning a [`Handle`] to the deepest existing component and
+    /// the remaining (non-existent) suffix of `path`.
+    ///
+    /// This is the same partial lookup used internally by [`mkdir_all`], and
+    /// is intended for users who need to implement their own "create the
+    /// missing components, then do something" logic without re-implementing
+    /// safe path resolution. If all of `path` exists, the [`Handle`] refers to
+    /// `path` itself and the remaining path is empty. If `no_follow_trailing`
+    /// is set, a trailing symlink is not followed (as with
+    /// [`resolve_nofollow`]).
+    ///
+    /// The remaining path is the literal suffix of `path` (or of the target of
+    /// the symlink being walked when resolution stopped) that was not walked
+    /// through. No cleaning is done, so it may contain `.` and `..` components
+    /// as well as repeated or trailing `/`s. Callers should be careful about
+    /// how they handle `..` components in the remaining path -- [`mkdir_all`]
+    /// refuses to create paths whose remaining path contains `..`, since `..`
+    /// could cancel out a component that does not exist.
+    ///
+    /// Note that if resolution stops part-way through a symlink, the returned
+    /// [`Handle`] is the directory containing the symlink and the remaining
+    /// path starts at the symlink (to match the behaviour of `openat2(2)`).
+    /// And (as with [`mkdir_all`]) any components created based on the result
+    /// of this method are not created atomically with this lookup, so an
+    /// attacker could create (or swap) components in the meantime.
+    ///
+    /// # Errors
+    ///
+    /// If resolution fails for any reason other than a component not
+    /// existing (`ENOENT`), an error is returned.
+    ///
+    /// [`mkdir_all`]: Self::mkdir_all
+    /// [`resolve_nofollow`]: Self::resolve_nofollow
+    pub fn resolve_partial<P: AsRef<Path>>(
+        &self,
+        path: P,
+        no_follow_trailing: bool,
+    ) -> Result<(Handle, PathBuf), Error> {
+        let (handle, remaining) = self
+            .resolver
+            .resolve_partial(self, path, no_follow_trailing)
+            .and_then(TryInto::try_into)?;
+        Ok((handle, remaining.unwrap_or_default()))
     }
 
     /// Open a path without creating an intermediate [`Handle`] object.
@@ -741,6 +3078,7 @@ impl RootRef<'_> {
     /// [`resolve`]: Self::resolve
     /// [`resolve_nofollow`]: Self::resolve_nofollow
     #[doc(alias = "pathrs_inroot_open")]
+    #[doc(alias = "open_beneath")]
     #[inline]
     pub fn open_subpath<P: AsRef<Path>, F: Into<OpenFlags>>(
         &self,
@@ -760,33 +3098,566 @@ impl RootRef<'_> {
         Ok((dir, name))
     }
 
-    /// Get the target of a symlink within a [`RootRef`].
+    /// Get the metadata of `path` within the [`RootRef`]'s tree, following
+    /// trailing symlinks.
     ///
-    /// **NOTE**: The returned path is not modified to be "safe" outside of the
-    /// root. You should not use this path for doing further path lookups -- use
-    /// [`resolve`] instead.
+    /// This is the in-root equivalent of [`std::fs::metadata`]. If `path` is a
+    /// dangling symlink, an error is returned.
     ///
-    /// This method is just shorthand for calling `readlinkat(2)` on the handle
-    /// returned by [`resolve_nofollow`].
+    /// This method is just shorthand for calling [`Handle::metadata`] on the
+    /// handle returned by [`resolve`].
     ///
     /// [`resolve`]: Self::resolve
+    pub fn metadata<P: AsRef<Path>>(&self, path: P) -> Result<Metadata, Error> {
+        self.resolve(path)?.metadata()
+    }
+
+    /// Get the metadata of `path` within the [`RootRef`]'s tree, without
+    /// following trailing symlinks.
+    ///
+    /// This is the in-root equivalent of [`std::fs::symlink_metadata`]. If
+    /// `path` is a symlink (even a dangling one), the metadata of the symlink
+    /// itself is returned.
+    ///
+    /// This method is just shorthand for calling [`Handle::metadata`] on the
+    /// handle returned by [`resolve_nofollow`].
+    ///
     /// [`resolve_nofollow`]: Self::resolve_nofollow
-    #[doc(alias = "pathrs_inroot_readlink")]
-    pub fn readlink<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf, Error> {
-        let link = self
-            .resolve_nofollow(path)
-            .wrap("resolve symlink O_NOFOLLOW for readlink")?;
-        syscalls::readlinkat(link, "").map_err(|err| {
-            ErrorImpl::RawOsError {
-                operation: "readlink resolve symlink".into(),
-                source: err,
-            }
-            .into()
-        })
+    pub fn metadata_nofollow<P: AsRef<Path>>(&self, path: P) -> Result<Metadata, Error> {
+        self.resolve_nofollow(path)?.metadata()
     }
 
-    /// Within the [`RootRef`]'s tree, create an inode at `path` as specified by
-    /// `inode_type`.
+    /// Get the extended metadata of `path` within the [`RootRef`]'s tree,
+    /// following trailing symlinks.
+    ///
+    /// `path` is resolved with [`resolve`], and then `statx(2)` is called on
+    /// the resolved handle with the fields requested in `mask` (only
+    /// requesting the fields you need lets the kernel avoid computing
+    /// expensive fields on some filesystems).
+    ///
+    /// # Errors
+    ///
+    /// On pre-4.11 kernels (which do not support `statx(2)`), an `ENOSYS` error
+    /// is returned.
+    ///
+    /// [`resolve`]: Self::resolve
+    pub fn statx<P: AsRef<Path>>(&self, path: P, mask: StatxMask) -> Result<Statx, Error> {
+        statx_handle(&self.resolve(path)?, mask)
+    }
+
+    /// Get the birth (creation) time of `path` within the [`RootRef`]'s tree,
+    /// following trailing symlinks.
+    ///
+    /// This is retrieved with `statx(2)` (requesting only `STATX_BTIME`) and
+    /// has nanosecond precision. Unlike [`Metadata::created`], if the
+    /// filesystem (or the running kernel) does not support birth times then
+    /// `Ok(None)` is returned rather than an error or a bogus timestamp.
+    ///
+    /// # Errors
+    ///
+    /// The errors are identical to [`resolve`].
+    ///
+    /// [`resolve`]: Self::resolve
+    #[doc(alias = "btime")]
+    pub fn created<P: AsRef<Path>>(&self, path: P) -> Result<Option<SystemTime>, Error> {
+        created_result(statx_handle(&self.resolve(path)?, StatxMask::BTIME))
+    }
+
+    /// Get the birth (creation) time of `path` within the [`RootRef`]'s tree,
+    /// without following trailing symlinks.
+    ///
+    /// This is identical to [`created`], except that `path` is resolved with
+    /// [`resolve_nofollow`] and so if `path` is a symlink, the birth time of
+    /// the symlink itself is returned.
+    ///
+    /// [`created`]: Self::created
+    /// [`resolve_nofollow`]: Self::resolve_nofollow
+    pub fn created_nofollow<P: AsRef<Path>>(&self, path: P) -> Result<Option<SystemTime>, Error> {
+        created_result(statx_handle(
+            &self.resolve_nofollow(path)?,
+            StatxMask::BTIME,
+        ))
+    }
+
+    /// Check whether `path` exists within the [`RootRef`]'s tree, following
+    /// trailing symlinks.
+    ///
+    /// Returns `Ok(false)` only if resolving `path` failed with `ENOENT` (so a
+    /// dangling trailing symlink is treated as not existing). All other errors
+    /// (such as `ENOTDIR`, permission errors, or [`ErrorKind::SafetyViolation`])
+    /// are returned to the caller, rather than being treated as the path not
+    /// existing.
+    ///
+    /// Note that the result is only a snapshot -- the path may be created or
+    /// removed immediately after this method returns. If you are going to
+    /// operate on the path, use [`resolve`] and operate on the handle instead.
+    ///
+    /// [`resolve`]: Self::resolve
+    /// [`ErrorKind::SafetyViolation`]: crate::error::ErrorKind::SafetyViolation
+    pub fn exists<P: AsRef<Path>>(&self, path: P) -> Result<bool, Error> {
+        exists_result(self.resolve(path))
+    }
+
+    /// Check whether `path` exists within the [`RootRef`]'s tree, without
+    /// following trailing symlinks.
+    ///
+    /// This is identical to [`exists`], except that `path` is resolved with
+    /// [`resolve_nofollow`] and so a dangling trailing symlink is considered to
+    /// exist.
+    ///
+    /// [`exists`]: Self::exists
+    /// [`resolve_nofollow`]: Self::resolve_nofollow
+    pub fn exists_nofollow<P: AsRef<Path>>(&self, path: P) -> Result<bool, Error> {
+        exists_result(self.resolve_nofollow(path))
+    }
+
+    /// Iterate over the entries of the directory at `path` within the
+    /// [`RootRef`]'s tree.
+    ///
+    /// This is the in-root equivalent of [`std::fs::read_dir`]. `path` is
+    /// resolved (following trailing symlinks) and re-opened as an
+    /// `O_DIRECTORY` handle which is held by the returned [`ReadDir`], so the
+    /// entries returned are guaranteed to be from the directory that was
+    /// resolved. The `.` and `..` entries are not returned.
+    ///
+    /// # Errors
+    ///
+    /// If `path` is not a directory, an `ENOTDIR` error is returned.
+    pub fn read_dir<P: AsRef<Path>>(&self, path: P) -> Result<ReadDir, Error> {
+        let dir = self
+            .resolve(path)?
+            .reopen(OpenFlags::O_DIRECTORY)
+            .wrap("open directory for iteration")?;
+        ReadDir::new(dir.into())
+    }
+
+    /// Recursively iterate over the entries of the directory tree at `path`
+    /// within the [`RootRef`]'s tree.
+    ///
+    /// `path` is opened in the same way as [`read_dir`], and the returned
+    /// [`Walk`] yields every entry beneath it in depth-first order. The paths of
+    /// the returned entries are `path` joined with the names of each entry and
+    /// its parent directories. Subdirectories are opened relative to their
+    /// parent without following symlinks, so the walk cannot be redirected
+    /// outside of the root. See [`Walk`] for how symlinks and errors within the
+    /// tree are handled.
+    ///
+    /// # Errors
+    ///
+    /// If `path` is not a directory, an `ENOTDIR` error is returned. Errors
+    /// encountered after the walk has started are returned by the iterator.
+    ///
+    /// [`read_dir`]: Self::read_dir
+    pub fn walk<P: AsRef<Path>>(&self, path: P) -> Result<Walk<'fd>, Error> {
+        let path = path.as_ref();
+        let dir = self
+            .resolve(path)?
+            .reopen(OpenFlags::O_DIRECTORY)
+            .wrap("open directory for walking")?;
+        Walk::new(*self, dir.into(), path.to_path_buf())
+    }
+
+    /// Change the permissions of `path` within the [`RootRef`]'s tree,
+    /// following trailing symlinks.
+    ///
+    /// `path` is resolved with [`resolve`] and the mode of the resolved handle
+    /// is changed through its `/proc/thread-self/fd/$n` magic-link (because
+    /// `fchmod(2)` does not work on `O_PATH` handles), so a symlink swapped in
+    /// by an attacker after `path` was resolved cannot redirect the operation
+    /// outside of the root.
+    ///
+    /// # Errors
+    ///
+    /// If `perm` contains bits outside of `0o7777`, an error of kind
+    /// [`ErrorKind::InvalidArgument`] is returned.
+    ///
+    /// [`resolve`]: Self::resolve
+    pub fn chmod<P: AsRef<Path>>(&self, path: P, perm: &Permissions) -> Result<(), Error> {
+        let handle = self.resolve(path)?;
+        self.chmod_handle(&handle, perm)
+    }
+
+    /// Change the permissions of `path` within the [`RootRef`]'s tree, without
+    /// following trailing symlinks.
+    ///
+    /// This is identical to [`chmod`], except that `path` is resolved with
+    /// [`resolve_nofollow`]. Note that Linux does not support changing the
+    /// permissions of symlinks, so if `path` is a symlink an `EOPNOTSUPP` error
+    /// is returned.
+    ///
+    /// [`chmod`]: Self::chmod
+    /// [`resolve_nofollow`]: Self::resolve_nofollow
+    pub fn chmod_nofollow<P: AsRef<Path>>(&self, path: P, perm: &Permissions) -> Result<(), Error> {
+        let handle = self.resolve_nofollow(path)?;
+        self.chmod_handle(&handle, perm)
+    }
+
+    fn chmod_handle(&self, handle: &Handle, perm: &Permissions) -> Result<(), Error> {
+        let mut mode = perm.mode();
+        if mode & !0o7777 != 0 {
+            Err(ErrorImpl::InvalidArgument {
+                name: "perm".into(),
+                description: "mode cannot contain non-0o7777 bits".into(),
+            })?
+        }
+        let meta = handle.as_fd().metadata()?;
+        // Older kernels would let you change the mode of a symlink through
+        // its magic-link (newer kernels return EOPNOTSUPP), so refuse to
+        // operate on symlinks ourselves to get consistent behaviour.
+        if meta.is_symlink() {
+            Err(ErrorImpl::OsError {
+                operation: "chmod".into(),
+                source: IOError::from_raw_os_error(libc::EOPNOTSUPP),
+            })
+            .wrap("cannot change the permissions of a symlink")?
+        }
+        if meta.mode() & libc::S_IFMT == libc::S_IFREG {
+            mode = self.setid_checked_mode(mode)?;
+        }
+        utils::fchmod_procfs(&GLOBAL_PROCFS_HANDLE, handle, mode).map_err(readonly_mount_context)
+    }
+
+    /// Change the owner of `path` within the [`RootRef`]'s tree, following
+    /// trailing symlinks.
+    ///
+    /// `path` is resolved with [`resolve`] and the owner of the resolved handle
+    /// is changed with `fchownat(2)` and `AT_EMPTY_PATH` (which works with
+    /// `O_PATH` handles), so a symlink swapped in by an attacker after `path`
+    /// was resolved cannot redirect the operation outside of the root. As with
+    /// `fchownat(2)`, passing `None` for `uid` or `gid` leaves that id
+    /// unchanged.
+    ///
+    /// [`resolve`]: Self::resolve
+    pub fn chown<P: AsRef<Path>>(
+        &self,
+        path: P,
+        uid: Option<u32>,
+        gid: Option<u32>,
+    ) -> Result<(), Error> {
+        let handle = self.resolve(path)?;
+        Self::chown_handle(&handle, uid, gid)
+    }
+
+    /// Change the owner of `path` within the [`RootRef`]'s tree, without
+    /// following trailing symlinks.
+    ///
+    /// This is identical to [`chown`], except that `path` is resolved with
+    /// [`resolve_nofollow`] and so if `path` is a symlink the owner of the
+    /// symlink itself is changed (like `lchown(2)`).
+    ///
+    /// [`chown`]: Self::chown
+    /// [`resolve_nofollow`]: Self::resolve_nofollow
+    pub fn chown_nofollow<P: AsRef<Path>>(
+        &self,
+        path: P,
+        uid: Option<u32>,
+        gid: Option<u32>,
+    ) -> Result<(), Error> {
+        let handle = self.resolve_nofollow(path)?;
+        Self::chown_handle(&handle, uid, gid)
+    }
+
+    fn chown_handle(handle: &Handle, uid: Option<u32>, gid: Option<u32>) -> Result<(), Error> {
+        syscalls::fchownat_empty(handle, uid, gid).map_err(|err| {
+            readonly_mount_context(
+                ErrorImpl::RawOsError {
+                    operation: "change owner of resolved handle".into(),
+                    source: err,
+                }
+                .into(),
+            )
+        })
+    }
+
+    /// Change the access and modification times of `path` within the
+    /// [`RootRef`]'s tree.
+    ///
+    /// If `follow` is `true`, `path` is resolved with [`resolve`] (following
+    /// trailing symlinks), otherwise it is resolved with [`resolve_nofollow`]
+    /// and the times of a trailing symlink itself are changed (as with
+    /// `AT_SYMLINK_NOFOLLOW`). The times are then changed using `utimensat(2)`
+    /// on the `/proc/thread-self/fd/$n` magic-link of the resolved handle, so
+    /// a symlink swapped in by an attacker after `path` was resolved cannot
+    /// redirect the operation outside of the root.
+    ///
+    /// Passing `None` for `atime` or `mtime` leaves that timestamp unchanged
+    /// (`UTIME_OMIT`). Timestamps are set with nanosecond precision (though
+    /// the filesystem may store them with a lower precision).
+    ///
+    /// # Errors
+    ///
+    /// If either of the provided times is before the Unix epoch, an error of
+    /// kind [`ErrorKind::InvalidArgument`] is returned.
+    ///
+    /// [`resolve`]: Self::resolve
+    /// [`resolve_nofollow`]: Self::resolve_nofollow
+    /// [`ErrorKind::InvalidArgument`]: crate::error::ErrorKind::InvalidArgument
+    pub fn set_times<P: AsRef<Path>>(
+        &self,
+        path: P,
+        atime: Option<SystemTime>,
+        mtime: Option<SystemTime>,
+        follow: bool,
+    ) -> Result<(), Error> {
+        let times = Timestamps {
+            last_access: utime_timespec("atime", atime)?,
+            last_modification: utime_timespec("mtime", mtime)?,
+        };
+        let handle = if follow {
+            self.resolve(path)?
+        } else {
+            self.resolve_nofollow(path)?
+        };
+        utils::futimens_procfs(&GLOBAL_PROCFS_HANDLE, &handle, &times)
+            .map_err(readonly_mount_context)
+    }
+
+    /// Get the target of a symlink within a [`RootRef`].
+    ///
+    /// **NOTE**: The returned path is not modified to be "safe" outside of the
+    /// root. You should not use this path for doing further path lookups -- use
+    /// [`resolve`] instead.
+    ///
+    /// This method is just shorthand for calling `readlinkat(2)` on the handle
+    /// returned by [`resolve_nofollow`].
+    ///
+    /// [`resolve`]: Self::resolve
+    /// [`resolve_nofollow`]: Self::resolve_nofollow
+    #[doc(alias = "pathrs_inroot_readlink")]
+    pub fn readlink<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf, Error> {
+        let link = self
+            .resolve_nofollow(path)
+            .wrap("resolve symlink O_NOFOLLOW for readlink")?;
+        syscalls::readlinkat(link, "").map_err(|err| {
+            ErrorImpl::RawOsError {
+                operation: "readlink resolve symlink".into(),
+                source: err,
+            }
+            .into()
+        })
+    }
+
+    /// Read the entire contents of a file within the [`RootRef`].
+    ///
+    /// This method is just shorthand for calling [`open_subpath`] with
+    /// [`OpenFlags::O_RDONLY`] and reading the file until EOF.
+    ///
+    /// Unlike [`std::fs::read`], the file size reported by `fstat(2)` is not
+    /// used to size the read. Many files in pseudo-filesystems such as `sysfs`
+    /// and `procfs` report an `st_size` of `0` even though they have contents,
+    /// so we always read until we hit EOF.
+    ///
+    /// # Errors
+    ///
+    /// If a read limit has been configured with [`RootRef::set_read_limit`]
+    /// and the file contains more data than the limit, an error of kind
+    /// [`ErrorKind::LimitExceeded`] is returned.
+    ///
+    /// [`open_subpath`]: Self::open_subpath
+    /// [`ErrorKind::LimitExceeded`]: crate::error::ErrorKind::LimitExceeded
+    pub fn read<P: AsRef<Path>>(&self, path: P) -> Result<Vec<u8>, Error> {
+        let file = self
+            .open_subpath(path, OpenFlags::O_RDONLY)
+            .wrap("open file for reading")?;
+        read_until_eof(file, self.read_limit)
+    }
+
+    /// Read the entire contents of a file within the [`RootRef`] into a
+    /// [`String`].
+    ///
+    /// As with [`read`], the file is read until EOF regardless of the file
+    /// size reported by `fstat(2)`.
+    ///
+    /// # Errors
+    ///
+    /// If the contents of the file are not valid UTF-8, an error is returned.
+    /// As with [`read`], the configured read limit (if any) also applies.
+    ///
+    /// [`read`]: Self::read
+    pub fn read_to_string<P: AsRef<Path>>(&self, path: P) -> Result<String, Error> {
+        let data = self.read(path)?;
+        String::from_utf8(data).map_err(|err| {
+            ErrorImpl::OsError {
+                operation: "convert file contents to string".into(),
+                source: IOError::new(IOErrorKind::InvalidData, err),
+            }
+            .into()
+        })
+    }
+
+    /// Within the [`RootRef`]'s tree, write `data` to the file at `path`,
+    /// creating it if it doesn't exist and truncating it if it does.
+    ///
+    /// This method is just shorthand for calling [`create_file`] with
+    /// `O_WRONLY|O_TRUNC` and writing all of `data` to the file. `perm` is
+    /// only used if the file is created.
+    ///
+    /// Unlike [`std::fs::write`], a trailing symlink in `path` is never
+    /// followed (`O_CREAT` would otherwise follow the symlink outside of the
+    /// [`RootRef`] in some cases), so if the final component of `path` is a
+    /// symlink an `ELOOP` error is returned. Symlinks in the parent
+    /// components of `path` are resolved inside the [`RootRef`] as usual.
+    ///
+    /// # Errors
+    ///
+    /// Identical to [`create_file`]. If an error occurs while writing the
+    /// data, the file may have been partially written.
+    ///
+    /// [`create_file`]: Self::create_file
+    pub fn write<P: AsRef<Path>>(
+        &self,
+        path: P,
+        perm: &Permissions,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        let mut file = self
+            .create_file(
+                path,
+                OpenFlags::O_WRONLY | OpenFlags::O_TRUNC | OpenFlags::O_NOFOLLOW,
+                perm,
+            )
+            .wrap("open file for writing")?;
+        file.write_all(data).map_err(|err| {
+            ErrorImpl::OsError {
+                operation: "write file contents".into(),
+                source: err,
+            }
+            .into()
+        })
+    }
+
+    /// Within the [`RootRef`]'s tree, truncate (or extend) the file at `path`
+    /// to `length` bytes.
+    ///
+    /// This is the in-root equivalent of [`truncate(2)`], and is just shorthand
+    /// for re-opening the handle returned by [`resolve`] with `O_WRONLY` and
+    /// calling [`File::set_len`]. As with `open(O_WRONLY)`, a trailing symlink
+    /// in `path` is followed (within the [`RootRef`]).
+    ///
+    /// # Errors
+    ///
+    /// If `path` is a directory, an `EISDIR` error is returned. If `path` is
+    /// any other kind of non-regular file, an `EINVAL` error is returned (as
+    /// with [`truncate(2)`]) without opening the file.
+    ///
+    /// [`truncate(2)`]: https://man7.org/linux/man-pages/man2/truncate.2.html
+    /// [`resolve`]: Self::resolve
+    pub fn truncate<P: AsRef<Path>>(&self, path: P, length: u64) -> Result<(), Error> {
+        // Check the inode type using the O_PATH handle before re-opening it,
+        // so that we never try to open FIFOs (which would block) or device
+        // inodes (which could have side-effects on open).
+        let handle = self.resolve(path)?;
+        let file_type = handle
+            .metadata()
+            .wrap("fstat file for truncation")?
+            .file_type();
+        if !file_type.is_file() {
+            Err(ErrorImpl::OsError {
+                operation: "open file for truncation".into(),
+                source: IOError::from_raw_os_error(if file_type.is_dir() {
+                    libc::EISDIR
+                } else {
+                    libc::EINVAL
+                }),
+            })?
+        }
+        let file = handle
+            .reopen(OpenFlags::O_WRONLY)
+            .map_err(readonly_mount_context)
+            .wrap("open file for truncation")?;
+        file.set_len(length).map_err(|err| {
+            readonly_mount_context(
+                ErrorImpl::OsError {
+                    operation: "truncate file".into(),
+                    source: err,
+                }
+                .into(),
+            )
+        })
+    }
+
+    /// Within the [`RootRef`]'s tree, synchronise the directory containing
+    /// `path` to disk.
+    ///
+    /// Creating, renaming or removing a file is only durable once the
+    /// directory containing it has been synced, so crash-consistent writers
+    /// usually need to call this after [`HandleRef::sync_all`] on the file
+    /// itself. The parent directory of `path` is resolved (the trailing
+    /// component of `path` is not looked up, so `path` need not exist),
+    /// re-opened read-only (because `fsync(2)` does not work on `O_PATH` file
+    /// descriptors) and then synced.
+    ///
+    /// # Errors
+    ///
+    /// If the parent of `path` is not a directory, an `ENOTDIR` error is
+    /// returned.
+    ///
+    /// [`HandleRef::sync_all`]: crate::HandleRef::sync_all
+    pub fn sync_parent<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let (parent, _) =
+            utils::path_split(path.as_ref()).wrap("split path into (parent, name)")?;
+        let dir = self
+            .resolve(parent)
+            .wrap("resolve parent directory")?
+            .reopen(OpenFlags::O_RDONLY | OpenFlags::O_DIRECTORY)
+            .wrap("open parent directory for syncing")?;
+        dir.sync_all().map_err(|err| {
+            ErrorImpl::OsError {
+                operation: "fsync parent directory".into(),
+                source: err,
+            }
+            .into()
+        })
+    }
+
+    /// Open a file within the [`RootRef`] so that it can be executed with
+    /// [`fexecve(3)`] or [`execveat(2)`] with `AT_EMPTY_PATH`.
+    ///
+    /// The file is opened with [`OpenFlags::O_RDONLY`] and is verified to be a
+    /// regular file. Executing the returned file (rather than executing the
+    /// path) guarantees that the executed inode is exactly the one that was
+    /// resolved inside the root, even if an attacker swaps the path
+    /// afterwards.
+    ///
+    /// Note that opening the file does not check whether it can actually be
+    /// executed. If the file does not have any execute bits set for the
+    /// caller, or is on a filesystem mounted with `noexec`, the subsequent
+    /// `fexecve(3)` will fail with `EACCES`. In addition, if the returned file
+    /// is a script then `fexecve(3)` will fail with `ENOENT` if the file
+    /// descriptor has `O_CLOEXEC` set (as it does by default), because the
+    /// interpreter will be unable to open `/dev/fd/...` after the exec.
+    ///
+    /// # Errors
+    ///
+    /// If `path` does not refer to a regular file, an `EACCES` error is
+    /// returned (matching the behaviour of `execve(2)` for non-regular files).
+    ///
+    /// [`fexecve(3)`]: https://man7.org/linux/man-pages/man3/fexecve.3.html
+    /// [`execveat(2)`]: https://man7.org/linux/man-pages/man2/execveat.2.html
+    pub fn open_exec<P: AsRef<Path>>(&self, path: P) -> Result<File, Error> {
+        let path = path.as_ref();
+        // Check the inode type using the O_PATH handle before re-opening it,
+        // so that we never try to open FIFOs (which would block) or device
+        // inodes (which could have side-effects on open).
+        let handle = self.resolve(path).wrap("resolve file for execution")?;
+        if !handle
+            .metadata()
+            .wrap("fstat file for execution")?
+            .is_file()
+        {
+            Err(ErrorImpl::OsError {
+                operation: "open file for execution".into(),
+                source: IOError::from_raw_os_error(libc::EACCES),
+            })
+            .with_wrap(|| format!("{path:?} is not a regular file"))?
+        }
+        handle
+            .reopen(OpenFlags::O_RDONLY)
+            .wrap("open file for execution")
+    }
+
+    /// Within the [`RootRef`]'s tree, create an inode at `path` as specified by
+    /// `inode_type`.
     ///
     /// # Errors
     ///
@@ -807,9 +3678,119 @@ impl RootRef<'_> {
             description: "file creation path has trailing slash".into(),
         })?;
 
+        self.create_at(dir, name, inode_type)
+    }
+
+    /// Within the [`RootRef`]'s tree, create a symlink at `path` pointing to
+    /// `target`, after checking that `target` resolves within the root.
+    ///
+    /// [`create`] with [`InodeType::Symlink`] creates symlinks with any target
+    /// (which is necessary for faithfully extracting archives). This method
+    /// instead first checks `target` by resolving it relative to the parent
+    /// directory of `path` with [`classify`], and refuses to create the
+    /// symlink if it would be dangling or if following it would escape the
+    /// root. Note that this means that absolute targets are always rejected
+    /// (programs outside of the root would resolve them relative to the host
+    /// `/`), as are targets that cross a mount (because [`classify`] cannot
+    /// see past mount boundaries).
+    ///
+    /// The check is only done at creation time -- the symlink may become
+    /// dangling if the tree is later modified.
+    ///
+    /// # Errors
+    ///
+    /// If `target` is dangling or escapes the root, an
+    /// [`ErrorKind::InvalidArgument`] error describing the reason is returned.
+    /// Otherwise, the error rules are identical to [`create`].
+    ///
+    /// [`create`]: Self::create
+    /// [`classify`]: Self::classify
+    pub fn symlink_checked<P: AsRef<Path>, T: AsRef<Path>>(
+        &self,
+        path: P,
+        target: T,
+    ) -> Result<(), Error> {
+        let (path, target) = (path.as_ref(), target.as_ref());
+        let (parent, _) = utils::path_split(path).wrap("split path into (parent, name)")?;
+        // Paths are always resolved relative to the root, but an absolute
+        // parent joined with the target would look like an absolute symlink
+        // target to classify (which is always rejected).
+        let parent = parent.strip_prefix("/").unwrap_or(parent);
+        match self
+            .classify(parent.join(target))
+            .wrap("check symlink target")?
+        {
+            Resolution::InsideRoot(_) => (),
+            Resolution::NotFound => Err(ErrorImpl::InvalidArgument {
+                name: "target".into(),
+                description: format!("symlink target {target:?} is dangling").into(),
+            })?,
+            Resolution::EscapeAttempt { component, reason } => Err(ErrorImpl::InvalidArgument {
+                name: "target".into(),
+                description: format!(
+                    "symlink target {target:?} escapes the root ({reason:?} at component {component:?})"
+                )
+                .into(),
+            })?,
+        }
+        self.create(path, &InodeType::Symlink(target.to_path_buf()))
+    }
+
+    /// Create an inode named `name` inside the already-resolved directory
+    /// `dir`, as specified by `inode_type`.
+    ///
+    /// This is equivalent to [`create`], except that the parent directory is
+    /// not looked up again for each call. This can be significantly more
+    /// efficient when creating many inodes in the same directory. `dir` should
+    /// be a [`Handle`] to a directory that was resolved within this
+    /// [`RootRef`] (such as one returned by [`resolve`] or [`mkdir_all`]).
+    ///
+    /// `name` must be a single path component (see [`Component`]). Note that
+    /// the target of an [`InodeType::Hardlink`] is still resolved within the
+    /// [`RootRef`] as usual.
+    ///
+    /// To make sure that a [`Handle`] from elsewhere (such as one resolved
+    /// within a different [`RootRef`]) cannot be used to create inodes outside
+    /// of the root, the `/proc/thread-self/fd` path of `dir` is checked to be
+    /// beneath the path of the root (as in [`relative_path_of`], but without
+    /// re-resolving the path).
+    ///
+    /// # Errors
+    ///
+    /// If `name` is not a single path component, an
+    /// [`ErrorKind::InvalidArgument`] error is returned. If `dir` is not
+    /// beneath the root, an [`ErrorKind::SafetyViolation`] error is returned.
+    /// Otherwise, the error rules are identical to [`create`].
+    ///
+    /// [`create`]: Self::create
+    /// [`resolve`]: Self::resolve
+    /// [`mkdir_all`]: Self::mkdir_all
+    /// [`relative_path_of`]: Self::relative_path_of
+    /// [`Component`]: crate::Component
+    /// [`ErrorKind::InvalidArgument`]: crate::error::ErrorKind::InvalidArgument
+    /// [`ErrorKind::SafetyViolation`]: crate::error::ErrorKind::SafetyViolation
+    pub fn create_in<N: AsRef<OsStr>>(
+        &self,
+        dir: &Handle,
+        name: N,
+        inode_type: &InodeType,
+    ) -> Result<(), Error> {
+        let name = Component::new(name).wrap("validate file creation name")?;
+        self.procfs_relative_path_of(dir)
+            .wrap("check that create_in directory is inside the root")?;
+        self.create_at(dir, name.as_path(), inode_type)
+    }
+
+    // Create an inode with the final component name inside dir.
+    fn create_at<Fd: AsFd>(
+        &self,
+        dir: Fd,
+        name: &Path,
+        inode_type: &InodeType,
+    ) -> Result<(), Error> {
         match inode_type {
             InodeType::File(perm) => {
-                let mode = perm.mode() & !libc::S_IFMT;
+                let mode = self.setid_checked_mode(perm.mode())? & !libc::S_IFMT;
                 syscalls::mknodat(dir, name, libc::S_IFREG | mode, 0)
             }
             InodeType::Directory(perm) => {
@@ -834,6 +3815,10 @@ impl RootRef<'_> {
                 let mode = perm.mode() & !libc::S_IFMT;
                 syscalls::mknodat(dir, name, libc::S_IFIFO | mode, 0)
             }
+            InodeType::Socket(perm) => {
+                let mode = perm.mode() & !libc::S_IFMT;
+                syscalls::mknodat(dir, name, libc::S_IFSOCK | mode, 0)
+            }
             InodeType::CharacterDevice(perm, dev) => {
                 let mode = perm.mode() & !libc::S_IFMT;
                 syscalls::mknodat(dir, name, libc::S_IFCHR | mode, *dev)
@@ -844,11 +3829,17 @@ impl RootRef<'_> {
             }
         }
         .map_err(|err| {
-            ErrorImpl::RawOsError {
-                operation: "pathrs create".into(),
-                source: err,
+            let err = readonly_mount_context(
+                ErrorImpl::RawOsError {
+                    operation: "pathrs create".into(),
+                    source: err,
+                }
+                .into(),
+            );
+            match inode_type {
+                InodeType::CharacterDevice(..) | InodeType::BlockDevice(..) => mknod_context(err),
+                _ => err,
             }
-            .into()
         })
     }
 
@@ -869,12 +3860,21 @@ impl RootRef<'_> {
     /// for ordinary files because there is no [`O_CREAT`]-equivalent for other
     /// inode types.
     ///
+    /// `O_CREAT` is added to `flags` automatically, and all other flags are
+    /// passed through unchanged. In particular, if `flags` contains `O_TRUNC`
+    /// (but not `O_EXCL`) and the file already exists, it is atomically
+    /// truncated when it is opened. Similarly, `O_SYNC` or `O_DSYNC` can be
+    /// used to make writes to the returned file synchronous -- but note that
+    /// making the creation of the file itself durable requires syncing its
+    /// parent directory with [`sync_parent`].
+    ///
     /// # Errors
     ///
     /// Identical to [`create`].
     ///
     /// [`create`]: Self::create
     /// [`create_file`]: Self::create_file
+    /// [`sync_parent`]: Self::sync_parent
     /// [`O_CREAT`]: http://man7.org/linux/man-pages/man2/open.2.html
     #[doc(alias = "pathrs_inroot_creat")]
     #[doc(alias = "pathrs_inroot_create")]
@@ -884,6 +3884,8 @@ impl RootRef<'_> {
         mut flags: OpenFlags,
         perm: &Permissions,
     ) -> Result<File, Error> {
+        let mode = self.setid_checked_mode(perm.mode())?;
+
         // The path doesn't exist yet, so we need to get a safe reference to the
         // parent and just operate on the final (slashless) component.
         let (dir, name) = self
@@ -898,16 +3900,570 @@ impl RootRef<'_> {
         // O_NOFOLLOW. We might want to expose that here, though because it
         // can't be done with the emulated backend that might be a bad idea.
         flags.insert(OpenFlags::O_CREAT);
-        let fd = syscalls::openat(dir, name, flags, perm.mode()).map_err(|err| {
-            ErrorImpl::RawOsError {
-                operation: "pathrs create_file".into(),
-                source: err,
-            }
+        let fd = syscalls::openat(dir, name, flags, mode).map_err(|err| {
+            readonly_mount_context(
+                ErrorImpl::RawOsError {
+                    operation: "pathrs create_file".into(),
+                    source: err,
+                }
+                .into(),
+            )
+        })?;
+
+        Ok(fd.into())
+    }
+
+    /// Within the [`RootRef`]'s tree, create a file with [`create_file`] and
+    /// set its owner to `uid` and `gid`.
+    ///
+    /// The owner is changed with `fchown(2)` on the file descriptor returned
+    /// by [`create_file`], which is guaranteed to be the inode that was
+    /// created, so (unlike calling [`chown`] on `path` afterwards) an attacker
+    /// swapping the path cannot redirect the ownership change to a different
+    /// file. Note that the ownership change is not atomic with the creation of
+    /// the file -- the file briefly has the ownership of the calling process
+    /// (and keeps it if changing the owner fails, in which case the created
+    /// file is not removed).
+    ///
+    /// As with [`create_file`], if `flags` does not contain `O_EXCL` then an
+    /// existing file at `path` is opened and its owner is changed.
+    ///
+    /// Changing the owner of a file to an arbitrary `uid` and `gid` requires
+    /// `CAP_CHOWN` (in the user namespace owning the filesystem).
+    ///
+    /// # Errors
+    ///
+    /// Identical to [`create_file`], as well as any error returned by
+    /// `fchown(2)` (such as `EPERM` if the process does not have `CAP_CHOWN`).
+    ///
+    /// [`create_file`]: Self::create_file
+    /// [`chown`]: Self::chown
+    #[doc(alias = "create_file_with_ownership")]
+    pub fn create_file_owned<P: AsRef<Path>>(
+        &self,
+        path: P,
+        flags: OpenFlags,
+        perm: &Permissions,
+        uid: u32,
+        gid: u32,
+    ) -> Result<File, Error> {
+        let file = self.create_file(path, flags, perm)?;
+        syscalls::fchown(&file, uid, gid).map_err(|err| {
+            readonly_mount_context(
+                ErrorImpl::RawOsError {
+                    operation: "change owner of newly created file".into(),
+                    source: err,
+                }
+                .into(),
+            )
         })?;
+        Ok(file)
+    }
+
+    /// Within the [`RootRef`]'s tree, create an anonymous (unnamed) file inside
+    /// the directory `dir` with `O_TMPFILE` and the mode given by `perm`.
+    ///
+    /// The file is created on the filesystem of `dir` (which is resolved
+    /// within the [`RootRef`], following trailing symlinks) but has no name
+    /// and so is not visible to other processes. It can later be given a name
+    /// inside the [`RootRef`]'s tree with [`link_tmpfile`] (unless `flags`
+    /// contains `O_EXCL`), which allows for an atomic "write then link into
+    /// place" pattern. If the file is never linked, it is removed
+    /// automatically once the returned [`File`] is closed.
+    ///
+    /// `flags` must contain either `O_WRONLY` or `O_RDWR`, and `O_TMPFILE` is
+    /// added automatically.
+    ///
+    /// # Errors
+    ///
+    /// If `flags` does not request write access or contains `O_CREAT`,
+    /// `O_PATH` or `O_DIRECTORY`, an [`ErrorKind::InvalidArgument`] error is
+    /// returned. If the filesystem of `dir` (or the running kernel) does not
+    /// support `O_TMPFILE`, an [`ErrorKind::NotSupported`] error is returned.
+    ///
+    /// [`link_tmpfile`]: Self::link_tmpfile
+    /// [`ErrorKind::InvalidArgument`]: crate::error::ErrorKind::InvalidArgument
+    /// [`ErrorKind::NotSupported`]: crate::error::ErrorKind::NotSupported
+    pub fn create_tmpfile<P: AsRef<Path>>(
+        &self,
+        dir: P,
+        flags: OpenFlags,
+        perm: &Permissions,
+    ) -> Result<File, Error> {
+        if !matches!(
+            flags.access_mode(),
+            Some(libc::O_WRONLY) | Some(libc::O_RDWR)
+        ) {
+            Err(ErrorImpl::InvalidArgument {
+                name: "flags".into(),
+                description: "O_TMPFILE files must be opened with O_WRONLY or O_RDWR".into(),
+            })?
+        }
+        let invalid_flags = OpenFlags::O_CREAT | OpenFlags::O_PATH | OpenFlags::O_DIRECTORY;
+        if flags.intersects(invalid_flags) {
+            Err(ErrorImpl::InvalidArgument {
+                name: "flags".into(),
+                description: format!(
+                    "invalid flags {:?} specified for O_TMPFILE",
+                    flags.intersection(invalid_flags)
+                )
+                .into(),
+            })?
+        }
+
+        let mode = self.setid_checked_mode(perm.mode())?;
+
+        let dir = self
+            .resolve(dir)
+            .wrap("resolve O_TMPFILE parent directory")?;
+
+        let fd = syscalls::openat(dir, ".", flags | OpenFlags::O_TMPFILE, mode).map_err(
+            |err| -> Error {
+                // Filesystems without O_TMPFILE support return EOPNOTSUPP, and
+                // kernels without O_TMPFILE support (pre-3.11) treat it as
+                // O_DIRECTORY and so return EISDIR.
+                if matches!(err.errno(), Errno::OPNOTSUPP | Errno::ISDIR) {
+                    ErrorImpl::NotSupported {
+                        feature: "O_TMPFILE".into(),
+                    }
+                    .into()
+                } else {
+                    readonly_mount_context(
+                        ErrorImpl::RawOsError {
+                            operation: "pathrs create_tmpfile".into(),
+                            source: err,
+                        }
+                        .into(),
+                    )
+                }
+            },
+        )?;
 
         Ok(fd.into())
     }
 
+    /// Within the [`RootRef`]'s tree, give an anonymous file created by
+    /// [`create_tmpfile`] the name `path`.
+    ///
+    /// The parent directory of `path` is resolved within the [`RootRef`], and
+    /// `path` must not already exist. Linking is done through the
+    /// `/proc/thread-self/fd/$n` magic-link of `file` (because `linkat(2)` with
+    /// `AT_EMPTY_PATH` requires `CAP_DAC_READ_SEARCH`).
+    ///
+    /// Note that `linkat(2)` cannot link a file across filesystems, so `path`
+    /// must be on the same filesystem as the directory the file was created
+    /// in.
+    ///
+    /// # Errors
+    ///
+    /// The error rules are identical to [`linkat(2)`]. In particular, files
+    /// created with `O_EXCL` cannot be linked (resulting in an `ENOENT` error).
+    ///
+    /// [`create_tmpfile`]: Self::create_tmpfile
+    /// [`linkat(2)`]: http://man7.org/linux/man-pages/man2/linkat.2.html
+    pub fn link_tmpfile<Fd: AsFd, P: AsRef<Path>>(&self, file: Fd, path: P) -> Result<(), Error> {
+        let (dir, name) = self
+            .resolve_parent(path.as_ref())
+            .wrap("resolve O_TMPFILE link path")?;
+        let name = name.ok_or_else(|| ErrorImpl::InvalidArgument {
+            name: "path".into(),
+            description: "O_TMPFILE link path has trailing slash".into(),
+        })?;
+
+        utils::linkat_procfs(&GLOBAL_PROCFS_HANDLE, file, dir, name).map_err(readonly_mount_context)
+    }
+
+    /// Within the [`RootRef`]'s tree, exclusively create a lock file at `path`
+    /// and take an exclusive `flock(2)` lock on it.
+    ///
+    /// The file is created with `O_CREAT|O_EXCL` and the mode given by `perm`.
+    /// The returned [`LockFile`] guard removes the file and releases the lock
+    /// when it is dropped (or when [`LockFile::release`] is called).
+    ///
+    /// # Errors
+    ///
+    /// If `path` already exists (meaning someone else currently holds the
+    /// lock), an error with [`ErrorKind::AlreadyHeld`] is returned. Otherwise,
+    /// the errors are identical to [`create_file`].
+    ///
+    /// [`ErrorKind::AlreadyHeld`]: crate::error::ErrorKind::AlreadyHeld
+    /// [`create_file`]: Self::create_file
+    pub fn create_lockfile<P: AsRef<Path>>(
+        &self,
+        path: P,
+        perm: &Permissions,
+    ) -> Result<LockFile, Error> {
+        let path = path.as_ref();
+        let mode = self.setid_checked_mode(perm.mode())?;
+        let (dir, name) = self
+            .resolve_parent(path)
+            .wrap("resolve lockfile creation path")?;
+        let name = name.ok_or_else(|| ErrorImpl::InvalidArgument {
+            name: "path".into(),
+            description: "lockfile creation path has trailing slash".into(),
+        })?;
+
+        let file: File = syscalls::openat(
+            &dir,
+            name,
+            OpenFlags::O_CREAT | OpenFlags::O_EXCL | OpenFlags::O_RDWR,
+            mode,
+        )
+        .map_err(|err| -> Error {
+            if err.errno() == Errno::EXIST {
+                ErrorImpl::AlreadyHeld {
+                    description: format!("lockfile {path:?} already exists").into(),
+                }
+                .into()
+            } else {
+                readonly_mount_context(
+                    ErrorImpl::RawOsError {
+                        operation: "pathrs create_lockfile".into(),
+                        source: err,
+                    }
+                    .into(),
+                )
+            }
+        })?
+        .into();
+
+        let mut lockfile = LockFile {
+            dir,
+            name: name.into(),
+            file,
+            released: false,
+        };
+
+        match syscalls::flock(&lockfile.file, FlockOperation::NonBlockingLockExclusive) {
+            Ok(()) => Ok(lockfile),
+            Err(err) => {
+                // Someone raced with us and locked the file we just created.
+                // They think they own it, so we must not remove it.
+                if err.errno() == Errno::WOULDBLOCK {
+                    lockfile.released = true;
+                    Err(ErrorImpl::AlreadyHeld {
+                        description: format!("lockfile {path:?} was locked by someone else").into(),
+                    })?
+                }
+                // Otherwise, dropping lockfile will remove the file.
+                Err(ErrorImpl::RawOsError {
+                    operation: "lock lockfile".into(),
+                    source: err,
+                })?
+            }
+        }
+    }
+
+    /// Within the [`RootRef`]'s tree, create a symlink at `path` pointing to
+    /// `target`, and return an `O_PATH` [`Handle`] to the symlink itself.
+    ///
+    /// This is equivalent to creating an [`InodeType::Symlink`] with
+    /// [`create`] and then using [`resolve_nofollow`] to get a handle to it,
+    /// except that the symlink is re-opened relative to the already-resolved
+    /// parent directory (avoiding a second lookup of the full path). Note that
+    /// (unlike [`create_file`]) a symlink cannot be created and opened
+    /// atomically, so (as with [`mkdir_all`]) we cannot guarantee that the
+    /// returned handle is the exact symlink we created. However, we do verify
+    /// that the handle is a symlink with the expected `target`.
+    ///
+    /// As with [`InodeType::Symlink`], the contents of `target` are not
+    /// verified in any way.
+    ///
+    /// # Errors
+    ///
+    /// Identical to [`create`]. In addition, if the symlink was swapped for a
+    /// different inode after it was created, an error is returned.
+    ///
+    /// [`create`]: Self::create
+    /// [`create_file`]: Self::create_file
+    /// [`mkdir_all`]: Self::mkdir_all
+    /// [`resolve_nofollow`]: Self::resolve_nofollow
+    pub fn symlink<P: AsRef<Path>, T: AsRef<Path>>(
+        &self,
+        path: P,
+        target: T,
+    ) -> Result<Handle, Error> {
+        let target = target.as_ref();
+        let (dir, name) = self
+            .resolve_parent(path.as_ref())
+            .wrap("resolve symlink creation path")?;
+        let name = name.ok_or_else(|| ErrorImpl::InvalidArgument {
+            name: "path".into(),
+            description: "symlink creation path has trailing slash".into(),
+        })?;
+
+        syscalls::symlinkat(target, &dir, name).map_err(|err| {
+            readonly_mount_context(
+                ErrorImpl::RawOsError {
+                    operation: "pathrs symlink".into(),
+                    source: err,
+                }
+                .into(),
+            )
+        })?;
+
+        // There is no O_CREAT equivalent for symlinks, so we need to re-open
+        // the symlink we just created. Because we open it relative to the
+        // parent directory, the only possible attack is an attacker swapping
+        // the symlink, which we can partially detect.
+        let link = syscalls::openat(&dir, name, OpenFlags::O_PATH | OpenFlags::O_NOFOLLOW, 0)
+            .map_err(|err| ErrorImpl::RawOsError {
+                operation: "open newly created symlink".into(),
+                source: err,
+            })?;
+
+        if !link
+            .metadata()
+            .wrap("fstat newly created symlink")?
+            .is_symlink()
+        {
+            Err(ErrorImpl::SafetyViolation {
+                description: format!(
+                    "newly created symlink {name:?} was swapped with a non-symlink"
+                )
+                .into(),
+            })?
+        }
+        let link_target = syscalls::readlinkat(&link, "").map_err(|err| ErrorImpl::RawOsError {
+            operation: "readlink newly created symlink".into(),
+            source: err,
+        })?;
+        if link_target != target {
+            Err(ErrorImpl::SafetyViolation {
+                description: format!(
+                    "newly created symlink {name:?} was swapped with a symlink to {link_target:?} (expected {target:?})"
+                )
+                .into(),
+            })?
+        }
+
+        Ok(Handle::from_fd(link))
+    }
+
+    /// Within the [`RootRef`]'s tree, copy the contents of the regular file at
+    /// `source` to a newly-created file at `destination`.
+    ///
+    /// Both `source` and `destination` are resolved inside the [`RootRef`]
+    /// (`destination` is created with [`create_file`] using `flags`), and the
+    /// copy is done entirely using the resulting file descriptors, so neither
+    /// side of the copy can be redirected while the copy is in progress. The
+    /// contents are copied with [`copy_file_range(2)`], falling back to an
+    /// ordinary read-write loop if the kernel or filesystem does not support
+    /// it (such as when copying between different filesystems on older
+    /// kernels). Holes in `source` (found with `SEEK_DATA` and `SEEK_HOLE`)
+    /// are skipped over rather than copied, so sparse files stay sparse,
+    /// unless `flags` contains `O_APPEND` or the filesystems do not support
+    /// sparse files.
+    ///
+    /// If `perm` is `None`, the new file is created with the permission bits
+    /// of `source` (as with [`create_file`], the process umask still
+    /// applies). The setuid, setgid and sticky bits of `source` are not
+    /// copied, so that copying a file cannot silently create a new setuid
+    /// binary -- if you need them, pass the mode explicitly with `perm`. The
+    /// returned [`File`] is the handle to `destination` that was written to.
+    ///
+    /// If a read limit has been configured with [`RootRef::set_read_limit`],
+    /// it also applies to the amount of data copied from `source`.
+    ///
+    /// # Errors
+    ///
+    /// If `source` is not a regular file, or `flags` does not permit writing
+    /// to the new file, an error is returned. If `source` contains more data
+    /// than the configured read limit, an [`ErrorKind::LimitExceeded`] error
+    /// is returned (if `source` grows during the copy, `destination` may have
+    /// already been partially written). Otherwise, the errors are
+    /// identical to [`open_subpath`] and [`create_file`]. Note that if
+    /// `flags` does not contain [`OpenFlags::O_EXCL`], an existing file at
+    /// `destination` will be opened (and, without [`OpenFlags::O_TRUNC`], only
+    /// partially overwritten).
+    ///
+    /// [`create_file`]: Self::create_file
+    /// [`open_subpath`]: Self::open_subpath
+    /// [`ErrorKind::LimitExceeded`]: crate::error::ErrorKind::LimitExceeded
+    /// [`copy_file_range(2)`]: https://man7.org/linux/man-pages/man2/copy_file_range.2.html
+    pub fn copy<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        source: P,
+        destination: Q,
+        flags: OpenFlags,
+        perm: Option<&Permissions>,
+    ) -> Result<File, Error> {
+        let source = source.as_ref();
+        if !matches!(flags.access_mode(), Some(libc::O_WRONLY | libc::O_RDWR)) {
+            Err(ErrorImpl::InvalidArgument {
+                name: "flags".into(),
+                description: "copy destination must be opened for writing".into(),
+            })?
+        }
+
+        // Check the inode type using the O_PATH handle before re-opening it,
+        // so that we never try to open FIFOs (which would block) or device
+        // inodes (which could have side-effects on open).
+        let src = self.resolve(source).wrap("resolve copy source")?;
+        let src_meta = src.metadata().wrap("fstat copy source")?;
+        if !src_meta.is_file() {
+            Err(ErrorImpl::InvalidArgument {
+                name: "source".into(),
+                description: format!("copy source {source:?} is not a regular file").into(),
+            })?
+        }
+        if let Some(limit) = self.read_limit {
+            if src_meta.len() > limit {
+                Err(ErrorImpl::LimitExceeded {
+                    description: format!(
+                        "copy source {source:?} is larger than the read limit ({limit} bytes)"
+                    )
+                    .into(),
+                })?
+            }
+        }
+        let mut src = src
+            .reopen(OpenFlags::O_RDONLY)
+            .wrap("open copy source for reading")?;
+        let perm = match perm {
+            Some(perm) => perm.clone(),
+            None => Permissions::from_mode(src_meta.mode() & 0o777),
+        };
+
+        let mut dst = self
+            .create_file(destination, flags, &perm)
+            .wrap("create copy destination")?;
+
+        copy_file_contents(
+            &mut src,
+            &mut dst,
+            flags.contains(OpenFlags::O_APPEND),
+            self.read_limit,
+        )?;
+
+        Ok(dst)
+    }
+
+    /// Within the [`RootRef`]'s tree, create a new file at `destination` that
+    /// shares the data of the regular file at `source` using the `FICLONE`
+    /// ioctl (a "reflink" copy, as with `cp --reflink`).
+    ///
+    /// As with [`copy`], both `source` and `destination` are resolved inside
+    /// the [`RootRef`] and the clone is done entirely using the resulting file
+    /// descriptors. `destination` must not already exist, and is created with
+    /// the mode given by `perm` (the process umask still applies). The
+    /// returned [`File`] is a read-write handle to the new file.
+    ///
+    /// Reflinks are only supported by some filesystems (such as btrfs and
+    /// XFS), and both files must be on the same filesystem. If the clone is
+    /// not possible and `copy_fallback` is set, the contents are copied in the
+    /// same manner as [`copy`] instead. If the clone (or the fallback copy)
+    /// fails, the newly created `destination` is removed (as long as it has
+    /// not been swapped with a different inode) and an error is returned.
+    ///
+    /// # Errors
+    ///
+    /// If the clone is not possible (the filesystem does not support reflinks
+    /// or `source` and `destination` are on different filesystems) and
+    /// `copy_fallback` is not set, an [`ErrorKind::NotSupported`] error is
+    /// returned. If `source` is not a regular file, an
+    /// [`ErrorKind::InvalidArgument`] error is returned. Otherwise, the errors
+    /// are identical to [`open_subpath`] and [`create_file`].
+    ///
+    /// [`copy`]: Self::copy
+    /// [`create_file`]: Self::create_file
+    /// [`open_subpath`]: Self::open_subpath
+    /// [`ErrorKind::InvalidArgument`]: crate::error::ErrorKind::InvalidArgument
+    /// [`ErrorKind::NotSupported`]: crate::error::ErrorKind::NotSupported
+    #[doc(alias = "FICLONE")]
+    pub fn reflink<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        source: P,
+        destination: Q,
+        perm: &Permissions,
+        copy_fallback: bool,
+    ) -> Result<File, Error> {
+        let source = source.as_ref();
+
+        // Check the inode type using the O_PATH handle before re-opening it,
+        // so that we never try to open FIFOs (which would block) or device
+        // inodes (which could have side-effects on open).
+        let src = self.resolve(source).wrap("resolve reflink source")?;
+        if !src.metadata().wrap("fstat reflink source")?.is_file() {
+            Err(ErrorImpl::InvalidArgument {
+                name: "source".into(),
+                description: format!("reflink source {source:?} is not a regular file").into(),
+            })?
+        }
+        let mut src = src
+            .reopen(OpenFlags::O_RDONLY)
+            .wrap("open reflink source for reading")?;
+
+        // We create the destination by hand (rather than with create_file) so
+        // that we can remove it from the same directory if the clone fails.
+        let mode = self.setid_checked_mode(perm.mode())?;
+        let (dir, name) = self
+            .resolve_parent(destination.as_ref())
+            .wrap("resolve reflink destination")?;
+        let name = name.ok_or_else(|| ErrorImpl::InvalidArgument {
+            name: "destination".into(),
+            description: "reflink destination has trailing slash".into(),
+        })?;
+        let mut dst: File = syscalls::openat(
+            &dir,
+            name,
+            OpenFlags::O_RDWR | OpenFlags::O_CREAT | OpenFlags::O_EXCL,
+            mode,
+        )
+        .map_err(|err| {
+            readonly_mount_context(
+                ErrorImpl::RawOsError {
+                    operation: "create reflink destination".into(),
+                    source: err,
+                }
+                .into(),
+            )
+        })?
+        .into();
+
+        let err: Error = match syscalls::ficlone(&dst, &src) {
+            Ok(()) => return Ok(dst),
+            // The filesystem doesn't support reflinks (older kernels return
+            // ENOTTY for unknown ioctls), or the files are not on the same
+            // filesystem.
+            Err(err)
+                if matches!(
+                    err.errno(),
+                    Errno::OPNOTSUPP | Errno::XDEV | Errno::INVAL | Errno::NOTTY
+                ) =>
+            {
+                if copy_fallback {
+                    match copy_file_contents(&mut src, &mut dst, false, self.read_limit) {
+                        Ok(()) => return Ok(dst),
+                        Err(err) => err,
+                    }
+                } else {
+                    ErrorImpl::NotSupported {
+                        feature: "FICLONE reflinks".into(),
+                    }
+                    .into()
+                }
+            }
+            Err(err) => ErrorImpl::RawOsError {
+                operation: "clone file contents".into(),
+                source: err,
+            }
+            .into(),
+        };
+        // Clean up the destination we just created. This is relative to the
+        // same directory handle, so it cannot remove anything outside of the
+        // root, but the name could have been swapped with a different inode in
+        // the meantime so we only remove it if it is still our file.
+        if let (Ok(dst_stat), Ok(name_stat)) =
+            (syscalls::fstatat(&dst, ""), syscalls::fstatat(&dir, name))
+        {
+            if (dst_stat.st_dev, dst_stat.st_ino) == (name_stat.st_dev, name_stat.st_ino) {
+                let _ = syscalls::unlinkat(&dir, name, AtFlags::empty());
+            }
+        }
+        Err(err)
+    }
+
     /// Within the [`RootRef`]'s tree, create a directory and any of its parent
     /// component if they are missing.
     ///
@@ -938,6 +4494,46 @@ impl RootRef<'_> {
     /// [`os.MkdirAll`]: https://pkg.go.dev/os#MkdirAll
     #[doc(alias = "pathrs_inroot_mkdir_all")]
     pub fn mkdir_all<P: AsRef<Path>>(&self, path: P, perm: &Permissions) -> Result<Handle, Error> {
+        Self::check_mkdir_perm(perm)?;
+        self.mkdir_all_with(path, |_| (perm.clone(), None))
+    }
+
+    /// Within the [`RootRef`]'s tree, create a directory and any of its parent
+    /// component if they are missing, setting the owner of any newly-created
+    /// directories to `uid` and `gid`.
+    ///
+    /// This is identical to [`mkdir_all`], except that each directory created
+    /// by this function is `fchown(2)`-ed (using the file descriptor opened for
+    /// the new directory) to the given owner before the next component is
+    /// created. Existing components do not have their permissions or owner
+    /// modified. This is useful for creating directories owned by a mapped user
+    /// (such as in rootless containers) without needing to walk the tree again
+    /// afterwards.
+    ///
+    /// Note that (as with [`mkdir_all`]) a directory cannot be created and
+    /// opened atomically, so an attacker with write access to a parent
+    /// directory could swap a newly-created directory with their own before it
+    /// is opened (in which case the attacker's directory would be `chown`-ed).
+    ///
+    /// # Errors
+    ///
+    /// In addition to the errors returned by [`mkdir_all`], changing the owner
+    /// of a directory generally requires `CAP_CHOWN`.
+    ///
+    /// [`mkdir_all`]: Self::mkdir_all
+    pub fn mkdir_all_owned<P: AsRef<Path>>(
+        &self,
+        path: P,
+        perm: &Permissions,
+        uid: u32,
+        gid: u32,
+    ) -> Result<Handle, Error> {
+        Self::check_mkdir_perm(perm)?;
+        self.mkdir_all_with(path, |_| (perm.clone(), Some((uid, gid))))
+    }
+
+    // Check that a mode is valid for mkdir_all.
+    fn check_mkdir_perm(perm: &Permissions) -> Result<(), Error> {
         if perm.mode() & !0o7777 != 0 {
             Err(ErrorImpl::InvalidArgument {
                 name: "perm".into(),
@@ -956,12 +4552,65 @@ impl RootRef<'_> {
                         .into(),
             })?
         }
+        Ok(())
+    }
 
+    /// Within the [`RootRef`]'s tree, create a directory and any of its parent
+    /// component if they are missing, with the mode and owner of each created
+    /// directory chosen by `per_component`.
+    ///
+    /// This is identical to [`mkdir_all`] (and [`mkdir_all_owned`]), except
+    /// that `per_component` is called before each directory is created and
+    /// returns the [`Permissions`] to create it with and (optionally) the
+    /// `(uid, gid)` to change its owner to. This makes it possible to
+    /// reproduce an intended ownership and mode layout (such as copying the
+    /// mode and owner of a template tree or the parent directory) in a single
+    /// pass. `per_component` is not called for existing components.
+    ///
+    /// The path passed to `per_component` is the root-relative path of the
+    /// directory about to be created, made up of the already-existing prefix
+    /// of `path` (exactly as it was passed, so it may contain symlinks or
+    /// `..` components) followed by the components created so far.
+    ///
+    /// # Errors
+    ///
+    /// The error rules are identical to [`mkdir_all_owned`]. If
+    /// `per_component` returns a mode containing bits outside of `0o1777`, an
+    /// [`ErrorKind::InvalidArgument`] error is returned (and no more
+    /// directories are created).
+    ///
+    /// [`mkdir_all`]: Self::mkdir_all
+    /// [`mkdir_all_owned`]: Self::mkdir_all_owned
+    pub fn mkdir_all_with<P, F>(&self, path: P, mut per_component: F) -> Result<Handle, Error>
+    where
+        P: AsRef<Path>,
+        F: FnMut(&Path) -> (Permissions, Option<(u32, u32)>),
+    {
+        let path = path.as_ref();
         let (handle, remaining) = self
             .resolver
-            .resolve_partial(self, path.as_ref(), false)
+            .resolve_partial(self, path, false)
             .and_then(TryInto::try_into)?;
 
+        // The remaining components are always a suffix of path (resolution
+        // stops at the top-most dangling symlink), so we can compute the path
+        // of each created component from the existing prefix of path.
+        let remaining_bytes = remaining
+            .as_ref()
+            .map(|remaining| remaining.as_os_str().as_bytes())
+            .unwrap_or_default();
+        let mut component_path = path
+            .as_os_str()
+            .as_bytes()
+            .strip_suffix(remaining_bytes)
+            .map(|prefix| PathBuf::from(OsStr::from_bytes(prefix)))
+            .ok_or_else(|| ErrorImpl::SafetyViolation {
+                description: format!(
+                    "remaining path {remaining:?} of mkdir_all is not a suffix of {path:?}"
+                )
+                .into(),
+            })?;
+
         // Re-open the handle with O_DIRECTORY to make sure it's a directory we
         // can use as well as to make sure we return an O_DIRECTORY regardless
         // of whether there are any remaining components (for consistency).
@@ -1013,19 +4662,26 @@ impl RootRef<'_> {
             // dangling symlink with only a trailing component missing), so we
             // can safely create the final component without worrying about
             // symlink-exchange attacks.
-            if let Err(err) = syscalls::mkdirat(&current, &part, perm.mode()) {
+            component_path.push(&part);
+            let (perm, owner) = per_component(&component_path);
+            Self::check_mkdir_perm(&perm)?;
+
+            let created = match syscalls::mkdirat(&current, &part, perm.mode()) {
+                Ok(()) => true,
                 // If we got EEXIST then either the directory existed before or
                 // a racing Root::mkdir_all created the directory before us. We
                 // can safely continue because the following openat() will only
                 // succeed if it is a directory at open()-time (and not another
                 // inode type an attacker might've swapped in).
-                if err.errno() != Errno::EXIST {
-                    Err(ErrorImpl::RawOsError {
+                Err(err) if err.errno() == Errno::EXIST => false,
+                Err(err) => Err(readonly_mount_context(
+                    ErrorImpl::RawOsError {
                         operation: "create next directory component".into(),
                         source: err,
-                    })?;
-                }
-            }
+                    }
+                    .into(),
+                ))?,
+            };
 
             // Get a handle to the directory we just created. Unfortunately we
             // can't do an atomic create+open (a-la O_CREAT) with mkdirat(), so
@@ -1060,239 +4716,1512 @@ impl RootRef<'_> {
             // verifying that directories we create weren't swapped really
             // doesn't seem to provide any practical benefit.
 
-            // Keep walking.
-            current = next.into();
-        }
+            // Only change the owner of directories we created, so that we do
+            // not modify existing directories.
+            if let (true, Some((uid, gid))) = (created, owner) {
+                syscalls::fchown(&next, uid, gid).map_err(|err| ErrorImpl::RawOsError {
+                    operation: "change owner of newly created directory".into(),
+                    source: err,
+                })?;
+            }
+
+            // Keep walking.
+            current = next.into();
+        }
+
+        Ok(Handle::from_fd(current))
+    }
+
+    /// Within the [`RootRef`]'s tree, remove the inode of type `inode_type` at
+    /// `path`.
+    ///
+    /// Any existing [`Handle`]s to `path` will continue to work as before,
+    /// since Linux does not invalidate file handles to unlinked files (though,
+    /// directory handling is not as simple).
+    ///
+    /// # Errors
+    ///
+    /// If the path does not exist, was not actually `inode_type`, or was a
+    /// non-empty directory an error will be returned. In order to remove a path
+    /// regardless of whether it exists, its type, or if it it's a non-empty
+    /// directory, you can use [`remove_all`].
+    ///
+    /// [`remove_all`]: Self::remove_all
+    fn remove_inode(&self, path: &Path, inode_type: RemoveInodeType) -> Result<(), Error> {
+        // unlinkat(2) doesn't let us remove an inode using just a handle (for
+        // obvious reasons -- on Unix hardlinks mean that "unlink this file"
+        // doesn't make sense without referring to a specific directory entry).
+        let (dir, name) = self
+            .resolve_parent(path.as_ref())
+            .wrap("resolve file removal path")?;
+        // TODO: rmdir() lets you use trailing slashes. We should probably allow
+        //       that too...
+        let name = name.ok_or_else(|| ErrorImpl::InvalidArgument {
+            name: "path".into(),
+            description: "file removal path has trailing slash".into(),
+        })?;
+
+        let flags = match inode_type {
+            RemoveInodeType::Regular => AtFlags::empty(),
+            RemoveInodeType::Directory => AtFlags::REMOVEDIR,
+        };
+        syscalls::unlinkat(dir, name, flags).map_err(|err| {
+            readonly_mount_context(
+                ErrorImpl::RawOsError {
+                    operation: "pathrs remove".into(),
+                    source: err,
+                }
+                .into(),
+            )
+        })
+    }
+
+    /// Within the [`RootRef`]'s tree, remove the empty directory at `path`.
+    ///
+    /// Any existing [`Handle`]s to `path` will continue to work as before,
+    /// since Linux does not invalidate file handles to unlinked files (though,
+    /// directory handling is not as simple).
+    ///
+    /// # Errors
+    ///
+    /// If the path does not exist, was not actually a directory, or was a
+    /// non-empty directory an error will be returned. In order to remove a
+    /// directory and all of its children, you can use [`remove_all`].
+    ///
+    /// [`remove_all`]: Self::remove_all
+    #[doc(alias = "pathrs_inroot_rmdir")]
+    #[inline]
+    pub fn remove_dir<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        self.remove_inode(path.as_ref(), RemoveInodeType::Directory)
+    }
+
+    /// Within the [`RootRef`]'s tree, remove the file (any non-directory inode)
+    /// at `path`.
+    ///
+    /// Any existing [`Handle`]s to `path` will continue to work as before,
+    /// since Linux does not invalidate file handles to unlinked files (though,
+    /// directory handling is not as simple).
+    ///
+    /// # Errors
+    ///
+    /// If the path does not exist or was actually a directory an error will be
+    /// returned. In order to remove a path regardless of its type (even if it
+    /// is a non-empty directory), you can use [`remove_all`].
+    ///
+    /// [`remove_all`]: Self::remove_all
+    #[doc(alias = "pathrs_inroot_unlink")]
+    #[inline]
+    pub fn remove_file<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        self.remove_inode(path.as_ref(), RemoveInodeType::Regular)
+    }
+
+    /// Within the [`RootRef`]'s tree, recursively delete the provided `path`
+    /// and any children it contains if it is a directory. This is effectively
+    /// equivalent to [`std::fs::remove_dir_all`], Go's [`os.RemoveAll`], or
+    /// Unix's `rm -r`.
+    ///
+    /// Any existing [`Handle`]s to paths within `path` will continue to work as
+    /// before, since Linux does not invalidate file handles to unlinked files
+    /// (though, directory handling is not as simple).
+    ///
+    /// # Errors
+    ///
+    /// If the path does not exist or some other error occurred during the
+    /// deletion process an error will be returned.
+    ///
+    /// [`os.RemoveAll`]: https://pkg.go.dev/os#RemoveAll
+    #[doc(alias = "pathrs_inroot_remove_all")]
+    pub fn remove_all<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        self.remove_all_with(path, |_, _| ControlFlow::Break(()))
+    }
+
+    /// Within the [`RootRef`]'s tree, recursively delete the provided `path`,
+    /// calling `on_error` for every entry that could not be deleted.
+    ///
+    /// This is identical to [`remove_all`], except that rather than always
+    /// giving up on the first error, `on_error` is called with the path of the
+    /// entry that could not be deleted (`path` joined with the names of the
+    /// entry and its parent directories below `path`, so it is relative to
+    /// the [`RootRef`] if `path` is) and the corresponding error. If `on_error`
+    /// returns:
+    ///
+    ///  * [`ControlFlow::Break`], the deletion is aborted and the error is
+    ///    returned (this is what [`remove_all`] does).
+    ///  * [`ControlFlow::Continue`], the entry is skipped and the rest of the
+    ///    tree is deleted on a best-effort basis. The parent directories of a
+    ///    skipped entry cannot be deleted (since they are not empty), but
+    ///    `on_error` is not called for them.
+    ///
+    /// Entries which are concurrently deleted by another process are not
+    /// considered errors.
+    ///
+    /// # Errors
+    ///
+    /// If `on_error` returned [`ControlFlow::Break`], the error passed to it is
+    /// returned. If `on_error` returned [`ControlFlow::Continue`] for every
+    /// error, `Ok(())` is returned even though `path` may not have been
+    /// completely deleted, so `on_error` should keep track of any failures
+    /// you care about. Errors that happen while resolving the parent
+    /// directory of `path` are returned directly without calling `on_error`.
+    ///
+    /// [`remove_all`]: Self::remove_all
+    pub fn remove_all_with<P, F>(&self, path: P, on_error: F) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+        F: FnMut(&Path, &Error) -> ControlFlow<()>,
+    {
+        let path = path.as_ref();
+        let (dir, name) = self.resolve_parent(path).wrap("resolve remove-all path")?;
+        // TODO: rmdir() lets you use trailing slashes. We should probably allow
+        //       that too...
+        let name = name.ok_or_else(|| ErrorImpl::InvalidArgument {
+            name: "path".into(),
+            description: "file removal path has trailing slash".into(),
+        })?;
+
+        utils::remove_all_with(&dir, name, path, on_error).map_err(readonly_mount_context)
+    }
+
+    /// Within the [`RootRef`]'s tree, perform a rename with the given `source`
+    /// and `directory`. The `flags` argument is passed directly to
+    /// [`renameat2(2)`].
+    ///
+    /// # Errors
+    ///
+    /// The error rules are identical to [`renameat2(2)`].
+    ///
+    /// [`renameat2(2)`]: http://man7.org/linux/man-pages/man2/renameat2.2.html
+    #[doc(alias = "pathrs_inroot_rename")]
+    pub fn rename<P: AsRef<Path>>(
+        &self,
+        source: P,
+        destination: P,
+        rflags: RenameFlags,
+    ) -> Result<(), Error> {
+        self.rename_impl(source.as_ref(), destination.as_ref(), rflags)
+            .map(|_| ())
+    }
+
+    /// Within the [`RootRef`]'s tree, perform a rename with the given `source`
+    /// and `destination` (as with [`rename`]), and return `O_PATH` handles to
+    /// the renamed inodes.
+    ///
+    /// The second element of the returned tuple is a handle to the inode that
+    /// is now at `destination` (the inode that was previously at `source`).
+    /// If `rflags` contains `RENAME_EXCHANGE`, the first element is a handle
+    /// to the inode that is now at `source` (the inode that was previously at
+    /// `destination`), otherwise it is `None`.
+    ///
+    /// The handles are opened after the rename has completed, by looking up
+    /// the final components of `source` and `destination` (without following
+    /// symlinks) inside the parent directories that were resolved for the
+    /// rename. Because a rename and open cannot be done atomically, an
+    /// attacker with write access to either parent directory could swap the
+    /// renamed inodes with their own before they are opened (as with
+    /// [`mkdir_all`]). However, the returned handles are always inside the
+    /// [`RootRef`]'s tree.
+    ///
+    /// # Errors
+    ///
+    /// The error rules are identical to [`rename`]. If opening either of the
+    /// renamed inodes fails, an error is returned but the rename is not
+    /// reverted.
+    ///
+    /// [`rename`]: Self::rename
+    /// [`mkdir_all`]: Self::mkdir_all
+    pub fn rename_at<P: AsRef<Path>>(
+        &self,
+        source: P,
+        destination: P,
+        rflags: RenameFlags,
+    ) -> Result<(Option<Handle>, Handle), Error> {
+        let ((src_dir, src_name), (dst_dir, dst_name)) =
+            self.rename_impl(source.as_ref(), destination.as_ref(), rflags)?;
+
+        let open_renamed = |dir: &OwnedFd, name: &Path| -> Result<Handle, Error> {
+            syscalls::openat(dir, name, OpenFlags::O_PATH | OpenFlags::O_NOFOLLOW, 0)
+                .map(Handle::from_fd)
+                .map_err(|err| {
+                    ErrorImpl::RawOsError {
+                        operation: "open renamed inode".into(),
+                        source: err,
+                    }
+                    .into()
+                })
+        };
+
+        let src_handle = if rflags.contains(RenameFlags::RENAME_EXCHANGE) {
+            Some(open_renamed(&src_dir, src_name).wrap("open exchanged rename source")?)
+        } else {
+            None
+        };
+        let dst_handle = open_renamed(&dst_dir, dst_name).wrap("open rename destination")?;
+        Ok((src_handle, dst_handle))
+    }
+
+    /// Do a [`renameat2(2)`] and return the parent directory handles and
+    /// final components that were used for the rename.
+    ///
+    /// [`renameat2(2)`]: http://man7.org/linux/man-pages/man2/renameat2.2.html
+    #[allow(clippy::type_complexity)]
+    fn rename_impl<'p>(
+        &self,
+        source: &'p Path,
+        destination: &'p Path,
+        rflags: RenameFlags,
+    ) -> Result<((OwnedFd, &'p Path), (OwnedFd, &'p Path)), Error> {
+        // renameat2(2) doesn't let us rename paths using just handles. In
+        // addition, the target path might not exist (except in the case of
+        // RENAME_EXCHANGE and clobbering).
+        let (src_dir, src_name) = self
+            .resolve_parent(source)
+            .wrap("resolve rename source path")?;
+        let src_name = src_name.ok_or_else(|| ErrorImpl::InvalidArgument {
+            name: "source".into(),
+            description: "rename source path has trailing slash".into(),
+        })?;
+        let (dst_dir, dst_name) = self
+            .resolve_parent(destination)
+            .wrap("resolve rename destination path")?;
+        let dst_name = dst_name.ok_or_else(|| ErrorImpl::InvalidArgument {
+            name: "destination".into(),
+            description: "rename destination path has trailing slash".into(),
+        })?;
+
+        syscalls::renameat2(&src_dir, src_name, &dst_dir, dst_name, rflags).map_err(|err| {
+            readonly_mount_context(
+                ErrorImpl::RawOsError {
+                    operation: "pathrs rename".into(),
+                    source: err,
+                }
+                .into(),
+            )
+        })?;
+        Ok(((src_dir, src_name), (dst_dir, dst_name)))
+    }
+
+    /// Find all paths within `search_root` in the [`RootRef`]'s tree which
+    /// are hardlinks to the file at `path`.
+    ///
+    /// `path` is resolved (following trailing symlinks) and then
+    /// `search_root` is scanned recursively for other directory entries
+    /// referencing the same inode (the same `st_dev` and `st_ino`). The
+    /// returned paths are `search_root` joined with the path of each entry
+    /// relative to `search_root`, and include `path` itself if it is inside
+    /// `search_root`. The scan stops early once `st_nlink` links have been
+    /// found.
+    ///
+    /// The scan never follows symlinks and does not descend into directories
+    /// on other filesystems, so it cannot escape `search_root`. Directories
+    /// which loop back to one of their ancestors (such as with bind-mounts)
+    /// are only scanned once, and subdirectories which cannot be read due to
+    /// insufficient permissions are skipped. However, the scan is not atomic
+    /// -- if the tree is modified during the scan, links may be missed and the
+    /// returned paths may no longer be accurate by the time they are used. The
+    /// returned paths should be re-resolved within the [`RootRef`] before they
+    /// are used.
+    ///
+    /// # Errors
+    ///
+    /// If `path` is a directory, an error of kind
+    /// [`ErrorKind::InvalidArgument`] is returned (directories cannot have
+    /// hardlinks).
+    pub fn find_hardlinks<P: AsRef<Path>, P2: AsRef<Path>>(
+        &self,
+        path: P,
+        search_root: P2,
+    ) -> Result<Vec<PathBuf>, Error> {
+        let search_root = search_root.as_ref();
+        let meta = self
+            .resolve(path)?
+            .as_fd()
+            .metadata()
+            .wrap("fstat hardlink target")?;
+        if meta.mode() & libc::S_IFMT == libc::S_IFDIR {
+            Err(ErrorImpl::InvalidArgument {
+                name: "path".into(),
+                description: "cannot find hardlinks of a directory".into(),
+            })?
+        }
+
+        let dir = self
+            .resolve(search_root)
+            .wrap("resolve hardlink search root")?
+            .reopen(OpenFlags::O_DIRECTORY)
+            .wrap("open hardlink search root")?;
+        let mut found = Vec::new();
+        utils::find_inode_links(
+            dir.into(),
+            search_root,
+            (meta.dev(), meta.ino()),
+            meta.nlink() as usize,
+            &mut found,
+        )?;
+        Ok(found)
+    }
+
+    /// Check whether `path` within the [`RootRef`]'s tree is a directory that
+    /// can be searched by the current process.
+    ///
+    /// `path` is resolved (following trailing symlinks) and the result is
+    /// collapsed into a [`DirProbe`], which is more convenient for pre-flight
+    /// checks than inspecting the errors returned by [`RootRef::resolve`].
+    /// Search permission is checked using the effective credentials of the
+    /// current process (including capabilities and ACLs).
+    ///
+    /// As with any pre-flight check, the result may be out of date by the time
+    /// it is acted upon.
+    ///
+    /// # Errors
+    ///
+    /// Errors other than those mapped to a [`DirProbe`] variant (such as
+    /// [`ErrorKind::SafetyViolation`]) are returned as-is.
+    pub fn probe_dir<P: AsRef<Path>>(&self, path: P) -> Result<DirProbe, Error> {
+        let handle = match self.resolve(path) {
+            Ok(handle) => handle,
+            Err(err) => {
+                return match err.kind() {
+                    ErrorKind::OsError(Some(libc::ENOENT)) => Ok(DirProbe::Missing),
+                    ErrorKind::OsError(Some(libc::ENOTDIR)) => Ok(DirProbe::NotADirectory),
+                    ErrorKind::OsError(Some(libc::EACCES)) => Ok(DirProbe::NoSearchPermission),
+                    _ => Err(err),
+                }
+            }
+        };
+
+        let meta = handle.as_fd().metadata().wrap("fstat probed directory")?;
+        if meta.mode() & libc::S_IFMT != libc::S_IFDIR {
+            return Ok(DirProbe::NotADirectory);
+        }
+
+        // Looking up "." requires search permission on the directory, so this
+        // is equivalent to checking for X_OK on the directory itself.
+        let res = match syscalls::faccessat(&handle, ".", Access::EXEC_OK) {
+            // faccessat2(2) is not supported on pre-5.8 kernels, so fall back
+            // to looking up "." with an O_PATH open.
+            Err(err) if matches!(err.errno(), Errno::NOSYS | Errno::INVAL) => {
+                syscalls::openat(&handle, ".", OpenFlags::O_PATH | OpenFlags::O_DIRECTORY, 0)
+                    .map(|_| ())
+            }
+            res => res,
+        };
+        match res {
+            Ok(()) => Ok(DirProbe::Usable),
+            Err(err) if err.errno() == Errno::ACCESS => Ok(DirProbe::NoSearchPermission),
+            Err(err) => Err(ErrorImpl::RawOsError {
+                operation: "check search permission of probed directory".into(),
+                source: err,
+            })?,
+        }
+    }
+
+    /// Check whether the current process can access `path` within the
+    /// [`RootRef`]'s tree with the given `mode`, in the same manner as
+    /// [`access(2)`].
+    ///
+    /// `path` is resolved inside the [`RootRef`] (following trailing symlinks
+    /// only if `follow` is set), and the check is then done on the resolved
+    /// handle with [`faccessat(2)`] and `AT_EACCESS` through its
+    /// `/proc/thread-self/fd` magic-link, so the checked inode is always the
+    /// one inside the root. As with `AT_EACCESS`, the effective credentials of
+    /// the current process are used (including capabilities and ACLs). An
+    /// empty `mode` (`F_OK`) only checks that `path` can be resolved.
+    ///
+    /// Note that any access check is inherently racy: the result may be out of
+    /// date by the time it is acted upon (the permissions of the inode may be
+    /// changed, or `path` may be swapped for a different inode). It is always
+    /// safer to just attempt the operation and handle the error.
+    ///
+    /// # Errors
+    ///
+    /// If the access check (or resolving `path`) fails with `EACCES`,
+    /// `Ok(false)` is returned. Other errors (such as `ENOENT` if `path` does
+    /// not exist, or `EROFS` if checking [`AccessMode::W_OK`] on a read-only
+    /// mount) are returned as-is.
+    ///
+    /// [`access(2)`]: http://man7.org/linux/man-pages/man2/access.2.html
+    /// [`faccessat(2)`]: http://man7.org/linux/man-pages/man2/faccessat.2.html
+    #[doc(alias = "faccessat")]
+    pub fn access<P: AsRef<Path>>(
+        &self,
+        path: P,
+        mode: AccessMode,
+        follow: bool,
+    ) -> Result<bool, Error> {
+        let res = if follow {
+            self.resolve(path)
+        } else {
+            self.resolve_nofollow(path)
+        };
+        let res = res.and_then(|handle| {
+            utils::faccessat_procfs(
+                &GLOBAL_PROCFS_HANDLE,
+                handle,
+                Access::from_bits_retain(mode.bits() as _),
+            )
+        });
+        match res {
+            Ok(()) => Ok(true),
+            Err(err) if err.kind() == ErrorKind::OsError(Some(libc::EACCES)) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Check whether a process with the given credentials would be permitted
+    /// to access `path` within the [`RootRef`]'s tree with the given `mode`.
+    ///
+    /// Unlike [`faccessat(2)`] (which checks the credentials of the calling
+    /// process), this simulates the kernel's discretionary access control
+    /// checks for the user `uid` with primary group `gid` and supplementary
+    /// `groups`. `path` is resolved (following trailing symlinks, with
+    /// [`RootRef::resolve_explain`]) and search permission is checked for the
+    /// root and every directory walked through (including those in symlink
+    /// targets), and `mode` is checked for the final component.
+    ///
+    /// Only the owner, group and other permission bits are taken into account.
+    /// Notably, this means the following are **not** simulated:
+    ///
+    /// * POSIX ACLs.
+    /// * Capabilities -- `uid` 0 is treated as having `CAP_DAC_OVERRIDE`, and
+    ///   no other user is treated as having any capabilities.
+    /// * LSMs (such as SELinux or AppArmor).
+    /// * Read-only mounts, immutable inodes, or `noexec` mounts.
+    ///
+    /// The resolution itself is done with the credentials of the calling
+    /// process, so this method will return an error if the calling process
+    /// cannot resolve `path`. As with [`RootRef::resolve_explain`], the result
+    /// is purely advisory and may be out of date if the filesystem is modified
+    /// concurrently.
+    ///
+    /// [`faccessat(2)`]: http://man7.org/linux/man-pages/man2/faccessat.2.html
+    pub fn access_as<P: AsRef<Path>>(
+        &self,
+        path: P,
+        uid: u32,
+        gid: u32,
+        groups: &[u32],
+        mode: AccessMode,
+    ) -> Result<bool, Error> {
+        let (handle, components) = self.resolve_explain(path);
+        let handle = handle?;
+
+        let dac_permits = |owner: u32, group: u32, file_mode: u32, want: AccessMode| -> bool {
+            let is_dir = file_mode & libc::S_IFMT == libc::S_IFDIR;
+            if uid == 0 {
+                // CAP_DAC_OVERRIDE permits everything except executing
+                // non-directories without any execute bits set.
+                return !want.contains(AccessMode::X_OK) || is_dir || file_mode & 0o111 != 0;
+            }
+            let shift = if uid == owner {
+                6
+            } else if gid == group || groups.contains(&group) {
+                3
+            } else {
+                0
+            };
+            let granted = AccessMode::from_bits_truncate(((file_mode >> shift) & 0o7) as _);
+            granted.contains(want)
+        };
+
+        // Search permission is needed for the root and every directory walked
+        // through, except for the final component (which may itself be a
+        // directory). Symlinks do not have meaningful permissions.
+        if !components.is_empty() {
+            let root_meta = self.inner.metadata().wrap("fstat root directory")?;
+            if !dac_permits(
+                root_meta.uid(),
+                root_meta.gid(),
+                root_meta.mode(),
+                AccessMode::X_OK,
+            ) {
+                return Ok(false);
+            }
+        }
+        let walked = &components[..components.len().saturating_sub(1)];
+        for component in walked {
+            if component.mode() & libc::S_IFMT == libc::S_IFDIR
+                && !dac_permits(
+                    component.uid(),
+                    component.gid(),
+                    component.mode(),
+                    AccessMode::X_OK,
+                )
+            {
+                return Ok(false);
+            }
+        }
+
+        let meta = handle.as_fd().metadata().wrap("fstat access target")?;
+        Ok(dac_permits(meta.uid(), meta.gid(), meta.mode(), mode))
+    }
+
+    /// Within the [`RootRef`]'s tree, rewrite the absolute symlink at `path` so
+    /// that its target starts with `new_prefix` rather than `old_prefix`.
+    ///
+    /// This is intended for migrating a subtree to a new location within a
+    /// root, where absolute symlinks (which are resolved relative to the root)
+    /// need to be updated to point to the new location. The symlink is
+    /// replaced atomically, by creating a new symlink with a temporary name
+    /// next to `path` and then renaming it over `path`.
+    ///
+    /// The prefix matching rule is as follows:
+    ///
+    ///  * Only absolute targets are rewritten. Relative targets are left
+    ///    untouched (and `None` is returned), as are absolute targets which do
+    ///    not start with `old_prefix`.
+    ///  * Matching is done on whole components (as with [`Path::strip_prefix`]),
+    ///    so an `old_prefix` of `/foo` matches `/foo` and `/foo/bar` but not
+    ///    `/foobar`. Repeated `/` separators, `.` components and trailing `/`s
+    ///    are ignored for the purposes of matching.
+    ///  * The remaining components of the target are appended to `new_prefix`
+    ///    as-is.
+    ///
+    /// If the symlink was rewritten, the new target is returned.
+    ///
+    /// Note that the symlink is not locked while it is being rewritten, so if
+    /// another process modifies `path` concurrently, its change may be
+    /// overwritten.
+    ///
+    /// # Errors
+    ///
+    /// If `path` is not a symlink, an error is returned. If `old_prefix` or
+    /// `new_prefix` are not absolute, or if any of `old_prefix`, `new_prefix`
+    /// or the target of a matching symlink contain `..` components, an error of
+    /// kind [`ErrorKind::InvalidArgument`] is returned. `..` components are
+    /// rejected because whether they are matched by the prefix depends on what
+    /// the preceding components are (which cannot be determined lexically),
+    /// and rejecting them also ensures the new target cannot be used to
+    /// reference paths above `new_prefix`.
+    pub fn rebase_symlink<P: AsRef<Path>, P1: AsRef<Path>, P2: AsRef<Path>>(
+        &self,
+        path: P,
+        old_prefix: P1,
+        new_prefix: P2,
+    ) -> Result<Option<PathBuf>, Error> {
+        let (old_prefix, new_prefix) = (old_prefix.as_ref(), new_prefix.as_ref());
+        for (name, prefix) in [("old_prefix", old_prefix), ("new_prefix", new_prefix)] {
+            if !prefix.is_absolute() {
+                Err(ErrorImpl::InvalidArgument {
+                    name: name.into(),
+                    description: "prefix must be an absolute path".into(),
+                })?
+            }
+            if prefix.components().any(|c| c == PathComponent::ParentDir) {
+                Err(ErrorImpl::InvalidArgument {
+                    name: name.into(),
+                    description: "prefix must not contain '..' components".into(),
+                })?
+            }
+        }
+
+        let (dir, name) = self
+            .resolve_parent(path.as_ref())
+            .wrap("resolve symlink parent directory")?;
+        let name = name.ok_or_else(|| ErrorImpl::InvalidArgument {
+            name: "path".into(),
+            description: "symlink path has trailing slash".into(),
+        })?;
+
+        let target = syscalls::readlinkat(&dir, name).map_err(|err| ErrorImpl::RawOsError {
+            operation: "read symlink target".into(),
+            source: err,
+        })?;
+        let remaining = match target.strip_prefix(old_prefix) {
+            Ok(remaining) if target.is_absolute() => remaining,
+            _ => return Ok(None),
+        };
+        if remaining
+            .components()
+            .any(|c| c == PathComponent::ParentDir)
+        {
+            Err(ErrorImpl::InvalidArgument {
+                name: "path".into(),
+                description: format!(
+                    "symlink target {target:?} contains '..' components and cannot be safely rebased"
+                )
+                .into(),
+            })?
+        }
+        let new_target = if remaining.as_os_str().is_empty() {
+            new_prefix.to_path_buf()
+        } else {
+            new_prefix.join(remaining)
+        };
+
+        // Create the new symlink with a temporary name and then rename it on
+        // top of the old one, so that the symlink is never missing.
+        let mut tmp_name = None;
+        for _ in 0..16 {
+            let candidate = temporary_name("rebase-symlink");
+            match syscalls::symlinkat(&new_target, &dir, &candidate) {
+                Ok(()) => {
+                    tmp_name = Some(candidate);
+                    break;
+                }
+                Err(err) if err.root_cause().raw_os_error() == Some(libc::EEXIST) => continue,
+                Err(err) => Err(readonly_mount_context(
+                    ErrorImpl::RawOsError {
+                        operation: "create rebased symlink".into(),
+                        source: err,
+                    }
+                    .into(),
+                ))?,
+            }
+        }
+        let tmp_name = tmp_name.ok_or_else(|| ErrorImpl::OsError {
+            operation: "create rebased symlink".into(),
+            source: IOError::from_raw_os_error(libc::EEXIST),
+        })?;
+        syscalls::renameat(&dir, &tmp_name, &dir, name).map_err(|err| {
+            let _ = syscalls::unlinkat(&dir, &tmp_name, AtFlags::empty());
+            ErrorImpl::RawOsError {
+                operation: "replace symlink with rebased symlink".into(),
+                source: err,
+            }
+        })?;
+        Ok(Some(new_target))
+    }
+}
+
+// Generate a name for a temporary file which is unlikely to collide with other
+// files (or other temporary files created by this process).
+fn temporary_name(purpose: &str) -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    format!(
+        ".pathrs-{purpose}.{}.{}",
+        process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+// Creating device inodes without CAP_MKNOD fails with a bare EPERM, which is
+// quite confusing for users running inside restricted containers. Add some
+// context to make the cause clearer (the errno is unchanged).
+fn mknod_context(err: Error) -> Error {
+    match err.kind() {
+        ErrorKind::OsError(Some(libc::EPERM)) if !can_mknod() => {
+            err.wrap("creating device inodes requires CAP_MKNOD, which appears to be unavailable")
+        }
+        _ => err,
+    }
+}
+
+// Write operations on a read-only mount fail with a bare EROFS, which can be
+// quite confusing for long-running programs whose mounts were remounted
+// read-only underneath them. Add some context to make the cause clearer (the
+// errno is unchanged, so this doesn't affect programmatic error handling).
+fn readonly_mount_context(err: Error) -> Error {
+    match err.kind() {
+        ErrorKind::OsError(Some(libc::EROFS)) => err.wrap("mount is read-only"),
+        _ => err,
+    }
+}
+
+/// Get the [`Statx`] of an already-resolved handle.
+fn statx_handle(handle: &Handle, mask: StatxMask) -> Result<Statx, Error> {
+    syscalls::statx(handle, "", mask.into())
+        .map(Statx::from_rustix)
+        .map_err(|err| {
+            ErrorImpl::RawOsError {
+                operation: "statx resolved handle".into(),
+                source: err,
+            }
+            .into()
+        })
+}
+
+/// Map the result of a `statx(2)` to the birth time, if it is available.
+fn created_result(res: Result<Statx, Error>) -> Result<Option<SystemTime>, Error> {
+    match res {
+        Ok(stx) => Ok(stx.btime()),
+        // Pre-4.11 kernels don't have statx(2), and so cannot tell us the
+        // birth time.
+        Err(err) if err.kind() == ErrorKind::OsError(Some(libc::ENOSYS)) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Map the result of a lookup to whether the path exists, only treating
+/// `ENOENT` as the path not existing.
+fn exists_result(res: Result<Handle, Error>) -> Result<bool, Error> {
+    match res {
+        Ok(_) => Ok(true),
+        Err(err) if err.kind() == ErrorKind::OsError(Some(libc::ENOENT)) => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+/// Convert an optional [`SystemTime`] to a [`Timespec`] for `utimensat(2)`,
+/// with `None` mapping to `UTIME_OMIT`.
+fn utime_timespec(name: &'static str, time: Option<SystemTime>) -> Result<Timespec, Error> {
+    let time = match time {
+        None => {
+            return Ok(Timespec {
+                tv_sec: 0,
+                tv_nsec: rustix_fs::UTIME_OMIT,
+            })
+        }
+        Some(time) => time,
+    };
+    let since_epoch = time
+        .duration_since(UNIX_EPOCH)
+        .map_err(|_| ErrorImpl::InvalidArgument {
+            name: name.into(),
+            description: "times before the unix epoch are not supported".into(),
+        })?;
+    Ok(Timespec {
+        tv_sec: since_epoch
+            .as_secs()
+            .try_into()
+            .map_err(|_| ErrorImpl::InvalidArgument {
+                name: name.into(),
+                description: "time is too far in the future".into(),
+            })?,
+        tv_nsec: since_epoch.subsec_nanos() as _,
+    })
+}
+
+/// Copy the remaining contents of `src` into `dst` (starting from the current
+/// file offsets of both), using `copy_file_range(2)` where possible. Holes in
+/// `src` are preserved in `dst` (see [`copy_sparse`]). If `append` is set,
+/// `dst` was opened with `O_APPEND` and so we need to use an ordinary
+/// read-write loop (and holes are filled in).
+fn copy_file_contents(
+    src: &mut File,
+    dst: &mut File,
+    append: bool,
+    limit: Option<u64>,
+) -> Result<(), Error> {
+    // We copy at most one byte more than the limit, so that we can tell
+    // whether the source was larger than the limit.
+    let max_copy = limit.map_or(u64::MAX, |limit| limit.saturating_add(1));
+    let copied = if append {
+        copy_data(src, dst, max_copy, true)?
+    } else {
+        copy_sparse(src, dst, max_copy)?
+    };
+
+    if let Some(limit) = limit {
+        if copied > limit {
+            Err(ErrorImpl::LimitExceeded {
+                description: format!(
+                    "file contents are larger than the read limit ({limit} bytes)"
+                )
+                .into(),
+            })?
+        }
+    }
+    Ok(())
+}
+
+/// Copy up to `max_copy` bytes of `src` into `dst` (starting from the current
+/// file offsets of both), skipping over the holes in `src` found with
+/// `SEEK_DATA` and `SEEK_HOLE`. If part of `dst` that is skipped over already
+/// contains data, it is turned into a hole with `FALLOC_FL_PUNCH_HOLE`, and
+/// `dst` is extended with `ftruncate(2)` if `src` ends with a hole. If the
+/// filesystems do not support this, the rest of the file is copied in full.
+///
+/// Returns the number of bytes of `src` that were copied (including holes).
+fn copy_sparse(src: &mut File, dst: &mut File, max_copy: u64) -> Result<u64, Error> {
+    let seek_error = |err| ErrorImpl::RawOsError {
+        operation: "seek in file being copied".into(),
+        source: err,
+    };
+    let file_size = |file: &File| {
+        file.metadata().map(|meta| meta.len()).map_err(|err| {
+            Error::from(ErrorImpl::OsError {
+                operation: "get size of file being copied".into(),
+                source: err,
+            })
+        })
+    };
+
+    let src_start = syscalls::lseek(&*src, SeekFrom::Current(0)).map_err(seek_error)?;
+    let dst_start = syscalls::lseek(&*dst, SeekFrom::Current(0)).map_err(seek_error)?;
+    let src_size = file_size(src)?;
+    let dst_size = file_size(dst)?;
+
+    let mut copied: u64 = 0;
+    while copied < max_copy {
+        let pos = src_start + copied;
+        if pos >= src_size {
+            break;
+        }
+        let data = match syscalls::lseek(&*src, SeekFrom::Data(pos as _)) {
+            Ok(data) => data.min(src_size),
+            // There is no data after pos, so the rest of the file is a hole.
+            Err(err) if err.errno() == Errno::NXIO => src_size,
+            // SEEK_DATA is not supported by the filesystem.
+            Err(err) if matches!(err.errno(), Errno::INVAL | Errno::OPNOTSUPP) => break,
+            Err(err) => Err(seek_error(err))?,
+        };
+        if data > pos {
+            // Skip over the hole, punching a hole in dst if there is already
+            // some data there.
+            let len = (data - pos).min(max_copy - copied);
+            let dst_pos = dst_start + copied;
+            if dst_pos < dst_size {
+                let punch_len = len.min(dst_size - dst_pos);
+                match syscalls::fallocate(
+                    &*dst,
+                    FallocateFlags::PUNCH_HOLE | FallocateFlags::KEEP_SIZE,
+                    dst_pos,
+                    punch_len,
+                ) {
+                    Ok(()) => (),
+                    Err(err) if matches!(err.errno(), Errno::NOSYS | Errno::OPNOTSUPP) => break,
+                    Err(err) => Err(ErrorImpl::RawOsError {
+                        operation: "punch hole in copy destination".into(),
+                        source: err,
+                    })?,
+                }
+            }
+            copied += len;
+            continue;
+        }
+        let hole = match syscalls::lseek(&*src, SeekFrom::Hole(pos as _)) {
+            Ok(hole) => hole,
+            // The file was truncated since we got its size.
+            Err(err) if err.errno() == Errno::NXIO => break,
+            Err(err) => Err(seek_error(err))?,
+        };
+        if hole <= pos {
+            break;
+        }
+        let len = (hole - pos).min(max_copy - copied);
+        syscalls::lseek(&*src, SeekFrom::Start(pos)).map_err(seek_error)?;
+        syscalls::lseek(&*dst, SeekFrom::Start(dst_start + copied)).map_err(seek_error)?;
+        let n = copy_data(src, dst, len, false)?;
+        copied += n;
+        if n < len {
+            // We hit EOF early, the file was truncated.
+            break;
+        }
+    }
+
+    // Copy anything left over in full. This is a no-op unless SEEK_DATA or
+    // hole punching is not supported, or the file was resized while copying.
+    syscalls::lseek(&*src, SeekFrom::Start(src_start + copied)).map_err(seek_error)?;
+    syscalls::lseek(&*dst, SeekFrom::Start(dst_start + copied)).map_err(seek_error)?;
+    if copied < max_copy {
+        copied += copy_data(src, dst, max_copy - copied, false)?;
+    }
+
+    // If src ended with a hole, we need to extend dst to the right size.
+    let dst_end = dst_start + copied;
+    if file_size(dst)? < dst_end {
+        dst.set_len(dst_end).map_err(|err| ErrorImpl::OsError {
+            operation: "extend copy destination".into(),
+            source: err,
+        })?;
+    }
+    Ok(copied)
+}
+
+/// Copy up to `max_copy` bytes of `src` into `dst` (starting from the current
+/// file offsets of both), using `copy_file_range(2)` where possible. If
+/// `append` is set, `dst` was opened with `O_APPEND` and so we need to use an
+/// ordinary read-write loop.
+///
+/// Returns the number of bytes copied.
+fn copy_data(src: &mut File, dst: &mut File, max_copy: u64, append: bool) -> Result<u64, Error> {
+    // Limit each copy_file_range(2) call to avoid holding up the kernel for
+    // too long with very large files.
+    const COPY_CHUNK_SIZE: usize = 1 << 30;
+
+    let mut copied: u64 = 0;
+
+    // copy_file_range(2) cannot be used with O_APPEND files, so just go
+    // straight to the fallback.
+    let mut use_fallback = append;
+    while !use_fallback && copied < max_copy {
+        let chunk = (max_copy - copied).min(COPY_CHUNK_SIZE as u64) as usize;
+        match syscalls::copy_file_range(&*src, &*dst, chunk) {
+            Ok(0) => break,
+            Ok(n) => copied += n as u64,
+            // The kernel or filesystem doesn't support copy_file_range(2)
+            // for these files. Because we only ever use the current file
+            // offsets, we can safely continue where we left off.
+            Err(err)
+                if matches!(
+                    err.errno(),
+                    Errno::NOSYS | Errno::XDEV | Errno::INVAL | Errno::OPNOTSUPP
+                ) =>
+            {
+                use_fallback = true
+            }
+            Err(err) => Err(ErrorImpl::RawOsError {
+                operation: "copy file contents".into(),
+                source: err,
+            })?,
+        }
+    }
+    if use_fallback && copied < max_copy {
+        copied +=
+            io::copy(&mut src.take(max_copy - copied), dst).map_err(|err| ErrorImpl::OsError {
+                operation: "copy file contents".into(),
+                source: err,
+            })?;
+    }
+    Ok(copied)
+}
+
+// Read from a reader until EOF, without trusting any size hints.
+//
+// This is necessary because pseudo-filesystems (sysfs, procfs, etc) often
+// report an st_size of 0 for files that have contents, and so any code which
+// tries to size its reads based on st_size will return truncated results. For
+// the same reason, the limit is checked against the data actually read.
+fn read_until_eof<R: Read>(reader: R, limit: Option<u64>) -> Result<Vec<u8>, Error> {
+    // Read at most one byte more than the limit, so that we can tell whether
+    // the file was larger than the limit without reading all of it.
+    let max_read = limit.map_or(u64::MAX, |limit| limit.saturating_add(1));
+    let mut data = Vec::new();
+    reader
+        .take(max_read)
+        .read_to_end(&mut data)
+        .map_err(|err| ErrorImpl::OsError {
+            operation: "read file contents".into(),
+            source: err,
+        })?;
+    if let Some(limit) = limit {
+        if data.len() as u64 > limit {
+            Err(ErrorImpl::LimitExceeded {
+                description: format!(
+                    "file contents are larger than the read limit ({limit} bytes)"
+                )
+                .into(),
+            })?
+        }
+    }
+    Ok(data)
+}
+
+impl<'fd> From<BorrowedFd<'fd>> for RootRef<'fd> {
+    /// Shorthand for [`RootRef::from_fd`].
+    fn from(fd: BorrowedFd<'fd>) -> Self {
+        Self::from_fd(fd)
+    }
+}
+
+impl AsFd for RootRef<'_> {
+    /// Access the underlying file descriptor for a [`RootRef`].
+    ///
+    /// **Note**: This method is primarily intended to allow for tests and other
+    /// code to check the status of the underlying file descriptor. It is not
+    /// safe to use this [`BorrowedFd`] directly to do filesystem operations.
+    /// Please use the provided [`RootRef`] methods.
+    #[inline]
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.inner.as_fd()
+    }
+}
+
+/// Guard returned by [`Root::enter`].
+///
+/// When dropped (including during unwinding from a panic), the current working
+/// directory of the process is restored to the directory it was in before
+/// [`Root::enter`] was called.
+#[derive(Debug)]
+#[must_use = "the working directory is restored as soon as the guard is dropped"]
+pub struct RootGuard {
+    old_cwd: OwnedFd,
+}
+
+impl Drop for RootGuard {
+    fn drop(&mut self) {
+        // There is nothing sane we can do if this fails (panicking in Drop
+        // would abort the process if we are already unwinding), and fchdir(2)
+        // to a directory we already have an fd for should not fail anyway.
+        let _ = syscalls::fchdir(&self.old_cwd);
+    }
+}
+
+/// Guard returned by [`Root::create_lockfile`].
+///
+/// While the [`LockFile`] is alive, the lock file exists and an exclusive
+/// `flock(2)` lock is held on it. When dropped, the lock file is removed (if it
+/// is still the same file we created) and the lock is released. Errors during
+/// this cleanup are ignored -- use [`LockFile::release`] if you need to handle
+/// them.
+#[derive(Debug)]
+#[must_use = "the lock file is removed as soon as the guard is dropped"]
+pub struct LockFile {
+    /// Handle to the directory containing the lock file.
+    dir: OwnedFd,
+    /// Name of the lock file within `dir`.
+    name: PathBuf,
+    /// The locked lock file.
+    file: File,
+    /// Whether the lock file has already been cleaned up.
+    released: bool,
+}
+
+impl LockFile {
+    /// Get a reference to the underlying locked [`File`].
+    #[inline]
+    pub fn file(&self) -> &File {
+        &self.file
+    }
+
+    /// Remove the lock file and release the lock, returning any errors
+    /// encountered during cleanup.
+    ///
+    /// If the lock file was replaced by a different file, the new file is left
+    /// alone (though the lock on the original file is still released).
+    pub fn release(mut self) -> Result<(), Error> {
+        self.released = true;
+        self.remove()
+    }
+
+    fn remove(&self) -> Result<(), Error> {
+        // Make sure we don't remove some other file that was swapped in place
+        // of our lock file.
+        let our_stat = syscalls::fstatat(&self.file, "").map_err(|err| ErrorImpl::RawOsError {
+            operation: "stat lockfile".into(),
+            source: err,
+        })?;
+        let stat = match syscalls::fstatat(&self.dir, &self.name) {
+            Ok(stat) => stat,
+            // Someone else already removed the lock file.
+            Err(err) if err.errno() == Errno::NOENT => return Ok(()),
+            Err(err) => Err(ErrorImpl::RawOsError {
+                operation: "stat lockfile path".into(),
+                source: err,
+            })?,
+        };
+        if (stat.st_dev, stat.st_ino) != (our_stat.st_dev, our_stat.st_ino) {
+            return Ok(());
+        }
+
+        syscalls::unlinkat(&self.dir, &self.name, AtFlags::empty()).map_err(|err| {
+            ErrorImpl::RawOsError {
+                operation: "remove lockfile".into(),
+                source: err,
+            }
+            .into()
+        })
+    }
+}
+
+impl AsFd for LockFile {
+    #[inline]
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.file.as_fd()
+    }
+}
+
+impl Drop for LockFile {
+    fn drop(&mut self) {
+        if !self.released {
+            // Cleanup is best-effort, we must not panic in Drop.
+            let _ = self.remove();
+        }
+        // The lock is released when self.file is closed.
+    }
+}
+
+/// The result of probing a directory with [`Root::probe_dir`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DirProbe {
+    /// The path is a directory which the current process can search.
+    Usable,
+    /// The path (or one of its parent components) is not a directory.
+    NotADirectory,
+    /// The path (or one of its parent components) does not exist.
+    Missing,
+    /// The path is a directory, but the current process does not have search
+    /// permission on it (or one of its parent components).
+    NoSearchPermission,
+}
+
+/// Extended metadata about an inode within a [`Root`], as returned by
+/// [`Root::statx`].
+///
+/// This exposes some of the fields returned by [`statx(2)`] that are not
+/// available through [`Metadata`]. Fields that were not returned by the kernel
+/// (see [`Statx::mask`]) are either `None` or zero.
+///
+/// [`statx(2)`]: http://man7.org/linux/man-pages/man2/statx.2.html
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Statx {
+    mask: StatxMask,
+    attributes: u64,
+    attributes_mask: u64,
+    mode: u32,
+    nlink: u32,
+    uid: u32,
+    gid: u32,
+    ino: u64,
+    size: u64,
+    blocks: u64,
+    blksize: u32,
+    atime: SystemTime,
+    btime: SystemTime,
+    ctime: SystemTime,
+    mtime: SystemTime,
+    dev: u64,
+    rdev: u64,
+    mnt_id: u64,
+}
+
+impl Statx {
+    fn from_rustix(stx: rustix_fs::Statx) -> Self {
+        fn timestamp(ts: rustix_fs::StatxTimestamp) -> SystemTime {
+            if ts.tv_sec >= 0 {
+                UNIX_EPOCH + Duration::new(ts.tv_sec as u64, ts.tv_nsec)
+            } else {
+                UNIX_EPOCH - Duration::from_secs(ts.tv_sec.unsigned_abs())
+                    + Duration::from_nanos(ts.tv_nsec.into())
+            }
+        }
+
+        Self {
+            mask: StatxMask::from_bits_retain(stx.stx_mask),
+            attributes: stx.stx_attributes,
+            attributes_mask: stx.stx_attributes_mask,
+            mode: stx.stx_mode.into(),
+            nlink: stx.stx_nlink,
+            uid: stx.stx_uid,
+            gid: stx.stx_gid,
+            ino: stx.stx_ino,
+            size: stx.stx_size,
+            blocks: stx.stx_blocks,
+            blksize: stx.stx_blksize,
+            atime: timestamp(stx.stx_atime),
+            btime: timestamp(stx.stx_btime),
+            ctime: timestamp(stx.stx_ctime),
+            mtime: timestamp(stx.stx_mtime),
+            dev: rustix_fs::makedev(stx.stx_dev_major, stx.stx_dev_minor),
+            rdev: rustix_fs::makedev(stx.stx_rdev_major, stx.stx_rdev_minor),
+            mnt_id: stx.stx_mnt_id,
+        }
+    }
+
+    fn time_field(&self, field: StatxMask, time: SystemTime) -> Option<SystemTime> {
+        self.mask.contains(field).then_some(time)
+    }
+
+    /// The set of fields that were returned by the kernel.
+    #[inline]
+    pub fn mask(&self) -> StatxMask {
+        self.mask
+    }
+
+    /// The `STATX_ATTR_*` attributes of the inode.
+    ///
+    /// Only the bits in [`Statx::attributes_mask`] are meaningful.
+    #[inline]
+    pub fn attributes(&self) -> u64 {
+        self.attributes
+    }
+
+    /// The set of `STATX_ATTR_*` attributes supported by the filesystem.
+    #[inline]
+    pub fn attributes_mask(&self) -> u64 {
+        self.attributes_mask
+    }
+
+    /// The mode of the inode (including the file type bits).
+    #[inline]
+    pub fn mode(&self) -> u32 {
+        self.mode
+    }
+
+    /// The number of hard links to the inode.
+    #[inline]
+    pub fn nlink(&self) -> u32 {
+        self.nlink
+    }
+
+    /// The owner uid of the inode.
+    #[inline]
+    pub fn uid(&self) -> u32 {
+        self.uid
+    }
+
+    /// The owner gid of the inode.
+    #[inline]
+    pub fn gid(&self) -> u32 {
+        self.gid
+    }
+
+    /// The inode number.
+    #[inline]
+    pub fn ino(&self) -> u64 {
+        self.ino
+    }
+
+    /// The size of the inode in bytes.
+    #[inline]
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// The number of 512-byte blocks allocated to the inode.
+    #[inline]
+    pub fn blocks(&self) -> u64 {
+        self.blocks
+    }
+
+    /// The preferred block size for I/O on the inode.
+    #[inline]
+    pub fn blksize(&self) -> u32 {
+        self.blksize
+    }
 
-        Ok(Handle::from_fd(current))
+    /// The last access time of the inode.
+    #[inline]
+    pub fn atime(&self) -> Option<SystemTime> {
+        self.time_field(StatxMask::ATIME, self.atime)
     }
 
-    /// Within the [`RootRef`]'s tree, remove the inode of type `inode_type` at
-    /// `path`.
-    ///
-    /// Any existing [`Handle`]s to `path` will continue to work as before,
-    /// since Linux does not invalidate file handles to unlinked files (though,
-    /// directory handling is not as simple).
-    ///
-    /// # Errors
-    ///
-    /// If the path does not exist, was not actually `inode_type`, or was a
-    /// non-empty directory an error will be returned. In order to remove a path
-    /// regardless of whether it exists, its type, or if it it's a non-empty
-    /// directory, you can use [`remove_all`].
-    ///
-    /// [`remove_all`]: Self::remove_all
-    fn remove_inode(&self, path: &Path, inode_type: RemoveInodeType) -> Result<(), Error> {
-        // unlinkat(2) doesn't let us remove an inode using just a handle (for
-        // obvious reasons -- on Unix hardlinks mean that "unlink this file"
-        // doesn't make sense without referring to a specific directory entry).
-        let (dir, name) = self
-            .resolve_parent(path.as_ref())
-            .wrap("resolve file removal path")?;
-        // TODO: rmdir() lets you use trailing slashes. We should probably allow
-        //       that too...
-        let name = name.ok_or_else(|| ErrorImpl::InvalidArgument {
-            name: "path".into(),
-            description: "file removal path has trailing slash".into(),
-        })?;
+    /// The birth (creation) time of the inode. This is not supported by all
+    /// filesystems.
+    #[inline]
+    pub fn btime(&self) -> Option<SystemTime> {
+        self.time_field(StatxMask::BTIME, self.btime)
+    }
 
-        let flags = match inode_type {
-            RemoveInodeType::Regular => AtFlags::empty(),
-            RemoveInodeType::Directory => AtFlags::REMOVEDIR,
-        };
-        syscalls::unlinkat(dir, name, flags).map_err(|err| {
-            ErrorImpl::RawOsError {
-                operation: "pathrs remove".into(),
-                source: err,
-            }
-            .into()
-        })
+    /// The last status change time of the inode.
+    #[inline]
+    pub fn ctime(&self) -> Option<SystemTime> {
+        self.time_field(StatxMask::CTIME, self.ctime)
     }
 
-    /// Within the [`RootRef`]'s tree, remove the empty directory at `path`.
-    ///
-    /// Any existing [`Handle`]s to `path` will continue to work as before,
-    /// since Linux does not invalidate file handles to unlinked files (though,
-    /// directory handling is not as simple).
-    ///
-    /// # Errors
-    ///
-    /// If the path does not exist, was not actually a directory, or was a
-    /// non-empty directory an error will be returned. In order to remove a
-    /// directory and all of its children, you can use [`remove_all`].
-    ///
-    /// [`remove_all`]: Self::remove_all
-    #[doc(alias = "pathrs_inroot_rmdir")]
+    /// The last modification time of the inode.
     #[inline]
-    pub fn remove_dir<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
-        self.remove_inode(path.as_ref(), RemoveInodeType::Directory)
+    pub fn mtime(&self) -> Option<SystemTime> {
+        self.time_field(StatxMask::MTIME, self.mtime)
     }
 
-    /// Within the [`RootRef`]'s tree, remove the file (any non-directory inode)
-    /// at `path`.
-    ///
-    /// Any existing [`Handle`]s to `path` will continue to work as before,
-    /// since Linux does not invalidate file handles to unlinked files (though,
-    /// directory handling is not as simple).
-    ///
-    /// # Errors
-    ///
-    /// If the path does not exist or was actually a directory an error will be
-    /// returned. In order to remove a path regardless of its type (even if it
-    /// is a non-empty directory), you can use [`remove_all`].
-    ///
-    /// [`remove_all`]: Self::remove_all
-    #[doc(alias = "pathrs_inroot_unlink")]
+    /// The device containing the inode.
     #[inline]
-    pub fn remove_file<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
-        self.remove_inode(path.as_ref(), RemoveInodeType::Regular)
+    pub fn dev(&self) -> u64 {
+        self.dev
     }
 
-    /// Within the [`RootRef`]'s tree, recursively delete the provided `path`
-    /// and any children it contains if it is a directory. This is effectively
-    /// equivalent to [`std::fs::remove_dir_all`], Go's [`os.RemoveAll`], or
-    /// Unix's `rm -r`.
-    ///
-    /// Any existing [`Handle`]s to paths within `path` will continue to work as
-    /// before, since Linux does not invalidate file handles to unlinked files
-    /// (though, directory handling is not as simple).
-    ///
-    /// # Errors
-    ///
-    /// If the path does not exist or some other error occurred during the
-    /// deletion process an error will be returned.
+    /// The device represented by the inode (if it is a device inode).
+    #[inline]
+    pub fn rdev(&self) -> u64 {
+        self.rdev
+    }
+
+    /// The mount ID of the mount containing the inode, if it was requested
+    /// with [`StatxMask::MNT_ID`] and is supported by the kernel (Linux 5.8 or
+    /// later).
     ///
-    /// [`os.RemoveAll`]: https://pkg.go.dev/os#RemoveAll
-    #[doc(alias = "pathrs_inroot_remove_all")]
-    pub fn remove_all<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
-        let (dir, name) = self
-            .resolve_parent(path.as_ref())
-            .wrap("resolve remove-all path")?;
-        // TODO: rmdir() lets you use trailing slashes. We should probably allow
-        //       that too...
-        let name = name.ok_or_else(|| ErrorImpl::InvalidArgument {
-            name: "path".into(),
-            description: "file removal path has trailing slash".into(),
-        })?;
+    /// Unlike [`Statx::dev`], the mount ID differs between bind-mounts of the
+    /// same filesystem, so it can be used to detect mount crossings.
+    #[inline]
+    pub fn mnt_id(&self) -> Option<u64> {
+        self.mask.contains(StatxMask::MNT_ID).then_some(self.mnt_id)
+    }
+}
 
-        utils::remove_all(&dir, name)
+/// The outcome of checking a path with [`Root::classify`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Resolution {
+    /// The path resolved without escaping the root. The canonical
+    /// root-relative path of the resolved file is included.
+    InsideRoot(PathBuf),
+    /// Resolving the path would have escaped the root.
+    EscapeAttempt {
+        /// The path component (from the path or from the target of a symlink)
+        /// that would have escaped the root.
+        component: PathBuf,
+        /// Why the component would have escaped the root.
+        reason: EscapeReason,
+    },
+    /// Some component of the path does not exist.
+    NotFound,
+}
+
+/// The reason for a [`Resolution::EscapeAttempt`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum EscapeReason {
+    /// The path itself is absolute.
+    AbsolutePath,
+    /// A `..` component would have walked above the root.
+    DotDot,
+    /// A symlink with an absolute target would have jumped out of the root.
+    AbsoluteSymlink,
+    /// A component is on a different mount to the root (such as a bind-mount
+    /// of a directory from outside of the root).
+    MountCrossing,
+    /// The resolver backend could not determine why the path would have
+    /// escaped the root (see [`Root::classify`]).
+    Unknown,
+}
+
+/// Diagnostic information about a single component walked through by
+/// [`Root::resolve_explain`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ComponentExplain {
+    pub(crate) name: OsString,
+    pub(crate) path: PathBuf,
+    pub(crate) mode: u32,
+    pub(crate) uid: u32,
+    pub(crate) gid: u32,
+    pub(crate) search_permitted: Option<bool>,
+}
+
+impl ComponentExplain {
+    /// The name of the component, as it appeared in the path being resolved
+    /// (or in the target of a symlink).
+    #[inline]
+    pub fn name(&self) -> &OsStr {
+        &self.name
     }
 
-    /// Within the [`RootRef`]'s tree, perform a rename with the given `source`
-    /// and `directory`. The `flags` argument is passed directly to
-    /// [`renameat2(2)`].
-    ///
-    /// # Errors
-    ///
-    /// The error rules are identical to [`renameat2(2)`].
-    ///
-    /// [`renameat2(2)`]: http://man7.org/linux/man-pages/man2/renameat2.2.html
-    #[doc(alias = "pathrs_inroot_rename")]
-    pub fn rename<P: AsRef<Path>>(
-        &self,
-        source: P,
-        destination: P,
-        rflags: RenameFlags,
-    ) -> Result<(), Error> {
-        // renameat2(2) doesn't let us rename paths using just handles. In
-        // addition, the target path might not exist (except in the case of
-        // RENAME_EXCHANGE and clobbering).
-        let (src_dir, src_name) = self
-            .resolve_parent(source.as_ref())
-            .wrap("resolve rename source path")?;
-        let src_name = src_name.ok_or_else(|| ErrorImpl::InvalidArgument {
-            name: "source".into(),
-            description: "rename source path has trailing slash".into(),
-        })?;
-        let (dst_dir, dst_name) = self
-            .resolve_parent(destination.as_ref())
-            .wrap("resolve rename destination path")?;
-        let dst_name = dst_name.ok_or_else(|| ErrorImpl::InvalidArgument {
-            name: "destination".into(),
-            description: "rename destination path has trailing slash".into(),
-        })?;
+    /// The path of the component relative to the root, at the time it was
+    /// walked through.
+    #[inline]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
 
-        syscalls::renameat2(src_dir, src_name, dst_dir, dst_name, rflags).map_err(|err| {
-            ErrorImpl::RawOsError {
-                operation: "pathrs rename".into(),
-                source: err,
-            }
-            .into()
-        })
+    /// The mode of the component (including the file type bits).
+    #[inline]
+    pub fn mode(&self) -> u32 {
+        self.mode
     }
-}
 
-impl<'fd> From<BorrowedFd<'fd>> for RootRef<'fd> {
-    /// Shorthand for [`RootRef::from_fd`].
-    fn from(fd: BorrowedFd<'fd>) -> Self {
-        Self::from_fd(fd)
+    /// The owner uid of the component.
+    #[inline]
+    pub fn uid(&self) -> u32 {
+        self.uid
     }
-}
 
-impl AsFd for RootRef<'_> {
-    /// Access the underlying file descriptor for a [`RootRef`].
+    /// The owner gid of the component.
+    #[inline]
+    pub fn gid(&self) -> u32 {
+        self.gid
+    }
+
+    /// Whether the current process has search (execute) permission on the
+    /// component, taking into account ACLs and capabilities.
     ///
-    /// **Note**: This method is primarily intended to allow for tests and other
-    /// code to check the status of the underlying file descriptor. It is not
-    /// safe to use this [`BorrowedFd`] directly to do filesystem operations.
-    /// Please use the provided [`RootRef`] methods.
+    /// This is `None` if the component is not a directory, or if the kernel
+    /// does not support the check (it requires `faccessat2(2)`, added in Linux
+    /// 5.8).
     #[inline]
-    fn as_fd(&self) -> BorrowedFd<'_> {
-        self.inner.as_fd()
+    pub fn search_permitted(&self) -> Option<bool> {
+        self.search_permitted
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{resolvers::ResolverBackend, Root, RootRef};
+    use crate::{
+        error::ErrorKind,
+        flags::{AbsoluteSymlinkPolicy, ReopenPolicy, ResolverFlags, SetidPolicy},
+        procfs::ProcfsHandle,
+        tests::common as tests_common,
+        Handle, HandleRef, InodeType, ResolverBackend, Root, RootBuilder, RootRef,
+    };
 
-    use std::os::unix::io::{AsFd, AsRawFd};
+    use std::{
+        fs::{self, Permissions},
+        os::unix::{
+            fs::{self as unix_fs, MetadataExt, PermissionsExt},
+            io::{AsFd, AsRawFd},
+        },
+        sync::Arc,
+        thread,
+        time::Duration,
+    };
 
     use anyhow::Error;
     use pretty_assertions::assert_eq;
 
-    impl Root {
-        // TODO: Should we make this public? Is there any real benefit?
-        #[inline]
-        pub(crate) fn resolver_backend(&self) -> ResolverBackend {
-            self.resolver.backend
-        }
-
-        // TODO: Should we make this public? Is there any real benefit?
-        #[inline]
-        pub(crate) fn set_resolver_backend(&mut self, backend: ResolverBackend) -> &mut Self {
-            self.resolver.backend = backend;
-            self
-        }
+    #[test]
+    fn same_inode_as() -> Result<(), Error> {
+        let root_dir = tests_common::create_basic_tree()?;
+        let root = Root::open(&root_dir)?;
 
-        // TODO: Should we make this public? Is there any real benefit?
-        #[inline]
-        pub(crate) fn with_resolver_backend(mut self, backend: ResolverBackend) -> Self {
-            self.set_resolver_backend(backend);
-            self
-        }
+        assert!(root.same_inode_as(&Root::open(&root_dir)?)?);
+        assert!(root.same_inode_as(&root.try_clone()?)?);
+        assert!(root.same_inode_as(&root.sub_root("b/..")?)?);
+        assert!(!root.same_inode_as(&root.sub_root("b")?)?);
+        assert!(root
+            .sub_root("e")?
+            .same_inode_as(&root.sub_root("b/c/d/e")?)?);
+        assert!(root
+            .as_ref()
+            .same_inode_as(&RootRef::from_fd(root.as_fd()))?);
+        Ok(())
     }
 
-    impl RootRef<'_> {
-        // TODO: Should we make this public? Is there any real benefit?
-        #[inline]
-        pub(crate) fn resolver_backend(&self) -> ResolverBackend {
-            self.resolver.backend
+    #[test]
+    #[cfg_attr(not(feature = "_test_as_root"), ignore)]
+    fn inode_type_from_metadata() -> Result<(), Error> {
+        let root_dir = tests_common::create_basic_tree()?;
+        let root = Root::open(&root_dir)?;
+        root.create(
+            "b/chr",
+            &InodeType::CharacterDevice(Permissions::from_mode(0o666), libc::makedev(1, 3)),
+        )?;
+        root.create(
+            "b/blk",
+            &InodeType::BlockDevice(Permissions::from_mode(0o660), libc::makedev(7, 0)),
+        )?;
+        // Set the modes explicitly to avoid the umask.
+        for (path, mode) in [
+            ("b/c/file", 0o4755),
+            ("b/c", 0o3750),
+            ("b/fifo", 0o2640),
+            ("b/sock", 0o1600),
+            ("b/chr", 0o666),
+            ("b/blk", 0o660),
+        ] {
+            fs::set_permissions(root_dir.path().join(path), Permissions::from_mode(mode))?;
         }
 
-        // TODO: Should we make this public? Is there any real benefit?
-        #[inline]
-        pub(crate) fn set_resolver_backend(&mut self, backend: ResolverBackend) -> &mut Self {
-            self.resolver.backend = backend;
-            self
+        for (path, expected_fmt, expected_mode) in [
+            ("b/c/file", libc::S_IFREG, 0o4755),
+            ("b/c", libc::S_IFDIR, 0o3750),
+            ("b/fifo", libc::S_IFIFO, 0o2640),
+            ("b/sock", libc::S_IFSOCK, 0o1600),
+            ("b/chr", libc::S_IFCHR, 0o666),
+            ("b/blk", libc::S_IFBLK, 0o660),
+        ] {
+            let meta = root.metadata_nofollow(path)?;
+            let inode_type = InodeType::from_metadata(&meta);
+            let (fmt, perm, rdev) = match inode_type {
+                Some(InodeType::File(ref perm)) => (libc::S_IFREG, perm, 0),
+                Some(InodeType::Directory(ref perm)) => (libc::S_IFDIR, perm, 0),
+                Some(InodeType::Fifo(ref perm)) => (libc::S_IFIFO, perm, 0),
+                Some(InodeType::Socket(ref perm)) => (libc::S_IFSOCK, perm, 0),
+                Some(InodeType::CharacterDevice(ref perm, dev)) => (libc::S_IFCHR, perm, dev),
+                Some(InodeType::BlockDevice(ref perm, dev)) => (libc::S_IFBLK, perm, dev),
+                ref other => panic!("unexpected InodeType::from_metadata({path:?}): {other:?}"),
+            };
+            assert_eq!(
+                (fmt, perm.mode(), rdev),
+                (expected_fmt, expected_mode, meta.rdev()),
+                "InodeType::from_metadata({path:?})"
+            );
         }
 
-        // TODO: Should we make this public? Is there any real benefit?
-        #[inline]
-        pub(crate) fn with_resolver_backend(mut self, backend: ResolverBackend) -> Self {
-            self.set_resolver_backend(backend);
-            self
-        }
+        assert!(
+            InodeType::from_metadata(&root.metadata_nofollow("b-file")?).is_none(),
+            "InodeType::from_metadata of a symlink should be None"
+        );
+
+        // The result can be used to re-create an equivalent inode.
+        let meta = root.metadata_nofollow("b/chr")?;
+        let inode_type = InodeType::from_metadata(&meta).expect("b/chr has an InodeType");
+        root.create("b/chr-copy", &inode_type)?;
+        let copy_meta = root.metadata_nofollow("b/chr-copy")?;
+        assert_eq!(
+            (copy_meta.mode() & libc::S_IFMT, copy_meta.rdev()),
+            (meta.mode() & libc::S_IFMT, meta.rdev()),
+            "re-created inode should have the same type and device"
+        );
+        Ok(())
     }
 
     #[test]
@@ -1314,4 +6243,260 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn resolver_backend() -> Result<(), Error> {
+        let root = Root::open(".")?;
+        assert_eq!(
+            root.resolver_backend(),
+            ResolverBackend::default(),
+            "Root::open should use the default backend"
+        );
+
+        for backend in [
+            ResolverBackend::KernelOpenat2,
+            ResolverBackend::EmulatedOpath,
+        ] {
+            let root = Root::open(".")?.with_resolver_backend(backend);
+            assert_eq!(root.resolver_backend(), backend);
+            assert_eq!(root.as_ref().resolver_backend(), backend);
+
+            let res = root.resolve(".");
+            if backend.supported() {
+                assert_eq!(res?.backend(), Some(backend));
+            } else {
+                // Unsupported backends must not silently fall back.
+                assert_eq!(
+                    res.map(|_| ()).map_err(|err| err.kind()),
+                    Err(ErrorKind::NotSupported),
+                    "unsupported backend {backend:?} should return NotSupported"
+                );
+            }
+        }
+        Ok(())
+    }
+
+    // Compile-time check that Root and friends can be shared between threads.
+    #[test]
+    fn send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+
+        assert_send_sync::<Root>();
+        assert_send_sync::<RootRef<'static>>();
+        assert_send_sync::<RootBuilder>();
+        assert_send_sync::<Handle>();
+        assert_send_sync::<HandleRef<'static>>();
+        assert_send_sync::<ProcfsHandle>();
+        assert_send_sync::<crate::error::Error>();
+    }
+
+    #[test]
+    fn concurrent_resolve() -> Result<(), Error> {
+        let root_dir = tests_common::create_basic_tree()?;
+        let root = Arc::new(Root::open(&root_dir)?);
+        let expected = root.resolve("b/c/file")?.metadata()?;
+
+        let threads = (0..8)
+            .map(|_| {
+                let root = Arc::clone(&root);
+                thread::spawn(move || -> Result<_, crate::error::Error> {
+                    (0..100)
+                        .map(|_| {
+                            let meta = root.resolve("b-file")?.metadata()?;
+                            Ok((meta.dev(), meta.ino()))
+                        })
+                        .collect::<Result<Vec<_>, _>>()
+                })
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            let results = thread.join().expect("resolve thread should not panic")?;
+            assert!(
+                results
+                    .into_iter()
+                    .all(|got| got == (expected.dev(), expected.ino())),
+                "concurrent resolve should give the same result"
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn builder() -> Result<(), Error> {
+        let default_root = Root::open(".")?;
+        for root in [
+            Root::builder().open(".")?,
+            RootBuilder::new().from_fd(Root::open(".")?)?,
+        ] {
+            assert_eq!(root.resolver_flags(), default_root.resolver_flags());
+            assert_eq!(root.resolver_backend(), default_root.resolver_backend());
+            assert_eq!(root.max_symlink_depth(), default_root.max_symlink_depth());
+            assert_eq!(root.resolve_deadline(), default_root.resolve_deadline());
+            assert_eq!(
+                root.absolute_symlink_policy(),
+                default_root.absolute_symlink_policy()
+            );
+            assert_eq!(root.reopen_policy(), default_root.reopen_policy());
+            assert_eq!(root.setid_policy(), default_root.setid_policy());
+            assert_eq!(root.read_limit(), default_root.read_limit());
+            assert_eq!(root.verify_mount_id(), default_root.verify_mount_id());
+        }
+
+        let builder = Root::builder()
+            .resolver_flags(ResolverFlags::NO_SYMLINKS)
+            .resolver_backend(ResolverBackend::EmulatedOpath)
+            .max_symlink_depth(5)
+            .resolve_deadline(Some(Duration::from_secs(5)))
+            .absolute_symlink_policy(AbsoluteSymlinkPolicy::Error)
+            .reopen_policy(ReopenPolicy::default().with_verify_inode(true))
+            .setid_policy(SetidPolicy::Strip)
+            .read_limit(Some(1024))
+            .verify_mount_id(true);
+        for root in [
+            builder.clone().open(".")?,
+            builder.from_fd(Root::open(".")?)?,
+        ] {
+            for (flags, backend, depth) in [
+                (
+                    root.resolver_flags(),
+                    root.resolver_backend(),
+                    root.max_symlink_depth(),
+                ),
+                (
+                    root.as_ref().resolver_flags(),
+                    root.as_ref().resolver_backend(),
+                    root.as_ref().max_symlink_depth(),
+                ),
+            ] {
+                assert_eq!(flags, ResolverFlags::NO_SYMLINKS);
+                assert_eq!(backend, ResolverBackend::EmulatedOpath);
+                assert_eq!(depth, 5);
+            }
+            assert_eq!(root.resolve_deadline(), Some(Duration::from_secs(5)));
+            assert_eq!(root.absolute_symlink_policy(), AbsoluteSymlinkPolicy::Error);
+            assert_eq!(
+                root.reopen_policy(),
+                ReopenPolicy::default().with_verify_inode(true)
+            );
+            assert_eq!(root.setid_policy(), SetidPolicy::Strip);
+            assert_eq!(root.read_limit(), Some(1024));
+            assert!(root.verify_mount_id(), "mount id verification enabled");
+            assert_eq!(
+                root.resolve(".")?.backend(),
+                Some(ResolverBackend::EmulatedOpath)
+            );
+        }
+
+        assert_eq!(
+            Root::builder()
+                .open("/nonexistent")
+                .map(|_| ())
+                .map_err(|err| err.kind()),
+            Err(ErrorKind::OsError(Some(libc::ENOENT))),
+            "RootBuilder::open of nonexistent path should fail"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn as_frozen_path() -> Result<(), Error> {
+        let root_dir = tests_common::create_basic_tree()?;
+        let root_dir = fs::canonicalize(root_dir.path())?;
+
+        let root = Root::open(root_dir.join("b/c"))?;
+        assert_eq!(root.as_frozen_path()?, root_dir.join("b/c"));
+        assert_eq!(root.as_ref().as_frozen_path()?, root_dir.join("b/c"));
+
+        // Moving the root is reflected in the path.
+        fs::rename(root_dir.join("b/c"), root_dir.join("new-c"))?;
+        assert_eq!(root.as_frozen_path()?, root_dir.join("new-c"));
+        Ok(())
+    }
+
+    #[test]
+    fn open_trailing_symlink() -> Result<(), Error> {
+        let root_dir = tests_common::create_basic_tree()?;
+        let root_dir = root_dir.path();
+        let expected = fs::metadata(root_dir.join("b/c"))?;
+
+        unix_fs::symlink("b/c", root_dir.join("dir-link"))?;
+
+        let meta = Root::open(root_dir.join("b/c"))?.resolve(".")?.metadata()?;
+        assert_eq!(
+            (meta.dev(), meta.ino()),
+            (expected.dev(), expected.ino()),
+            "Root::open should open b/c"
+        );
+        // Root::open_resolving is needed to follow the trailing symlink.
+        let meta = Root::open_resolving(root_dir.join("dir-link"))?
+            .resolve(".")?
+            .metadata()?;
+        assert_eq!(
+            (meta.dev(), meta.ino()),
+            (expected.dev(), expected.ino()),
+            "Root::open_resolving should follow trailing symlinks"
+        );
+
+        for (path, expected_err) in [
+            (root_dir.join("dir-link"), libc::ELOOP),
+            (root_dir.join("b-file"), libc::ELOOP),
+            (root_dir.join("a-fake1"), libc::ELOOP),
+            (root_dir.join("b/c/file"), libc::ENOTDIR),
+            (root_dir.join("nonexistent"), libc::ENOENT),
+        ] {
+            assert_eq!(
+                Root::open(&path).map(|_| ()).map_err(|err| err.kind()),
+                Err(ErrorKind::OsError(Some(expected_err))),
+                "Root::open({path:?}) should fail"
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn open_resolving() -> Result<(), Error> {
+        let root_dir = tests_common::create_basic_tree()?;
+        let root_dir = root_dir.path();
+        let expected = fs::metadata(root_dir.join("b/c/d"))?;
+
+        unix_fs::symlink("b/c", root_dir.join("rel-link"))?;
+        unix_fs::symlink(root_dir.join("b/c"), root_dir.join("abs-link"))?;
+
+        for path in [
+            root_dir.join("b/c/d"),
+            root_dir.join("rel-link/d"),
+            root_dir.join("abs-link/d/"),
+            root_dir.join("abs-link/../../rel-link/./d"),
+        ] {
+            let root = Root::open_resolving(&path)?;
+            let meta = root.resolve(".")?.metadata()?;
+            assert_eq!(
+                (meta.dev(), meta.ino()),
+                (expected.dev(), expected.ino()),
+                "Root::open_resolving({path:?}) should open b/c/d"
+            );
+        }
+
+        for (path, expected_err) in [
+            (root_dir.join("b-file"), libc::ENOTDIR),
+            (root_dir.join("a-fake1"), libc::ENOENT),
+            (root_dir.join("loop/basic-loop1"), libc::ELOOP),
+        ] {
+            assert_eq!(
+                Root::open_resolving(&path)
+                    .map(|_| ())
+                    .map_err(|err| err.kind()),
+                Err(ErrorKind::OsError(Some(expected_err))),
+                "Root::open_resolving({path:?}) should fail"
+            );
+        }
+        assert_eq!(
+            Root::open_resolving("")
+                .map(|_| ())
+                .map_err(|err| err.kind()),
+            Err(ErrorKind::InvalidArgument),
+            "Root::open_resolving of an empty path should fail"
+        );
+        Ok(())
+    }
 }
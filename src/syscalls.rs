@@ -26,7 +26,7 @@ use crate::{
 };
 
 use std::{
-    ffi::OsStr,
+    ffi::{OsStr, OsString},
     fmt,
     io::Error as IOError,
     mem::MaybeUninit,
@@ -41,11 +41,13 @@ use bitflags::bitflags;
 use once_cell::sync::Lazy;
 use rustix::{
     fs::{
-        self as rustix_fs, AtFlags, Dev, FileType, Mode, RawMode, Stat, StatFs, Statx, StatxFlags,
+        self as rustix_fs, Access, AtFlags, Dev, FallocateFlags, FileType, FlockOperation, Mode,
+        RawMode, SeekFrom, Stat, StatFs, StatVfs, Statx, StatxFlags, Timestamps, XattrFlags,
     },
     io::Errno,
     mount::{self as rustix_mount, FsMountFlags, FsOpenFlags, MountAttrFlags, OpenTreeFlags},
-    process as rustix_process, thread as rustix_thread,
+    process as rustix_process,
+    thread::{self as rustix_thread, CapabilitySets},
 };
 
 // TODO: Figure out how we can put a backtrace here (it seems we can't use
@@ -201,6 +203,9 @@ pub(crate) enum Error {
     #[error("fstatfs({fd})")]
     Fstatfs { fd: FrozenFd, source: Errno },
 
+    #[error("fstatvfs({fd})")]
+    Fstatvfs { fd: FrozenFd, source: Errno },
+
     #[error("fstatat({dirfd}, {path}, 0x{flags:x})")]
     Fstatat {
         dirfd: FrozenFd,
@@ -209,6 +214,15 @@ pub(crate) enum Error {
         source: Errno,
     },
 
+    #[error("faccessat({dirfd}, {path}, {access:?}, 0x{flags:x})")]
+    Faccessat {
+        dirfd: FrozenFd,
+        path: PathBuf,
+        access: Access,
+        flags: AtFlags,
+        source: Errno,
+    },
+
     #[error("statx({dirfd}, {path}, flags=0x{flags:x}, mask=0x{mask:x})")]
     Statx {
         dirfd: FrozenFd,
@@ -218,6 +232,113 @@ pub(crate) enum Error {
         source: Errno,
     },
 
+    #[error("fchdir({fd})")]
+    Fchdir { fd: FrozenFd, source: Errno },
+
+    #[error("fcntl({fd}, F_GETFL)")]
+    FcntlGetfl { fd: FrozenFd, source: Errno },
+
+    #[error("fchown({fd}, {uid}, {gid})")]
+    Fchown {
+        fd: FrozenFd,
+        uid: u32,
+        gid: u32,
+        source: Errno,
+    },
+
+    #[error("fchmodat({dirfd}, {path}, 0o{mode:o})")]
+    Fchmodat {
+        dirfd: FrozenFd,
+        path: PathBuf,
+        mode: RawMode,
+        source: Errno,
+    },
+
+    #[error("utimensat({dirfd}, {path}, {times:?})")]
+    Utimensat {
+        dirfd: FrozenFd,
+        path: PathBuf,
+        times: Timestamps,
+        source: Errno,
+    },
+
+    #[error("fchownat({dirfd}, {path}, {uid:?}, {gid:?}, 0x{flags:x})")]
+    Fchownat {
+        dirfd: FrozenFd,
+        path: PathBuf,
+        uid: Option<u32>,
+        gid: Option<u32>,
+        flags: AtFlags,
+        source: Errno,
+    },
+
+    #[error("flock({fd}, {operation:?})")]
+    Flock {
+        fd: FrozenFd,
+        operation: FlockOperation,
+        source: Errno,
+    },
+
+    #[error("fgetxattr({fd}, {name:?})")]
+    Fgetxattr {
+        fd: FrozenFd,
+        name: OsString,
+        source: Errno,
+    },
+
+    #[error("fsetxattr({fd}, {name:?}, <{size} bytes>, {flags:?})")]
+    Fsetxattr {
+        fd: FrozenFd,
+        name: OsString,
+        size: usize,
+        flags: XattrFlags,
+        source: Errno,
+    },
+
+    #[error("fremovexattr({fd}, {name:?})")]
+    Fremovexattr {
+        fd: FrozenFd,
+        name: OsString,
+        source: Errno,
+    },
+
+    #[error("flistxattr({fd})")]
+    Flistxattr { fd: FrozenFd, source: Errno },
+
+    #[error("copy_file_range({fd_in}, {fd_out}, {len})")]
+    CopyFileRange {
+        fd_in: FrozenFd,
+        fd_out: FrozenFd,
+        len: usize,
+        source: Errno,
+    },
+
+    #[error("lseek({fd}, {pos:?})")]
+    Lseek {
+        fd: FrozenFd,
+        pos: SeekFrom,
+        source: Errno,
+    },
+
+    #[error("fallocate({fd}, {mode:?}, {offset}, {len})")]
+    Fallocate {
+        fd: FrozenFd,
+        mode: FallocateFlags,
+        offset: u64,
+        len: u64,
+        source: Errno,
+    },
+
+    #[error("ioctl({dst}, FICLONE, {src})")]
+    Ficlone {
+        dst: FrozenFd,
+        src: FrozenFd,
+        source: Errno,
+    },
+
+    #[error("capget(self)")]
+    Capget { source: Errno },
+
     #[error("fsopen({fstype}, {flags:?})")]
     Fsopen {
         fstype: String,
@@ -269,8 +390,26 @@ impl Error {
             Error::Renameat { source, .. } => source,
             Error::Renameat2 { source, .. } => source,
             Error::Fstatfs { source, .. } => source,
+            Error::Fstatvfs { source, .. } => source,
             Error::Fstatat { source, .. } => source,
+            Error::Faccessat { source, .. } => source,
             Error::Statx { source, .. } => source,
+            Error::Fchdir { source, .. } => source,
+            Error::FcntlGetfl { source, .. } => source,
+            Error::Fchmodat { source, .. } => source,
+            Error::Utimensat { source, .. } => source,
+            Error::Fchownat { source, .. } => source,
+            Error::Fchown { source, .. } => source,
+            Error::Capget { source, .. } => source,
+            Error::Flock { source, .. } => source,
+            Error::Fgetxattr { source, .. } => source,
+            Error::Fsetxattr { source, .. } => source,
+            Error::Fremovexattr { source, .. } => source,
+            Error::Flistxattr { source, .. } => source,
+            Error::CopyFileRange { source, .. } => source,
+            Error::Lseek { source, .. } => source,
+            Error::Fallocate { source, .. } => source,
+            Error::Ficlone { source, .. } => source,
             Error::Fsopen { source, .. } => source,
             Error::FsconfigCreate { source, .. } => source,
             Error::FsconfigSetString { source, .. } => source,
@@ -577,6 +716,18 @@ pub(crate) fn fstatfs<Fd: AsFd>(fd: Fd) -> Result<StatFs, Error> {
     })
 }
 
+/// Wrapper for `fstatvfs(3)`.
+///
+/// This is needed because Rust doesn't provide any interface for `fstatvfs(3)`.
+pub(crate) fn fstatvfs<Fd: AsFd>(fd: Fd) -> Result<StatVfs, Error> {
+    let fd = fd.as_fd().hotfix_rustix_fd()?;
+
+    rustix_fs::fstatvfs(fd).map_err(|errno| Error::Fstatvfs {
+        fd: fd.into(),
+        source: errno,
+    })
+}
+
 /// Wrapper for `fstatat(2)`, which auto-sets `AT_NO_AUTOMOUNT |
 /// AT_SYMLINK_NOFOLLOW | AT_EMPTY_PATH`.
 ///
@@ -594,6 +745,49 @@ pub(crate) fn fstatat<Fd: AsFd, P: AsRef<Path>>(dirfd: Fd, path: P) -> Result<St
     })
 }
 
+/// Check whether the current process can access `path` (without following
+/// trailing symlinks) using its effective credentials. Note that this requires
+/// faccessat2(2) (Linux 5.8) because of the AT_* flags.
+pub(crate) fn faccessat<Fd: AsFd, P: AsRef<Path>>(
+    dirfd: Fd,
+    path: P,
+    access: Access,
+) -> Result<(), Error> {
+    let dirfd = dirfd.as_fd().hotfix_rustix_fd()?;
+    let path = path.as_ref();
+    let flags = AtFlags::EACCESS | AtFlags::SYMLINK_NOFOLLOW;
+
+    rustix_fs::accessat(dirfd, path, access, flags).map_err(|errno| Error::Faccessat {
+        dirfd: dirfd.into(),
+        path: path.into(),
+        access,
+        flags,
+        source: errno,
+    })
+}
+
+/// Check whether the current process can access `path` (following trailing
+/// symlinks, including magic-links) using its effective credentials. Unlike
+/// [`faccessat`], this can be emulated with plain `faccessat(2)` on pre-5.8
+/// kernels (as long as the process is not setuid or setgid).
+pub(crate) fn faccessat_follow<Fd: AsFd, P: AsRef<Path>>(
+    dirfd: Fd,
+    path: P,
+    access: Access,
+) -> Result<(), Error> {
+    let dirfd = dirfd.as_fd().hotfix_rustix_fd()?;
+    let path = path.as_ref();
+    let flags = AtFlags::EACCESS;
+
+    rustix_fs::accessat(dirfd, path, access, flags).map_err(|errno| Error::Faccessat {
+        dirfd: dirfd.into(),
+        path: path.into(),
+        access,
+        flags,
+        source: errno,
+    })
+}
+
 pub(crate) fn statx<Fd: AsFd, P: AsRef<Path>>(
     dirfd: Fd,
     path: P,
@@ -612,6 +806,297 @@ pub(crate) fn statx<Fd: AsFd, P: AsRef<Path>>(
     })
 }
 
+/// Wrapper for `fchdir(2)`.
+///
+/// Note that this changes the current working directory of the whole process
+/// (unless the calling thread has been `unshare(CLONE_FS)`-ed).
+pub(crate) fn fchdir<Fd: AsFd>(fd: Fd) -> Result<(), Error> {
+    let fd = fd.as_fd().hotfix_rustix_fd()?;
+
+    rustix_process::fchdir(fd).map_err(|errno| Error::Fchdir {
+        fd: fd.into(),
+        source: errno,
+    })
+}
+
+/// Wrapper for `fcntl(F_GETFL)`.
+pub(crate) fn fcntl_getfl<Fd: AsFd>(fd: Fd) -> Result<OpenFlags, Error> {
+    let fd = fd.as_fd().hotfix_rustix_fd()?;
+
+    rustix_fs::fcntl_getfl(fd)
+        .map(|flags| OpenFlags::from_bits_retain(flags.bits() as libc::c_int))
+        .map_err(|errno| Error::FcntlGetfl {
+            fd: fd.into(),
+            source: errno,
+        })
+}
+
+/// Wrapper for `fchmodat(2)`.
+///
+/// Note that `fchmodat(2)` always follows trailing symlinks (including
+/// magic-links).
+pub(crate) fn fchmodat<Fd: AsFd, P: AsRef<Path>>(
+    dirfd: Fd,
+    path: P,
+    mode: RawMode,
+) -> Result<(), Error> {
+    let dirfd = dirfd.as_fd().hotfix_rustix_fd()?;
+    let path = path.as_ref();
+
+    rustix_fs::chmodat(dirfd, path, Mode::from_raw_mode(mode), AtFlags::empty()).map_err(|errno| {
+        Error::Fchmodat {
+            dirfd: dirfd.into(),
+            path: path.into(),
+            mode,
+            source: errno,
+        }
+    })
+}
+
+/// Wrapper for `utimensat(2)`.
+pub(crate) fn utimensat<Fd: AsFd, P: AsRef<Path>>(
+    dirfd: Fd,
+    path: P,
+    times: &Timestamps,
+) -> Result<(), Error> {
+    let dirfd = dirfd.as_fd().hotfix_rustix_fd()?;
+    let path = path.as_ref();
+
+    rustix_fs::utimensat(dirfd, path, times, AtFlags::empty()).map_err(|errno| Error::Utimensat {
+        dirfd: dirfd.into(),
+        path: path.into(),
+        times: times.clone(),
+        source: errno,
+    })
+}
+
+/// Wrapper for `fchownat(2)` with `AT_EMPTY_PATH`, which changes the owner of
+/// the inode referenced by `fd` (which may be an `O_PATH` file descriptor,
+/// including one referencing a symlink). Passing `None` leaves the
+/// corresponding id unchanged.
+pub(crate) fn fchownat_empty<Fd: AsFd>(
+    fd: Fd,
+    uid: Option<u32>,
+    gid: Option<u32>,
+) -> Result<(), Error> {
+    let fd = fd.as_fd().hotfix_rustix_fd()?;
+    let flags = AtFlags::EMPTY_PATH | AtFlags::SYMLINK_NOFOLLOW;
+
+    // SAFETY: The kernel treats -1 as "do not change", which is the only
+    //         special value for uid_t and gid_t.
+    let (owner, group) = unsafe {
+        (
+            uid.map(|uid| rustix_process::Uid::from_raw(uid)),
+            gid.map(|gid| rustix_process::Gid::from_raw(gid)),
+        )
+    };
+    rustix_fs::chownat(fd, "", owner, group, flags).map_err(|errno| Error::Fchownat {
+        dirfd: fd.into(),
+        path: "".into(),
+        uid,
+        gid,
+        flags,
+        source: errno,
+    })
+}
+
+/// Wrapper for `fchown(2)`.
+///
+/// Note that `fchown(2)` does not work on `O_PATH` file descriptors.
+pub(crate) fn fchown<Fd: AsFd>(fd: Fd, uid: u32, gid: u32) -> Result<(), Error> {
+    let fd = fd.as_fd().hotfix_rustix_fd()?;
+
+    // SAFETY: The kernel treats -1 as "do not change", which is the only
+    //         special value for uid_t and gid_t.
+    let (owner, group) = unsafe {
+        (
+            rustix_process::Uid::from_raw(uid),
+            rustix_process::Gid::from_raw(gid),
+        )
+    };
+    rustix_fs::fchown(fd, Some(owner), Some(group)).map_err(|errno| Error::Fchown {
+        fd: fd.into(),
+        uid,
+        gid,
+        source: errno,
+    })
+}
+
+pub(crate) fn flock<Fd: AsFd>(fd: Fd, operation: FlockOperation) -> Result<(), Error> {
+    let fd = fd.as_fd().hotfix_rustix_fd()?;
+
+    rustix_fs::flock(fd, operation).map_err(|errno| Error::Flock {
+        fd: fd.into(),
+        operation,
+        source: errno,
+    })
+}
+
+/// Wrapper for `fgetxattr(2)`.
+///
+/// The size of the value is first queried, and the call is retried if the
+/// value grew in between the two calls.
+pub(crate) fn fgetxattr<Fd: AsFd, N: AsRef<OsStr>>(fd: Fd, name: N) -> Result<Vec<u8>, Error> {
+    let fd = fd.as_fd().hotfix_rustix_fd()?;
+    let name = name.as_ref();
+    let err = |errno| Error::Fgetxattr {
+        fd: fd.into(),
+        name: name.into(),
+        source: errno,
+    };
+
+    loop {
+        let size = rustix_fs::fgetxattr(fd, name, &mut []).map_err(err)?;
+        let mut value = vec![0u8; size];
+        match rustix_fs::fgetxattr(fd, name, &mut value) {
+            Ok(n) => {
+                value.truncate(n);
+                return Ok(value);
+            }
+            // The value grew between the two calls.
+            Err(Errno::RANGE) => continue,
+            Err(errno) => return Err(err(errno)),
+        }
+    }
+}
+
+/// Wrapper for `fsetxattr(2)`.
+pub(crate) fn fsetxattr<Fd: AsFd, N: AsRef<OsStr>>(
+    fd: Fd,
+    name: N,
+    value: &[u8],
+    flags: XattrFlags,
+) -> Result<(), Error> {
+    let fd = fd.as_fd().hotfix_rustix_fd()?;
+    let name = name.as_ref();
+
+    rustix_fs::fsetxattr(fd, name, value, flags).map_err(|errno| Error::Fsetxattr {
+        fd: fd.into(),
+        name: name.into(),
+        size: value.len(),
+        flags,
+        source: errno,
+    })
+}
+
+/// Wrapper for `fremovexattr(2)`.
+pub(crate) fn fremovexattr<Fd: AsFd, N: AsRef<OsStr>>(fd: Fd, name: N) -> Result<(), Error> {
+    let fd = fd.as_fd().hotfix_rustix_fd()?;
+    let name = name.as_ref();
+
+    rustix_fs::fremovexattr(fd, name).map_err(|errno| Error::Fremovexattr {
+        fd: fd.into(),
+        name: name.into(),
+        source: errno,
+    })
+}
+
+/// Wrapper for `flistxattr(2)`, returning the list of names.
+///
+/// As with [`fgetxattr`], the size of the list is first queried, and the call
+/// is retried if the list grew in between the two calls.
+pub(crate) fn flistxattr<Fd: AsFd>(fd: Fd) -> Result<Vec<OsString>, Error> {
+    let fd = fd.as_fd().hotfix_rustix_fd()?;
+    let err = |errno| Error::Flistxattr {
+        fd: fd.into(),
+        source: errno,
+    };
+
+    let list = loop {
+        let size = rustix_fs::flistxattr(fd, &mut []).map_err(err)?;
+        let mut list = vec![0u8; size];
+        match rustix_fs::flistxattr(fd, &mut list) {
+            Ok(n) => {
+                list.truncate(n);
+                break list;
+            }
+            // The list grew between the two calls.
+            Err(Errno::RANGE) => continue,
+            Err(errno) => return Err(err(errno)),
+        }
+    };
+
+    // The list is a sequence of nul-terminated names.
+    Ok(list
+        .split(|&c| c == b'\0')
+        .filter(|name| !name.is_empty())
+        .map(|name| OsStr::from_bytes(name).to_os_string())
+        .collect())
+}
+
+/// Wrapper for `copy_file_range(2)`, using (and updating) the current file
+/// offsets of both file descriptors.
+pub(crate) fn copy_file_range<Fd1: AsFd, Fd2: AsFd>(
+    fd_in: Fd1,
+    fd_out: Fd2,
+    len: usize,
+) -> Result<usize, Error> {
+    let fd_in = fd_in.as_fd().hotfix_rustix_fd()?;
+    let fd_out = fd_out.as_fd().hotfix_rustix_fd()?;
+
+    rustix_fs::copy_file_range(fd_in, None, fd_out, None, len).map_err(|errno| {
+        Error::CopyFileRange {
+            fd_in: fd_in.into(),
+            fd_out: fd_out.into(),
+            len,
+            source: errno,
+        }
+    })
+}
+
+/// Wrapper for `lseek(2)`, including `SEEK_DATA` and `SEEK_HOLE`.
+pub(crate) fn lseek<Fd: AsFd>(fd: Fd, pos: SeekFrom) -> Result<u64, Error> {
+    let fd = fd.as_fd().hotfix_rustix_fd()?;
+
+    rustix_fs::seek(fd, pos).map_err(|errno| Error::Lseek {
+        fd: fd.into(),
+        pos,
+        source: errno,
+    })
+}
+
+/// Wrapper for `fallocate(2)`.
+pub(crate) fn fallocate<Fd: AsFd>(
+    fd: Fd,
+    mode: FallocateFlags,
+    offset: u64,
+    len: u64,
+) -> Result<(), Error> {
+    let fd = fd.as_fd().hotfix_rustix_fd()?;
+
+    rustix_fs::fallocate(fd, mode, offset, len).map_err(|errno| Error::Fallocate {
+        fd: fd.into(),
+        mode,
+        offset,
+        len,
+        source: errno,
+    })
+}
+
+/// Wrapper for `ioctl(FICLONE)`, making `dst` share the data extents of
+/// `src` (a "reflink" copy).
+pub(crate) fn ficlone<Fd1: AsFd, Fd2: AsFd>(dst: Fd1, src: Fd2) -> Result<(), Error> {
+    let dst = dst.as_fd().hotfix_rustix_fd()?;
+    let src = src.as_fd().hotfix_rustix_fd()?;
+
+    // FICLONE is not available on SPARC, so just act as though the filesystem
+    // does not support it.
+    #[cfg(not(any(target_arch = "sparc", target_arch = "sparc64")))]
+    let ret = rustix_fs::ioctl_ficlone(dst, src);
+    #[cfg(any(target_arch = "sparc", target_arch = "sparc64"))]
+    let ret = Err(Errno::OPNOTSUPP);
+
+    ret.map_err(|errno| Error::Ficlone {
+        dst: dst.into(),
+        src: src.into(),
+        source: errno,
+    })
+}
+
+pub(crate) fn capget() -> Result<CapabilitySets, Error> {
+    rustix_thread::capabilities(None).map_err(|errno| Error::Capget { source: errno })
+}
+
 // MSRV(1.80): Use LazyLock.
 pub(crate) static OPENAT2_IS_SUPPORTED: Lazy<bool> =
     Lazy::new(|| openat2(AT_FDCWD, ".", &Default::default()).is_ok());
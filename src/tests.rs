@@ -65,6 +65,7 @@ pub(in crate::tests) mod traits {
 
 mod test_procfs;
 mod test_resolve;
+mod test_resolve_conformance;
 mod test_resolve_partial;
 mod test_root_ops;
 
@@ -142,6 +142,12 @@ impl CapiRoot {
                     libc::S_IFIFO | perm.mode(),
                     0,
                 ),
+                InodeType::Socket(perm) => capi::core::pathrs_inroot_mknod(
+                    root_fd.into(),
+                    path.as_ptr(),
+                    libc::S_IFSOCK | perm.mode(),
+                    0,
+                ),
                 InodeType::CharacterDevice(perm, dev) => capi::core::pathrs_inroot_mknod(
                     root_fd.into(),
                     path.as_ptr(),
@@ -112,6 +112,18 @@ pub(in crate::tests) fn check_oflags<Fd: AsFd>(fd: Fd, flags: OpenFlags) -> Resu
     Ok(())
 }
 
+/// Check that `handle.reopen(flags)` is rejected as invalid.
+pub(in crate::tests) fn check_reopen_invalid<H: HandleImpl>(
+    handle: H,
+    flags: OpenFlags,
+) -> Result<(), Error> {
+    tests_common::check_err(
+        &handle.reopen(flags),
+        &Err::<(), _>(ErrorKind::InvalidArgument),
+    )
+    .with_context(|| format!("reopen handle {flags:?}"))
+}
+
 pub(in crate::tests) fn check_reopen<H: HandleImpl>(
     handle: H,
     flags: OpenFlags,
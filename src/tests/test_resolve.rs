@@ -20,13 +20,35 @@
 #[cfg(feature = "capi")]
 use crate::tests::capi::CapiRoot;
 use crate::{
-    error::ErrorKind, flags::ResolverFlags, resolvers::ResolverBackend,
-    tests::common as tests_common, Root,
+    error::{Error as PathrsError, ErrorKind},
+    flags::{AbsoluteSymlinkPolicy, OpenFlags, ResolverFlags},
+    procfs::ProcfsHandle,
+    resolvers::{opath, ResolverBackend},
+    tests::common::{self as tests_common, MountType},
+    utils::FdExt,
+    EscapeReason, Resolution, Root,
 };
 
-use std::path::Path;
+use std::{
+    ffi::OsStr,
+    fmt,
+    fs::{self, Permissions},
+    os::unix::{
+        fs::PermissionsExt,
+        io::{AsFd, AsRawFd},
+        net::UnixStream,
+    },
+    path::Path,
+    thread,
+    time::Duration,
+};
 
-use anyhow::Error;
+use anyhow::{Context, Error};
+use rustix::{
+    mount::{self as rustix_mount, UnmountFlags},
+    process as rustix_process,
+    thread::{self as rustix_thread, CapabilityFlags},
+};
 
 macro_rules! resolve_tests {
     // resolve_tests! {
@@ -446,6 +468,16 @@ resolve_tests! {
         symlink_component_nosym1: resolve("e/f", rflags = NO_SYMLINKS) => Err(ErrorKind::OsError(Some(libc::ELOOP)));
         symlink_component_nosym2: resolve("link2/link1_abs/target_rel", rflags = NO_SYMLINKS) => Err(ErrorKind::OsError(Some(libc::ELOOP)));
         loop_nosym: resolve("loop/link", rflags = NO_SYMLINKS) => Err(ErrorKind::OsError(Some(libc::ELOOP)));
+        // RESOLVE_BENEATH.
+        dir_beneath: resolve("b/c/d/e", rflags = NO_ESCAPE_BENEATH) => Ok(("b/c/d/e", libc::S_IFDIR));
+        dotdot_inside_beneath: resolve("b/c/../c/d/../../c", rflags = NO_ESCAPE_BENEATH) => Ok(("b/c", libc::S_IFDIR));
+        symlink_rel_beneath: resolve("link3/target_rel", rflags = NO_ESCAPE_BENEATH) => Ok(("target", libc::S_IFDIR));
+        dotdot_escape_beneath1: resolve("..", rflags = NO_ESCAPE_BENEATH) => Err(ErrorKind::OsError(Some(libc::EXDEV)));
+        dotdot_escape_beneath2: resolve("b/../../b", rflags = NO_ESCAPE_BENEATH) => Err(ErrorKind::OsError(Some(libc::EXDEV)));
+        symlink_escape_beneath: resolve("escape-link1", rflags = NO_ESCAPE_BENEATH) => Err(ErrorKind::OsError(Some(libc::EXDEV)));
+        symlink_abs_beneath1: resolve("e", rflags = NO_ESCAPE_BENEATH) => Err(ErrorKind::OsError(Some(libc::EXDEV)));
+        symlink_abs_beneath2: resolve("link2/link1_abs/target_rel", rflags = NO_ESCAPE_BENEATH) => Err(ErrorKind::OsError(Some(libc::EXDEV)));
+        abs_path_beneath: resolve("/b/c", rflags = NO_ESCAPE_BENEATH) => Err(ErrorKind::OsError(Some(libc::EXDEV)));
         // fs.protected_symlinks for a directory owned by us.
         protected_symlinks_selfdir_selfsym: resolve("tmpfs-self/link-self") => Ok(("tmpfs-self/file", libc::S_IFREG));
         protected_symlinks_selfdir_selfsym_nofollow: resolve("tmpfs-self/link-self", no_follow_trailing = true) => Ok(("tmpfs-self/link-self", libc::S_IFLNK));
@@ -489,6 +521,932 @@ resolve_tests! {
     }
 }
 
+resolve_tests! {
+    [tests_common::create_basic_tree()?]
+    rust-fn resolve_deadline_expired(mut root: Root) {
+        root.set_resolve_deadline(Some(Duration::ZERO));
+        assert_eq!(
+            root.resolve_deadline(),
+            Some(Duration::ZERO),
+            "resolve deadline should be set by set_resolve_deadline"
+        );
+
+        tests_common::check_err(&root.resolve("b/c/file"), &Err::<(), _>(ErrorKind::TimedOut))?;
+        tests_common::check_err(
+            &root.mkdir_all("b/c/d/e/f/g", &Permissions::from_mode(0o755)),
+            &Err::<(), _>(ErrorKind::TimedOut),
+        )?;
+
+        // mkdir_all must not have created anything after timing out.
+        root.set_resolve_deadline(None);
+        tests_common::check_err(
+            &root.resolve("b/c/d/e/f/g"),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::ENOENT))),
+        )?;
+    } => ()
+}
+
+resolve_tests! {
+    [tests_common::create_basic_tree()?]
+    rust-fn resolve_deadline_ok(mut root: Root) {
+        root.set_resolve_deadline(Some(Duration::from_secs(3600)));
+        assert_eq!(
+            root.resolve("b-file")?.as_unsafe_path_unchecked()?,
+            root.resolve("b/c/file")?.as_unsafe_path_unchecked()?,
+            "resolve within deadline should succeed"
+        );
+
+        // Absurdly large deadlines are treated as unlimited.
+        root.set_resolve_deadline(Some(Duration::MAX));
+        root.resolve("b/c/file")?;
+    } => ()
+}
+
+resolve_tests! {
+    [tests_common::create_basic_tree()?]
+    rust-fn absolute_symlink_policy_error(mut root: Root) {
+        assert_eq!(
+            root.absolute_symlink_policy(),
+            AbsoluteSymlinkPolicy::InRoot,
+            "default absolute symlink policy should be InRoot"
+        );
+        let target = root.resolve("link1/target_abs")?.as_unsafe_path_unchecked()?;
+
+        // Explicitly requesting openat2 with this option results in a
+        // NotSupported error (see emulated_only_options_forced_openat2).
+        if root.resolver_backend() == ResolverBackend::KernelOpenat2 {
+            root.set_resolver_backend(ResolverBackend::EmulatedOpath);
+        }
+        root.set_absolute_symlink_policy(AbsoluteSymlinkPolicy::Error);
+        for path in ["root-link1", "link1/target_abs", "link2/link1_abs/target_rel", "e/f"] {
+            tests_common::check_err(
+                &root.resolve(path),
+                &Err::<(), _>(ErrorKind::OsError(Some(libc::ELOOP))),
+            )?;
+        }
+
+        // Relative symlinks are still followed.
+        assert_eq!(
+            root.resolve("link1/target_rel")?.as_unsafe_path_unchecked()?,
+            target,
+            "relative symlinks should be followed with AbsoluteSymlinkPolicy::Error"
+        );
+        // Absolute symlinks can still be operated on if they are not followed.
+        root.resolve_nofollow("link1/target_abs")?;
+    } => ()
+}
+
+resolve_tests! {
+    [tests_common::create_basic_tree()?]
+    rust-fn resolve_no_automount(mut root: Root) {
+        let want = root.resolve("b/c/file")?.as_unsafe_path_unchecked()?;
+        // Explicitly requesting openat2 with this option results in a
+        // NotSupported error (see emulated_only_options_forced_openat2).
+        if root.resolver_backend() == ResolverBackend::KernelOpenat2 {
+            root.set_resolver_backend(ResolverBackend::EmulatedOpath);
+        }
+        root.set_resolver_flags(root.resolver_flags() | ResolverFlags::NO_AUTOMOUNT);
+        // There are no automount points in the test tree, so resolution
+        // should work as usual (but always with the emulated backend).
+        let handle = root.resolve("b-file")?;
+        assert_eq!(
+            handle.backend(),
+            Some(ResolverBackend::EmulatedOpath),
+            "NO_AUTOMOUNT should always use the emulated backend"
+        );
+        assert_eq!(
+            handle.as_unsafe_path_unchecked()?,
+            want,
+            "NO_AUTOMOUNT should not affect ordinary resolution"
+        );
+        tests_common::check_err(
+            &root.resolve("b/c/nonexistent"),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::ENOENT))),
+        )?;
+    } => ()
+}
+
+resolve_tests! {
+    [tests_common::create_basic_tree()?]
+    rust-fn resolve_require_owner(mut root: Root) {
+        let uid = rustix_process::geteuid().as_raw();
+        // Explicitly requesting openat2 with this option results in a
+        // NotSupported error (see emulated_only_options_forced_openat2).
+        if root.resolver_backend() == ResolverBackend::KernelOpenat2 {
+            root.set_resolver_backend(ResolverBackend::EmulatedOpath);
+        }
+
+        assert_eq!(
+            root.resolve_require_owner("b-file", uid)?.as_unsafe_path_unchecked()?,
+            root.resolve("b/c/file")?.as_unsafe_path_unchecked()?,
+            "resolve_require_owner should succeed when all components are owned by uid"
+        );
+        tests_common::check_err(
+            &root.resolve_require_owner("b/c/file", uid.wrapping_add(1)),
+            &Err::<(), _>(ErrorKind::SafetyViolation),
+        )?;
+    } => ()
+}
+
+resolve_tests! {
+    [tests_common::create_basic_tree()?]
+    #[cfg(feature = "_test_as_root")]
+    rust-fn resolve_require_owner_other_dir(mut root: Root) {
+        rustix::fs::chownat(
+            root.as_fd(),
+            "b/c",
+            Some(unsafe { rustix_process::Uid::from_raw(1000) }),
+            None,
+            rustix::fs::AtFlags::SYMLINK_NOFOLLOW,
+        )?;
+        // Explicitly requesting openat2 with this option results in a
+        // NotSupported error (see emulated_only_options_forced_openat2).
+        if root.resolver_backend() == ResolverBackend::KernelOpenat2 {
+            root.set_resolver_backend(ResolverBackend::EmulatedOpath);
+        }
+
+        root.resolve_require_owner("b", 0)?;
+        tests_common::check_err(
+            &root.resolve_require_owner("b/c", 0),
+            &Err::<(), _>(ErrorKind::SafetyViolation),
+        )?;
+        tests_common::check_err(
+            &root.resolve_require_owner("b-file", 0),
+            &Err::<(), _>(ErrorKind::SafetyViolation),
+        )?;
+        // The plain resolver doesn't care about ownership.
+        root.resolve("b/c/file")?;
+    } => ()
+}
+
+// Options which can only be implemented by the emulated backend silently use
+// it by default, but must not override an explicitly requested openat2 backend.
+#[test]
+fn emulated_only_options_forced_openat2() -> Result<(), Error> {
+    let root_dir = tests_common::create_basic_tree()?;
+    let root = Root::open(&root_dir)?;
+    let no_automount = root
+        .try_clone()?
+        .with_resolver_flags(ResolverFlags::NO_AUTOMOUNT);
+    let abs_error = root
+        .try_clone()?
+        .with_absolute_symlink_policy(AbsoluteSymlinkPolicy::Error);
+
+    for (name, root) in [
+        ("NO_AUTOMOUNT", &no_automount),
+        ("AbsoluteSymlinkPolicy::Error", &abs_error),
+    ] {
+        assert_eq!(
+            root.resolve("b/c/file")?.backend(),
+            Some(ResolverBackend::EmulatedOpath),
+            "{name} should use the emulated backend by default"
+        );
+        let root = root
+            .try_clone()?
+            .with_resolver_backend(ResolverBackend::EmulatedOpath);
+        root.resolve("b/c/file")?;
+    }
+    assert_eq!(
+        root.resolve_require_owner("b/c/file", rustix_process::geteuid().as_raw())?
+            .backend(),
+        Some(ResolverBackend::EmulatedOpath),
+        "resolve_require_owner should use the emulated backend by default"
+    );
+
+    if !ResolverBackend::KernelOpenat2.supported() {
+        return Ok(());
+    }
+    for root in [&no_automount, &abs_error] {
+        let root = root
+            .try_clone()?
+            .with_resolver_backend(ResolverBackend::KernelOpenat2);
+        tests_common::check_err(
+            &root.resolve("b/c/file"),
+            &Err::<(), _>(ErrorKind::NotSupported),
+        )?;
+        tests_common::check_err(
+            &root.open_subpath("b/c/file", OpenFlags::O_RDONLY),
+            &Err::<(), _>(ErrorKind::NotSupported),
+        )?;
+    }
+    let root = root.with_resolver_backend(ResolverBackend::KernelOpenat2);
+    tests_common::check_err(
+        &root.resolve_require_owner("b/c/file", rustix_process::geteuid().as_raw()),
+        &Err::<(), _>(ErrorKind::NotSupported),
+    )?;
+    Ok(())
+}
+
+resolve_tests! {
+    [tests_common::create_basic_tree()?]
+    rust-fn resolve_explain(mut root: Root) {
+        let (handle, explain) = root.resolve_explain("b-file");
+        let handle = handle?;
+        assert_eq!(
+            handle.as_unsafe_path_unchecked()?,
+            root.resolve("b/c/file")?.as_unsafe_path_unchecked()?,
+            "resolve_explain should resolve the same path as resolve"
+        );
+        assert_eq!(
+            explain
+                .iter()
+                .map(|c| (c.name(), c.path(), c.mode() & libc::S_IFMT))
+                .collect::<Vec<_>>(),
+            vec![
+                (OsStr::new("b-file"), Path::new("/b-file"), libc::S_IFLNK),
+                (OsStr::new("b"), Path::new("/b"), libc::S_IFDIR),
+                (OsStr::new("c"), Path::new("/b/c"), libc::S_IFDIR),
+                (OsStr::new("file"), Path::new("/b/c/file"), libc::S_IFREG),
+            ],
+            "resolve_explain components"
+        );
+        for component in explain {
+            assert_eq!(component.uid(), rustix_process::geteuid().as_raw());
+            let expected = (component.mode() & libc::S_IFMT == libc::S_IFDIR).then_some(true);
+            assert_eq!(
+                component.search_permitted(),
+                expected,
+                "search permission for {:?}",
+                component.path()
+            );
+        }
+
+        // The components walked through before an error are still returned.
+        let (res, explain) = root.resolve_explain("b/c/nonexistent");
+        tests_common::check_err(&res, &Err::<(), _>(ErrorKind::OsError(Some(libc::ENOENT))))?;
+        assert_eq!(
+            explain.iter().map(|c| c.path()).collect::<Vec<_>>(),
+            vec![Path::new("/b"), Path::new("/b/c")],
+            "resolve_explain components on error"
+        );
+    } => ()
+}
+
+resolve_tests! {
+    [tests_common::create_basic_tree()?]
+    rust-fn resolve_all(mut root: Root) {
+        let paths = [
+            "b/c/file",
+            "b-file",
+            "a-fake1",
+            "escape-link1",
+            "loop/basic-loop1",
+            "b/c/file/foo",
+            "e/f",
+            "..",
+            "b/c/nonexistent",
+            "b/c",
+        ]
+        .repeat(8);
+        let results = root.resolve_all(&paths);
+        assert_eq!(results.len(), paths.len(), "resolve_all should return one result per path");
+        // Each result should be identical to an individual resolve, in order.
+        for (path, result) in paths.iter().zip(results) {
+            match (result, root.resolve(path)) {
+                (Ok(got), Ok(want)) => assert_eq!(
+                    got.as_unsafe_path_unchecked()?,
+                    want.as_unsafe_path_unchecked()?,
+                    "resolve_all({path:?}) should resolve the same path as resolve"
+                ),
+                (Err(got), Err(want)) => assert_eq!(
+                    got.kind(),
+                    want.kind(),
+                    "resolve_all({path:?}) should fail in the same way as resolve"
+                ),
+                (got, want) => panic!("resolve_all({path:?}) gave {got:?} but resolve gave {want:?}"),
+            }
+        }
+
+        assert!(root.resolve_all(Vec::<&str>::new()).is_empty(), "empty batch should give no results");
+    } => ()
+}
+
+resolve_tests! {
+    [tests_common::create_basic_tree()?]
+    rust-fn classify(mut root: Root) {
+        for (path, expected) in [
+            ("b/c/file", Resolution::InsideRoot("b/c/file".into())),
+            ("b-file", Resolution::InsideRoot("b/c/file".into())),
+            ("a/../b/./c", Resolution::InsideRoot("b/c".into())),
+            ("link3/target_rel", Resolution::InsideRoot("target".into())),
+            (".", Resolution::InsideRoot(".".into())),
+            ("b/c/nonexistent", Resolution::NotFound),
+            ("a-fake1", Resolution::NotFound),
+        ] {
+            assert_eq!(root.classify(path)?, expected, "classify({path:?})");
+        }
+
+        let emulated = root.resolver_backend() == ResolverBackend::EmulatedOpath;
+        // The reason for absolute paths is known even with openat2(2).
+        assert_eq!(
+            root.classify("/b/c")?,
+            Resolution::EscapeAttempt {
+                component: "/".into(),
+                reason: EscapeReason::AbsolutePath,
+            },
+            "classify(\"/b/c\")"
+        );
+        for (path, component, reason) in [
+            ("../b", "..", EscapeReason::DotDot),
+            ("a/../../b", "..", EscapeReason::DotDot),
+            ("escape-link1", "..", EscapeReason::DotDot),
+            ("root-link1", "root-link1", EscapeReason::AbsoluteSymlink),
+            ("e/f", "e", EscapeReason::AbsoluteSymlink),
+        ] {
+            let result = root.classify(path)?;
+            if emulated {
+                assert_eq!(
+                    result,
+                    Resolution::EscapeAttempt {
+                        component: component.into(),
+                        reason
+                    },
+                    "classify({path:?})"
+                );
+            } else {
+                assert!(
+                    matches!(
+                        result,
+                        Resolution::EscapeAttempt {
+                            reason: EscapeReason::Unknown,
+                            ..
+                        }
+                    ),
+                    "classify({path:?}) should be an escape attempt: {result:?}"
+
+
+                );
+            }
+        }
+
+        tests_common::check_err(
+            &root.classify("b/c/file/foo"),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::ENOTDIR))),
+        )?;
+    } => ()
+}
+
+#[test]
+#[cfg_attr(not(feature = "_test_as_root"), ignore)]
+fn root_classify_mount_crossing() -> Result<(), Error> {
+    tests_common::in_mnt_ns(|| {
+        let root_dir = tests_common::create_basic_tree()?;
+        tests_common::mount(root_dir.path().join("b/c/d"), MountType::Tmpfs)?;
+
+        for backend in [
+            ResolverBackend::EmulatedOpath,
+            ResolverBackend::KernelOpenat2,
+        ] {
+            if !backend.supported() {
+                continue;
+            }
+            let root = Root::open(&root_dir)?.with_resolver_backend(backend);
+            let result = root.classify("b/c/d")?;
+            assert_eq!(
+                result,
+                Resolution::EscapeAttempt {
+                    component: "d".into(),
+                    reason: match backend {
+                        ResolverBackend::EmulatedOpath => EscapeReason::MountCrossing,
+                        _ => EscapeReason::Unknown,
+                    },
+                },
+                "classify of a mountpoint with {backend:?}"
+            );
+            // The normal resolver doesn't care about mounts.
+            root.resolve("b/c/d")?;
+        }
+        Ok(())
+    })
+}
+
+#[test]
+fn root_resolve_explain_no_search() -> Result<(), Error> {
+    let root_dir = tests_common::create_basic_tree()?;
+    fs::set_permissions(root_dir.path().join("b/c"), Permissions::from_mode(0o600))?;
+    let root = Root::open(&root_dir)?;
+
+    // Capabilities are per-thread, so drop the DAC override capabilities in a
+    // separate thread to avoid affecting other tests.
+    thread::spawn(move || -> Result<(), Error> {
+        let mut caps = rustix_thread::capabilities(None)?;
+        caps.effective
+            .remove(CapabilityFlags::DAC_OVERRIDE | CapabilityFlags::DAC_READ_SEARCH);
+        rustix_thread::set_capabilities(None, caps)?;
+
+        let (res, explain) = root.resolve_explain("b/c");
+        res?;
+        assert_eq!(
+            explain
+                .iter()
+                .map(|c| (c.name(), c.search_permitted()))
+                .collect::<Vec<_>>(),
+            vec![
+                (OsStr::new("b"), Some(true)),
+                (OsStr::new("c"), Some(false))
+            ],
+            "b/c should not be searchable"
+        );
+        Ok(())
+    })
+    .join()
+    .expect("thread should not panic")
+}
+
+resolve_tests! {
+    [tests_common::create_basic_tree()?]
+    rust-fn open_nearest_ancestor(mut root: Root) {
+        for (path, expected_path, expected_count) in [
+            ("b/c/d/e/f/g/h", "b/c/d/e/f", 5),
+            ("b//c/./nonexistent/", "b/c", 3),
+            ("/b/c/file", "b/c/file", 3),
+            ("a/nonexistent", "a", 1),
+            ("nonexistent", ".", 0),
+            ("e/f/nonexistent", "b/c/d/e/f", 2),
+            // Resolution stops at the top-most dangling symlink.
+            ("link3/deep_dangling1/foo", "link3", 1),
+        ] {
+            let (handle, count) = root.open_nearest_ancestor(path)?;
+            assert_eq!(
+                (handle.as_unsafe_path_unchecked()?, count),
+                (root.resolve(expected_path)?.as_unsafe_path_unchecked()?, expected_count),
+                "open_nearest_ancestor({path:?})"
+            );
+        }
+
+        tests_common::check_err(
+            &root.open_nearest_ancestor("b/c/file/foo"),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::ENOTDIR))),
+        )?;
+    } => ()
+}
+
+resolve_tests! {
+    [tests_common::create_basic_tree()?]
+    rust-fn resolve_partial(mut root: Root) {
+        for (path, no_follow_trailing, expected_path, expected_remaining) in [
+            ("b/c/d/e/f/g/h", false, "b/c/d/e/f", "g/h"),
+            ("b//c/./nonexistent/../foo/", false, "b/c", "nonexistent/../foo/"),
+            ("/b/c/file", false, "b/c/file", ""),
+            ("b-file", false, "b/c/file", ""),
+            ("nonexistent", false, ".", "nonexistent"),
+            ("e/f/nonexistent", false, "b/c/d/e/f", "nonexistent"),
+            // Resolution stops at the top-most dangling symlink.
+            ("link3/deep_dangling1/foo", false, "link3", "deep_dangling1/foo"),
+        ] {
+            let (handle, remaining) = root.resolve_partial(path, no_follow_trailing)?;
+            assert_eq!(
+                (handle.as_unsafe_path_unchecked()?, remaining.as_path()),
+                (root.resolve(expected_path)?.as_unsafe_path_unchecked()?, Path::new(expected_remaining)),
+                "resolve_partial({path:?}, {no_follow_trailing})"
+            );
+        }
+
+        // With no_follow_trailing, trailing symlinks are returned as-is.
+        let (handle, remaining) = root.resolve_partial("b-file", true)?;
+        assert_eq!(
+            (handle.as_unsafe_path_unchecked()?, remaining.as_path()),
+            (root.resolve_nofollow("b-file")?.as_unsafe_path_unchecked()?, Path::new("")),
+            "resolve_partial(\"b-file\", true)"
+        );
+
+        tests_common::check_err(
+            &root.resolve_partial("b/c/file/foo", false),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::ENOTDIR))),
+        )?;
+    } => ()
+}
+
+// Make sure that absurdly deep paths and long symlink chains don't cause the
+// emulated resolver to blow the stack. We run the lookups in a thread with a
+// deliberately small stack so that any recursion in the resolver shows up as a
+// crash rather than silently succeeding with the default 2MiB test stack.
+const SMALL_STACK_SIZE: usize = 256 * 1024;
+
+#[test]
+fn root_resolve_deep_path_opath() -> Result<(), Error> {
+    let root_dir = tests_common::create_basic_tree()?;
+    let root = Root::open(&root_dir)?.with_resolver_backend(ResolverBackend::EmulatedOpath);
+
+    // 10,000 components, which is far longer than PATH_MAX (so the kernel
+    // backend cannot be tested with this path).
+    let path = "b/../".repeat(4_999) + "b/c/file";
+
+    let handle = utils::on_small_stack(move || root.resolve(path))??;
+    assert_eq!(
+        handle.as_unsafe_path_unchecked()?,
+        root_dir.path().join("b/c/file"),
+        "resolve of very deep path should give the right file"
+    );
+    Ok(())
+}
+
+#[test]
+fn root_resolve_deep_path_openat2() -> Result<(), Error> {
+    let root_dir = tests_common::create_basic_tree()?;
+    let root = Root::open(&root_dir)?.with_resolver_backend(ResolverBackend::KernelOpenat2);
+    if !root.resolver_backend().supported() {
+        // Skip if not supported.
+        return Ok(());
+    }
+
+    let path = "b/../".repeat(4_999) + "b/c/file";
+    tests_common::check_err(
+        &root.resolve(path),
+        &Err::<(), _>(ErrorKind::OsError(Some(libc::ENAMETOOLONG))),
+    )
+}
+
+macro_rules! symlink_chain_tests {
+    ($($test_name:ident ($backend:ident, $length:expr) => $expected:expr);+ $(;)?) => {
+        $(
+            paste::paste! {
+                #[test]
+                fn [<root_resolve_ $test_name>]() -> Result<(), Error> {
+                    let root_dir = tests_common::create_basic_tree()?;
+                    let root = Root::open(&root_dir)?
+                        .with_resolver_backend(ResolverBackend::$backend);
+                    if !root.resolver_backend().supported() {
+                        // Skip if not supported.
+                        return Ok(());
+                    }
+
+                    let start = utils::create_symlink_chain(&root_dir, "chain", $length, "/b/c/file")?;
+
+                    let expected: Result<&str, ErrorKind> = $expected;
+                    let res = utils::on_small_stack(move || root.resolve(start))?;
+                    match expected {
+                        Ok(path) => assert_eq!(
+                            res?.as_unsafe_path_unchecked()?,
+                            root_dir.path().join(path),
+                            "resolve of symlink chain should give the right file"
+                        ),
+                        Err(_) => tests_common::check_err(&res, &expected)?,
+                    }
+                    Ok(())
+                }
+            }
+        )*
+    }
+}
+
+symlink_chain_tests! {
+    // The kernel limits symlink traversals to 40 (MAXSYMLINKS), so a chain of
+    // 39 symlinks (plus the one in the chain root) must work on all backends.
+    symlink_chain_under_limit_opath(EmulatedOpath, 39) => Ok("b/c/file");
+    symlink_chain_under_limit_openat2(KernelOpenat2, 39) => Ok("b/c/file");
+    symlink_chain_over_limit_opath(EmulatedOpath, 1_000) => Err(ErrorKind::OsError(Some(libc::ELOOP)));
+    symlink_chain_over_limit_openat2(KernelOpenat2, 1_000) => Err(ErrorKind::OsError(Some(libc::ELOOP)));
+}
+
+// The emulated resolver can give more precise errors than openat2(2) for the
+// various "path is too complicated" failure modes. The ErrorKind is the same
+// for both backends, but the error descriptions should differ.
+fn check_opath_err_description<T: fmt::Debug>(
+    res: Result<T, PathrsError>,
+    expected_kind: ErrorKind,
+    expected_description: &str,
+) -> Result<(), Error> {
+    tests_common::check_err(&res, &Err::<(), _>(expected_kind))?;
+    let err = res.expect_err("resolution should have failed");
+    assert!(
+        err.to_string().contains(expected_description),
+        "error {err} should contain {expected_description:?}"
+    );
+    Ok(())
+}
+
+#[test]
+fn root_resolve_opath_symlink_cycle_error() -> Result<(), Error> {
+    let root_dir = tests_common::create_basic_tree()?;
+    let root = Root::open(&root_dir)?.with_resolver_backend(ResolverBackend::EmulatedOpath);
+
+    for path in [
+        "loop/link",
+        "loop/basic-loop1",
+        "loop/basic-loop2",
+        "loop/basic-loop3",
+    ] {
+        check_opath_err_description(
+            root.resolve(path),
+            ErrorKind::OsError(Some(libc::ELOOP)),
+            "symlink cycle detected",
+        )?;
+    }
+    Ok(())
+}
+
+#[test]
+fn root_resolve_opath_nosym_error() -> Result<(), Error> {
+    let root_dir = tests_common::create_basic_tree()?;
+
+    for (path, component) in [
+        ("link2/link1_abs/target_rel", "/link2/link1_abs"),
+        ("e/f", "/e"),
+        ("b-file", "/b-file"),
+    ] {
+        // Both backends should return ELOOP.
+        for backend in [
+            ResolverBackend::KernelOpenat2,
+            ResolverBackend::EmulatedOpath,
+        ] {
+            if !backend.supported() {
+                continue;
+            }
+            let root = Root::open(&root_dir)?
+                .with_resolver_backend(backend)
+                .with_resolver_flags(ResolverFlags::NO_SYMLINKS);
+            tests_common::check_err(
+                &root.resolve(path),
+                &Err::<(), _>(ErrorKind::OsError(Some(libc::ELOOP))),
+            )
+            .with_context(|| format!("resolve {path:?} with {backend:?}"))?;
+        }
+
+        // The emulated backend can also tell us which component was a symlink.
+        let root = Root::open(&root_dir)?
+            .with_resolver_backend(ResolverBackend::EmulatedOpath)
+            .with_resolver_flags(ResolverFlags::NO_SYMLINKS);
+        check_opath_err_description(
+            root.resolve(path),
+            ErrorKind::OsError(Some(libc::ELOOP)),
+            &format!("(at {component:?} in root) is a symlink"),
+        )?;
+    }
+    Ok(())
+}
+
+#[test]
+fn root_resolve_opath_symlink_limit_error() -> Result<(), Error> {
+    let root_dir = tests_common::create_basic_tree()?;
+    let root = Root::open(&root_dir)?.with_resolver_backend(ResolverBackend::EmulatedOpath);
+
+    let start = utils::create_symlink_chain(&root_dir, "chain", 1_000, "/b/c/file")?;
+    let res = utils::on_small_stack(move || root.resolve(start))?;
+    check_opath_err_description(
+        res,
+        ErrorKind::OsError(Some(libc::ELOOP)),
+        "exceeded symlink limit",
+    )
+}
+
+#[test]
+fn root_resolve_max_symlink_depth() -> Result<(), Error> {
+    let root_dir = tests_common::create_basic_tree()?;
+    let chain40 = utils::create_symlink_chain(&root_dir, "chain40", 40, "/b/c/file")?;
+    let chain41 = utils::create_symlink_chain(&root_dir, "chain41", 41, "/b/c/file")?;
+    let chain5 = utils::create_symlink_chain(&root_dir, "chain5", 5, "/b/c/file")?;
+
+    for backend in [
+        ResolverBackend::KernelOpenat2,
+        ResolverBackend::EmulatedOpath,
+    ] {
+        if !backend.supported() {
+            continue;
+        }
+        let mut root = Root::open(&root_dir)?.with_resolver_backend(backend);
+        assert_eq!(
+            root.max_symlink_depth(),
+            40,
+            "default symlink depth should match the kernel"
+        );
+
+        // The default limit should match the kernel.
+        root.resolve(&chain40)
+            .with_context(|| format!("resolve 40-link chain with {backend:?}"))?;
+        tests_common::check_err(
+            &root.resolve(&chain41),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::ELOOP))),
+        )
+        .with_context(|| format!("resolve 41-link chain with {backend:?}"))?;
+
+        // A limit of 0 is equivalent to NO_SYMLINKS.
+        root.set_max_symlink_depth(0);
+        tests_common::check_err(
+            &root.resolve("b-file"),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::ELOOP))),
+        )
+        .with_context(|| format!("resolve symlink with zero depth with {backend:?}"))?;
+        root.resolve_nofollow("b-file")?;
+        root.resolve("b/c/file")?;
+
+        // Other limits are only supported by the emulated backend.
+        root.set_max_symlink_depth(5);
+        match backend {
+            ResolverBackend::KernelOpenat2 => tests_common::check_err(
+                &root.resolve(&chain5),
+                &Err::<(), _>(ErrorKind::NotSupported),
+            )?,
+            ResolverBackend::EmulatedOpath => {
+                root.resolve(&chain5)?;
+                check_opath_err_description(
+                    root.as_ref().with_max_symlink_depth(4).resolve(&chain5),
+                    ErrorKind::OsError(Some(libc::ELOOP)),
+                    "exceeded symlink limit (4 symlinks)",
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn root_resolve_opath_name_too_long_error() -> Result<(), Error> {
+    let root_dir = tests_common::create_basic_tree()?;
+    let root = Root::open(&root_dir)?.with_resolver_backend(ResolverBackend::EmulatedOpath);
+
+    check_opath_err_description(
+        root.resolve(format!("b/{}/c", "a".repeat(256))),
+        ErrorKind::OsError(Some(libc::ENAMETOOLONG)),
+        "longer than the maximum filename length",
+    )
+}
+
+#[test]
+fn root_resolve_no_magiclinks() -> Result<(), Error> {
+    // Keep a socket open so that we have a magic-link with a "socket:[ino]"
+    // (relative) target.
+    let (sock, _peer) = UnixStream::pair().context("create socketpair")?;
+    let sock_path = format!("thread-self/fd/{}", sock.as_raw_fd());
+
+    for backend in [
+        ResolverBackend::KernelOpenat2,
+        ResolverBackend::EmulatedOpath,
+    ] {
+        if !backend.supported() {
+            continue;
+        }
+        let root = Root::open("/proc")?
+            .with_resolver_backend(backend)
+            .with_resolver_flags(ResolverFlags::NO_MAGICLINKS);
+
+        // Pseudo-magic-links are still permitted.
+        root.resolve("self/sched")
+            .with_context(|| format!("resolve pseudo-magic-link with {backend:?}"))?;
+        // Magic-links can still be opened with O_NOFOLLOW.
+        root.resolve_nofollow(&sock_path)
+            .with_context(|| format!("resolve_nofollow magic-link with {backend:?}"))?;
+
+        let expected = match backend {
+            ResolverBackend::KernelOpenat2 => ErrorKind::OsError(Some(libc::ELOOP)),
+            ResolverBackend::EmulatedOpath => ErrorKind::SafetyViolation,
+        };
+        for path in ["self/exe", "self/root/etc/passwd", &sock_path] {
+            tests_common::check_err(&root.resolve(path), &Err::<(), _>(expected))
+                .with_context(|| format!("resolve magic-link {path:?} with {backend:?}"))?;
+        }
+    }
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(not(feature = "_test_as_root"), ignore)]
+fn root_resolve_opath_no_procfs() -> Result<(), Error> {
+    tests_common::in_mnt_ns(|| {
+        rustix_mount::unmount("/proc", UnmountFlags::DETACH).context("unmount /proc")?;
+
+        // Capabilities are per-thread, so drop CAP_SYS_ADMIN (which would let
+        // us create a new procfs instance) in a separate thread to avoid
+        // affecting other tests.
+        thread::spawn(|| -> Result<(), Error> {
+            let mut caps = rustix_thread::capabilities(None)?;
+            caps.effective.remove(CapabilityFlags::SYS_ADMIN);
+            rustix_thread::set_capabilities(None, caps)?;
+
+            // We cannot reset the global procfs handle, so check that the
+            // emulated resolver's procfs check rejects a failed attempt at
+            // getting a fresh handle.
+            let res = opath::require_procfs(ProcfsHandle::new());
+            check_opath_err_description(
+                res,
+                ErrorKind::NotSupported,
+                "emulated resolver could not get a /proc handle",
+            )
+        })
+        .join()
+        .expect("thread should not panic")
+    })
+}
+
+#[test]
+#[cfg_attr(not(feature = "_test_as_root"), ignore)]
+fn root_resolve_no_cross_mount() -> Result<(), Error> {
+    let root_dir = tests_common::create_basic_tree()?;
+
+    tests_common::in_mnt_ns(|| {
+        tests_common::mount(root_dir.path().join("b/c"), MountType::Tmpfs)?;
+        tests_common::mount(
+            root_dir.path().join("target"),
+            MountType::Bind {
+                src: root_dir.path().join("target"),
+            },
+        )?;
+
+        for backend in [
+            ResolverBackend::KernelOpenat2,
+            ResolverBackend::EmulatedOpath,
+        ] {
+            if !backend.supported() {
+                continue;
+            }
+            let root = Root::open(&root_dir)?
+                .with_resolver_backend(backend)
+                .with_resolver_flags(ResolverFlags::NO_CROSS_MOUNT);
+
+            // Components on the same mount are fine.
+            root.resolve("b")?;
+
+            // Walking into the tmpfs should fail, both directly and through a
+            // symlink (even if we would later walk back out of it).
+            for path in ["b/c", "b/c/d", "b/c/..", "b-file", "e"] {
+                tests_common::check_err(
+                    &root.resolve(path),
+                    &Err::<(), _>(ErrorKind::OsError(Some(libc::EXDEV))),
+                )
+                .with_context(|| format!("resolve {path:?} with {backend:?}"))?;
+            }
+
+            // Bind-mounts of the same filesystem can only be detected by
+            // openat2(2). The emulated backend only compares st_dev.
+            let res = root.resolve("target");
+            match backend {
+                ResolverBackend::KernelOpenat2 => tests_common::check_err(
+                    &res,
+                    &Err::<(), _>(ErrorKind::OsError(Some(libc::EXDEV))),
+                )?,
+                ResolverBackend::EmulatedOpath => {
+                    res.context("emulated NO_CROSS_MOUNT cannot detect same-fs bind-mounts")?;
+                }
+            }
+
+            // Without the flag, walking into mounts is permitted.
+            root.as_ref()
+                .with_resolver_flags(ResolverFlags::empty())
+                .resolve("b/c")?;
+        }
+        Ok(())
+    })
+}
+
+#[test]
+#[cfg_attr(not(feature = "_test_as_root"), ignore)]
+fn root_verify_mount_id() -> Result<(), Error> {
+    let root_dir = tests_common::create_basic_tree()?;
+
+    tests_common::in_mnt_ns(|| {
+        for backend in [
+            ResolverBackend::KernelOpenat2,
+            ResolverBackend::EmulatedOpath,
+        ] {
+            if !backend.supported() {
+                continue;
+            }
+            let mut root = Root::open(&root_dir)?
+                .with_resolver_backend(backend)
+                .with_verify_mount_id(true)?;
+            assert!(root.verify_mount_id(), "mount id verification enabled");
+            let mnt_id = root.mount_id()?;
+
+            // Nothing has changed yet.
+            root.resolve("b/c/file")?;
+
+            // Over-mount the root with a bind-mount of itself. The root handle
+            // still references the same inode on the old mount.
+            tests_common::mount(
+                root_dir.path(),
+                MountType::Bind {
+                    src: root_dir.path().into(),
+                },
+            )?;
+            assert_eq!(
+                root.mount_id()?,
+                mnt_id,
+                "mount id of root handle should not change after over-mount"
+            );
+
+            tests_common::check_err(
+                &root.resolve("b/c/file"),
+                &Err::<(), _>(ErrorKind::SafetyViolation),
+            )
+            .with_context(|| format!("resolve after over-mount with {backend:?}"))?;
+            tests_common::check_err(
+                &root.open_subpath("b/c/file", OpenFlags::O_RDONLY),
+                &Err::<(), _>(ErrorKind::SafetyViolation),
+            )
+            .with_context(|| format!("open_subpath after over-mount with {backend:?}"))?;
+
+            // Without verification, the old mount is silently used.
+            root.set_verify_mount_id(false)?;
+            assert!(!root.verify_mount_id(), "mount id verification disabled");
+            root.resolve("b/c/file")?;
+
+            rustix_mount::unmount(root_dir.path(), UnmountFlags::DETACH)
+                .context("unmount root over-mount")?;
+        }
+        Ok(())
+    })
+}
+
 mod utils {
     use crate::{
         error::ErrorKind,
@@ -501,11 +1459,52 @@ mod utils {
         utils::FdExt,
     };
 
-    use std::{os::unix::fs::MetadataExt, path::Path};
+    use std::{
+        os::unix::fs::{self as unixfs, MetadataExt},
+        path::{Path, PathBuf},
+        thread,
+    };
 
     use anyhow::{Context, Error};
     use pretty_assertions::assert_eq;
 
+    /// Run a function in a thread with a stack much smaller than the default.
+    pub(super) fn on_small_stack<F, T>(func: F) -> Result<T, Error>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        Ok(thread::Builder::new()
+            .stack_size(super::SMALL_STACK_SIZE)
+            .spawn(func)
+            .context("spawn small-stack thread")?
+            .join()
+            .expect("small-stack thread should not panic"))
+    }
+
+    /// Create a chain of `length` symlinks inside `dir`, with the final symlink
+    /// pointing to `target`. Returns the (root-relative) path of the first
+    /// symlink in the chain.
+    pub(super) fn create_symlink_chain<P: AsRef<Path>>(
+        root_dir: P,
+        dir: &str,
+        length: usize,
+        target: &str,
+    ) -> Result<PathBuf, Error> {
+        let chain_dir = root_dir.as_ref().join(dir);
+        std::fs::create_dir(&chain_dir).context("create symlink chain directory")?;
+        for idx in 0..length {
+            let link_target = if idx + 1 == length {
+                target.into()
+            } else {
+                format!("link{}", idx + 1)
+            };
+            unixfs::symlink(link_target, chain_dir.join(format!("link{idx}")))
+                .with_context(|| format!("create symlink chain link {idx}"))?;
+        }
+        Ok(Path::new(dir).join("link0"))
+    }
+
     pub(super) fn check_root_resolve<R, H, P: AsRef<Path>>(
         root: R,
         unsafe_path: P,
@@ -571,12 +1570,11 @@ mod utils {
                     OpenFlags::O_RDONLY | OpenFlags::O_NOFOLLOW,
                     None,
                 )?;
-                tests_common::check_reopen(
+                tests_common::check_reopen_invalid(
                     &handle,
                     OpenFlags::O_PATH | OpenFlags::O_NOFOLLOW,
-                    None,
                 )?;
-                tests_common::check_reopen(&handle, OpenFlags::O_PATH, None)?;
+                tests_common::check_reopen_invalid(&handle, OpenFlags::O_PATH)?;
                 // Forcefully set O_CLOEXEC.
                 tests_common::check_reopen(
                     &handle,
@@ -598,12 +1596,11 @@ mod utils {
                     OpenFlags::O_RDONLY | OpenFlags::O_NOFOLLOW,
                     None,
                 )?;
-                tests_common::check_reopen(
+                tests_common::check_reopen_invalid(
                     &handle,
                     OpenFlags::O_PATH | OpenFlags::O_NOFOLLOW,
-                    None,
                 )?;
-                tests_common::check_reopen(&handle, OpenFlags::O_PATH, None)?;
+                tests_common::check_reopen_invalid(&handle, OpenFlags::O_PATH)?;
                 // Forcefully set O_CLOEXEC.
                 tests_common::check_reopen(
                     &handle,
@@ -624,39 +1621,24 @@ mod utils {
 
                 tests_common::check_reopen(&handle, OpenFlags::O_RDONLY, Some(libc::ELOOP))
                     .context("reopen(O_RDONLY) of a symlink handle should fail with ELOOP")?;
-                tests_common::check_reopen(&handle, OpenFlags::O_PATH, Some(libc::ELOOP))
-                    .context("reopen(O_PATH) of a symlink handle should fail with ELOOP")?;
-                tests_common::check_reopen(
+                tests_common::check_reopen_invalid(&handle, OpenFlags::O_PATH)?;
+                tests_common::check_reopen_invalid(
                     &handle,
                     OpenFlags::O_PATH | OpenFlags::O_NOFOLLOW,
-                    Some(libc::ELOOP),
-                )
-                .context("reopen(O_PATH|O_NOFOLLOW) of a symlink handle should fail with ELOOP")?;
+                )?;
             }
             _ => {
-                tests_common::check_reopen(&handle, OpenFlags::O_PATH, None)?;
-                tests_common::check_reopen(
-                    &handle,
+                // We cannot safely re-open other file types (opening a FIFO
+                // could block), and O_PATH re-opens are always rejected.
+                for flags in [
+                    OpenFlags::O_PATH,
                     OpenFlags::O_PATH | OpenFlags::O_DIRECTORY,
-                    Some(libc::ENOTDIR),
-                )?;
-                // Make sure O_NOFOLLOW acts the way you would expect.
-                tests_common::check_reopen(
-                    &handle,
                     OpenFlags::O_PATH | OpenFlags::O_NOFOLLOW,
-                    None,
-                )?;
-                // Forcefully set O_CLOEXEC.
-                tests_common::check_reopen(
-                    &handle,
                     OpenFlags::O_PATH | OpenFlags::O_CLOEXEC,
-                    None,
-                )?;
-                tests_common::check_reopen(
-                    &handle,
                     OpenFlags::O_PATH | OpenFlags::O_DIRECTORY | OpenFlags::O_CLOEXEC,
-                    Some(libc::ENOTDIR),
-                )?;
+                ] {
+                    tests_common::check_reopen_invalid(&handle, flags)?;
+                }
             }
         }
 
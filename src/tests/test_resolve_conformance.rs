@@ -0,0 +1,218 @@
+/*
+ * libpathrs: safe path resolution on Linux
+ * Copyright (C) 2019-2024 Aleksa Sarai <cyphar@cyphar.com>
+ * Copyright (C) 2019-2024 SUSE LLC
+ *
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Lesser General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or (at your
+ * option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY
+ * or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License
+ * for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Cross-backend conformance tests.
+//!
+//! Each scenario is resolved using both the openat2(2) and emulated O_PATH
+//! resolvers, and the observable results (the inode of the resolved handle,
+//! or the kind of the returned error) must be identical. Any divergence
+//! between the two backends is a bug.
+
+use crate::{
+    error::{Error as PathrsError, ErrorKind},
+    flags::ResolverFlags,
+    resolvers::ResolverBackend,
+    tests::common::{self as tests_common, MountType},
+    utils::FdExt,
+    Handle, Root,
+};
+
+use std::{
+    fs,
+    os::unix::{fs::MetadataExt, io::AsFd},
+    path::Path,
+};
+
+use anyhow::Error;
+use pretty_assertions::assert_eq;
+
+/// The observable result of a resolution: the (dev, ino) of the resolved
+/// handle or the kind of the error.
+type Outcome = Result<(u64, u64), ErrorKind>;
+
+fn outcome(res: Result<Handle, PathrsError>) -> Result<Outcome, Error> {
+    Ok(match res {
+        Ok(handle) => {
+            let meta = handle.as_fd().metadata()?;
+            Ok((meta.dev(), meta.ino()))
+        }
+        Err(err) => Err(err.kind()),
+    })
+}
+
+/// Resolve `path` within `root_dir` with both backends and check that the
+/// outcomes are identical.
+fn check_conformance<P: AsRef<Path>>(
+    root_dir: &Path,
+    path: P,
+    rflags: ResolverFlags,
+    no_follow_trailing: bool,
+) -> Result<(), Error> {
+    let path = path.as_ref();
+    let mut outcomes = Vec::new();
+    for backend in [
+        ResolverBackend::KernelOpenat2,
+        ResolverBackend::EmulatedOpath,
+    ] {
+        if !backend.supported() {
+            // Skip if not supported.
+            return Ok(());
+        }
+        let root = Root::open(root_dir)?
+            .with_resolver_backend(backend)
+            .with_resolver_flags(rflags);
+        let res = match no_follow_trailing {
+            true => root.resolve_nofollow(path),
+            false => root.resolve(path),
+        };
+        outcomes.push((backend, outcome(res)?));
+    }
+
+    let (openat2, opath) = (&outcomes[0], &outcomes[1]);
+    assert_eq!(
+        openat2.1, opath.1,
+        "resolution of {path:?} (flags {rflags:?}, nofollow {no_follow_trailing}) differs between backends"
+    );
+    Ok(())
+}
+
+macro_rules! conformance_tests {
+    // conformance_tests! {
+    //     [create_root_path] {
+    //         test_name: resolve("path");
+    //         test_name: resolve_nofollow("path");
+    //         test_name: resolve("path", ResolverFlags::...);
+    //     }
+    // }
+    (@impl [$root_dir:expr] $test_name:ident: $path:expr, $rflags:expr, $no_follow:expr) => {
+        paste::paste! {
+            #[test]
+            fn [<conformance_ $test_name>]() -> Result<(), Error> {
+                let root_dir = $root_dir;
+                check_conformance(root_dir.as_ref(), $path, $rflags, $no_follow)
+            }
+        }
+    };
+
+    ([$root_dir:expr] { $($test_name:ident : $op:ident ($path:expr $(, $rflags:expr)?) );* $(;)? }) => {
+        $(
+            conformance_tests! { @$op [$root_dir] $test_name: $path $(, $rflags)? }
+        )*
+    };
+
+    (@resolve [$root_dir:expr] $test_name:ident: $path:expr) => {
+        conformance_tests! { @resolve [$root_dir] $test_name: $path, ResolverFlags::empty() }
+    };
+    (@resolve [$root_dir:expr] $test_name:ident: $path:expr, $rflags:expr) => {
+        conformance_tests! { @impl [$root_dir] $test_name: $path, $rflags, false }
+    };
+
+    (@resolve_nofollow [$root_dir:expr] $test_name:ident: $path:expr) => {
+        conformance_tests! { @resolve_nofollow [$root_dir] $test_name: $path, ResolverFlags::empty() }
+    };
+    (@resolve_nofollow [$root_dir:expr] $test_name:ident: $path:expr, $rflags:expr) => {
+        conformance_tests! { @impl [$root_dir] $test_name: $path, $rflags, true }
+    };
+}
+
+conformance_tests! {
+    [tests_common::create_basic_tree()?] {
+        // Basic lookups.
+        basic_dir: resolve("a");
+        basic_file: resolve("b/c/file");
+        basic_enoent: resolve("b/c/nonexistent");
+        basic_enotdir: resolve("b/c/file/foo");
+        basic_trailing_slash_dir: resolve("b/c/");
+        basic_trailing_slash_file: resolve("b/c/file/");
+        basic_fifo: resolve("b/fifo");
+        basic_sock: resolve("b/sock");
+        // ".." clamping.
+        dotdot_root: resolve("..");
+        dotdot_deep: resolve("../../../../../b/c");
+        dotdot_inner: resolve("b/c/d/../../../../../../b/c/file");
+        dotdot_absolute: resolve("/../../a");
+        // Symlink escape attempts.
+        escape_root_link1: resolve("root-link1");
+        escape_root_link2: resolve("root-link2/b/c");
+        escape_root_link3: resolve("root-link3/a");
+        escape_link1: resolve("escape-link1");
+        escape_link2: resolve("escape-link2");
+        escape_link_nofollow: resolve_nofollow("escape-link1");
+        // Non-lexical symlinks.
+        nonlexical_abs: resolve("link3/target_abs");
+        nonlexical_rel: resolve("link3/target_rel");
+        nonlexical_dotdot: resolve("link3/target_rel/../link1/target_abs/..");
+        absolute_symlink: resolve("e/f");
+        // Dangling symlinks.
+        dangling_basic: resolve("a-fake1");
+        dangling_dotdot: resolve("a-fake3");
+        dangling_abs_dotdot: resolve("c/a-fake3");
+        dangling_deep1: resolve("link3/deep_dangling1");
+        dangling_deep2: resolve("link3/deep_dangling2");
+        dangling_chain: resolve("dangling/a");
+        dangling_chain_file: resolve("dangling-file/a");
+        dangling_nofollow: resolve_nofollow("a-fake1");
+        // Symlink loops.
+        loop_basic1: resolve("loop/basic-loop1");
+        loop_basic2: resolve("loop/basic-loop2");
+        loop_basic3: resolve("loop/basic-loop3");
+        loop_complex: resolve("loop/link");
+        loop_nofollow: resolve_nofollow("loop/basic-loop1");
+        // Resolver flags.
+        nosym_trailing: resolve("b-file", ResolverFlags::NO_SYMLINKS);
+        nosym_nofollow_trailing: resolve_nofollow("b-file", ResolverFlags::NO_SYMLINKS);
+        nosym_component: resolve("e/f", ResolverFlags::NO_SYMLINKS);
+        nosym_plain: resolve("b/c/file", ResolverFlags::NO_SYMLINKS);
+    }
+}
+
+#[test]
+#[cfg_attr(not(feature = "_test_as_root"), ignore)]
+fn conformance_mount_crossing() -> Result<(), Error> {
+    let root_dir = tests_common::create_basic_tree()?;
+
+    tests_common::in_mnt_ns(|| {
+        // Both resolvers permit crossing mountpoints inside the root, and ".."
+        // out of a mountpoint should be handled the same way.
+        tests_common::mount(root_dir.path().join("b/c"), MountType::Tmpfs)?;
+        fs::create_dir_all(root_dir.path().join("b/c/x/y"))?;
+        tests_common::mount(
+            root_dir.path().join("a"),
+            MountType::Bind {
+                src: root_dir.path().join("target"),
+            },
+        )?;
+
+        for (path, no_follow) in [
+            ("b/c", false),
+            ("b/c/x/y", false),
+            ("b/c/file", false),
+            ("b/c/x/../../c/x", false),
+            ("b/c/x/../../../..", false),
+            ("a", false),
+            ("a/..", false),
+            ("link1/target_abs", false),
+            ("e", false),
+            ("e", true),
+        ] {
+            check_conformance(root_dir.path(), path, ResolverFlags::empty(), no_follow)?;
+        }
+        Ok(())
+    })
+}
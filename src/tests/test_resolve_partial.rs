@@ -789,22 +789,21 @@ mod utils {
 
                 tests_common::check_reopen(&handle, OpenFlags::O_RDONLY, Some(libc::ELOOP))
                     .context("reopen(O_RDONLY) of a symlink handle should fail with ELOOP")?;
-                tests_common::check_reopen(&handle, OpenFlags::O_PATH, Some(libc::ELOOP))
-                    .context("reopen(O_PATH) of a symlink handle should fail with ELOOP")?;
-                tests_common::check_reopen(
+                tests_common::check_reopen_invalid(&handle, OpenFlags::O_PATH)?;
+                tests_common::check_reopen_invalid(
                     &handle,
                     OpenFlags::O_PATH | OpenFlags::O_NOFOLLOW,
-                    Some(libc::ELOOP),
-                )
-                .context("reopen(O_PATH|O_NOFOLLOW) of a symlink handle should fail with ELOOP")?;
+                )?;
             }
             _ => {
-                tests_common::check_reopen(&handle, OpenFlags::O_PATH, None)?;
-                tests_common::check_reopen(
-                    &handle,
+                // We cannot safely re-open other file types (opening a FIFO
+                // could block), and O_PATH re-opens are always rejected.
+                for flags in [
+                    OpenFlags::O_PATH,
                     OpenFlags::O_PATH | OpenFlags::O_DIRECTORY,
-                    Some(libc::ENOTDIR),
-                )?;
+                ] {
+                    tests_common::check_reopen_invalid(&handle, flags)?;
+                }
             }
         }
 
@@ -21,18 +21,60 @@
 use crate::tests::capi;
 use crate::{
     error::ErrorKind,
-    flags::{OpenFlags, RenameFlags},
+    flags::{
+        AccessMode, OpenFlags, RenameFlags, ReopenPolicy, ResolverFlags, SetidPolicy, StatxMask,
+    },
     resolvers::ResolverBackend,
-    tests::common as tests_common,
-    InodeType, Root,
+    syscalls,
+    tests::common::{self as tests_common, MountType},
+    utils::FdExt,
+    DirProbe, InodeType, Root,
 };
 
-use std::{fs::Permissions, os::unix::fs::PermissionsExt};
+use std::{
+    collections::HashMap,
+    fs::{self, File, Permissions},
+    io::{Read, Seek, SeekFrom, Write},
+    ops::ControlFlow,
+    os::unix::{
+        fs::{MetadataExt, PermissionsExt},
+        io::{AsFd, AsRawFd},
+    },
+    panic,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, UNIX_EPOCH},
+};
 
-use anyhow::Error;
+use anyhow::{Context, Error};
+use rustix::{
+    fs::{self as rustix_fs, FlockOperation},
+    mount::{self as rustix_mount, MountFlags},
+    thread::{self as rustix_thread, CapabilityFlags},
+};
 
 macro_rules! root_op_tests {
     ($(#[$meta:meta])* fn $test_name:ident ($root_var:ident) $body:block) => {
+        root_op_tests! {
+            $(#[$meta])*
+            @rust-only fn $test_name ($root_var) $body
+        }
+
+        paste::paste! {
+            $(#[$meta])*
+            #[cfg(feature = "capi")]
+            #[test]
+            fn [<capi_root_ $test_name>]() -> Result<(), Error> {
+                let root_dir = tests_common::create_basic_tree()?;
+                let $root_var = capi::CapiRoot::open(&root_dir)?;
+
+                $body
+            }
+        }
+    };
+
+    // Operations which are not exposed through the C API.
+    ($(#[$meta:meta])* @rust-only fn $test_name:ident ($root_var:ident) $body:block) => {
         paste::paste! {
             $(#[$meta])*
             #[test]
@@ -114,16 +156,6 @@ macro_rules! root_op_tests {
 
                 $body
             }
-
-            $(#[$meta])*
-            #[cfg(feature = "capi")]
-            #[test]
-            fn [<capi_root_ $test_name>]() -> Result<(), Error> {
-                let root_dir = tests_common::create_basic_tree()?;
-                let $root_var = capi::CapiRoot::open(&root_dir)?;
-
-                $body
-            }
         }
     };
 
@@ -177,6 +209,14 @@ macro_rules! root_op_tests {
             } => $expected_result
         }
     };
+    ($(#[cfg($ignore_meta:meta)])* @impl mksock $test_name:ident ($path:expr, $mode:literal) => $expected_result:expr) => {
+        root_op_tests!{
+            $(#[cfg_attr(not($ignore_meta), ignore)])*
+            @mknod fn $test_name ($path) {
+                InodeType::Socket(Permissions::from_mode($mode))
+            } => $expected_result
+        }
+    };
     ($(#[cfg($ignore_meta:meta)])* @impl mkblk $test_name:ident ($path:expr, $mode:literal, $major:literal, $minor:literal) => $expected_result:expr) => {
         root_op_tests!{
             #[cfg_attr(not(feature = "_test_as_root"), ignore)]
@@ -312,209 +352,2444 @@ macro_rules! root_op_tests {
         }
     };
 
-    ($(#[cfg($ignore_meta:meta)])* @impl mkdir_all_racing $test_name:ident ( $($args:tt)* ) => $expected_result:expr) => {
-        root_op_tests! {
-            $(#[cfg_attr(not($ignore_meta), ignore)])*
-            @impl-race mkdir_all_racing $test_name( $($args)* ) => $expected_result
+    ($(#[cfg($ignore_meta:meta)])* @impl mkdir_all_racing $test_name:ident ( $($args:tt)* ) => $expected_result:expr) => {
+        root_op_tests! {
+            $(#[cfg_attr(not($ignore_meta), ignore)])*
+            @impl-race mkdir_all_racing $test_name( $($args)* ) => $expected_result
+        }
+    };
+
+    ($(#[cfg($ignore_meta:meta)])* @impl remove_all_racing $test_name:ident ( $($args:tt)* ) => $expected_result:expr) => {
+        root_op_tests! {
+            $(#[cfg_attr(not($ignore_meta), ignore)])*
+            @impl-race remove_all_racing $test_name( $($args)* ) => $expected_result
+        }
+    };
+
+    // root_tests!{
+    //      ...
+    // }
+    ($($(#[cfg($ignore_meta:meta)])* $test_name:ident: $op_name:ident ( $($args:tt)* ) => $expected_result:expr );+ $(;)?) => {
+        paste::paste! {
+            $(
+                root_op_tests!{
+                    $(#[cfg($ignore_meta)])*
+                    @impl $op_name [<$op_name _ $test_name>]( $($args)* ) => $expected_result
+                }
+            )*
+        }
+    };
+}
+
+root_op_tests! {
+    plain: mkfile("abc", 0o444) => Ok(("abc", libc::S_IFREG | 0o444));
+    exist_file: mkfile("b/c/file", 0o444) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
+    exist_dir: mkfile("a", 0o444) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
+    exist_symlink: mkfile("b-file", 0o444) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
+    exist_dangling_symlink: mkfile("a-fake1", 0o444) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
+
+    plain: mkdir("abc", 0o311) => Ok(("abc", libc::S_IFDIR | 0o311));
+    exist_file: mkdir("b/c/file", 0o444) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
+    exist_dir: mkdir("a", 0o444) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
+    exist_symlink: mkdir("b-file", 0o444) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
+    exist_dangling_symlink: mkdir("a-fake1", 0o444) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
+
+    plain: symlink("abc", "/NEWLINK") => Ok(("abc", libc::S_IFLNK | 0o777));
+    exist_file: symlink("b/c/file", "/NEWLINK") => Err(ErrorKind::OsError(Some(libc::EEXIST)));
+    exist_dir: symlink("a", "/NEWLINK") => Err(ErrorKind::OsError(Some(libc::EEXIST)));
+    exist_symlink: symlink("b-file", "/NEWLINK") => Err(ErrorKind::OsError(Some(libc::EEXIST)));
+    exist_dangling_symlink: symlink("a-fake1", "/NEWLINK") => Err(ErrorKind::OsError(Some(libc::EEXIST)));
+
+    plain: hardlink("abc", "b/c/file") => Ok(("abc", libc::S_IFREG | 0o644));
+    exist_file: hardlink("b/c/file", "/b/c/file") => Err(ErrorKind::OsError(Some(libc::EEXIST)));
+    exist_dir: hardlink("a", "/b/c/file") => Err(ErrorKind::OsError(Some(libc::EEXIST)));
+    exist_symlink: hardlink("b-file", "/b/c/file") => Err(ErrorKind::OsError(Some(libc::EEXIST)));
+    exist_dangling_symlink: hardlink("a-fake1", "/b/c/file") => Err(ErrorKind::OsError(Some(libc::EEXIST)));
+    to_symlink: hardlink("link", "b-file") => Ok(("link", libc::S_IFLNK | 0o777));
+    to_dangling_symlink: hardlink("link", "a-fake1") => Ok(("link", libc::S_IFLNK | 0o777));
+
+    plain: mkfifo("abc", 0o222) => Ok(("abc", libc::S_IFIFO | 0o222));
+    exist_file: mkfifo("b/c/file", 0o444) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
+    exist_dir: mkfifo("a", 0o444) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
+    exist_symlink: mkfifo("b-file", 0o444) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
+    exist_dangling_symlink: mkfifo("a-fake1", 0o444) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
+
+    plain: mksock("abc", 0o222) => Ok(("abc", libc::S_IFSOCK | 0o222));
+    exist_file: mksock("b/c/file", 0o444) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
+    exist_dir: mksock("a", 0o444) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
+    exist_symlink: mksock("b-file", 0o444) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
+    exist_dangling_symlink: mksock("a-fake1", 0o444) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
+
+    plain: mkblk("abc", 0o001, 123, 456) => Ok(("abc", libc::S_IFBLK | 0o001));
+    exist_file: mkblk("b/c/file", 0o444, 123, 456) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
+    exist_dir: mkblk("a", 0o444, 123, 456) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
+    exist_symlink: mkblk("b-file", 0o444, 123, 456) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
+    exist_dangling_symlink: mkblk("a-fake1", 0o444, 123, 456) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
+
+    plain: mkchar("abc", 0o010, 111, 222) => Ok(("abc", libc::S_IFCHR | 0o010));
+    exist_file: mkchar("b/c/file", 0o444, 123, 456) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
+    exist_dir: mkchar("a", 0o444, 123, 456) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
+    exist_symlink: mkchar("b-file", 0o444, 123, 456) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
+    exist_dangling_symlink: mkchar("a-fake1", 0o444, 123, 456) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
+
+    plain: create_file("abc", O_RDONLY, 0o100) => Ok("abc");
+    oexcl_plain: create_file("abc", O_EXCL|O_RDONLY, 0o100) => Ok("abc");
+    exist: create_file("b/c/file", O_RDONLY, 0o100) => Ok("b/c/file");
+    oexcl_exist: create_file("a", O_EXCL|O_RDONLY, 0o100) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
+    exist_dir: create_file("b/c/d", O_RDONLY, 0o100) => Err(ErrorKind::OsError(Some(libc::EISDIR)));
+    symlink: create_file("b-file", O_RDONLY, 0o100) => Err(ErrorKind::OsError(Some(libc::ELOOP)));
+    oexcl_symlink: create_file("b-file", O_EXCL|O_RDONLY, 0o100) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
+    oexcl_dangling_symlink: create_file("a-fake1", O_EXCL|O_RDONLY, 0o100) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
+
+    ocreat: open_subpath("abc", O_CREAT|O_RDONLY) => Err(ErrorKind::InvalidArgument);
+    oexcl: open_subpath("abc", O_EXCL|O_RDONLY) => Err(ErrorKind::InvalidArgument);
+    ocreat_oexcl: open_subpath("abc", O_CREAT|O_EXCL|O_RDONLY) => Err(ErrorKind::InvalidArgument);
+    enoent: open_subpath("abc", O_RDONLY) => Err(ErrorKind::OsError(Some(libc::ENOENT)));
+    exist_file: open_subpath("b/c/file", O_RDONLY) => Ok("b/c/file");
+    exist_dir: open_subpath("b/c/d", O_RDONLY) => Ok("b/c/d");
+    symlink: open_subpath("b-file", O_RDONLY) => Ok("b/c/file");
+    ocreat_symlink: open_subpath("b-file", O_CREAT|O_RDONLY) => Err(ErrorKind::InvalidArgument);
+    nofollow_symlink: open_subpath("b-file", O_NOFOLLOW|O_RDONLY) => Err(ErrorKind::OsError(Some(libc::ELOOP)));
+    opath_nofollow_symlink: open_subpath("b-file", O_PATH|O_NOFOLLOW) => Ok("b-file");
+    nofollow_odir_symlink:  open_subpath("b-file", O_DIRECTORY|O_NOFOLLOW) => Err(ErrorKind::OsError(Some(libc::ENOTDIR)));
+    opath_nofollow_odir_symlink:  open_subpath("b-file", O_DIRECTORY|O_PATH|O_NOFOLLOW) => Err(ErrorKind::OsError(Some(libc::ENOTDIR)));
+    dangling_symlink: open_subpath("a-fake1",O_RDONLY) => Err(ErrorKind::OsError(Some(libc::ENOENT)));
+    ocreat_dangling_symlink: open_subpath("a-fake1", O_CREAT|O_RDONLY) => Err(ErrorKind::InvalidArgument);
+
+    empty_dir: remove_dir("a") => Ok(());
+    empty_dir: remove_file("a") => Err(ErrorKind::OsError(Some(libc::EISDIR)));
+    empty_dir: remove_all("a") => Ok(());
+    nonempty_dir: remove_dir("b") => Err(ErrorKind::OsError(Some(libc::ENOTEMPTY)));
+    nonempty_dir: remove_file("b") => Err(ErrorKind::OsError(Some(libc::EISDIR)));
+    nonempty_dir: remove_all("b") => Ok(());
+    deep_dir: remove_dir("deep-rmdir") => Err(ErrorKind::OsError(Some(libc::ENOTEMPTY)));
+    deep_dir: remove_file("deep-rmdir") => Err(ErrorKind::OsError(Some(libc::EISDIR)));
+    deep_dir: remove_all("deep-rmdir") => Ok(());
+    file: remove_dir("b/c/file") => Err(ErrorKind::OsError(Some(libc::ENOTDIR)));
+    file: remove_file("b/c/file") => Ok(());
+    file: remove_all("b/c/file") => Ok(());
+    fifo: remove_dir("b/fifo") => Err(ErrorKind::OsError(Some(libc::ENOTDIR)));
+    fifo: remove_file("b/fifo") => Ok(());
+    fifo: remove_all("b/fifo") => Ok(());
+    sock: remove_dir("b/sock") => Err(ErrorKind::OsError(Some(libc::ENOTDIR)));
+    sock: remove_file("b/sock") => Ok(());
+    sock: remove_all("b/sock") => Ok(());
+    enoent: remove_dir("abc") => Err(ErrorKind::OsError(Some(libc::ENOENT)));
+    enoent: remove_file("abc") => Err(ErrorKind::OsError(Some(libc::ENOENT)));
+    enoent: remove_all("abc") => Ok(());
+    symlink: remove_dir("b-file") => Err(ErrorKind::OsError(Some(libc::ENOTDIR)));
+    symlink: remove_file("b-file") => Ok(());
+    symlink: remove_all("b-file") => Ok(());
+    dangling_symlink: remove_dir("a-fake1") => Err(ErrorKind::OsError(Some(libc::ENOTDIR)));
+    dangling_symlink: remove_file("a-fake1") => Ok(());
+    dangling_symlink: remove_all("a-fake1") => Ok(());
+    dir_trailing_slash: remove_dir("a/") => Err(ErrorKind::InvalidArgument);
+    dir_trailing_slash: remove_file("a/") => Err(ErrorKind::InvalidArgument);
+    dir_trailing_slash: remove_all("a/") => Err(ErrorKind::InvalidArgument);
+    file_trailing_slash: remove_dir("b/c/file/") => Err(ErrorKind::InvalidArgument);
+    file_trailing_slash: remove_file("b/c/file/") => Err(ErrorKind::InvalidArgument);
+    file_trailing_slash: remove_all("b/c/file/") => Err(ErrorKind::InvalidArgument);
+
+    plain: rename("a", "aa", RenameFlags::empty()) => Ok(());
+    noreplace_plain: rename("a", "aa", RenameFlags::RENAME_NOREPLACE) => Ok(());
+    noreplace_symlink: rename("a", "b-file", RenameFlags::RENAME_NOREPLACE) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
+    noreplace_dangling_symlink: rename("a", "a-fake1", RenameFlags::RENAME_NOREPLACE) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
+    noreplace_eexist: rename("a", "e", RenameFlags::RENAME_NOREPLACE) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
+    whiteout_plain: rename("a", "aa", RenameFlags::RENAME_WHITEOUT) => Ok(());
+    exchange_plain: rename("a", "e", RenameFlags::RENAME_EXCHANGE) => Ok(());
+    exchange_enoent: rename("a", "aa", RenameFlags::RENAME_EXCHANGE) => Err(ErrorKind::OsError(Some(libc::ENOENT)));
+
+    invalid_mode_type: mkdir_all("foo", libc::S_IFDIR | 0o777) => Err(ErrorKind::InvalidArgument);
+    invalid_mode_garbage: mkdir_all("foo", 0o12340777) => Err(ErrorKind::InvalidArgument);
+    invalid_mode_setuid: mkdir_all("foo", libc::S_ISUID | 0o777) => Err(ErrorKind::InvalidArgument);
+    invalid_mode_setgid: mkdir_all("foo", libc::S_ISGID | 0o777) => Err(ErrorKind::InvalidArgument);
+    existing: mkdir_all("a", 0o711) => Ok(());
+    basic: mkdir_all("a/b/c/d/e/f/g/h/i/j", 0o711) => Ok(());
+    sticky: mkdir_all("foo", libc::S_ISVTX | 0o711) => Ok(());
+    dotdot_in_nonexisting: mkdir_all("a/b/c/d/e/f/g/h/i/j/k/../lmnop", 0o711) => Err(ErrorKind::OsError(Some(libc::ENOENT)));
+    dotdot_in_existing: mkdir_all("b/c/../c/./d/e/f/g/h", 0o711) => Ok(());
+    dotdot_after_symlink: mkdir_all("e/../dd/ee/ff", 0o711) => Ok(());
+    // Check that trying to create under a file fails.
+    nondir_trailing: mkdir_all("b/c/file", 0o711) => Err(ErrorKind::OsError(Some(libc::ENOTDIR)));
+    nondir_dotdot: mkdir_all("b/c/file/../d", 0o711) => Err(ErrorKind::OsError(Some(libc::ENOTDIR)));
+    nondir_subdir: mkdir_all("b/c/file/subdir", 0o711) => Err(ErrorKind::OsError(Some(libc::ENOTDIR)));
+    nondir_symlink_trailing: mkdir_all("b-file", 0o711) => Err(ErrorKind::OsError(Some(libc::ENOTDIR)));
+    nondir_symlink_dotdot: mkdir_all("b-file/../d", 0o711) => Err(ErrorKind::OsError(Some(libc::ENOTDIR)));
+    nondir_symlink_subdir: mkdir_all("b-file/subdir", 0o711) => Err(ErrorKind::OsError(Some(libc::ENOTDIR)));
+    // Dangling symlinks are not followed.
+    dangling1_trailing: mkdir_all("a-fake1", 0o711) => Err(ErrorKind::OsError(Some(libc::ENOTDIR)));
+    dangling1_basic: mkdir_all("a-fake1/foo", 0o711) => Err(ErrorKind::OsError(Some(libc::ENOTDIR)));
+    dangling1_dotdot: mkdir_all("a-fake1/../bar/baz", 0o711) => Err(ErrorKind::OsError(Some(libc::ENOENT)));
+    dangling2_trailing: mkdir_all("a-fake2", 0o711) => Err(ErrorKind::OsError(Some(libc::ENOTDIR)));
+    dangling2_basic: mkdir_all("a-fake2/foo", 0o711) => Err(ErrorKind::OsError(Some(libc::ENOTDIR)));
+    dangling2_dotdot: mkdir_all("a-fake2/../bar/baz", 0o711) => Err(ErrorKind::OsError(Some(libc::ENOENT)));
+    dangling3_trailing: mkdir_all("a-fake3", 0o711) => Err(ErrorKind::OsError(Some(libc::ENOTDIR)));
+    dangling3_basic: mkdir_all("a-fake3/foo", 0o711) => Err(ErrorKind::OsError(Some(libc::ENOTDIR)));
+    dangling3_dotdot: mkdir_all("a-fake3/../bar/baz", 0o711) => Err(ErrorKind::OsError(Some(libc::ENOENT)));
+    // Non-lexical symlinks should work.
+    nonlexical_basic: mkdir_all("target/foo", 0o711) => Ok(());
+    nonlexical_level1_abs: mkdir_all("link1/target_abs/foo", 0o711) => Ok(());
+    nonlexical_level1_rel: mkdir_all("link1/target_rel/foo", 0o711) => Ok(());
+    nonlexical_level2_abs_abs: mkdir_all("link2/link1_abs/target_abs/foo", 0o711) => Ok(());
+    nonlexical_level2_abs_rel: mkdir_all("link2/link1_abs/target_rel/foo", 0o711) => Ok(());
+    nonlexical_level2_abs_open: mkdir_all("link2/link1_abs/../target/foo", 0o711) => Ok(());
+    nonlexical_level2_rel_abs: mkdir_all("link2/link1_rel/target_abs/foo", 0o711) => Ok(());
+    nonlexical_level2_rel_rel: mkdir_all("link2/link1_rel/target_rel/foo", 0o711) => Ok(());
+    nonlexical_level2_rel_open: mkdir_all("link2/link1_rel/../target/foo", 0o711) => Ok(());
+    nonlexical_level3_abs: mkdir_all("link3/target_abs/foo", 0o711) => Ok(());
+    nonlexical_level3_rel: mkdir_all("link3/target_rel/foo", 0o711) => Ok(());
+    // But really tricky dangling symlinks should fail.
+    dangling_tricky1_trailing: mkdir_all("link3/deep_dangling1", 0o711) => Err(ErrorKind::OsError(Some(libc::ENOTDIR)));
+    dangling_tricky1_basic: mkdir_all("link3/deep_dangling1/foo", 0o711) => Err(ErrorKind::OsError(Some(libc::ENOTDIR)));
+    dangling_tricky1_dotdot: mkdir_all("link3/deep_dangling1/../bar", 0o711) => Err(ErrorKind::OsError(Some(libc::ENOENT)));
+    dangling_tricky2_trailing: mkdir_all("link3/deep_dangling2", 0o711) => Err(ErrorKind::OsError(Some(libc::ENOTDIR)));
+    dangling_tricky2_basic: mkdir_all("link3/deep_dangling2/foo", 0o711) => Err(ErrorKind::OsError(Some(libc::ENOTDIR)));
+    dangling_tricky2_dotdot: mkdir_all("link3/deep_dangling2/../bar", 0o711) => Err(ErrorKind::OsError(Some(libc::ENOENT)));
+    // And trying to mkdir inside a loop should fail.
+    loop_trailing: mkdir_all("loop/link", 0o711) => Err(ErrorKind::OsError(Some(libc::ELOOP)));
+    loop_basic: mkdir_all("loop/link/foo", 0o711) => Err(ErrorKind::OsError(Some(libc::ELOOP)));
+    loop_dotdot: mkdir_all("loop/link/../foo", 0o711) => Err(ErrorKind::OsError(Some(libc::ELOOP)));
+    // Make sure the S_ISGID handling is correct.
+    setgid_selfdir: mkdir_all("setgid-self/a/b/c/d", 0o711) => Ok(());
+    #[cfg(feature = "_test_as_root")]
+    setgid_otherdir: mkdir_all("setgid-other/a/b/c/d", 0o711) => Ok(());
+
+    // Check that multiple mkdir_alls racing against each other will not result
+    // in a spurious error. <https://github.com/opencontainers/runc/issues/4543>
+    plain: mkdir_all_racing("a/b/c/d/e/f/g/h/i/j/k/l/m/n/o/p/q/r/s/t/u/v/w/x/y/z", 0o711) => Ok(());
+
+    // Check that multiple rmdir_alls racing against each other will not result
+    // in a spurious error.
+    plain: remove_all_racing("deep-rmdir") => Ok(());
+}
+
+root_op_tests! {
+    // Pseudo-filesystem files (sysfs, procfs) report an st_size of 0 but still
+    // have contents. A FIFO has the same property, so we use it to make sure
+    // that read() reads until EOF rather than trusting st_size.
+    @rust-only fn read_fifo_zero_size(root) {
+        let root_dir = root.as_fd().as_unsafe_path_unchecked()?;
+        assert_eq!(
+            root.resolve("b/fifo")?.as_fd().metadata()?.size(),
+            0,
+            "fifo should have a zero st_size"
+        );
+
+        let writer = utils::spawn_fifo_writer(root_dir.join("b/fifo"), b"some fifo data\n");
+        let data = root.read("b/fifo")?;
+        writer.join().expect("fifo writer should not panic")?;
+        assert_eq!(data, b"some fifo data\n", "read should read fifo until EOF");
+        Ok(())
+    }
+}
+
+root_op_tests! {
+    @rust-only fn read_to_string_fifo_zero_size(root) {
+        let root_dir = root.as_fd().as_unsafe_path_unchecked()?;
+        let writer = utils::spawn_fifo_writer(root_dir.join("b/fifo"), b"hello world");
+
+        let data = root.read_to_string("b/fifo")?;
+        writer.join().expect("fifo writer should not panic")?;
+        assert_eq!(data, "hello world", "read_to_string should read fifo until EOF");
+        Ok(())
+    }
+}
+
+root_op_tests! {
+    @rust-only fn root_handle(root) {
+        let handle = root.root_handle()?;
+        assert_ne!(
+            handle.as_fd().as_raw_fd(),
+            root.as_fd().as_raw_fd(),
+            "root_handle should return a copy of the root fd"
+        );
+        assert_eq!(
+            handle.as_fd().as_unsafe_path_unchecked()?,
+            root.resolve(".")?.as_fd().as_unsafe_path_unchecked()?,
+            "root_handle should refer to the root directory"
+        );
+        assert_eq!(
+            handle.backend(),
+            root.resolve(".")?.backend(),
+            "root_handle should have the same backend as resolved handles"
+        );
+        Ok(())
+    }
+}
+
+root_op_tests! {
+    @rust-only fn read_enoent(root) {
+        tests_common::check_err(&root.read("abc"), &Err::<(), _>(ErrorKind::OsError(Some(libc::ENOENT))))
+    }
+}
+
+root_op_tests! {
+    @rust-only fn read_to_string_invalid_utf8(root) {
+        let root_dir = root.as_fd().as_unsafe_path_unchecked()?;
+        let writer = utils::spawn_fifo_writer(root_dir.join("b/fifo"), b"\xff\xfe");
+
+        let res = root.read_to_string("b/fifo");
+        writer.join().expect("fifo writer should not panic")?;
+        assert!(res.is_err(), "read_to_string of invalid utf-8 should fail");
+        Ok(())
+    }
+}
+
+root_op_tests! {
+    @rust-only fn read_limit(root) {
+        let root_dir = root.as_fd().as_unsafe_path_unchecked()?;
+        let contents = vec![b'x'; 10000];
+        fs::write(root_dir.join("b/c/big-file"), &contents)?;
+
+        assert_eq!(root.read_limit(), None, "default read limit should be unset");
+        let mut root = root.with_read_limit(Some(10000));
+        assert_eq!(
+            root.read("b/c/big-file")?,
+            contents,
+            "read of file exactly at the read limit should succeed"
+        );
+        for limit in [0, 4096, 9999] {
+            root.set_read_limit(Some(limit));
+            tests_common::check_err(
+                &root.read("b/c/big-file"),
+                &Err::<(), _>(ErrorKind::LimitExceeded),
+            )?;
+            tests_common::check_err(
+                &root.read_to_string("b/c/big-file"),
+                &Err::<(), _>(ErrorKind::LimitExceeded),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+root_op_tests! {
+    @rust-only fn read_write(root) {
+        let perm = Permissions::from_mode(0o640);
+
+        root.write("b/c/new-file", &perm, b"hello world")?;
+        assert_eq!(root.read("b/c/new-file")?, b"hello world", "read should return written data");
+        assert_eq!(
+            root.metadata("b/c/new-file")?.mode() & 0o7777,
+            0o640,
+            "write should create the file with the requested mode"
+        );
+
+        // Existing files are truncated and keep their mode.
+        root.write("b/c/new-file", &Permissions::from_mode(0o600), b"foo")?;
+        assert_eq!(root.read("b/c/new-file")?, b"foo", "write should truncate the file");
+        assert_eq!(
+            root.metadata("b/c/new-file")?.mode() & 0o7777,
+            0o640,
+            "write should not change the mode of existing files"
+        );
+
+        // Symlinks in parent components are resolved inside the root.
+        root.write("e/../../new-file2", &perm, b"bar")?;
+        assert_eq!(root.read("b/c/new-file2")?, b"bar", "write should resolve paths inside root");
+
+        // Trailing symlinks are never followed, even dangling ones.
+        for path in ["b-file", "a-fake1", "c/a-fake1", "escape-link2"] {
+            tests_common::check_err(
+                &root.write(path, &perm, b"evil"),
+                &Err::<(), _>(ErrorKind::OsError(Some(libc::ELOOP))),
+            )
+            .with_context(|| format!("write to trailing symlink {path:?}"))?;
+        }
+        assert_ne!(root.read("b-file")?, b"evil", "write should not follow trailing symlink");
+        tests_common::check_err(
+            &root.resolve("a-fake1"),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::ENOENT))),
+        )?;
+
+        tests_common::check_err(
+            &root.read("b/c"),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::EISDIR))),
+        )?;
+        tests_common::check_err(
+            &root.write("b/c", &perm, b"foo"),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::EISDIR))),
+        )?;
+        tests_common::check_err(
+            &root.read("nonexistent"),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::ENOENT))),
+        )?;
+        Ok(())
+    }
+}
+
+root_op_tests! {
+    @rust-only fn symlink_checked(root) {
+        for (path, target) in [
+            ("b/c/link-file", "file"),
+            ("b/c/link-up", "../../a"),
+            ("link-dir", "b/c/d/e"),
+            ("b/link-via-symlink", "../b-file"),
+            // Absolute paths are relative to the root.
+            ("/b/c/link-abs", "file"),
+            ("/link-abs-dir", "b/c"),
+        ] {
+            root.symlink_checked(path, target)?;
+            assert_eq!(
+                root.readlink(path)?,
+                Path::new(target),
+                "symlink_checked({path:?}, {target:?}) target"
+            );
+        }
+
+        for (path, target) in [
+            // Dangling.
+            ("b/c/bad1", "nonexistent"),
+            ("b/c/bad2", "../../a-fake1"),
+            // Escapes the root.
+            ("b/c/bad3", "../../.."),
+            ("bad4", "../b"),
+            ("b/c/bad5", "/b/c/file"),
+            ("b/c/bad6", "../../root-link1"),
+        ] {
+            tests_common::check_err(
+                &root.symlink_checked(path, target),
+                &Err::<(), _>(ErrorKind::InvalidArgument),
+            )
+            .with_context(|| format!("symlink_checked({path:?}, {target:?})"))?;
+            assert!(
+                !root.exists_nofollow(path)?,
+                "symlink_checked({path:?}, {target:?}) should not create the symlink"
+            );
+        }
+
+        // The usual create errors still apply.
+        tests_common::check_err(
+            &root.symlink_checked("b/c/link-file", "file"),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::EEXIST))),
+        )?;
+        Ok(())
+    }
+}
+
+root_op_tests! {
+    @rust-only fn create_file_owned(root) {
+        let perm = Permissions::from_mode(0o644);
+        let mut file = root.create_file_owned(
+            "b/c/owned",
+            OpenFlags::O_RDWR | OpenFlags::O_EXCL,
+            &perm,
+            1000,
+            1001,
+        )?;
+        file.write_all(b"data")?;
+        let meta = root.metadata("b/c/owned")?;
+        assert_eq!(
+            (meta.uid(), meta.gid()),
+            (1000, 1001),
+            "create_file_owned should change the owner"
+        );
+        assert_eq!(
+            (file.metadata()?.dev(), file.metadata()?.ino()),
+            (meta.dev(), meta.ino()),
+            "create_file_owned should return the created file"
+        );
+
+        tests_common::check_err(
+            &root.create_file_owned("b/c/owned", OpenFlags::O_RDWR | OpenFlags::O_EXCL, &perm, 0, 0),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::EEXIST))),
+        )?;
+        Ok(())
+    }
+}
+
+root_op_tests! {
+    @rust-only fn truncate(root) {
+        let perm = Permissions::from_mode(0o644);
+
+        root.write("b/c/file", &perm, b"hello world")?;
+        root.truncate("b/c/file", 5)?;
+        assert_eq!(root.read("b/c/file")?, b"hello", "truncate should shrink the file");
+        root.truncate("b/c/file", 8)?;
+        assert_eq!(root.read("b/c/file")?, b"hello\0\0\0", "truncate should extend the file");
+
+        // Trailing symlinks are followed (inside the root).
+        root.truncate("b-file", 2)?;
+        assert_eq!(root.read("b/c/file")?, b"he", "truncate should follow trailing symlinks");
+
+        // create_file with O_TRUNC truncates existing files.
+        root.create_file("b/c/file", OpenFlags::O_WRONLY | OpenFlags::O_TRUNC, &perm)?;
+        assert_eq!(root.read("b/c/file")?, b"", "create_file with O_TRUNC should truncate");
+
+        // Opening a FIFO would block forever, so the type must be checked
+        // before the file is opened.
+        root.create("b/c/fifo", &InodeType::Fifo(Permissions::from_mode(0o644)))?;
+
+        for (path, expected_err) in [
+            ("b/c", libc::EISDIR),
+            ("e", libc::EISDIR),
+            ("b/c/fifo", libc::EINVAL),
+            ("nonexistent", libc::ENOENT),
+            ("a-fake1", libc::ENOENT),
+        ] {
+            tests_common::check_err(
+                &root.truncate(path, 0),
+                &Err::<(), _>(ErrorKind::OsError(Some(expected_err))),
+            )
+            .with_context(|| format!("truncate {path:?}"))?;
+        }
+        Ok(())
+    }
+}
+
+root_op_tests! {
+    @rust-only fn open_exec(root) {
+        let file = root.open_exec("b/c/file")?;
+        let (file_meta, handle_meta) = (file.metadata()?, root.resolve("b/c/file")?.as_fd().metadata()?);
+        assert_eq!(
+            (file_meta.dev(), file_meta.ino()),
+            (handle_meta.dev(), handle_meta.ino()),
+            "open_exec should open the resolved inode"
+        );
+        assert!(file_meta.is_file(), "open_exec should return a regular file");
+
+        tests_common::check_err(&root.open_exec("b/c"), &Err::<(), _>(ErrorKind::OsError(Some(libc::EACCES))))?;
+        tests_common::check_err(&root.open_exec("b/c/nonexistent"), &Err::<(), _>(ErrorKind::OsError(Some(libc::ENOENT))))?;
+
+        // Opening a FIFO would block forever, so the type must be checked
+        // before the file is opened.
+        root.create("b/c/fifo", &InodeType::Fifo(Permissions::from_mode(0o755)))?;
+        tests_common::check_err(&root.open_exec("b/c/fifo"), &Err::<(), _>(ErrorKind::OsError(Some(libc::EACCES))))?;
+        Ok(())
+    }
+}
+
+root_op_tests! {
+    @rust-only fn setid(root) {
+        let root_dir = root.as_fd().as_unsafe_path_unchecked()?;
+        fs::set_permissions(root_dir.join("b/c/file"), Permissions::from_mode(0o4755))?;
+        File::create(root_dir.join("b/setgid-file"))?;
+        fs::set_permissions(root_dir.join("b/setgid-file"), Permissions::from_mode(0o2711))?;
+        fs::set_permissions(root_dir.join("b/c"), Permissions::from_mode(0o2755))?;
+        std::os::unix::fs::symlink("/b/c/file", root_dir.join("setuid-link"))?;
+
+        for path in ["b/c/file", "b/setgid-file", "setuid-link"] {
+            tests_common::check_err(
+                &root.resolve_denying_setid(path),
+                &Err::<(), _>(ErrorKind::SafetyViolation),
+            )
+            .with_context(|| format!("resolve_denying_setid({path:?})"))?;
+        }
+        // Setgid directories are not considered setid.
+        root.resolve_denying_setid("b/c")?;
+
+        root.strip_setid("setuid-link")?;
+        root.strip_setid("b/setgid-file")?;
+        root.strip_setid("b/c")?;
+        for (path, mode) in [("b/c/file", 0o100755), ("b/setgid-file", 0o100711), ("b/c", 0o42755)] {
+            assert_eq!(
+                root.resolve_nofollow(path)?.as_fd().metadata()?.mode(),
+                mode,
+                "strip_setid should only clear setid bits on regular files ({path:?})"
+            );
+        }
+        root.resolve_denying_setid("b/c/file")?;
+        root.resolve_denying_setid("b/setgid-file")?;
+        Ok(())
+    }
+}
+
+root_op_tests! {
+    @rust-only fn setid_policy(root) {
+        const SETID_BITS: u32 = libc::S_ISUID | libc::S_ISGID;
+        assert_eq!(root.setid_policy(), SetidPolicy::Allow, "default setid policy");
+
+        // By default, setid bits are applied faithfully.
+        root.create_file("allow-file", OpenFlags::O_RDONLY, &Permissions::from_mode(0o4755))?;
+        assert_eq!(
+            root.metadata("allow-file")?.mode() & SETID_BITS,
+            libc::S_ISUID,
+            "SetidPolicy::Allow should keep setid bits"
+        );
+
+        let mut root = root.with_setid_policy(SetidPolicy::Strip);
+        root.create_file("strip-file", OpenFlags::O_RDONLY, &Permissions::from_mode(0o6755))?;
+        root.create("strip-mknod", &InodeType::File(Permissions::from_mode(0o2755)))?;
+        root.chmod("allow-file", &Permissions::from_mode(0o6711))?;
+        for path in ["strip-file", "strip-mknod", "allow-file"] {
+            assert_eq!(
+                root.metadata(path)?.mode() & SETID_BITS,
+                0,
+                "SetidPolicy::Strip should clear setid bits ({path:?})"
+            );
+        }
+        assert_eq!(
+            root.metadata("allow-file")?.mode() & 0o7777,
+            0o711,
+            "SetidPolicy::Strip should keep the other mode bits"
+        );
+        // Directories are not affected.
+        root.chmod("b/c", &Permissions::from_mode(0o2755))?;
+        assert_eq!(
+            root.metadata("b/c")?.mode() & SETID_BITS,
+            libc::S_ISGID,
+            "setid policy should not affect directories"
+        );
+
+        root.forbid_suid_sgid(true);
+        assert_eq!(root.setid_policy(), SetidPolicy::Error);
+        tests_common::check_err(
+            &root.create_file("error-file", OpenFlags::O_RDONLY, &Permissions::from_mode(0o4755)),
+            &Err::<(), _>(ErrorKind::SafetyViolation),
+        )?;
+        tests_common::check_err(
+            &root.create("error-file", &InodeType::File(Permissions::from_mode(0o2755))),
+            &Err::<(), _>(ErrorKind::SafetyViolation),
+        )?;
+        tests_common::check_err(
+            &root.chmod("strip-file", &Permissions::from_mode(0o4755)),
+            &Err::<(), _>(ErrorKind::SafetyViolation),
+        )?;
+        tests_common::check_err(
+            &root.metadata_nofollow("error-file"),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::ENOENT))),
+        )?;
+        assert_eq!(
+            root.metadata("strip-file")?.mode() & SETID_BITS,
+            0,
+            "rejected chmod should not change the mode"
+        );
+        // Modes without setid bits are unaffected.
+        root.create_file("error-file", OpenFlags::O_RDONLY, &Permissions::from_mode(0o755))?;
+        // mkdir_all always rejects setid bits, regardless of the policy.
+        tests_common::check_err(
+            &root.mkdir_all("setid-dir", &Permissions::from_mode(0o2755)),
+            &Err::<(), _>(ErrorKind::InvalidArgument),
+        )?;
+
+        root.forbid_suid_sgid(false);
+        assert_eq!(root.setid_policy(), SetidPolicy::Allow);
+        Ok(())
+    }
+}
+
+root_op_tests! {
+    @rust-only fn rebase_symlink(root) {
+        for (name, target) in [
+            ("abs", "/old/foo//bar"),
+            ("exact", "/old/"),
+            ("prefix-only", "/oldfoo/bar"),
+            ("relative", "old/foo"),
+            ("dotdot", "/old/../etc/passwd"),
+        ] {
+            root.create(format!("b/{name}"), &InodeType::Symlink(target.into()))?;
+        }
+
+        assert_eq!(
+            root.rebase_symlink("b/abs", "/old", "/new/dir")?,
+            Some("/new/dir/foo/bar".into()),
+            "rebase_symlink should rewrite matching absolute symlink"
+        );
+        assert_eq!(root.readlink("b/abs")?, Path::new("/new/dir/foo/bar"));
+        assert_eq!(
+            root.rebase_symlink("b/exact", "/old", "/new")?,
+            Some("/new".into()),
+            "rebase_symlink should rewrite symlink equal to the prefix"
+        );
+        assert_eq!(root.readlink("b/exact")?, Path::new("/new"));
+
+        for name in ["prefix-only", "relative"] {
+            let path = format!("b/{name}");
+            let target = root.readlink(&path)?;
+            assert_eq!(
+                root.rebase_symlink(&path, "/old", "/new")?,
+                None,
+                "rebase_symlink should not rewrite non-matching symlink {path:?}"
+            );
+            assert_eq!(root.readlink(&path)?, target);
+        }
+
+        tests_common::check_err(
+            &root.rebase_symlink("b/dotdot", "/old", "/new"),
+            &Err::<(), _>(ErrorKind::InvalidArgument),
+        )?;
+        tests_common::check_err(
+            &root.rebase_symlink("b/abs", "/new/dir", "new"),
+            &Err::<(), _>(ErrorKind::InvalidArgument),
+        )?;
+        tests_common::check_err(
+            &root.rebase_symlink("b/abs", "/new/dir/..", "/new"),
+            &Err::<(), _>(ErrorKind::InvalidArgument),
+        )?;
+        tests_common::check_err(
+            &root.rebase_symlink("b/c/file", "/old", "/new"),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::EINVAL))),
+        )?;
+
+        // No temporary files should be left behind.
+        let root_dir = root.as_fd().as_unsafe_path_unchecked()?;
+        for entry in fs::read_dir(root_dir.join("b"))? {
+            let name = entry?.file_name();
+            assert!(
+                !name.to_string_lossy().starts_with(".pathrs-"),
+                "rebase_symlink left behind temporary file {name:?}"
+            );
+        }
+        Ok(())
+    }
+}
+
+root_op_tests! {
+    @rust-only fn find_hardlinks(root) {
+        root.create_file("b/hl-target", OpenFlags::O_EXCL, &Permissions::from_mode(0o644))?;
+        root.create("b/c/hl-link1", &InodeType::Hardlink("b/hl-target".into()))?;
+        root.create("b/c/d/hl-link2", &InodeType::Hardlink("b/hl-target".into()))?;
+        root.create("hl-symlink", &InodeType::Symlink("b/hl-target".into()))?;
+
+        let mut links = root.find_hardlinks("b/c/hl-link1", "/")?;
+        links.sort();
+        assert_eq!(
+            links,
+            vec![
+                PathBuf::from("/b/c/d/hl-link2"),
+                PathBuf::from("/b/c/hl-link1"),
+                PathBuf::from("/b/hl-target"),
+            ],
+            "find_hardlinks should find all hardlinks but not symlinks"
+        );
+
+        // Trailing symlinks are followed for the target.
+        let mut links = root.find_hardlinks("hl-symlink", "b/c")?;
+        links.sort();
+        assert_eq!(
+            links,
+            vec![PathBuf::from("b/c/d/hl-link2"), PathBuf::from("b/c/hl-link1")],
+            "find_hardlinks should only search within search_root"
+        );
+
+        tests_common::check_err(
+            &root.find_hardlinks("b/c", "/"),
+            &Err::<(), _>(ErrorKind::InvalidArgument),
+        )?;
+        Ok(())
+    }
+}
+
+root_op_tests! {
+    @rust-only fn chmod(root) {
+        let root_dir = root.as_fd().as_unsafe_path_unchecked()?;
+        let mode_of = |path: &str| -> Result<u32, Error> {
+            Ok(fs::symlink_metadata(root_dir.join(path))?.mode() & 0o7777)
+        };
+
+        root.chmod("b/c/file", &Permissions::from_mode(0o600))?;
+        assert_eq!(mode_of("b/c/file")?, 0o600);
+        // Trailing symlinks are followed.
+        root.chmod("b-file", &Permissions::from_mode(0o4751))?;
+        assert_eq!(mode_of("b/c/file")?, 0o4751);
+        root.chmod_nofollow("b/c", &Permissions::from_mode(0o711))?;
+        assert_eq!(mode_of("b/c")?, 0o711);
+
+        tests_common::check_err(
+            &root.chmod_nofollow("b-file", &Permissions::from_mode(0o600)),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::EOPNOTSUPP))),
+        )?;
+        tests_common::check_err(
+            &root.chmod("b/c/file", &Permissions::from_mode(0o10644)),
+            &Err::<(), _>(ErrorKind::InvalidArgument),
+        )?;
+        assert_eq!(mode_of("b/c/file")?, 0o4751);
+        Ok(())
+    }
+}
+
+root_op_tests! {
+    @rust-only fn chown(root) {
+        let root_dir = root.as_fd().as_unsafe_path_unchecked()?;
+        let owner_of = |path: &str| -> Result<(u32, u32), Error> {
+            let meta = fs::symlink_metadata(root_dir.join(path))?;
+            Ok((meta.uid(), meta.gid()))
+        };
+        let (uid, gid) = owner_of("b/c/file")?;
+
+        // Changing the owner to the current owner is always permitted.
+        root.chown("b/c/file", Some(uid), Some(gid))?;
+        root.chown("b/c/file", None, None)?;
+        assert_eq!(owner_of("b/c/file")?, (uid, gid));
+
+        if syscalls::geteuid() != 0 {
+            // Without CAP_CHOWN we cannot give files away.
+            tests_common::check_err(
+                &root.chown("b/c/file", Some(1000), None),
+                &Err::<(), _>(ErrorKind::OsError(Some(libc::EPERM))),
+            )?;
+            return Ok(());
+        }
+
+        // Trailing symlinks are followed, and None leaves the id unchanged.
+        root.chown("b-file", Some(1000), None)?;
+        assert_eq!(owner_of("b/c/file")?, (1000, gid));
+        root.chown("b/c/file", None, Some(1001))?;
+        assert_eq!(owner_of("b/c/file")?, (1000, 1001));
+
+        // chown_nofollow changes the symlink itself.
+        let link_owner = owner_of("b-file")?;
+        root.chown_nofollow("b-file", Some(2000), Some(2001))?;
+        assert_eq!(owner_of("b-file")?, (2000, 2001));
+        assert_eq!(owner_of("b/c/file")?, (1000, 1001));
+        assert_ne!(link_owner, (2000, 2001));
+        Ok(())
+    }
+}
+
+root_op_tests! {
+    @rust-only fn exists(root) {
+        for (path, exists, exists_nofollow) in [
+            (".", true, true),
+            ("b/c/file", true, true),
+            ("b-file", true, true),
+            ("e", true, true),
+            ("a-fake1", false, true),
+            ("c/a-fake1", false, true),
+            ("nonexistent", false, false),
+            ("a/nonexistent", false, false),
+        ] {
+            assert_eq!(root.exists(path)?, exists, "exists({path:?})");
+            assert_eq!(root.exists_nofollow(path)?, exists_nofollow, "exists_nofollow({path:?})");
+        }
+
+        // Errors other than ENOENT are not treated as the path not existing.
+        tests_common::check_err(
+            &root.exists("b/c/file/foo"),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::ENOTDIR))),
+        )?;
+        tests_common::check_err(
+            &root.exists_nofollow("b/c/file/foo"),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::ENOTDIR))),
+        )?;
+        tests_common::check_err(
+            &root.exists("loop/basic-loop1"),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::ELOOP))),
+        )?;
+        assert!(root.exists_nofollow("loop/basic-loop1")?, "exists_nofollow of symlink loop");
+        Ok(())
+    }
+}
+
+root_op_tests! {
+    @rust-only fn statx(root) {
+        let meta = root.metadata("b/c/file")?;
+        let stx = root.statx("b-file", StatxMask::BASIC_STATS | StatxMask::MNT_ID)?;
+        assert!(
+            stx.mask().contains(StatxMask::BASIC_STATS),
+            "statx should return requested basic stats (got {:?})",
+            stx.mask()
+        );
+        assert_eq!(
+            (stx.dev(), stx.ino(), stx.mode(), stx.nlink(), stx.size()),
+            (meta.dev(), meta.ino(), meta.mode(), meta.nlink() as u32, meta.size()),
+            "statx should follow trailing symlinks"
+        );
+        assert_eq!((stx.uid(), stx.gid()), (meta.uid(), meta.gid()));
+        assert_eq!(stx.mtime(), Some(meta.modified()?), "statx mtime");
+        assert_eq!(stx.atime(), Some(meta.accessed()?), "statx atime");
+
+        // The mount id is the same for inodes on the same mount.
+        if let Some(mnt_id) = stx.mnt_id() {
+            assert_eq!(
+                root.statx("b/c", StatxMask::MNT_ID)?.mnt_id(),
+                Some(mnt_id),
+                "statx mnt_id of same mount"
+            );
+        }
+
+        tests_common::check_err(
+            &root.statx("a-fake1", StatxMask::BASIC_STATS),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::ENOENT))),
+        )?;
+        Ok(())
+    }
+}
+
+root_op_tests! {
+    @rust-only fn created(root) {
+        // Birth times are optional, so compare against std (which returns an
+        // error if the filesystem doesn't support them).
+        assert_eq!(
+            root.created("b-file")?,
+            root.metadata("b/c/file")?.created().ok(),
+            "created should follow trailing symlinks"
+        );
+        assert_eq!(
+            root.created_nofollow("b-file")?,
+            root.metadata_nofollow("b-file")?.created().ok(),
+            "created_nofollow should return the symlink's own btime"
+        );
+        assert_eq!(
+            root.created_nofollow("a-fake1")?,
+            root.metadata_nofollow("a-fake1")?.created().ok(),
+            "created_nofollow should work on dangling symlinks"
+        );
+        tests_common::check_err(
+            &root.created("a-fake1"),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::ENOENT))),
+        )?;
+        Ok(())
+    }
+}
+
+root_op_tests! {
+    @rust-only fn set_times(root) {
+        let atime = UNIX_EPOCH + Duration::new(1_000_000_000, 123_456_789);
+        let mtime = UNIX_EPOCH + Duration::new(1_500_000_000, 987_654_321);
+
+        // Trailing symlinks are followed.
+        let link_meta = root.metadata_nofollow("b-file")?;
+        root.set_times("b-file", Some(atime), Some(mtime), true)?;
+        let meta = root.metadata("b/c/file")?;
+        assert_eq!(meta.accessed()?, atime, "set_times should set atime with ns precision");
+        assert_eq!(meta.modified()?, mtime, "set_times should set mtime with ns precision");
+        assert_eq!(
+            root.metadata_nofollow("b-file")?.modified()?,
+            link_meta.modified()?,
+            "set_times(follow) should not change symlink times"
+        );
+
+        // UTIME_OMIT for None.
+        let new_mtime = UNIX_EPOCH + Duration::new(1_600_000_000, 1);
+        root.set_times("b/c/file", None, Some(new_mtime), true)?;
+        let meta = root.metadata("b/c/file")?;
+        assert_eq!(meta.accessed()?, atime, "set_times(None) should not change atime");
+        assert_eq!(meta.modified()?, new_mtime, "set_times should change mtime");
+        root.set_times("b/c/file", None, None, true)?;
+        assert_eq!(root.metadata("b/c/file")?.modified()?, new_mtime, "set_times(None, None) should be a no-op");
+
+        // Trailing symlinks are not followed.
+        root.set_times("b-file", Some(atime), Some(mtime), false)?;
+        let link_meta = root.metadata_nofollow("b-file")?;
+        assert_eq!(link_meta.modified()?, mtime, "set_times(nofollow) should set symlink mtime");
+        assert_eq!(
+            root.metadata("b/c/file")?.modified()?,
+            new_mtime,
+            "set_times(nofollow) should not change symlink target times"
+        );
+
+        tests_common::check_err(
+            &root.set_times("b/c/file", Some(UNIX_EPOCH - Duration::from_secs(1)), None, true),
+            &Err::<(), _>(ErrorKind::InvalidArgument),
+        )?;
+        tests_common::check_err(
+            &root.set_times("b/c/file", None, Some(UNIX_EPOCH - Duration::from_nanos(1)), false),
+            &Err::<(), _>(ErrorKind::InvalidArgument),
+        )?;
+        tests_common::check_err(
+            &root.set_times("a-fake1", Some(atime), None, true),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::ENOENT))),
+        )?;
+        Ok(())
+    }
+}
+
+root_op_tests! {
+    @rust-only fn sync_parent(root) {
+        root.sync_parent("b/c/file")?;
+        // The trailing component doesn't need to exist.
+        root.sync_parent("b/c/nonexistent")?;
+        root.sync_parent("e/f")?;
+        tests_common::check_err(
+            &root.sync_parent("b/c/file/foo"),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::ENOTDIR))),
+        )?;
+        tests_common::check_err(
+            &root.sync_parent("nonexistent/foo"),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::ENOENT))),
+        )?;
+        Ok(())
+    }
+}
+
+root_op_tests! {
+    @rust-only fn read_dir(root) {
+        let root_dir = root.as_fd().as_unsafe_path_unchecked()?;
+        for (path, target) in [(".", "."), ("b", "b"), ("b/c", "b/c"), ("e", "b/c/d/e")] {
+            let mut entries = root
+                .read_dir(path)?
+                .map(|entry| {
+                    let entry = entry?;
+                    let file_type = entry.file_type()?;
+                    Ok((entry.file_name(), file_type.is_dir(), file_type.is_symlink()))
+                })
+                .collect::<Result<Vec<_>, crate::error::Error>>()?;
+            entries.sort();
+
+            let mut expected = fs::read_dir(root_dir.join(target))?
+                .map(|entry| {
+                    let entry = entry?;
+                    let file_type = entry.file_type()?;
+                    Ok((entry.file_name(), file_type.is_dir(), file_type.is_symlink()))
+                })
+                .collect::<Result<Vec<_>, std::io::Error>>()?;
+            expected.sort();
+
+            assert_eq!(entries, expected, "read_dir({path:?}) entries");
+        }
+
+        tests_common::check_err(
+            &root.read_dir("b/c/file"),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::ENOTDIR))),
+        )?;
+        tests_common::check_err(
+            &root.read_dir("nonexistent"),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::ENOENT))),
+        )?;
+        Ok(())
+    }
+}
+
+root_op_tests! {
+    @rust-only fn walk(root) {
+        fn collect_walk(walk: crate::Walk<'_>) -> Result<Vec<(PathBuf, usize, bool)>, crate::error::Error> {
+            walk.map(|entry| {
+                let entry = entry?;
+                Ok((entry.path().to_path_buf(), entry.depth(), entry.file_type()?.is_dir()))
+            })
+            .collect()
+        }
+
+        let entries = collect_walk(root.walk("b")?)?;
+        // Directories must be returned before their children.
+        for (idx, (path, _, _)) in entries.iter().enumerate() {
+            if let Some(parent) = path.parent().filter(|parent| *parent != Path::new("b")) {
+                let parent_idx = entries.iter().position(|(path, _, _)| path == parent);
+                assert!(
+                    matches!(parent_idx, Some(parent_idx) if parent_idx < idx),
+                    "walk returned {path:?} before its parent {parent:?}: {entries:?}"
+                );
+            }
+        }
+        let mut entries = entries;
+        entries.sort();
+        assert_eq!(
+            entries,
+            vec![
+                ("b/c".into(), 1, true),
+                ("b/c/d".into(), 2, true),
+                ("b/c/d/e".into(), 3, true),
+                ("b/c/d/e/f".into(), 4, true),
+                ("b/c/file".into(), 2, false),
+                ("b/fifo".into(), 1, false),
+                ("b/sock".into(), 1, false),
+            ],
+            "walk(\"b\") entries"
+        );
+
+        let mut entries = collect_walk(root.walk("b")?.with_max_depth(2))?;
+        entries.sort();
+        assert_eq!(
+            entries,
+            vec![
+                ("b/c".into(), 1, true),
+                ("b/c/d".into(), 2, true),
+                ("b/c/file".into(), 2, false),
+                ("b/fifo".into(), 1, false),
+                ("b/sock".into(), 1, false),
+            ],
+            "walk(\"b\") entries with max_depth=2"
+        );
+
+        // Symlinks are not walked into by default, and are resolved inside the
+        // root when they are.
+        root.create_file("a/afile", OpenFlags::O_RDWR, &Permissions::from_mode(0o644))?;
+        root.create("b/c/d/escape", &InodeType::Symlink("../../../../../../a".into()))?;
+        let mut entries = collect_walk(root.walk("b/c/d")?)?;
+        entries.sort();
+        assert_eq!(
+            entries,
+            vec![
+                ("b/c/d/e".into(), 1, true),
+                ("b/c/d/e/f".into(), 2, true),
+                ("b/c/d/escape".into(), 1, false),
+            ],
+            "walk(\"b/c/d\") entries without following symlinks"
+        );
+        let mut entries = collect_walk(root.walk("b/c/d")?.with_follow_links(true))?;
+        entries.sort();
+        assert_eq!(
+            entries,
+            vec![
+                ("b/c/d/e".into(), 1, true),
+                ("b/c/d/e/f".into(), 2, true),
+                ("b/c/d/escape".into(), 1, false),
+                ("b/c/d/escape/afile".into(), 2, false),
+            ],
+            "walk(\"b/c/d\") entries following symlinks"
+        );
+
+        // Loops are reported as errors without stopping the walk.
+        root.create("b/c/d/e/f/up", &InodeType::Symlink("/b/c".into()))?;
+        let results = root
+            .walk("b/c")?
+            .with_follow_links(true)
+            .collect::<Vec<_>>();
+        let errors = results.iter().filter(|res| res.is_err()).count();
+        assert_eq!(errors, 1, "walk with symlink loop should have one error: {results:?}");
+        tests_common::check_err(
+            results.iter().find(|res| res.is_err()).expect("error in walk"),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::ELOOP))),
+        )?;
+        assert!(
+            results.iter().flatten().any(|entry| entry.path() == Path::new("b/c/file")),
+            "walk should continue after a loop error: {results:?}"
+        );
+
+        // Device numbers are only reported when requested.
+        assert!(
+            root.walk("b")?.flatten().all(|entry| entry.dev().is_none()),
+            "walk should not report dev by default"
+        );
+        let root_dev = root.metadata(".")?.dev();
+        for entry in root.walk("b")?.with_dev(true) {
+            let entry = entry?;
+            assert_eq!(entry.dev(), Some(root_dev), "dev of {:?}", entry.path());
+        }
+
+        tests_common::check_err(
+            &root.walk("b/c/file"),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::ENOTDIR))),
+        )?;
+        Ok(())
+    }
+}
+
+root_op_tests! {
+    @rust-only fn metadata(root) {
+        root.create("meta-dangling", &InodeType::Symlink("/nonexistent".into()))?;
+        let root_dir = root.as_fd().as_unsafe_path_unchecked()?;
+
+        for (path, target) in [
+            ("b/c/file", "b/c/file"),
+            ("b-file", "b/c/file"),
+            ("b/c", "b/c"),
+            ("e", "b/c/d/e"),
+        ] {
+            let meta = root.metadata(path)?;
+            let expected = fs::symlink_metadata(root_dir.join(target))?;
+            assert_eq!(
+                (meta.dev(), meta.ino(), meta.mode()),
+                (expected.dev(), expected.ino(), expected.mode()),
+                "metadata({path:?}) should match the target of {path:?}"
+            );
+        }
+        for path in ["b/c/file", "b-file", "b/c", "e", "meta-dangling"] {
+            let meta = root.metadata_nofollow(path)?;
+            let expected = fs::symlink_metadata(root_dir.join(path))?;
+            assert_eq!(
+                (meta.dev(), meta.ino(), meta.mode()),
+                (expected.dev(), expected.ino(), expected.mode()),
+                "metadata_nofollow({path:?}) should match {path:?}"
+            );
+        }
+        assert!(root.metadata_nofollow("meta-dangling")?.file_type().is_symlink());
+
+        tests_common::check_err(
+            &root.metadata("meta-dangling"),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::ENOENT))),
+        )?;
+        tests_common::check_err(
+            &root.metadata_nofollow("nonexistent"),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::ENOENT))),
+        )?;
+        Ok(())
+    }
+}
+
+root_op_tests! {
+    @rust-only fn symlink(root) {
+        for (path, target, resolved) in [
+            ("new-link1", "/b/c/file", Some("b/c/file")),
+            ("b/c/new-link2", "../../e", Some("b/c/d/e")),
+            ("a/new-dangling", "/nonexistent", None),
+        ] {
+            let link = root.symlink(path, target)?;
+            let meta = link.metadata()?;
+            assert!(meta.file_type().is_symlink(), "symlink({path:?}) handle should be a symlink");
+            assert_eq!(
+                syscalls::readlinkat(&link, "")?,
+                Path::new(target),
+                "symlink({path:?}) should have the requested target"
+            );
+
+            let expected = root.resolve_nofollow(path)?.metadata()?;
+            assert_eq!(
+                (meta.dev(), meta.ino()),
+                (expected.dev(), expected.ino()),
+                "symlink({path:?}) handle should be the created symlink"
+            );
+
+            match resolved {
+                Some(resolved) => {
+                    let got = root.resolve(path)?.metadata()?;
+                    let want = root.resolve(resolved)?.metadata()?;
+                    assert_eq!(
+                        (got.dev(), got.ino()),
+                        (want.dev(), want.ino()),
+                        "symlink({path:?}) should resolve to {resolved:?}"
+                    );
+                }
+                None => tests_common::check_err(
+                    &root.resolve(path),
+                    &Err::<(), _>(ErrorKind::OsError(Some(libc::ENOENT))),
+                )?,
+            }
+        }
+
+        tests_common::check_err(
+            &root.symlink("b-file", "/b/c/file"),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::EEXIST))),
+        )?;
+        tests_common::check_err(
+            &root.symlink("b/c/", "/b/c/file"),
+            &Err::<(), _>(ErrorKind::InvalidArgument),
+        )?;
+        tests_common::check_err(
+            &root.symlink("nonexistent/new-link4", "/b/c/file"),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::ENOENT))),
+        )?;
+        Ok(())
+    }
+}
+
+root_op_tests! {
+    @rust-only fn copy(root) {
+        let data = b"some file contents\n".repeat(1024);
+        root.create_file("b/c/file", OpenFlags::O_WRONLY | OpenFlags::O_TRUNC, &Permissions::from_mode(0o644))?
+            .write_all(&data)?;
+        root.chmod("b/c/file", &Permissions::from_mode(0o640))?;
+
+        // The source mode is preserved (and symlinks in both paths are
+        // resolved inside the root).
+        let mut file = root.copy("b-file", "e/new-copy1", OpenFlags::O_RDWR | OpenFlags::O_EXCL, None)?;
+        assert_eq!(file.metadata()?.mode() & 0o7777, 0o640, "copy should preserve source mode");
+        let mut got = Vec::new();
+        file.seek(SeekFrom::Start(0))?;
+        file.read_to_end(&mut got)?;
+        assert_eq!(got, data, "copy should copy the entire file");
+        assert_eq!(root.read("b/c/d/e/new-copy1")?, data, "copy should copy the entire file");
+
+        // Explicit mode override.
+        let file = root.copy("b/c/file", "new-copy2", OpenFlags::O_WRONLY | OpenFlags::O_EXCL, Some(&Permissions::from_mode(0o600)))?;
+        assert_eq!(file.metadata()?.mode() & 0o7777, 0o600, "copy should use the requested mode");
+        assert_eq!(root.read("new-copy2")?, data, "copy should copy the entire file");
+
+        // O_APPEND uses the read-write fallback.
+        root.copy("b/c/file", "new-copy2", OpenFlags::O_WRONLY | OpenFlags::O_APPEND, None)?;
+        assert_eq!(root.read("new-copy2")?, data.repeat(2), "copy with O_APPEND should append");
+
+        tests_common::check_err(
+            &root.copy("b/c/file", "new-copy2", OpenFlags::O_WRONLY | OpenFlags::O_EXCL, None),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::EEXIST))),
+        )?;
+        tests_common::check_err(
+            &root.copy("b/c", "new-copy3", OpenFlags::O_WRONLY, None),
+            &Err::<(), _>(ErrorKind::InvalidArgument),
+        )?;
+        tests_common::check_err(
+            &root.copy("b/c/file", "new-copy3", OpenFlags::O_RDONLY, None),
+            &Err::<(), _>(ErrorKind::InvalidArgument),
+        )?;
+        tests_common::check_err(
+            &root.copy("nonexistent", "new-copy3", OpenFlags::O_WRONLY, None),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::ENOENT))),
+        )?;
+        assert!(
+            root.resolve_nofollow("new-copy3").is_err(),
+            "failed copy should not create the destination"
+        );
+
+        // FIFOs are rejected without being opened (which would block).
+        root.create("b/c/fifo", &InodeType::Fifo(Permissions::from_mode(0o644)))?;
+        tests_common::check_err(
+            &root.copy("b/c/fifo", "new-copy3", OpenFlags::O_WRONLY, None),
+            &Err::<(), _>(ErrorKind::InvalidArgument),
+        )?;
+
+        // Setuid and setgid bits are not copied unless explicitly requested.
+        root.chmod("b/c/file", &Permissions::from_mode(0o6755))?;
+        let file = root.copy("b/c/file", "new-copy4", OpenFlags::O_WRONLY | OpenFlags::O_EXCL, None)?;
+        assert_eq!(file.metadata()?.mode() & 0o7000, 0, "copy should not copy setid bits by default");
+
+        // Holes in the source are preserved, including a trailing hole.
+        const HOLE_SIZE: u64 = 4 << 20;
+        let mut sparse = root.create_file("sparse", OpenFlags::O_WRONLY | OpenFlags::O_EXCL, &Permissions::from_mode(0o644))?;
+        sparse.write_all(b"head")?;
+        sparse.seek(SeekFrom::Start(HOLE_SIZE))?;
+        sparse.write_all(b"tail")?;
+        sparse.set_len(2 * HOLE_SIZE)?;
+        let mut expected = vec![0u8; 2 * HOLE_SIZE as usize];
+        expected[..4].copy_from_slice(b"head");
+        expected[HOLE_SIZE as usize..HOLE_SIZE as usize + 4].copy_from_slice(b"tail");
+        let file = root.copy("sparse", "sparse-copy", OpenFlags::O_WRONLY | OpenFlags::O_EXCL, None)?;
+        let meta = file.metadata()?;
+        assert_eq!(meta.len(), 2 * HOLE_SIZE, "sparse copy should have the same size");
+        assert!(
+            meta.blocks() * 512 < HOLE_SIZE / 4,
+            "sparse copy should not allocate the holes ({} blocks allocated)",
+            meta.blocks()
+        );
+        assert!(root.read("sparse-copy")? == expected, "sparse copy should have the same contents");
+
+        // Copying over an existing file turns the existing data in the holes
+        // into holes.
+        root.create_file("sparse-copy2", OpenFlags::O_WRONLY | OpenFlags::O_EXCL, &Permissions::from_mode(0o644))?
+            .write_all(&vec![b'x'; 3 * HOLE_SIZE as usize])?;
+        root.copy("sparse", "sparse-copy2", OpenFlags::O_WRONLY, None)?;
+        expected.extend(vec![b'x'; HOLE_SIZE as usize]);
+        assert!(
+            root.read("sparse-copy2")? == expected,
+            "sparse copy over an existing file should clear the existing data in holes"
+        );
+
+        // The read limit applies to the copy source.
+        let root = root.with_read_limit(Some(data.len() as u64 - 1));
+        tests_common::check_err(
+            &root.copy("b/c/file", "new-copy5", OpenFlags::O_WRONLY | OpenFlags::O_EXCL, None),
+            &Err::<(), _>(ErrorKind::LimitExceeded),
+        )?;
+        assert!(
+            root.resolve_nofollow("new-copy5").is_err(),
+            "copy over the read limit should not create the destination"
+        );
+        let root = root.with_read_limit(Some(data.len() as u64));
+        root.copy("b/c/file", "new-copy5", OpenFlags::O_WRONLY | OpenFlags::O_EXCL, None)?;
+        assert_eq!(root.read("new-copy5")?, data, "copy at the read limit should succeed");
+        Ok(())
+    }
+}
+
+root_op_tests! {
+    @rust-only fn reflink(root) {
+        let data = b"some file contents\n".repeat(1024);
+        root.create_file("b/c/file", OpenFlags::O_WRONLY | OpenFlags::O_TRUNC, &Permissions::from_mode(0o644))?
+            .write_all(&data)?;
+
+        // Whether FICLONE works depends on the filesystem used for the test
+        // directory, so we accept either outcome.
+        match root.reflink("b-file", "e/new-reflink1", &Permissions::from_mode(0o600), false) {
+            Ok(file) => {
+                assert_eq!(file.metadata()?.mode() & 0o7777, 0o600, "reflink should use the requested mode");
+                assert_eq!(root.read("b/c/d/e/new-reflink1")?, data, "reflink should clone the entire file");
+            }
+            Err(err) => {
+                assert_eq!(err.kind(), ErrorKind::NotSupported, "unexpected reflink error: {err:?}");
+                assert!(
+                    root.resolve_nofollow("b/c/d/e/new-reflink1").is_err(),
+                    "failed reflink should remove the destination"
+                );
+            }
+        }
+
+        // With copy_fallback, the operation always succeeds.
+        let file = root.reflink("b-file", "new-reflink2", &Permissions::from_mode(0o600), true)?;
+        assert_eq!(file.metadata()?.mode() & 0o7777, 0o600, "reflink should use the requested mode");
+        assert_eq!(root.read("new-reflink2")?, data, "reflink should copy the entire file");
+
+        tests_common::check_err(
+            &root.reflink("b/c/file", "new-reflink2", &Permissions::from_mode(0o600), true),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::EEXIST))),
+        )?;
+        tests_common::check_err(
+            &root.reflink("b/c", "new-reflink3", &Permissions::from_mode(0o600), true),
+            &Err::<(), _>(ErrorKind::InvalidArgument),
+        )?;
+        tests_common::check_err(
+            &root.reflink("nonexistent", "new-reflink3", &Permissions::from_mode(0o600), true),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::ENOENT))),
+        )?;
+        assert!(
+            root.resolve_nofollow("new-reflink3").is_err(),
+            "failed reflink should not create the destination"
+        );
+
+        // FIFOs are rejected without being opened (which would block).
+        root.create("b/c/fifo", &InodeType::Fifo(Permissions::from_mode(0o644)))?;
+        tests_common::check_err(
+            &root.reflink("b/c/fifo", "new-reflink3", &Permissions::from_mode(0o600), true),
+            &Err::<(), _>(ErrorKind::InvalidArgument),
+        )?;
+
+        // If the fallback copy fails, the destination is removed.
+        let root = root.with_read_limit(Some(data.len() as u64 - 1));
+        match root.reflink("b/c/file", "new-reflink4", &Permissions::from_mode(0o600), true) {
+            // FICLONE is supported, so the read limit does not apply.
+            Ok(_) => assert_eq!(
+                root.metadata_nofollow("new-reflink4")?.len(),
+                data.len() as u64,
+                "reflink should clone the entire file"
+            ),
+            Err(err) => {
+                assert_eq!(err.kind(), ErrorKind::LimitExceeded, "unexpected reflink error: {err:?}");
+                assert!(
+                    root.resolve_nofollow("new-reflink4").is_err(),
+                    "failed fallback copy should remove the destination"
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+root_op_tests! {
+    @rust-only fn rename_whiteout(root) {
+        if !RenameFlags::RENAME_WHITEOUT.is_supported() {
+            return Ok(());
+        }
+        let src_meta = root.metadata("b/c/file")?;
+
+        let res = root.rename("b/c/file", "b/c/new-file", RenameFlags::RENAME_WHITEOUT);
+        if !crate::can_mknod() {
+            // Without CAP_MKNOD we cannot create the whiteout.
+            tests_common::check_err(&res, &Err::<(), _>(ErrorKind::OsError(Some(libc::EPERM))))?;
+            return Ok(());
+        }
+        res?;
+
+        let moved_meta = root.metadata("b/c/new-file")?;
+        assert_eq!(
+            (moved_meta.dev(), moved_meta.ino()),
+            (src_meta.dev(), src_meta.ino()),
+            "RENAME_WHITEOUT should move the source to the destination"
+        );
+        let whiteout_meta = root.metadata_nofollow("b/c/file")?;
+        assert_eq!(
+            whiteout_meta.mode() & libc::S_IFMT,
+            libc::S_IFCHR,
+            "RENAME_WHITEOUT should create a character device at the source"
+        );
+        assert_eq!(
+            syscalls::devmajorminor(whiteout_meta.rdev()),
+            (0, 0),
+            "RENAME_WHITEOUT should create a 0:0 whiteout at the source"
+        );
+
+        // The kernel rejects RENAME_WHITEOUT|RENAME_EXCHANGE.
+        tests_common::check_err(
+            &root.rename("b/c/new-file", "a", RenameFlags::RENAME_WHITEOUT | RenameFlags::RENAME_EXCHANGE),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::EINVAL))),
+        )?;
+        Ok(())
+    }
+}
+
+root_op_tests! {
+    @rust-only fn canonicalize(root) {
+        for (path, expected) in [
+            ("b/c/file", "b/c/file"),
+            ("b-file", "b/c/file"),
+            ("e", "b/c/d/e"),
+            ("e/../../d/./e/", "b/c/d/e"),
+            ("a/../b/c/../c/d", "b/c/d"),
+            ("/", "."),
+            ("../../..", "."),
+            ("b/../..", "."),
+        ] {
+            let canonical = root.canonicalize(path)?;
+            assert_eq!(canonical, Path::new(expected), "canonicalize({path:?})");
+
+            // The canonical path must reference the same file.
+            let meta = root.metadata(path)?;
+            let canonical_meta = root.metadata_nofollow(&canonical)?;
+            assert_eq!(
+                (canonical_meta.dev(), canonical_meta.ino()),
+                (meta.dev(), meta.ino()),
+                "canonicalize({path:?}) = {canonical:?} should reference the same file"
+            );
+        }
+
+        for (path, expected_err) in [
+            ("nonexistent", libc::ENOENT),
+            ("a-fake1", libc::ENOENT),
+            ("loop/basic-loop1", libc::ELOOP),
+        ] {
+            tests_common::check_err(
+                &root.canonicalize(path),
+                &Err::<(), _>(ErrorKind::OsError(Some(expected_err))),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+root_op_tests! {
+    @rust-only fn relative_path_of(root) {
+        for (path, expected) in [
+            ("b/c/file", "b/c/file"),
+            ("b-file", "b/c/file"),
+            ("e", "b/c/d/e"),
+            ("/", "."),
+        ] {
+            let handle = root.resolve(path)?;
+            assert_eq!(
+                root.relative_path_of(&handle)?,
+                Path::new(expected),
+                "relative_path_of(resolve({path:?}))"
+            );
+        }
+        let handle = root.resolve_nofollow("b-file")?;
+        assert_eq!(root.relative_path_of(&handle)?, Path::new("b-file"));
+
+        // Handles from a sub-root are still beneath the root, but not the
+        // other way around.
+        let sub_root = root.sub_root("b")?;
+        let handle = sub_root.resolve("c/file")?;
+        assert_eq!(root.relative_path_of(&handle)?, Path::new("b/c/file"));
+        assert_eq!(sub_root.relative_path_of(&handle)?, Path::new("c/file"));
+        tests_common::check_err(
+            &sub_root.relative_path_of(&root.resolve("a")?),
+            &Err::<(), _>(ErrorKind::SafetyViolation),
+        )?;
+
+        // Deleted files have no path.
+        root.create("b/deleted", &InodeType::File(Permissions::from_mode(0o644)))?;
+        let handle = root.resolve("b/deleted")?;
+        root.remove_file("b/deleted")?;
+        tests_common::check_err(
+            &root.relative_path_of(&handle),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::ENOENT))),
+        )?;
+        Ok(())
+    }
+}
+
+root_op_tests! {
+    @rust-only fn sub_root(root) {
+        let sub = root.sub_root("b")?;
+        assert_eq!(sub.resolver_flags(), root.resolver_flags(), "sub-root resolver flags");
+        assert_eq!(sub.resolver_backend(), root.resolver_backend(), "sub-root resolver backend");
+
+        let meta = root.metadata("b/c/file")?;
+        for path in ["c/file", "/c/file", "../../c/file", "c/../../c/file"] {
+            let sub_meta = sub.metadata(path)?;
+            assert_eq!(
+                (sub_meta.dev(), sub_meta.ino()),
+                (meta.dev(), meta.ino()),
+                "sub-root resolve({path:?}) should be scoped to the sub-root"
+            );
+        }
+        // Paths outside of the sub-root are not visible.
+        tests_common::check_err(
+            &sub.resolve("b-file"),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::ENOENT))),
+        )?;
+
+        // Symlinks are followed when resolving the sub-root.
+        let sub = root.sub_root("e")?;
+        let meta = root.metadata("b/c/d/e")?;
+        let sub_meta = sub.metadata(".")?;
+        assert_eq!(
+            (sub_meta.dev(), sub_meta.ino()),
+            (meta.dev(), meta.ino()),
+            "sub_root(\"e\") should be rooted at b/c/d/e"
+        );
+
+        for (path, expected_err) in [
+            ("b/c/file", libc::ENOTDIR),
+            ("b-file", libc::ENOTDIR),
+            ("nonexistent", libc::ENOENT),
+            ("a-fake1", libc::ENOENT),
+        ] {
+            tests_common::check_err(
+                &root.sub_root(path),
+                &Err::<(), _>(ErrorKind::OsError(Some(expected_err))),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+root_op_tests! {
+    @rust-only fn tmpfile(root) {
+        let data = b"tmpfile contents";
+
+        let res = root.create_tmpfile("e", OpenFlags::O_RDWR, &Permissions::from_mode(0o640));
+        if let Err(ref err) = res {
+            if err.kind() == ErrorKind::NotSupported {
+                // The filesystem doesn't support O_TMPFILE.
+                return Ok(());
+            }
+        }
+        let mut file = res?;
+        file.write_all(data)?;
+        assert_eq!(file.metadata()?.nlink(), 0, "O_TMPFILE file should have no links");
+
+        root.link_tmpfile(&file, "e/new-tmpfile")?;
+        assert_eq!(file.metadata()?.nlink(), 1, "linked O_TMPFILE file should have a link");
+        assert_eq!(root.read("b/c/d/e/new-tmpfile")?, data, "linked O_TMPFILE file should have the written contents");
+        assert_eq!(
+            root.metadata("b/c/d/e/new-tmpfile")?.mode() & 0o7777,
+            0o640,
+            "O_TMPFILE file should have the requested mode"
+        );
+        tests_common::check_err(
+            &root.link_tmpfile(&file, "b/c/file"),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::EEXIST))),
+        )?;
+
+        // O_EXCL tmpfiles cannot be linked.
+        let file = root.create_tmpfile("b/c", OpenFlags::O_WRONLY | OpenFlags::O_EXCL, &Permissions::from_mode(0o600))?;
+        tests_common::check_err(
+            &root.link_tmpfile(&file, "b/c/new-tmpfile"),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::ENOENT))),
+        )?;
+
+        for flags in [OpenFlags::O_RDONLY, OpenFlags::O_RDWR | OpenFlags::O_CREAT, OpenFlags::O_WRONLY | OpenFlags::O_DIRECTORY] {
+            tests_common::check_err(
+                &root.create_tmpfile("b/c", flags, &Permissions::from_mode(0o600)),
+                &Err::<(), _>(ErrorKind::InvalidArgument),
+            )?;
+        }
+        tests_common::check_err(
+            &root.create_tmpfile("b/c/file", OpenFlags::O_RDWR, &Permissions::from_mode(0o600)),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::ENOTDIR))),
+        )?;
+        Ok(())
+    }
+}
+
+root_op_tests! {
+    @rust-only fn rename_at(root) {
+        let link_meta = root.metadata_nofollow("b-file")?;
+
+        // A plain rename only returns the destination, which is not followed
+        // if it is a symlink.
+        let (src, dst) = root.rename_at("b-file", "e/new-link", RenameFlags::empty())?;
+        assert!(src.is_none(), "plain rename_at should not return a source handle");
+        let dst_meta = dst.metadata()?;
+        assert_eq!(
+            (dst_meta.dev(), dst_meta.ino()),
+            (link_meta.dev(), link_meta.ino()),
+            "rename_at should return a handle to the renamed inode"
+        );
+        assert!(dst_meta.file_type().is_symlink(), "rename_at should not follow trailing symlinks");
+        assert!(!root.exists_nofollow("b-file")?, "rename_at should move the source");
+
+        tests_common::check_err(
+            &root.rename_at("nonexistent", "new-file", RenameFlags::empty()),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::ENOENT))),
+        )?;
+
+        if !RenameFlags::RENAME_EXCHANGE.is_supported() {
+            return Ok(());
+        }
+        let file_meta = root.metadata("b/c/file")?;
+        let dir_meta = root.metadata("a")?;
+        let (src, dst) = root.rename_at("b/c/file", "a", RenameFlags::RENAME_EXCHANGE)?;
+        let src_meta = src.expect("RENAME_EXCHANGE should return a source handle").metadata()?;
+        let dst_meta = dst.metadata()?;
+        assert_eq!(
+            (src_meta.dev(), src_meta.ino()),
+            (dir_meta.dev(), dir_meta.ino()),
+            "RENAME_EXCHANGE source handle should reference the old destination"
+        );
+        assert_eq!(
+            (dst_meta.dev(), dst_meta.ino()),
+            (file_meta.dev(), file_meta.ino()),
+            "RENAME_EXCHANGE destination handle should reference the old source"
+        );
+        Ok(())
+    }
+}
+
+root_op_tests! {
+    @rust-only fn mkdir_all_owned(root) {
+        let root_dir = root.as_fd().as_unsafe_path_unchecked()?;
+        let old_meta = fs::metadata(root_dir.join("b/c"))?;
+
+        let res = root.mkdir_all_owned("b/c/new1/new2", &Permissions::from_mode(0o711), 1000, 1001);
+        if syscalls::geteuid() != 0 {
+            // Without CAP_CHOWN we cannot give the directory away.
+            tests_common::check_err(&res, &Err::<(), _>(ErrorKind::OsError(Some(libc::EPERM))))?;
+            return Ok(());
+        }
+        let handle = res?;
+
+        for path in ["b/c/new1", "b/c/new1/new2"] {
+            let meta = fs::symlink_metadata(root_dir.join(path))?;
+            assert!(meta.is_dir(), "{path:?} should be a directory");
+            assert_eq!(
+                (meta.uid(), meta.gid()),
+                (1000, 1001),
+                "new directory {path:?} should be owned by the requested owner"
+            );
+        }
+        let meta = fs::metadata(root_dir.join("b/c"))?;
+        assert_eq!(
+            (meta.uid(), meta.gid(), meta.mode()),
+            (old_meta.uid(), old_meta.gid(), old_meta.mode()),
+            "existing directory should not be modified"
+        );
+        assert_eq!(
+            handle.metadata()?.ino(),
+            fs::metadata(root_dir.join("b/c/new1/new2"))?.ino(),
+            "returned handle should reference the final directory"
+        );
+
+        // Existing directories are left untouched.
+        root.mkdir_all_owned("b/c/new1/new2/new3", &Permissions::from_mode(0o711), 2000, 2001)?;
+        let meta = fs::metadata(root_dir.join("b/c/new1"))?;
+        assert_eq!((meta.uid(), meta.gid()), (1000, 1001));
+        let meta = fs::metadata(root_dir.join("b/c/new1/new2/new3"))?;
+        assert_eq!((meta.uid(), meta.gid()), (2000, 2001));
+        Ok(())
+    }
+}
+
+root_op_tests! {
+    @rust-only fn mkdir_all_with(root) {
+        let root_dir = root.as_fd().as_unsafe_path_unchecked()?;
+
+        let mut paths = Vec::new();
+        let handle = root.mkdir_all_with("e/new1/./new2//new3", |path| {
+            paths.push(path.to_path_buf());
+            match paths.len() {
+                1 => (Permissions::from_mode(0o700), Some((1000, 1001))),
+                2 => (Permissions::from_mode(0o1710), None),
+                _ => (Permissions::from_mode(0o755), Some((2000, 2001))),
+            }
+        })?;
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("e/new1"),
+                PathBuf::from("e/new1/new2"),
+                PathBuf::from("e/new1/new2/new3"),
+            ],
+            "mkdir_all_with should be called for each created component"
+        );
+
+        let (euid, egid) = (syscalls::geteuid(), rustix::process::getegid().as_raw());
+        for (path, mode, owner) in [
+            ("b/c/d/e/new1", 0o700, (1000, 1001)),
+            ("b/c/d/e/new1/new2", 0o1710, (euid, egid)),
+            ("b/c/d/e/new1/new2/new3", 0o755, (2000, 2001)),
+        ] {
+            let meta = fs::symlink_metadata(root_dir.join(path))?;
+            assert_eq!(
+                (meta.mode() & 0o7777, (meta.uid(), meta.gid())),
+                (mode, owner),
+                "mode and owner of {path:?}"
+            );
+        }
+        assert_eq!(
+            handle.metadata()?.ino(),
+            fs::metadata(root_dir.join("b/c/d/e/new1/new2/new3"))?.ino(),
+            "returned handle should reference the final directory"
+        );
+
+        // Existing components are not passed to the callback.
+        let mut paths = Vec::new();
+        root.mkdir_all_with("e/new1/new2", |path| {
+            paths.push(path.to_path_buf());
+            (Permissions::from_mode(0o755), None)
+        })?;
+        assert!(paths.is_empty(), "no components should have been created: {paths:?}");
+
+        // Invalid modes stop the creation.
+        let res = root.mkdir_all_with("b/c/bad1/bad2", |path| {
+            if path == Path::new("b/c/bad1") {
+                (Permissions::from_mode(0o755), None)
+            } else {
+                (Permissions::from_mode(0o4755), None)
+            }
+        });
+        tests_common::check_err(&res, &Err::<(), _>(ErrorKind::InvalidArgument))?;
+        assert!(root.exists("b/c/bad1")?, "first component should be created");
+        assert!(!root.exists("b/c/bad1/bad2")?, "invalid component should not be created");
+        Ok(())
+    }
+}
+
+root_op_tests! {
+    @rust-only fn access(root) {
+        root.create("acc", &InodeType::Directory(Permissions::from_mode(0o755)))?;
+        root.create_file("acc/file", OpenFlags::O_EXCL, &Permissions::from_mode(0o644))?;
+        root.create_file("acc/exec", OpenFlags::O_EXCL, &Permissions::from_mode(0o755))?;
+        root.create("acc/link", &InodeType::Symlink("file".into()))?;
+
+        assert!(root.access("acc/file", AccessMode::empty(), true)?, "F_OK should succeed");
+        assert!(root.access("acc/file", AccessMode::R_OK | AccessMode::W_OK, true)?, "file should be readable and writable");
+        assert!(root.access("acc/exec", AccessMode::X_OK, true)?, "file with execute bits should be executable");
+        assert!(root.access("acc", AccessMode::X_OK, true)?, "directory should be searchable");
+        // Even root cannot execute a file without any execute bits set.
+        assert!(!root.access("acc/file", AccessMode::X_OK, true)?, "file without execute bits should not be executable");
+
+        // Trailing symlinks are only followed if requested.
+        assert!(!root.access("acc/link", AccessMode::X_OK, true)?, "followed symlink should check the target");
+        assert!(root.access("acc/link", AccessMode::X_OK, false)?, "unfollowed symlink should check the symlink");
+
+        tests_common::check_err(
+            &root.access("acc/nonexistent", AccessMode::empty(), true),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::ENOENT))),
+        )?;
+        Ok(())
+    }
+}
+
+root_op_tests! {
+    @rust-only fn access_as(root) {
+        root.create("acc", &InodeType::Directory(Permissions::from_mode(0o755)))?;
+        root.create_file("acc/file", OpenFlags::O_EXCL, &Permissions::from_mode(0o644))?;
+        root.create("acc/link", &InodeType::Symlink("file".into()))?;
+
+        let root_dir = root.as_fd().as_unsafe_path_unchecked()?;
+        fs::set_permissions(root_dir.join("acc"), Permissions::from_mode(0o750))?;
+        fs::set_permissions(root_dir.join("acc/file"), Permissions::from_mode(0o640))?;
+
+        let meta = fs::metadata(root_dir.join("acc/file"))?;
+        let (owner, group) = (meta.uid(), meta.gid());
+        let other = owner.wrapping_add(1000);
+        let other_group = group.wrapping_add(1000);
+        let rw = AccessMode::R_OK | AccessMode::W_OK;
+
+        for (path, uid, gid, groups, mode, expected) in [
+            // Owner.
+            ("acc/file", owner, other_group, &[][..], rw, true),
+            ("acc/file", owner, other_group, &[], AccessMode::X_OK, false),
+            // Primary and supplementary groups.
+            ("acc/file", other, group, &[], AccessMode::R_OK, true),
+            ("acc/file", other, group, &[], rw, false),
+            ("acc/file", other, other_group, &[group], AccessMode::R_OK, true),
+            // Trailing symlinks are followed.
+            ("acc/link", other, other_group, &[group], AccessMode::R_OK, true),
+            ("acc/link", other, other_group, &[group], rw, false),
+            // Other users cannot search acc.
+            ("acc", other, other_group, &[], AccessMode::R_OK, false),
+            ("acc/file", other, other_group, &[], AccessMode::empty(), false),
+            ("acc/link", other, other_group, &[], AccessMode::empty(), false),
+            // Root can do anything except exec a file with no exec bits.
+            ("acc/file", 0, 0, &[], rw, true),
+            ("acc/file", 0, 0, &[], AccessMode::X_OK, false),
+            ("acc", 0, 0, &[], AccessMode::X_OK, true),
+        ] {
+            assert_eq!(
+                root.access_as(path, uid, gid, groups, mode)?,
+                expected,
+                "access_as({path:?}, {uid}, {gid}, {groups:?}, {mode:?})"
+            );
+        }
+
+        tests_common::check_err(
+            &root.access_as("acc/nonexistent", owner, group, &[], AccessMode::empty()),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::ENOENT))),
+        )?;
+        Ok(())
+    }
+}
+
+root_op_tests! {
+    @rust-only fn reopen_policy(root) {
+        assert_eq!(root.reopen_policy(), ReopenPolicy::default());
+        tests_common::check_err(
+            &root.resolve_nofollow("b-file")?.reopen(OpenFlags::O_RDONLY),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::ELOOP))),
+        )?;
+
+        let policy = ReopenPolicy::default().with_verify_inode(true);
+        let root = root.with_reopen_policy(policy);
+        assert_eq!(root.reopen_policy(), policy);
+
+        let link = root.resolve_nofollow("b-file")?;
+        assert_eq!(link.reopen_policy(), policy, "handle should inherit root reopen policy");
+        // Symlinks can never be re-opened.
+        tests_common::check_err(
+            &link.reopen(OpenFlags::O_RDONLY),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::ELOOP))),
+        )?;
+        // O_PATH re-opens are rejected regardless of the policy.
+        tests_common::check_err(
+            &link.reopen(OpenFlags::O_PATH),
+            &Err::<(), _>(ErrorKind::InvalidArgument),
+        )?;
+
+        let file = root.resolve("b/c/file")?;
+        assert_eq!(file.try_clone()?.reopen_policy(), policy);
+        assert_eq!(file.as_ref().reopen_policy(), policy);
+        file.reopen(OpenFlags::O_RDONLY)?;
+        root.open_subpath("b/c/file", OpenFlags::O_RDONLY)?;
+        Ok(())
+    }
+}
+
+root_op_tests! {
+    @rust-only fn enter(root) {
+        let root_dir = root.as_fd().as_unsafe_path_unchecked()?;
+        utils::in_private_cwd(|| {
+            let old_cwd = syscalls::getcwd()?;
+            assert_ne!(old_cwd, root_dir, "cwd should not start inside root");
+
+            {
+                let _guard = root.enter()?;
+                assert_eq!(syscalls::getcwd()?, root_dir, "cwd should be the root after enter");
+                // Relative paths should now be relative to the root.
+                File::open("b/c/file")?;
+            }
+
+            assert_eq!(syscalls::getcwd()?, old_cwd, "cwd should be restored when guard is dropped");
+            Ok(())
+        })
+    }
+}
+
+root_op_tests! {
+    @rust-only fn enter_panic(root) {
+        let root_dir = root.as_fd().as_unsafe_path_unchecked()?;
+        utils::in_private_cwd(|| {
+            let old_cwd = syscalls::getcwd()?;
+
+            let res = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+                let _guard = root.enter().expect("enter root");
+                assert_eq!(
+                    syscalls::getcwd().expect("getcwd"),
+                    root_dir,
+                    "cwd should be the root after enter"
+                );
+                panic!("panic while inside root");
+            }));
+            assert!(res.is_err(), "closure should have panicked");
+
+            assert_eq!(syscalls::getcwd()?, old_cwd, "cwd should be restored after panic");
+            Ok(())
+        })
+    }
+}
+
+root_op_tests! {
+    @rust-only fn resolve_both(root) {
+        let (link, target) = root.resolve_both("b-file")?;
+        assert!(
+            link.as_fd().metadata()?.is_symlink(),
+            "resolve_both should return the symlink itself"
+        );
+        let target = target.expect("resolve_both of a symlink should return a target");
+        assert_eq!(
+            target.as_fd().as_unsafe_path_unchecked()?,
+            root.resolve("b/c/file")?.as_fd().as_unsafe_path_unchecked()?,
+            "resolve_both symlink target should match resolve"
+        );
+
+        let (file, target) = root.resolve_both("b/c/file")?;
+        assert_eq!(
+            file.as_fd().metadata()?.mode() & libc::S_IFMT,
+            libc::S_IFREG,
+            "resolve_both of a regular file should return the file"
+        );
+        assert!(
+            target.is_none(),
+            "resolve_both of a regular file should not return a target"
+        );
+
+        // Symlink targets inside the parent directory, outside of it, and
+        // absolute symlinks must all be resolved inside the root.
+        root.create("b/c/rel-link", &InodeType::Symlink("d/../file".into()))?;
+        for (path, want) in [
+            ("b/c/rel-link", "b/c/file"),
+            ("link1/target_rel", "target"),
+            ("link1/target_abs", "target"),
+            ("link3/target_rel", "target"),
+            ("e", "b/c/d/e"),
+            ("root-link3", "."),
+        ] {
+            let (link, target) = root.resolve_both(path)?;
+            assert_eq!(
+                link.as_fd().as_unsafe_path_unchecked()?,
+                root.resolve_nofollow(path)?.as_fd().as_unsafe_path_unchecked()?,
+                "resolve_both({path:?}) symlink should match resolve_nofollow"
+            );
+            assert_eq!(
+                target
+                    .expect("resolve_both of a symlink should return a target")
+                    .as_fd()
+                    .as_unsafe_path_unchecked()?,
+                root.resolve(want)?.as_fd().as_unsafe_path_unchecked()?,
+                "resolve_both({path:?}) symlink target should match resolve"
+            );
+        }
+
+        // Trailing "." and ".." components are never symlinks.
+        for (path, want) in [("e/..", "b/c/d"), ("b/c/.", "b/c"), ("e/", "b/c/d/e")] {
+            let (dir, target) = root.resolve_both(path)?;
+            assert_eq!(
+                dir.as_fd().as_unsafe_path_unchecked()?,
+                root.resolve(want)?.as_fd().as_unsafe_path_unchecked()?,
+                "resolve_both({path:?}) should match resolve"
+            );
+            assert!(target.is_none(), "resolve_both({path:?}) should not return a target");
+        }
+        Ok(())
+    }
+}
+
+root_op_tests! {
+    @rust-only fn resolve_both_dangling(root) {
+        // A dangling symlink can be resolved with resolve_nofollow but the
+        // target lookup must fail.
+        tests_common::check_err(
+            &root.resolve_both("link3/deep_dangling1"),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::ENOENT))),
+        )
+    }
+}
+
+root_op_tests! {
+    @rust-only fn create_lockfile(root) {
+        let perm = Permissions::from_mode(0o600);
+
+        let lockfile = root.create_lockfile("b/c/lock", &perm)?;
+        root.resolve("b/c/lock").context("lock file should exist")?;
+
+        tests_common::check_err(
+            &root.create_lockfile("b/c/lock", &perm),
+            &Err::<(), _>(ErrorKind::AlreadyHeld),
+        )?;
+
+        // The file should be flock(2)ed.
+        let other = root.resolve("b/c/lock")?.reopen(OpenFlags::O_RDONLY)?;
+        assert_eq!(
+            rustix_fs::flock(&other, FlockOperation::NonBlockingLockExclusive),
+            Err(rustix::io::Errno::WOULDBLOCK),
+            "lock file should be locked while the guard is alive"
+        );
+
+        drop(lockfile);
+        tests_common::check_err(
+            &root.resolve("b/c/lock"),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::ENOENT))),
+        )?;
+
+        // Now we can take the lock again.
+        root.create_lockfile("b/c/lock", &perm)?.release()?;
+        tests_common::check_err(
+            &root.resolve("b/c/lock"),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::ENOENT))),
+        )
+    }
+}
+
+root_op_tests! {
+    @rust-only fn create_lockfile_replaced(root) {
+        let lockfile = root.create_lockfile("b/c/lock", &Permissions::from_mode(0o600))?;
+
+        // If the lock file is replaced, the new file must not be removed.
+        root.rename("b/c/file", "b/c/lock", RenameFlags::empty())?;
+        lockfile.release()?;
+        root.resolve("b/c/lock")
+            .context("replaced lock file should not have been removed")?;
+        Ok(())
+    }
+}
+
+root_op_tests! {
+    @rust-only fn create_in(root) {
+        let dir = root.resolve("b/c")?;
+
+        root.create_in(&dir, "file2", &InodeType::File(Permissions::from_mode(0o600)))?;
+        root.create_in(&dir, "dir", &InodeType::Directory(Permissions::from_mode(0o711)))?;
+        root.create_in(&dir, "symlink", &InodeType::Symlink("/b/c/file".into()))?;
+        root.create_in(&dir, "hardlink", &InodeType::Hardlink("b-file".into()))?;
+        root.create_in(&dir, "fifo", &InodeType::Fifo(Permissions::from_mode(0o644)))?;
+
+        for (path, mode) in [
+            ("b/c/file2", libc::S_IFREG | 0o600),
+            ("b/c/dir", libc::S_IFDIR | 0o711),
+            ("b/c/symlink", libc::S_IFLNK | 0o777),
+            ("b/c/hardlink", libc::S_IFLNK | 0o777),
+            ("b/c/fifo", libc::S_IFIFO | 0o644),
+        ] {
+            let meta = root.resolve_nofollow(path)?.as_fd().metadata()?;
+            // The umask may have cleared some of the group/other bits.
+            assert_eq!(
+                meta.mode() & !0o077,
+                mode & !0o077,
+                "create_in should have created {path:?} with the right mode"
+            );
         }
-    };
+        assert_eq!(
+            root.readlink("b/c/symlink")?,
+            Path::new("/b/c/file"),
+            "create_in symlink should have the right target"
+        );
+        Ok(())
+    }
+}
 
-    ($(#[cfg($ignore_meta:meta)])* @impl remove_all_racing $test_name:ident ( $($args:tt)* ) => $expected_result:expr) => {
-        root_op_tests! {
-            $(#[cfg_attr(not($ignore_meta), ignore)])*
-            @impl-race remove_all_racing $test_name( $($args)* ) => $expected_result
-        }
-    };
+root_op_tests! {
+    @rust-only fn create_in_exist(root) {
+        let dir = root.resolve("b/c")?;
+        tests_common::check_err(
+            &root.create_in(&dir, "file", &InodeType::File(Permissions::from_mode(0o644))),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::EEXIST))),
+        )
+    }
+}
 
-    // root_tests!{
-    //      ...
-    // }
-    ($($(#[cfg($ignore_meta:meta)])* $test_name:ident: $op_name:ident ( $($args:tt)* ) => $expected_result:expr );+ $(;)?) => {
-        paste::paste! {
-            $(
-                root_op_tests!{
-                    $(#[cfg($ignore_meta)])*
-                    @impl $op_name [<$op_name _ $test_name>]( $($args)* ) => $expected_result
-                }
-            )*
-        }
-    };
+root_op_tests! {
+    @rust-only fn create_in_outside_root(root) {
+        let other_dir = tests_common::create_basic_tree()?;
+        let other_root = Root::open(&other_dir)?;
+        let dir = other_root.resolve("b/c")?;
+        tests_common::check_err(
+            &root.create_in(&dir, "file2", &InodeType::File(Permissions::from_mode(0o644))),
+            &Err::<(), _>(ErrorKind::SafetyViolation),
+        )?;
+        assert!(
+            !other_root.exists("b/c/file2")?,
+            "create_in with a handle outside the root must not create anything"
+        );
+        Ok(())
+    }
 }
 
 root_op_tests! {
-    plain: mkfile("abc", 0o444) => Ok(("abc", libc::S_IFREG | 0o444));
-    exist_file: mkfile("b/c/file", 0o444) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
-    exist_dir: mkfile("a", 0o444) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
-    exist_symlink: mkfile("b-file", 0o444) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
-    exist_dangling_symlink: mkfile("a-fake1", 0o444) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
+    @rust-only fn create_in_bad_name(root) {
+        let dir = root.resolve("b")?;
+        for name in ["", ".", "..", "c/file2", "../a", "/a", "c/"] {
+            tests_common::check_err(
+                &root.create_in(&dir, name, &InodeType::Directory(Permissions::from_mode(0o755))),
+                &Err::<(), _>(ErrorKind::InvalidArgument),
+            )
+            .with_context(|| format!("create_in with name {name:?}"))?;
+        }
+        Ok(())
+    }
+}
 
-    plain: mkdir("abc", 0o311) => Ok(("abc", libc::S_IFDIR | 0o311));
-    exist_file: mkdir("b/c/file", 0o444) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
-    exist_dir: mkdir("a", 0o444) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
-    exist_symlink: mkdir("b-file", 0o444) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
-    exist_dangling_symlink: mkdir("a-fake1", 0o444) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
+#[test]
+fn root_read_procfs_zero_size() -> Result<(), Error> {
+    let root = Root::open("/proc")?;
+    assert_eq!(
+        root.resolve("self/status")?.as_fd().metadata()?.size(),
+        0,
+        "procfs files should have a zero st_size"
+    );
+    let status = root.read_to_string("self/status")?;
+    assert!(
+        status.contains("Pid:"),
+        "read_to_string should read entire procfs file: {status:?}"
+    );
+    Ok(())
+}
 
-    plain: symlink("abc", "/NEWLINK") => Ok(("abc", libc::S_IFLNK | 0o777));
-    exist_file: symlink("b/c/file", "/NEWLINK") => Err(ErrorKind::OsError(Some(libc::EEXIST)));
-    exist_dir: symlink("a", "/NEWLINK") => Err(ErrorKind::OsError(Some(libc::EEXIST)));
-    exist_symlink: symlink("b-file", "/NEWLINK") => Err(ErrorKind::OsError(Some(libc::EEXIST)));
-    exist_dangling_symlink: symlink("a-fake1", "/NEWLINK") => Err(ErrorKind::OsError(Some(libc::EEXIST)));
+#[test]
+#[cfg_attr(not(feature = "_test_as_root"), ignore)]
+fn root_mount_is_readonly() -> Result<(), Error> {
+    let root_dir = tests_common::create_basic_tree()?;
+    let root = Root::open(&root_dir)?;
+    assert!(
+        !root.mount_is_readonly()?,
+        "tmpdir mount should not be read-only"
+    );
+
+    tests_common::in_mnt_ns(|| {
+        tests_common::mount(
+            &root_dir,
+            MountType::Bind {
+                src: root_dir.path().to_path_buf(),
+            },
+        )?;
+        rustix_mount::mount_remount(root_dir.path(), MountFlags::BIND | MountFlags::RDONLY, "")
+            .context("remount root bind-mount read-only")?;
+
+        let ro_root = Root::open(&root_dir)?;
+        assert!(
+            ro_root.mount_is_readonly()?,
+            "read-only bind-mount should be read-only"
+        );
 
-    plain: hardlink("abc", "b/c/file") => Ok(("abc", libc::S_IFREG | 0o644));
-    exist_file: hardlink("b/c/file", "/b/c/file") => Err(ErrorKind::OsError(Some(libc::EEXIST)));
-    exist_dir: hardlink("a", "/b/c/file") => Err(ErrorKind::OsError(Some(libc::EEXIST)));
-    exist_symlink: hardlink("b-file", "/b/c/file") => Err(ErrorKind::OsError(Some(libc::EEXIST)));
-    exist_dangling_symlink: hardlink("a-fake1", "/b/c/file") => Err(ErrorKind::OsError(Some(libc::EEXIST)));
-    to_symlink: hardlink("link", "b-file") => Ok(("link", libc::S_IFLNK | 0o777));
-    to_dangling_symlink: hardlink("link", "a-fake1") => Ok(("link", libc::S_IFLNK | 0o777));
+        let res = ro_root.create("b/c/file2", &InodeType::File(Permissions::from_mode(0o644)));
+        tests_common::check_err(&res, &Err::<(), _>(ErrorKind::OsError(Some(libc::EROFS))))?;
+        let err = res.expect_err("create on read-only mount should fail");
+        assert!(
+            err.to_string().contains("mount is read-only"),
+            "EROFS error should mention read-only mount: {err}"
+        );
+        Ok(())
+    })
+}
 
-    plain: mkfifo("abc", 0o222) => Ok(("abc", libc::S_IFIFO | 0o222));
-    exist_file: mkfifo("b/c/file", 0o444) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
-    exist_dir: mkfifo("a", 0o444) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
-    exist_symlink: mkfifo("b-file", 0o444) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
-    exist_dangling_symlink: mkfifo("a-fake1", 0o444) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
+#[test]
+#[cfg_attr(not(feature = "_test_as_root"), ignore)]
+fn root_pin_mount() -> Result<(), Error> {
+    let root_dir = tests_common::create_basic_tree()?;
 
-    plain: mkblk("abc", 0o001, 123, 456) => Ok(("abc", libc::S_IFBLK | 0o001));
-    exist_file: mkblk("b/c/file", 0o444, 123, 456) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
-    exist_dir: mkblk("a", 0o444, 123, 456) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
-    exist_symlink: mkblk("b-file", 0o444, 123, 456) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
-    exist_dangling_symlink: mkblk("a-fake1", 0o444, 123, 456) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
+    tests_common::in_mnt_ns(|| {
+        let root = Root::open(&root_dir)?.with_resolver_flags(ResolverFlags::NO_SYMLINKS);
+        let pinned = root.pin_mount()?;
+        assert_eq!(
+            pinned.resolver_flags(),
+            ResolverFlags::NO_SYMLINKS,
+            "pinned root should have the same configuration"
+        );
 
-    plain: mkchar("abc", 0o010, 111, 222) => Ok(("abc", libc::S_IFCHR | 0o010));
-    exist_file: mkchar("b/c/file", 0o444, 123, 456) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
-    exist_dir: mkchar("a", 0o444, 123, 456) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
-    exist_symlink: mkchar("b-file", 0o444, 123, 456) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
-    exist_dangling_symlink: mkchar("a-fake1", 0o444, 123, 456) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
+        // Mounting on top of a directory inside the root must not affect the
+        // pinned root.
+        tests_common::mount(root_dir.path().join("b"), MountType::Tmpfs)?;
+        tests_common::check_err(
+            &root.resolve("b/c/file"),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::ENOENT))),
+        )?;
+        pinned.resolve("b/c/file")?;
+
+        // Files created through the pinned root are visible through the
+        // original mount.
+        pinned.create_file("a/new", OpenFlags::O_WRONLY, &Permissions::from_mode(0o644))?;
+        root.resolve("a/new")?;
+        Ok(())
+    })
+}
 
-    plain: create_file("abc", O_RDONLY, 0o100) => Ok("abc");
-    oexcl_plain: create_file("abc", O_EXCL|O_RDONLY, 0o100) => Ok("abc");
-    exist: create_file("b/c/file", O_RDONLY, 0o100) => Ok("b/c/file");
-    oexcl_exist: create_file("a", O_EXCL|O_RDONLY, 0o100) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
-    exist_dir: create_file("b/c/d", O_RDONLY, 0o100) => Err(ErrorKind::OsError(Some(libc::EISDIR)));
-    symlink: create_file("b-file", O_RDONLY, 0o100) => Err(ErrorKind::OsError(Some(libc::ELOOP)));
-    oexcl_symlink: create_file("b-file", O_EXCL|O_RDONLY, 0o100) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
-    oexcl_dangling_symlink: create_file("a-fake1", O_EXCL|O_RDONLY, 0o100) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
+#[test]
+#[cfg_attr(not(feature = "_test_as_root"), ignore)]
+fn root_walk_group_by_filesystem() -> Result<(), Error> {
+    let root_dir = tests_common::create_basic_tree()?;
+
+    tests_common::in_mnt_ns(|| {
+        tests_common::mount(root_dir.path().join("b/c/d"), MountType::Tmpfs)?;
+        let root = Root::open(&root_dir)?;
+        root.create_file(
+            "b/c/d/tmpfs-file",
+            OpenFlags::O_WRONLY,
+            &Permissions::from_mode(0o644),
+        )?;
+
+        let root_dev = root.metadata(".")?.dev();
+        let tmpfs_dev = root.metadata("b/c/d")?.dev();
+        assert_ne!(
+            root_dev, tmpfs_dev,
+            "tmpfs should be a different filesystem"
+        );
 
-    ocreat: open_subpath("abc", O_CREAT|O_RDONLY) => Err(ErrorKind::InvalidArgument);
-    oexcl: open_subpath("abc", O_EXCL|O_RDONLY) => Err(ErrorKind::InvalidArgument);
-    ocreat_oexcl: open_subpath("abc", O_CREAT|O_EXCL|O_RDONLY) => Err(ErrorKind::InvalidArgument);
-    enoent: open_subpath("abc", O_RDONLY) => Err(ErrorKind::OsError(Some(libc::ENOENT)));
-    exist_file: open_subpath("b/c/file", O_RDONLY) => Ok("b/c/file");
-    exist_dir: open_subpath("b/c/d", O_RDONLY) => Ok("b/c/d");
-    symlink: open_subpath("b-file", O_RDONLY) => Ok("b/c/file");
-    ocreat_symlink: open_subpath("b-file", O_CREAT|O_RDONLY) => Err(ErrorKind::InvalidArgument);
-    nofollow_symlink: open_subpath("b-file", O_NOFOLLOW|O_RDONLY) => Err(ErrorKind::OsError(Some(libc::ELOOP)));
-    opath_nofollow_symlink: open_subpath("b-file", O_PATH|O_NOFOLLOW) => Ok("b-file");
-    nofollow_odir_symlink:  open_subpath("b-file", O_DIRECTORY|O_NOFOLLOW) => Err(ErrorKind::OsError(Some(libc::ENOTDIR)));
-    opath_nofollow_odir_symlink:  open_subpath("b-file", O_DIRECTORY|O_PATH|O_NOFOLLOW) => Err(ErrorKind::OsError(Some(libc::ENOTDIR)));
-    dangling_symlink: open_subpath("a-fake1",O_RDONLY) => Err(ErrorKind::OsError(Some(libc::ENOENT)));
-    ocreat_dangling_symlink: open_subpath("a-fake1", O_CREAT|O_RDONLY) => Err(ErrorKind::InvalidArgument);
+        let groups = root
+            .walk("b")?
+            .group_by_filesystem()?
+            .into_iter()
+            .map(|(dev, entries)| {
+                let mut paths = entries
+                    .iter()
+                    .map(|entry| entry.path().to_path_buf())
+                    .collect::<Vec<_>>();
+                paths.sort();
+                (dev, paths)
+            })
+            .collect::<HashMap<_, _>>();
+        assert_eq!(
+            groups,
+            HashMap::from([
+                (
+                    root_dev,
+                    vec![
+                        PathBuf::from("b/c"),
+                        "b/c/file".into(),
+                        "b/fifo".into(),
+                        "b/sock".into(),
+                    ]
+                ),
+                (tmpfs_dev, vec!["b/c/d".into(), "b/c/d/tmpfs-file".into()]),
+            ]),
+            "walk entries grouped by filesystem"
+        );
+        Ok(())
+    })
+}
 
-    empty_dir: remove_dir("a") => Ok(());
-    empty_dir: remove_file("a") => Err(ErrorKind::OsError(Some(libc::EISDIR)));
-    empty_dir: remove_all("a") => Ok(());
-    nonempty_dir: remove_dir("b") => Err(ErrorKind::OsError(Some(libc::ENOTEMPTY)));
-    nonempty_dir: remove_file("b") => Err(ErrorKind::OsError(Some(libc::EISDIR)));
-    nonempty_dir: remove_all("b") => Ok(());
-    deep_dir: remove_dir("deep-rmdir") => Err(ErrorKind::OsError(Some(libc::ENOTEMPTY)));
-    deep_dir: remove_file("deep-rmdir") => Err(ErrorKind::OsError(Some(libc::EISDIR)));
-    deep_dir: remove_all("deep-rmdir") => Ok(());
-    file: remove_dir("b/c/file") => Err(ErrorKind::OsError(Some(libc::ENOTDIR)));
-    file: remove_file("b/c/file") => Ok(());
-    file: remove_all("b/c/file") => Ok(());
-    fifo: remove_dir("b/fifo") => Err(ErrorKind::OsError(Some(libc::ENOTDIR)));
-    fifo: remove_file("b/fifo") => Ok(());
-    fifo: remove_all("b/fifo") => Ok(());
-    sock: remove_dir("b/sock") => Err(ErrorKind::OsError(Some(libc::ENOTDIR)));
-    sock: remove_file("b/sock") => Ok(());
-    sock: remove_all("b/sock") => Ok(());
-    enoent: remove_dir("abc") => Err(ErrorKind::OsError(Some(libc::ENOENT)));
-    enoent: remove_file("abc") => Err(ErrorKind::OsError(Some(libc::ENOENT)));
-    enoent: remove_all("abc") => Ok(());
-    symlink: remove_dir("b-file") => Err(ErrorKind::OsError(Some(libc::ENOTDIR)));
-    symlink: remove_file("b-file") => Ok(());
-    symlink: remove_all("b-file") => Ok(());
-    dangling_symlink: remove_dir("a-fake1") => Err(ErrorKind::OsError(Some(libc::ENOTDIR)));
-    dangling_symlink: remove_file("a-fake1") => Ok(());
-    dangling_symlink: remove_all("a-fake1") => Ok(());
-    dir_trailing_slash: remove_dir("a/") => Err(ErrorKind::InvalidArgument);
-    dir_trailing_slash: remove_file("a/") => Err(ErrorKind::InvalidArgument);
-    dir_trailing_slash: remove_all("a/") => Err(ErrorKind::InvalidArgument);
-    file_trailing_slash: remove_dir("b/c/file/") => Err(ErrorKind::InvalidArgument);
-    file_trailing_slash: remove_file("b/c/file/") => Err(ErrorKind::InvalidArgument);
-    file_trailing_slash: remove_all("b/c/file/") => Err(ErrorKind::InvalidArgument);
+#[test]
+#[cfg_attr(not(feature = "_test_as_root"), ignore)]
+fn root_find_hardlinks_bind_loop() -> Result<(), Error> {
+    let root_dir = tests_common::create_basic_tree()?;
+
+    tests_common::in_mnt_ns(|| {
+        // Create a loop within the same filesystem.
+        tests_common::mount(
+            root_dir.path().join("b/c/d"),
+            MountType::Bind {
+                src: root_dir.path().join("b"),
+            },
+        )?;
+        let root = Root::open(&root_dir)?;
+
+        // The target is outside of the search root, so the scan will not stop
+        // early by finding every link.
+        root.create_file(
+            "hl-target",
+            OpenFlags::O_EXCL,
+            &Permissions::from_mode(0o644),
+        )?;
+        root.create("b/hl-link", &InodeType::Hardlink("hl-target".into()))?;
 
-    plain: rename("a", "aa", RenameFlags::empty()) => Ok(());
-    noreplace_plain: rename("a", "aa", RenameFlags::RENAME_NOREPLACE) => Ok(());
-    noreplace_symlink: rename("a", "b-file", RenameFlags::RENAME_NOREPLACE) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
-    noreplace_dangling_symlink: rename("a", "a-fake1", RenameFlags::RENAME_NOREPLACE) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
-    noreplace_eexist: rename("a", "e", RenameFlags::RENAME_NOREPLACE) => Err(ErrorKind::OsError(Some(libc::EEXIST)));
-    whiteout_plain: rename("a", "aa", RenameFlags::RENAME_WHITEOUT) => Ok(());
-    exchange_plain: rename("a", "e", RenameFlags::RENAME_EXCHANGE) => Ok(());
-    exchange_enoent: rename("a", "aa", RenameFlags::RENAME_EXCHANGE) => Err(ErrorKind::OsError(Some(libc::ENOENT)));
+        assert_eq!(
+            root.find_hardlinks("hl-target", "b")?,
+            vec![PathBuf::from("b/hl-link")],
+            "find_hardlinks should not scan bind-mount loops"
+        );
+        Ok(())
+    })
+}
 
-    invalid_mode_type: mkdir_all("foo", libc::S_IFDIR | 0o777) => Err(ErrorKind::InvalidArgument);
-    invalid_mode_garbage: mkdir_all("foo", 0o12340777) => Err(ErrorKind::InvalidArgument);
-    invalid_mode_setuid: mkdir_all("foo", libc::S_ISUID | 0o777) => Err(ErrorKind::InvalidArgument);
-    invalid_mode_setgid: mkdir_all("foo", libc::S_ISGID | 0o777) => Err(ErrorKind::InvalidArgument);
-    existing: mkdir_all("a", 0o711) => Ok(());
-    basic: mkdir_all("a/b/c/d/e/f/g/h/i/j", 0o711) => Ok(());
-    sticky: mkdir_all("foo", libc::S_ISVTX | 0o711) => Ok(());
-    dotdot_in_nonexisting: mkdir_all("a/b/c/d/e/f/g/h/i/j/k/../lmnop", 0o711) => Err(ErrorKind::OsError(Some(libc::ENOENT)));
-    dotdot_in_existing: mkdir_all("b/c/../c/./d/e/f/g/h", 0o711) => Ok(());
-    dotdot_after_symlink: mkdir_all("e/../dd/ee/ff", 0o711) => Ok(());
-    // Check that trying to create under a file fails.
-    nondir_trailing: mkdir_all("b/c/file", 0o711) => Err(ErrorKind::OsError(Some(libc::ENOTDIR)));
-    nondir_dotdot: mkdir_all("b/c/file/../d", 0o711) => Err(ErrorKind::OsError(Some(libc::ENOTDIR)));
-    nondir_subdir: mkdir_all("b/c/file/subdir", 0o711) => Err(ErrorKind::OsError(Some(libc::ENOTDIR)));
-    nondir_symlink_trailing: mkdir_all("b-file", 0o711) => Err(ErrorKind::OsError(Some(libc::ENOTDIR)));
-    nondir_symlink_dotdot: mkdir_all("b-file/../d", 0o711) => Err(ErrorKind::OsError(Some(libc::ENOTDIR)));
-    nondir_symlink_subdir: mkdir_all("b-file/subdir", 0o711) => Err(ErrorKind::OsError(Some(libc::ENOTDIR)));
-    // Dangling symlinks are not followed.
-    dangling1_trailing: mkdir_all("a-fake1", 0o711) => Err(ErrorKind::OsError(Some(libc::ENOTDIR)));
-    dangling1_basic: mkdir_all("a-fake1/foo", 0o711) => Err(ErrorKind::OsError(Some(libc::ENOTDIR)));
-    dangling1_dotdot: mkdir_all("a-fake1/../bar/baz", 0o711) => Err(ErrorKind::OsError(Some(libc::ENOENT)));
-    dangling2_trailing: mkdir_all("a-fake2", 0o711) => Err(ErrorKind::OsError(Some(libc::ENOTDIR)));
-    dangling2_basic: mkdir_all("a-fake2/foo", 0o711) => Err(ErrorKind::OsError(Some(libc::ENOTDIR)));
-    dangling2_dotdot: mkdir_all("a-fake2/../bar/baz", 0o711) => Err(ErrorKind::OsError(Some(libc::ENOENT)));
-    dangling3_trailing: mkdir_all("a-fake3", 0o711) => Err(ErrorKind::OsError(Some(libc::ENOTDIR)));
-    dangling3_basic: mkdir_all("a-fake3/foo", 0o711) => Err(ErrorKind::OsError(Some(libc::ENOTDIR)));
-    dangling3_dotdot: mkdir_all("a-fake3/../bar/baz", 0o711) => Err(ErrorKind::OsError(Some(libc::ENOENT)));
-    // Non-lexical symlinks should work.
-    nonlexical_basic: mkdir_all("target/foo", 0o711) => Ok(());
-    nonlexical_level1_abs: mkdir_all("link1/target_abs/foo", 0o711) => Ok(());
-    nonlexical_level1_rel: mkdir_all("link1/target_rel/foo", 0o711) => Ok(());
-    nonlexical_level2_abs_abs: mkdir_all("link2/link1_abs/target_abs/foo", 0o711) => Ok(());
-    nonlexical_level2_abs_rel: mkdir_all("link2/link1_abs/target_rel/foo", 0o711) => Ok(());
-    nonlexical_level2_abs_open: mkdir_all("link2/link1_abs/../target/foo", 0o711) => Ok(());
-    nonlexical_level2_rel_abs: mkdir_all("link2/link1_rel/target_abs/foo", 0o711) => Ok(());
-    nonlexical_level2_rel_rel: mkdir_all("link2/link1_rel/target_rel/foo", 0o711) => Ok(());
-    nonlexical_level2_rel_open: mkdir_all("link2/link1_rel/../target/foo", 0o711) => Ok(());
-    nonlexical_level3_abs: mkdir_all("link3/target_abs/foo", 0o711) => Ok(());
-    nonlexical_level3_rel: mkdir_all("link3/target_rel/foo", 0o711) => Ok(());
-    // But really tricky dangling symlinks should fail.
-    dangling_tricky1_trailing: mkdir_all("link3/deep_dangling1", 0o711) => Err(ErrorKind::OsError(Some(libc::ENOTDIR)));
-    dangling_tricky1_basic: mkdir_all("link3/deep_dangling1/foo", 0o711) => Err(ErrorKind::OsError(Some(libc::ENOTDIR)));
-    dangling_tricky1_dotdot: mkdir_all("link3/deep_dangling1/../bar", 0o711) => Err(ErrorKind::OsError(Some(libc::ENOENT)));
-    dangling_tricky2_trailing: mkdir_all("link3/deep_dangling2", 0o711) => Err(ErrorKind::OsError(Some(libc::ENOTDIR)));
-    dangling_tricky2_basic: mkdir_all("link3/deep_dangling2/foo", 0o711) => Err(ErrorKind::OsError(Some(libc::ENOTDIR)));
-    dangling_tricky2_dotdot: mkdir_all("link3/deep_dangling2/../bar", 0o711) => Err(ErrorKind::OsError(Some(libc::ENOENT)));
-    // And trying to mkdir inside a loop should fail.
-    loop_trailing: mkdir_all("loop/link", 0o711) => Err(ErrorKind::OsError(Some(libc::ELOOP)));
-    loop_basic: mkdir_all("loop/link/foo", 0o711) => Err(ErrorKind::OsError(Some(libc::ELOOP)));
-    loop_dotdot: mkdir_all("loop/link/../foo", 0o711) => Err(ErrorKind::OsError(Some(libc::ELOOP)));
-    // Make sure the S_ISGID handling is correct.
-    setgid_selfdir: mkdir_all("setgid-self/a/b/c/d", 0o711) => Ok(());
-    #[cfg(feature = "_test_as_root")]
-    setgid_otherdir: mkdir_all("setgid-other/a/b/c/d", 0o711) => Ok(());
+#[test]
+#[cfg_attr(not(feature = "_test_as_root"), ignore)]
+fn root_remove_all_with() -> Result<(), Error> {
+    let root_dir = tests_common::create_basic_tree()?;
+    let root = Root::open(&root_dir)?;
+
+    tests_common::in_mnt_ns(|| {
+        // Mount-points cannot be removed (even by root).
+        tests_common::mount(root_dir.path().join("b/c/d"), MountType::Tmpfs)?;
+
+        // The default remove_all gives up on the first error.
+        tests_common::check_err(
+            &root.remove_all("b"),
+            &Err::<(), _>(ErrorKind::OsError(Some(libc::EBUSY))),
+        )?;
+
+        // Continuing past errors removes everything else.
+        let mut failed = Vec::new();
+        root.remove_all_with("b", |path, err| {
+            failed.push((path.to_path_buf(), err.kind()));
+            ControlFlow::Continue(())
+        })?;
+        assert_eq!(
+            failed,
+            vec![(
+                PathBuf::from("b/c/d"),
+                ErrorKind::OsError(Some(libc::EBUSY))
+            )],
+            "remove_all_with should report only the mount-point"
+        );
+        assert!(root.exists("b/c/d")?, "mount-point should not be removed");
+        assert!(!root.exists("b/c/file")?, "other entries should be removed");
+        assert_eq!(
+            root.read_dir("b")?
+                .map(|entry| entry.map(|entry| entry.file_name()))
+                .collect::<Result<Vec<_>, _>>()?,
+            vec!["c"],
+            "only the parents of the mount-point should remain"
+        );
 
-    // Check that multiple mkdir_alls racing against each other will not result
-    // in a spurious error. <https://github.com/opencontainers/runc/issues/4543>
-    plain: mkdir_all_racing("a/b/c/d/e/f/g/h/i/j/k/l/m/n/o/p/q/r/s/t/u/v/w/x/y/z", 0o711) => Ok(());
+        // Aborting on the first error returns that error.
+        let mut calls = 0;
+        let res = root.remove_all_with("b", |_, _| {
+            calls += 1;
+            ControlFlow::Break(())
+        });
+        tests_common::check_err(&res, &Err::<(), _>(ErrorKind::OsError(Some(libc::EBUSY))))?;
+        assert_eq!(calls, 1, "remove_all_with should stop after Break");
+        Ok(())
+    })
+}
 
-    // Check that multiple rmdir_alls racing against each other will not result
-    // in a spurious error.
-    plain: remove_all_racing("deep-rmdir") => Ok(());
+#[test]
+fn root_create_device_without_cap_mknod() -> Result<(), Error> {
+    let root_dir = tests_common::create_basic_tree()?;
+
+    // Capabilities are per-thread, so drop CAP_MKNOD in a separate thread to
+    // avoid affecting other tests.
+    thread::spawn(move || -> Result<(), Error> {
+        let mut caps = rustix_thread::capabilities(None)?;
+        caps.effective.remove(CapabilityFlags::MKNOD);
+        rustix_thread::set_capabilities(None, caps)?;
+        assert!(
+            !crate::can_mknod(),
+            "can_mknod should be false without CAP_MKNOD"
+        );
+
+        let root = Root::open(&root_dir)?;
+        let res = root.create(
+            "b/c/chr",
+            &InodeType::CharacterDevice(Permissions::from_mode(0o644), libc::makedev(1, 3)),
+        );
+        tests_common::check_err(&res, &Err::<(), _>(ErrorKind::OsError(Some(libc::EPERM))))?;
+        let err = res.expect_err("mknod without CAP_MKNOD should fail");
+        assert!(
+            err.to_string().contains("CAP_MKNOD"),
+            "EPERM from device creation should mention CAP_MKNOD: {err}"
+        );
+
+        // Non-device inodes don't need CAP_MKNOD.
+        root.create("b/c/fifo", &InodeType::Fifo(Permissions::from_mode(0o644)))?;
+        Ok(())
+    })
+    .join()
+    .expect("thread should not panic")
+}
+
+#[test]
+fn root_create_file_owned_without_cap_chown() -> Result<(), Error> {
+    let root_dir = tests_common::create_basic_tree()?;
+
+    // Capabilities are per-thread, so drop CAP_CHOWN in a separate thread to
+    // avoid affecting other tests.
+    thread::spawn(move || -> Result<(), Error> {
+        let mut caps = rustix_thread::capabilities(None)?;
+        caps.effective.remove(CapabilityFlags::CHOWN);
+        rustix_thread::set_capabilities(None, caps)?;
+
+        let root = Root::open(&root_dir)?;
+        let res = root.create_file_owned(
+            "b/c/owned",
+            OpenFlags::O_RDWR | OpenFlags::O_EXCL,
+            &Permissions::from_mode(0o644),
+            1000,
+            1000,
+        );
+        tests_common::check_err(&res, &Err::<(), _>(ErrorKind::OsError(Some(libc::EPERM))))?;
+        // The file was still created, with our ownership.
+        let meta = root.metadata("b/c/owned")?;
+        assert_eq!(
+            (meta.uid(), meta.gid()),
+            (
+                rustix::process::geteuid().as_raw(),
+                rustix::process::getegid().as_raw()
+            ),
+            "file should keep the owner of the caller if fchown fails"
+        );
+        Ok(())
+    })
+    .join()
+    .expect("thread should not panic")
+}
+
+#[test]
+fn root_probe_dir() -> Result<(), Error> {
+    let root_dir = tests_common::create_basic_tree()?;
+    fs::create_dir(root_dir.path().join("nosearch"))?;
+    fs::set_permissions(
+        root_dir.path().join("nosearch"),
+        Permissions::from_mode(0o600),
+    )?;
+
+    // Capabilities are per-thread, so drop CAP_DAC_OVERRIDE and
+    // CAP_DAC_READ_SEARCH in a separate thread to avoid affecting other tests.
+    thread::spawn(move || -> Result<(), Error> {
+        let mut caps = rustix_thread::capabilities(None)?;
+        caps.effective
+            .remove(CapabilityFlags::DAC_OVERRIDE | CapabilityFlags::DAC_READ_SEARCH);
+        rustix_thread::set_capabilities(None, caps)?;
+
+        let root = Root::open(&root_dir)?;
+        for (path, expected) in [
+            (".", DirProbe::Usable),
+            ("b/c", DirProbe::Usable),
+            ("e", DirProbe::Usable),
+            ("b/c/file", DirProbe::NotADirectory),
+            ("b/c/file/foo", DirProbe::NotADirectory),
+            ("nonexistent", DirProbe::Missing),
+            ("b/nonexistent/foo", DirProbe::Missing),
+            ("nosearch", DirProbe::NoSearchPermission),
+            ("nosearch/foo", DirProbe::NoSearchPermission),
+        ] {
+            assert_eq!(
+                root.probe_dir(path)?,
+                expected,
+                "probe_dir({path:?}) should return {expected:?}"
+            );
+        }
+        Ok(())
+    })
+    .join()
+    .expect("thread should not panic")
 }
 
 mod utils {
@@ -532,7 +2807,8 @@ mod utils {
     };
 
     use std::{
-        fs::Permissions,
+        fs::{OpenOptions, Permissions},
+        io::Write,
         os::unix::{
             fs::{MetadataExt, PermissionsExt},
             io::{AsFd, OwnedFd},
@@ -547,6 +2823,7 @@ mod utils {
     use rustix::{
         fs::{Mode, RawMode},
         process as rustix_process,
+        thread::{self as rustix_thread, UnshareFlags},
     };
 
     fn root_roundtrip<R: RootImpl>(root: R) -> Result<R::Cloned, Error> {
@@ -596,7 +2873,10 @@ mod utils {
 
                 match inode_type {
                     // No need for extra checks for these types.
-                    InodeType::File(_) | InodeType::Directory(_) | InodeType::Fifo(_) => (),
+                    InodeType::File(_)
+                    | InodeType::Directory(_)
+                    | InodeType::Fifo(_)
+                    | InodeType::Socket(_) => (),
                     // Check
                     InodeType::CharacterDevice(_, dev) | InodeType::BlockDevice(_, dev) => {
                         assert_eq!(meta.rdev(), dev, "device type of mknod mismatch");
@@ -1062,4 +3342,36 @@ mod utils {
         }
         Ok(())
     }
+
+    pub(super) fn spawn_fifo_writer<P: AsRef<Path>>(
+        path: P,
+        data: &'static [u8],
+    ) -> thread::JoinHandle<Result<(), Error>> {
+        let path = path.as_ref().to_path_buf();
+        thread::spawn(move || {
+            let mut fifo = OpenOptions::new()
+                .write(true)
+                .open(&path)
+                .with_context(|| format!("open fifo {path:?} for writing"))?;
+            fifo.write_all(data).context("write to fifo")?;
+            Ok(())
+        })
+    }
+
+    // Run a function in a separate thread with its own fs_struct, so that
+    // changing the working directory doesn't affect other tests.
+    pub(super) fn in_private_cwd<F, T>(func: F) -> Result<T, Error>
+    where
+        F: FnOnce() -> Result<T, Error> + Send,
+        T: Send,
+    {
+        thread::scope(|s| {
+            s.spawn(|| {
+                rustix_thread::unshare(UnshareFlags::FS).context("unshare(CLONE_FS)")?;
+                func()
+            })
+            .join()
+            .expect("test thread should not panic")
+        })
+    }
 }
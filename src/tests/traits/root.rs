@@ -92,15 +92,27 @@ impl RootImpl for Root {
 
     fn resolver(&self) -> Resolver {
         Resolver {
-            backend: self.resolver_backend(),
+            backend: Some(self.resolver_backend()),
             flags: self.resolver_flags(),
+            deadline: self.resolve_deadline(),
+            absolute_symlinks: self.absolute_symlink_policy(),
+            required_owner: None,
+            reopen_policy: self.reopen_policy(),
+            setid_policy: self.setid_policy(),
+            max_symlink_depth: self.max_symlink_depth(),
+            expected_mnt_id: None,
         }
     }
 
     fn from_fd<Fd: Into<OwnedFd>>(fd: Fd, resolver: Resolver) -> Self::Cloned {
         Self::Cloned::from_fd(fd)
-            .with_resolver_backend(resolver.backend)
+            .with_resolver_backend(resolver.backend.unwrap_or_default())
             .with_resolver_flags(resolver.flags)
+            .with_resolve_deadline(resolver.deadline)
+            .with_absolute_symlink_policy(resolver.absolute_symlinks)
+            .with_reopen_policy(resolver.reopen_policy)
+            .with_setid_policy(resolver.setid_policy)
+            .with_max_symlink_depth(resolver.max_symlink_depth)
     }
 
     fn try_clone(&self) -> Result<Self::Cloned, anyhow::Error> {
@@ -177,15 +189,27 @@ impl RootImpl for &Root {
 
     fn resolver(&self) -> Resolver {
         Resolver {
-            backend: self.resolver_backend(),
+            backend: Some(self.resolver_backend()),
             flags: self.resolver_flags(),
+            deadline: self.resolve_deadline(),
+            absolute_symlinks: self.absolute_symlink_policy(),
+            required_owner: None,
+            reopen_policy: self.reopen_policy(),
+            setid_policy: self.setid_policy(),
+            max_symlink_depth: self.max_symlink_depth(),
+            expected_mnt_id: None,
         }
     }
 
     fn from_fd<Fd: Into<OwnedFd>>(fd: Fd, resolver: Resolver) -> Self::Cloned {
         Self::Cloned::from_fd(fd)
-            .with_resolver_backend(resolver.backend)
+            .with_resolver_backend(resolver.backend.unwrap_or_default())
             .with_resolver_flags(resolver.flags)
+            .with_resolve_deadline(resolver.deadline)
+            .with_absolute_symlink_policy(resolver.absolute_symlinks)
+            .with_reopen_policy(resolver.reopen_policy)
+            .with_setid_policy(resolver.setid_policy)
+            .with_max_symlink_depth(resolver.max_symlink_depth)
     }
 
     fn try_clone(&self) -> Result<Self::Cloned, anyhow::Error> {
@@ -262,15 +286,27 @@ impl RootImpl for RootRef<'_> {
 
     fn resolver(&self) -> Resolver {
         Resolver {
-            backend: self.resolver_backend(),
+            backend: Some(self.resolver_backend()),
             flags: self.resolver_flags(),
+            deadline: self.resolve_deadline(),
+            absolute_symlinks: self.absolute_symlink_policy(),
+            required_owner: None,
+            reopen_policy: self.reopen_policy(),
+            setid_policy: self.setid_policy(),
+            max_symlink_depth: self.max_symlink_depth(),
+            expected_mnt_id: None,
         }
     }
 
     fn from_fd<Fd: Into<OwnedFd>>(fd: Fd, resolver: Resolver) -> Self::Cloned {
         Self::Cloned::from_fd(fd)
-            .with_resolver_backend(resolver.backend)
+            .with_resolver_backend(resolver.backend.unwrap_or_default())
             .with_resolver_flags(resolver.flags)
+            .with_resolve_deadline(resolver.deadline)
+            .with_absolute_symlink_policy(resolver.absolute_symlinks)
+            .with_reopen_policy(resolver.reopen_policy)
+            .with_setid_policy(resolver.setid_policy)
+            .with_max_symlink_depth(resolver.max_symlink_depth)
     }
 
     fn try_clone(&self) -> Result<Self::Cloned, anyhow::Error> {
@@ -347,15 +383,27 @@ impl RootImpl for &RootRef<'_> {
 
     fn resolver(&self) -> Resolver {
         Resolver {
-            backend: self.resolver_backend(),
+            backend: Some(self.resolver_backend()),
             flags: self.resolver_flags(),
+            deadline: self.resolve_deadline(),
+            absolute_symlinks: self.absolute_symlink_policy(),
+            required_owner: None,
+            reopen_policy: self.reopen_policy(),
+            setid_policy: self.setid_policy(),
+            max_symlink_depth: self.max_symlink_depth(),
+            expected_mnt_id: None,
         }
     }
 
     fn from_fd<Fd: Into<OwnedFd>>(fd: Fd, resolver: Resolver) -> Self::Cloned {
         Self::Cloned::from_fd(fd)
-            .with_resolver_backend(resolver.backend)
+            .with_resolver_backend(resolver.backend.unwrap_or_default())
             .with_resolver_flags(resolver.flags)
+            .with_resolve_deadline(resolver.deadline)
+            .with_absolute_symlink_policy(resolver.absolute_symlinks)
+            .with_reopen_policy(resolver.reopen_policy)
+            .with_setid_policy(resolver.setid_policy)
+            .with_max_symlink_depth(resolver.max_symlink_depth)
     }
 
     fn try_clone(&self) -> Result<Self::Cloned, anyhow::Error> {
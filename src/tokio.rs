@@ -0,0 +1,178 @@
+/*
+ * libpathrs: safe path resolution on Linux
+ * Copyright (C) 2019-2021 Aleksa Sarai <cyphar@cyphar.com>
+ * Copyright (C) 2019-2021 SUSE LLC
+ *
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Lesser General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or (at your
+ * option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY
+ * or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License
+ * for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Async wrappers for [`Root`] operations, for use with the [`tokio`] runtime.
+//!
+//! Path resolution is always done with blocking syscalls, which can stall for
+//! a long time on slow (or network) filesystems. The functions in this module
+//! run the corresponding [`Root`] method on [`tokio`]'s blocking thread pool
+//! (using [`spawn_blocking`]) so that the async executor is not blocked. The
+//! returned [`Handle`]s and [`File`]s are owned (and [`Send`]), so they can be
+//! freely held across `.await` points.
+//!
+//! Each call uses a duplicate of the [`Root`] file descriptor (see
+//! [`Root::try_clone`]) with the same configuration as `root`, so `root` does
+//! not need to outlive the blocking task.
+//!
+//! This module is only available with the `tokio` feature.
+//!
+//! [`spawn_blocking`]: tokio::task::spawn_blocking
+
+use crate::{
+    error::{Error, ErrorImpl},
+    flags::OpenFlags,
+    Handle, Root,
+};
+
+use std::{
+    fs::{File, Permissions},
+    io::{Error as IOError, ErrorKind as IOErrorKind},
+    panic,
+    path::Path,
+};
+
+/// Run `op` with a copy of `root` on the blocking thread pool.
+async fn run_blocking<T, F>(root: &Root, op: F) -> Result<T, Error>
+where
+    T: Send + 'static,
+    F: FnOnce(Root) -> Result<T, Error> + Send + 'static,
+{
+    let root = root.try_clone()?;
+    match ::tokio::task::spawn_blocking(move || op(root)).await {
+        Ok(res) => res,
+        // Propagate panics from the blocking task to the caller.
+        Err(err) if err.is_panic() => panic::resume_unwind(err.into_panic()),
+        Err(err) => Err(ErrorImpl::OsError {
+            operation: "run blocking task".into(),
+            source: IOError::new(IOErrorKind::Other, err),
+        })?,
+    }
+}
+
+/// Async version of [`Root::resolve`].
+pub async fn resolve<P: AsRef<Path>>(root: &Root, path: P) -> Result<Handle, Error> {
+    let path = path.as_ref().to_path_buf();
+    run_blocking(root, move |root| root.resolve(path)).await
+}
+
+/// Async version of [`Root::resolve_nofollow`].
+pub async fn resolve_nofollow<P: AsRef<Path>>(root: &Root, path: P) -> Result<Handle, Error> {
+    let path = path.as_ref().to_path_buf();
+    run_blocking(root, move |root| root.resolve_nofollow(path)).await
+}
+
+/// Async version of [`Root::create_file`].
+///
+/// Note that the returned [`File`] is an ordinary [`std::fs::File`]. To do
+/// async I/O on it, you can use [`tokio::fs::File::from_std`].
+pub async fn create_file<P: AsRef<Path>>(
+    root: &Root,
+    path: P,
+    flags: OpenFlags,
+    perm: &Permissions,
+) -> Result<File, Error> {
+    let (path, perm) = (path.as_ref().to_path_buf(), perm.clone());
+    run_blocking(root, move |root| root.create_file(path, flags, &perm)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{error::ErrorKind, flags::OpenFlags, tests::common as tests_common, Root};
+
+    use std::{
+        fs::{self, Permissions},
+        io::Write,
+        os::unix::fs::{MetadataExt, PermissionsExt},
+    };
+
+    use anyhow::Error;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn resolve() -> Result<(), Error> {
+        let root_dir = tests_common::create_basic_tree()?;
+        let root = Root::open(&root_dir)?;
+        let runtime = ::tokio::runtime::Builder::new_current_thread().build()?;
+
+        runtime.block_on(async {
+            let expected = root.resolve("b/c/file")?.metadata()?;
+            let handle = super::resolve(&root, "b-file").await?;
+            let meta = handle.metadata()?;
+            assert_eq!(
+                (meta.dev(), meta.ino()),
+                (expected.dev(), expected.ino()),
+                "async resolve should give the same handle as resolve"
+            );
+
+            let handle = super::resolve_nofollow(&root, "b-file").await?;
+            assert!(
+                handle.metadata()?.is_symlink(),
+                "async resolve_nofollow should not follow trailing symlinks"
+            );
+
+            assert_eq!(
+                super::resolve(&root, "nonexistent")
+                    .await
+                    .map(|_| ())
+                    .map_err(|err| err.kind()),
+                Err(ErrorKind::OsError(Some(libc::ENOENT))),
+                "async resolve of nonexistent path should fail"
+            );
+            Ok::<_, Error>(())
+        })
+    }
+
+    #[test]
+    fn create_file() -> Result<(), Error> {
+        let root_dir = tests_common::create_basic_tree()?;
+        let root = Root::open(&root_dir)?;
+        let runtime = ::tokio::runtime::Builder::new_current_thread().build()?;
+
+        runtime.block_on(async {
+            let mut file = super::create_file(
+                &root,
+                "e/new-file",
+                OpenFlags::O_WRONLY | OpenFlags::O_EXCL,
+                &Permissions::from_mode(0o600),
+            )
+            .await?;
+            file.write_all(b"hello async")?;
+            assert_eq!(
+                fs::read(root_dir.path().join("b/c/d/e/new-file"))?,
+                b"hello async",
+                "async create_file should create the file inside the root"
+            );
+
+            assert_eq!(
+                super::create_file(
+                    &root,
+                    "e/new-file",
+                    OpenFlags::O_WRONLY | OpenFlags::O_EXCL,
+                    &Permissions::from_mode(0o600),
+                )
+                .await
+                .map(|_| ())
+                .map_err(|err| err.kind()),
+                Err(ErrorKind::OsError(Some(libc::EEXIST))),
+                "async create_file with O_EXCL of existing file should fail"
+            );
+            Ok::<_, Error>(())
+        })
+    }
+}
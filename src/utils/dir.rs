@@ -24,12 +24,20 @@ use crate::{
 };
 
 use std::{
+    collections::HashSet,
     ffi::OsStr,
-    os::unix::{ffi::OsStrExt, io::AsFd},
-    path::Path,
+    ops::ControlFlow,
+    os::unix::{
+        ffi::OsStrExt,
+        io::{AsFd, BorrowedFd, OwnedFd},
+    },
+    path::{Path, PathBuf},
 };
 
-use rustix::fs::{AtFlags, Dir};
+use rustix::{
+    fs::{AtFlags, Dir},
+    io::Errno,
+};
 
 trait RmdirResultExt {
     // ENOENT from a removal function should be treated the same as an Ok(()).
@@ -69,18 +77,67 @@ fn remove_inode<Fd: AsFd>(dirfd: Fd, name: &Path) -> Result<(), Error> {
         })
 }
 
-pub(crate) fn remove_all<Fd: AsFd>(dirfd: Fd, name: &Path) -> Result<(), Error> {
-    let dirfd = dirfd.as_fd();
+/// Recursively remove `name` (inside `dirfd`). `on_error` is called with the
+/// path (`path` joined with the names of any children) of every entry that
+/// could not be removed. If `on_error` returns [`ControlFlow::Continue`], the
+/// entry is skipped and we try to remove the rest of the tree (its parent
+/// directories will then not be removed, and `on_error` is not called for
+/// them). If it returns [`ControlFlow::Break`], the error is returned
+/// immediately.
+pub(crate) fn remove_all_with<Fd, F>(
+    dirfd: Fd,
+    name: &Path,
+    path: &Path,
+    mut on_error: F,
+) -> Result<(), Error>
+where
+    Fd: AsFd,
+    F: FnMut(&Path, &Error) -> ControlFlow<()>,
+{
+    remove_all_inner(dirfd.as_fd(), name, path, &mut on_error)
+        .map(|_| ())
+        .map_err(|Aborted(err)| err)
+}
+
+/// An error which `on_error` asked [`remove_all_with`] to abort on (and has
+/// thus already been reported).
+struct Aborted(Error);
+
+fn report_error<F>(path: &Path, err: Error, on_error: &mut F) -> Result<bool, Aborted>
+where
+    F: FnMut(&Path, &Error) -> ControlFlow<()>,
+{
+    match on_error(path, &err) {
+        ControlFlow::Continue(()) => Ok(false),
+        ControlFlow::Break(()) => Err(Aborted(err)),
+    }
+}
 
+// Returns whether the entry was removed. Errors for the entry itself are
+// reported to on_error here, so callers must not report them again.
+fn remove_all_inner<F>(
+    dirfd: BorrowedFd<'_>,
+    name: &Path,
+    path: &Path,
+    on_error: &mut F,
+) -> Result<bool, Aborted>
+where
+    F: FnMut(&Path, &Error) -> ControlFlow<()>,
+{
     if name.as_os_str().as_bytes().contains(&b'/') {
-        Err(ErrorImpl::SafetyViolation {
-            description: "remove_all reached a component containing '/'".into(),
-        })?;
+        return report_error(
+            path,
+            ErrorImpl::SafetyViolation {
+                description: "remove_all reached a component containing '/'".into(),
+            }
+            .into(),
+            on_error,
+        );
     }
 
     // Fast path -- try to remove it with unlink/rmdir.
     if remove_inode(dirfd, name).ignore_enoent().is_ok() {
-        return Ok(());
+        return Ok(true);
     }
 
     // Try to delete all children. We need to re-do the iteration until there
@@ -99,10 +156,13 @@ pub(crate) fn remove_all<Fd: AsFd>(dirfd: Fd, name: &Path) -> Result<(), Error>
         Err(err) => match err.kind().errno() {
             // The path was deleted between us trying to with remove_inode() and
             // now -- just return as if we were the ones that deleted it.
-            Some(libc::ENOENT) => return Ok(()),
-            _ => Err(err)?,
+            Some(libc::ENOENT) => return Ok(true),
+            _ => return report_error(path, err.into(), on_error),
         },
     };
+    // Children which could not be removed (and which on_error told us to
+    // skip), so that we don't keep trying to remove them forever.
+    let mut failed = HashSet::new();
     loop {
         // TODO: Dir creates a new file descriptor rather than reusing the one
         //       we have, and RawDir can't be used as an Iterator yet (rustix
@@ -126,14 +186,15 @@ pub(crate) fn remove_all<Fd: AsFd>(dirfd: Fd, name: &Path) -> Result<(), Error>
                 // TODO: Maybe we want to just break out of the loop here as
                 //       well, rather than return an error? If remove_inode()
                 //       again succeeds we're golden.
-                _ => Err(err)?,
+                _ => return report_error(path, err.into(), on_error),
             },
         }
-        .filter(|res| {
-            !matches!(
-                res.as_ref().map(|dentry| dentry.file_name().to_bytes()),
-                Ok(b".") | Ok(b"..")
-            )
+        .filter(|res| match res {
+            Ok(dentry) => {
+                let name = dentry.file_name().to_bytes();
+                name != b"." && name != b".." && !failed.contains(name)
+            }
+            Err(_) => true,
         })
         .peekable();
 
@@ -143,37 +204,199 @@ pub(crate) fn remove_all<Fd: AsFd>(dirfd: Fd, name: &Path) -> Result<(), Error>
         }
 
         // Recurse into all of the children and try to delete them.
+        let mut new_failures = Vec::new();
         for child in iter {
             // TODO: We probably want to break out of the scan loop here if this
             //       is an error as well.
-            let child = child.map_err(|err| ErrorImpl::OsError {
+            let child = match child.map_err(|err| ErrorImpl::OsError {
                 operation: format!("scan directory {name:?}").into(),
                 source: err.into(),
-            })?;
-            let name: &Path = OsStr::from_bytes(child.file_name().to_bytes()).as_ref();
-            remove_all(&subdir, name).ignore_enoent()?
+            }) {
+                Ok(child) => child,
+                Err(err) => {
+                    report_error(path, err.into(), on_error)?;
+                    // We cannot make any more progress scanning this
+                    // directory, so don't try to remove it.
+                    return Ok(false);
+                }
+            };
+            let child_name = child.file_name().to_bytes();
+            let name: &Path = OsStr::from_bytes(child_name).as_ref();
+            if !remove_all_inner(subdir.as_fd(), name, &path.join(name), on_error)? {
+                new_failures.push(child_name.to_vec());
+            }
         }
+        failed.extend(new_failures);
+    }
+
+    // If we skipped some children, the directory cannot be empty, so there is
+    // no point trying to remove it (the failures have already been reported).
+    if !failed.is_empty() {
+        return Ok(false);
     }
 
     // We have deleted all of the children of the directory, let's try to delete
     // the inode again (it should be empty now -- an attacker could add things
     // but we can just error out in that case, and if they swapped it to a file
     // then remove_inode will take care of that).
-    remove_inode(dirfd, name)
+    match remove_inode(dirfd, name)
         .ignore_enoent()
         .with_wrap(|| format!("deleting emptied directory {name:?}"))
+    {
+        Ok(()) => Ok(true),
+        Err(err) => report_error(path, err, on_error),
+    }
+}
+
+/// Scan the directory `dirfd` (whose path is `dirpath`) and all of its
+/// subdirectories for non-directory inodes with the given `(dev, ino)`,
+/// appending their paths to `found`. The scan stops once `found` contains
+/// `limit` entries.
+///
+/// Symlinks are never followed, and directories on a different device to
+/// `dev` are not scanned (hardlinks cannot cross filesystems). Directories
+/// which are the same inode as one of their ancestors (such as with bind-mount
+/// loops) are skipped, as are subdirectories we do not have permission to
+/// read.
+pub(crate) fn find_inode_links(
+    dirfd: OwnedFd,
+    dirpath: &Path,
+    (dev, ino): (u64, u64),
+    limit: usize,
+    found: &mut Vec<PathBuf>,
+) -> Result<(), Error> {
+    struct ScanLevel {
+        fd: OwnedFd,
+        iter: Dir,
+        path: PathBuf,
+        id: (u64, u64),
+    }
+
+    impl ScanLevel {
+        fn new(fd: OwnedFd, path: PathBuf) -> Result<Self, Error> {
+            let stat = syscalls::fstatat(&fd, "").map_err(|err| ErrorImpl::RawOsError {
+                operation: "stat directory to scan for hardlinks".into(),
+                source: err,
+            })?;
+            let iter = Dir::read_from(&fd).map_err(|err| ErrorImpl::OsError {
+                operation: "create directory iterator".into(),
+                source: err.into(),
+            })?;
+            Ok(Self {
+                fd,
+                iter,
+                path,
+                id: (stat.st_dev, stat.st_ino),
+            })
+        }
+    }
+
+    // Subdirectories we cannot read are skipped rather than failing the
+    // entire scan.
+    fn is_unreadable(err: &Error) -> bool {
+        matches!(err.kind().errno(), Some(libc::EACCES | libc::EPERM))
+    }
+
+    // Use an explicit stack rather than recursion, so that deep trees cannot
+    // overflow our stack.
+    let mut stack = vec![ScanLevel::new(dirfd, dirpath.to_path_buf())
+        .with_wrap(|| format!("scan directory {dirpath:?} for hardlinks"))?];
+    while found.len() < limit {
+        let level = match stack.last_mut() {
+            Some(level) => level,
+            None => break,
+        };
+        let child = match level.iter.next() {
+            Some(Ok(child)) => child,
+            Some(Err(Errno::ACCESS | Errno::PERM)) => {
+                stack.pop();
+                continue;
+            }
+            Some(Err(err)) => Err(ErrorImpl::OsError {
+                operation: format!("scan directory {:?}", level.path).into(),
+                source: err.into(),
+            })?,
+            None => {
+                stack.pop();
+                continue;
+            }
+        };
+        let name: &Path = OsStr::from_bytes(child.file_name().to_bytes()).as_ref();
+        if name == Path::new(".") || name == Path::new("..") {
+            continue;
+        }
+
+        // The entry might have been removed or swapped since we read the
+        // directory, in which case we just skip it.
+        let stat = match syscalls::fstatat(&level.fd, name) {
+            Ok(stat) => stat,
+            Err(err) if err.root_cause().raw_os_error() == Some(libc::ENOENT) => continue,
+            Err(err) => Err(ErrorImpl::RawOsError {
+                operation: "stat directory entry".into(),
+                source: err,
+            })?,
+        };
+        if stat.st_dev != dev {
+            continue;
+        }
+
+        let path = level.path.join(name);
+        if stat.st_mode & libc::S_IFMT == libc::S_IFDIR {
+            // O_NOFOLLOW is set by syscalls::openat, so if the directory was
+            // swapped with a symlink we will just get an error.
+            let subdir = match syscalls::openat(&level.fd, name, OpenFlags::O_DIRECTORY, 0) {
+                Ok(fd) => fd,
+                Err(err)
+                    if matches!(
+                        err.root_cause().raw_os_error(),
+                        Some(
+                            libc::ENOENT | libc::ENOTDIR | libc::ELOOP | libc::EACCES | libc::EPERM
+                        )
+                    ) =>
+                {
+                    continue
+                }
+                Err(err) => Err(ErrorImpl::RawOsError {
+                    operation: "open directory to scan for hardlinks".into(),
+                    source: err,
+                })?,
+            };
+            let subdir = match ScanLevel::new(subdir, path) {
+                Ok(subdir) => subdir,
+                Err(err) if is_unreadable(&err) => continue,
+                Err(err) => return Err(err),
+            };
+            // Bind-mounts can create loops within a single filesystem, so
+            // don't scan a directory which is one of its own ancestors.
+            if stack.iter().any(|ancestor| ancestor.id == subdir.id) {
+                continue;
+            }
+            stack.push(subdir);
+        } else if stat.st_ino == ino {
+            found.push(path);
+        }
+    }
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
-    use super::remove_all;
-    use crate::{error::ErrorKind, tests::common as tests_common, Root};
+    use super::remove_all_with;
+    use crate::{
+        error::{Error as PathrsError, ErrorKind},
+        tests::common as tests_common,
+        Root,
+    };
 
-    use std::{os::unix::io::OwnedFd, path::Path};
+    use std::{ops::ControlFlow, os::unix::io::OwnedFd, path::Path};
 
     use anyhow::Error;
     use pretty_assertions::assert_eq;
 
+    fn remove_all(dirfd: &OwnedFd, name: &Path) -> Result<(), PathrsError> {
+        remove_all_with(dirfd, name, name, |_, _| ControlFlow::Break(()))
+    }
+
     #[test]
     fn remove_all_basic() -> Result<(), Error> {
         let dir = tests_common::create_basic_tree()?;
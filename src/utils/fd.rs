@@ -19,7 +19,7 @@
 
 use crate::{
     error::{Error, ErrorExt, ErrorImpl},
-    flags::OpenFlags,
+    flags::{OpenFlags, ReopenPolicy},
     procfs::{ProcfsBase, ProcfsHandle},
     syscalls,
 };
@@ -34,7 +34,7 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use rustix::fs::{self as rustix_fs, StatExt, StatxFlags};
+use rustix::fs::{self as rustix_fs, Access, AtFlags, StatExt, StatxFlags, Timestamps};
 
 pub(crate) struct Metadata(rustix_fs::Stat);
 
@@ -42,6 +42,10 @@ impl Metadata {
     pub(crate) fn is_symlink(&self) -> bool {
         self.mode() & libc::S_IFMT == libc::S_IFLNK
     }
+
+    pub(crate) fn is_dir(&self) -> bool {
+        self.mode() & libc::S_IFMT == libc::S_IFDIR
+    }
 }
 
 #[allow(clippy::useless_conversion)] // 32-bit arches
@@ -117,8 +121,17 @@ pub(crate) trait FdExt {
     /// [`File::metadata`]: std::fs::File::metadata
     fn metadata(&self) -> Result<Metadata, Error>;
 
-    /// Re-open a file descriptor.
-    fn reopen(&self, procfs: &ProcfsHandle, flags: OpenFlags) -> Result<OwnedFd, Error>;
+    /// Check whether two file descriptors reference the same inode (the same
+    /// `st_dev` and `st_ino`).
+    fn is_same_inode<Other: AsFd>(&self, other: Other) -> Result<bool, Error>;
+
+    /// Re-open a file descriptor, applying the given [`ReopenPolicy`].
+    fn reopen(
+        &self,
+        procfs: &ProcfsHandle,
+        flags: OpenFlags,
+        policy: ReopenPolicy,
+    ) -> Result<OwnedFd, Error>;
 
     /// Get the path this RawFd is referencing.
     ///
@@ -147,6 +160,114 @@ pub(crate) trait FdExt {
     fn is_magiclink_filesystem(&self) -> Result<bool, Error>;
 }
 
+/// Change the mode of the inode referenced by `fd`, which may be an `O_PATH`
+/// file descriptor.
+///
+/// `fchmod(2)` does not work on `O_PATH` file descriptors (and `fchmodat(2)`
+/// only supports `AT_EMPTY_PATH` with `fchmodat2(2)`, added in Linux 6.6), so
+/// this is done through the `/proc/thread-self/fd/$n` magic-link (after
+/// checking that it has not been over-mounted with
+/// [`ProcfsFdDir::verified_magiclink`]).
+///
+/// [`ProcfsFdDir::verified_magiclink`]: crate::procfs::ProcfsFdDir::verified_magiclink
+pub(crate) fn fchmod_procfs<Fd: AsFd>(
+    procfs: &ProcfsHandle,
+    fd: Fd,
+    mode: libc::mode_t,
+) -> Result<(), Error> {
+    let fd_dir = procfs.open_fd_dir()?;
+    let name = fd_dir.verified_magiclink(fd)?;
+    syscalls::fchmodat(&fd_dir, name, mode).map_err(|err| {
+        ErrorImpl::RawOsError {
+            operation: "chmod file through procfs magic-link".into(),
+            source: err,
+        }
+        .into()
+    })
+}
+
+/// Set the timestamps of the inode referenced by `fd`.
+///
+/// As with [`fchmod_procfs`], `futimens(3)` does not work on `O_PATH` file
+/// descriptors, so this is done through the `/proc/thread-self/fd/$n`
+/// magic-link (which also works for file descriptors referencing symlinks, as
+/// the magic-link jumps directly to the symlink without following it). The
+/// magic-link is checked in the same way as [`fchmod_procfs`].
+pub(crate) fn futimens_procfs<Fd: AsFd>(
+    procfs: &ProcfsHandle,
+    fd: Fd,
+    times: &Timestamps,
+) -> Result<(), Error> {
+    let fd_dir = procfs.open_fd_dir()?;
+    let name = fd_dir.verified_magiclink(fd)?;
+    syscalls::utimensat(&fd_dir, name, times).map_err(|err| {
+        ErrorImpl::RawOsError {
+            operation: "set file times through procfs magic-link".into(),
+            source: err,
+        }
+        .into()
+    })
+}
+
+/// Check whether the current process can access the inode referenced by `fd`
+/// using its effective credentials.
+///
+/// `faccessat2(2)` does not support `AT_EMPTY_PATH`, so this is done by
+/// following the `/proc/thread-self/fd/$n` magic-link (after checking that it
+/// has not been over-mounted, in the same way as [`fchmod_procfs`]).
+pub(crate) fn faccessat_procfs<Fd: AsFd>(
+    procfs: &ProcfsHandle,
+    fd: Fd,
+    access: Access,
+) -> Result<(), Error> {
+    let fd_dir = procfs.open_fd_dir()?;
+    let name = fd_dir.verified_magiclink(&fd)?;
+    syscalls::faccessat_follow(&fd_dir, name, access).map_err(|err| {
+        ErrorImpl::RawOsError {
+            operation: "check access through procfs magic-link".into(),
+            source: err,
+        }
+        .into()
+    })
+}
+
+/// Create a new hardlink named `name` inside `dir` to the inode referenced by
+/// `fd` (which may be an anonymous `O_TMPFILE` file).
+///
+/// `linkat(2)` with `AT_EMPTY_PATH` requires `CAP_DAC_READ_SEARCH`, so this is
+/// instead done by following the `/proc/thread-self/fd/$n` magic-link with
+/// `AT_SYMLINK_FOLLOW` (after checking that it has not been over-mounted, in
+/// the same way as [`fchmod_procfs`]).
+pub(crate) fn linkat_procfs<Fd: AsFd, DirFd: AsFd, P: AsRef<Path>>(
+    procfs: &ProcfsHandle,
+    fd: Fd,
+    dir: DirFd,
+    name: P,
+) -> Result<(), Error> {
+    let fd_dir = procfs.open_fd_dir()?;
+    let link = fd_dir.verified_magiclink(&fd)?;
+    syscalls::linkat(&fd_dir, link, dir, name, AtFlags::SYMLINK_FOLLOW).map_err(|err| {
+        ErrorImpl::RawOsError {
+            operation: "link file through procfs magic-link".into(),
+            source: err,
+        }
+        .into()
+    })
+}
+
+/// Get the `/proc/thread-self/fd/$n` magic-link path for a file descriptor.
+///
+/// Note that (unlike the rest of our procfs operations) the path is resolved
+/// by the kernel from the host's `/proc` and so is not protected against an
+/// attacker that can over-mount `/proc`. This should only be used for
+/// operations which have no `*at(2)` variant and cannot be done with a file
+/// descriptor.
+pub(crate) fn proc_fd_magiclink_path<Fd: AsFd>(fd: Fd) -> Result<PathBuf, Error> {
+    Ok(PathBuf::from("/proc")
+        .join(ProcfsBase::ProcThreadSelf.into_path(None))
+        .join(proc_subpath(fd.as_fd())?))
+}
+
 fn proc_subpath<Fd: AsRawFd>(fd: Fd) -> Result<String, Error> {
     let fd = fd.as_raw_fd();
     if fd == libc::AT_FDCWD {
@@ -190,8 +311,19 @@ impl<Fd: AsFd> FdExt for Fd {
         Ok(Metadata(stat))
     }
 
-    fn reopen(&self, procfs: &ProcfsHandle, mut flags: OpenFlags) -> Result<OwnedFd, Error> {
+    fn is_same_inode<Other: AsFd>(&self, other: Other) -> Result<bool, Error> {
+        let (meta, other_meta) = (self.metadata()?, other.metadata()?);
+        Ok((meta.dev(), meta.ino()) == (other_meta.dev(), other_meta.ino()))
+    }
+
+    fn reopen(
+        &self,
+        procfs: &ProcfsHandle,
+        mut flags: OpenFlags,
+        policy: ReopenPolicy,
+    ) -> Result<OwnedFd, Error> {
         let fd = self.as_fd();
+        let meta = self.metadata()?;
 
         // For file descriptors referencing a symlink (i.e. opened with
         // O_PATH|O_NOFOLLOW) there is no logic behind trying to do a "reopen"
@@ -199,7 +331,7 @@ impl<Fd: AsFd> FdExt for Fd {
         // itself is done through a symlink. Even with O_EMPTYPATH you probably
         // wouldn't ever want to re-open it (all you can get is another
         // O_PATH|O_EMPTYPATH).
-        if self.metadata()?.is_symlink() {
+        if meta.is_symlink() {
             Err(Error::from(ErrorImpl::OsError {
                 operation: "reopen".into(),
                 source: IOError::from_raw_os_error(libc::ELOOP),
@@ -213,9 +345,26 @@ impl<Fd: AsFd> FdExt for Fd {
         flags.remove(OpenFlags::O_NOFOLLOW);
 
         // TODO: Add support for O_EMPTYPATH once that exists...
-        procfs
+        let file = procfs
             .open_follow(ProcfsBase::ProcThreadSelf, proc_subpath(fd)?, flags)
-            .map(OwnedFd::from)
+            .map(OwnedFd::from)?;
+
+        if policy.verify_inode() {
+            let new_meta = file.metadata().wrap("fstat re-opened file")?;
+            if (new_meta.dev(), new_meta.ino()) != (meta.dev(), meta.ino()) {
+                Err(ErrorImpl::SafetyViolation {
+                    description: format!(
+                        "re-opened file is a different inode to the original handle ({}:{} != {}:{})",
+                        new_meta.dev(),
+                        new_meta.ino(),
+                        meta.dev(),
+                        meta.ino()
+                    )
+                    .into(),
+                })?
+            }
+        }
+        Ok(file)
     }
 
     fn as_unsafe_path(&self, procfs: &ProcfsHandle) -> Result<PathBuf, Error> {
@@ -224,11 +373,8 @@ impl<Fd: AsFd> FdExt for Fd {
     }
 
     fn as_unsafe_path_unchecked(&self) -> Result<PathBuf, Error> {
-        let fd = self.as_fd();
         // "/proc/thread-self/fd/$n"
-        let fd_path = PathBuf::from("/proc")
-            .join(ProcfsBase::ProcThreadSelf.into_path(None))
-            .join(proc_subpath(fd)?);
+        let fd_path = proc_fd_magiclink_path(self)?;
 
         // Because this code is used within syscalls, we can't even check the
         // filesystem type of /proc (unless we were to copy the logic here).
@@ -308,9 +454,42 @@ pub(crate) fn fetch_mnt_id<Fd: AsFd, P: AsRef<Path>>(
     }
 }
 
+/// Check whether `fd` refers to an automount point which has not been
+/// triggered yet, using `STATX_ATTR_AUTOMOUNT`.
+pub(crate) fn is_automount_point<Fd: AsFd>(fd: Fd) -> Result<bool, Error> {
+    const STATX_ATTR_AUTOMOUNT: u64 = 0x1000;
+
+    let stx = match syscalls::statx(fd, "", StatxFlags::TYPE) {
+        Ok(stx) => stx,
+        Err(err) if matches!(err.root_cause().raw_os_error(), Some(libc::ENOSYS)) => {
+            Err(ErrorImpl::NotSupported {
+                feature: "statx".into(),
+            })?
+        }
+        Err(err) => Err(ErrorImpl::RawOsError {
+            operation: "check automount attribute of inode".into(),
+            source: err,
+        })?,
+    };
+    // Kernels before Linux 5.8 do not tell us whether STATX_ATTR_AUTOMOUNT is
+    // supported, so we cannot trust a missing attribute.
+    if stx.stx_attributes_mask & STATX_ATTR_AUTOMOUNT == 0 {
+        Err(ErrorImpl::NotSupported {
+            feature: "STATX_ATTR_AUTOMOUNT".into(),
+        })?
+    }
+    Ok(stx.stx_attributes & STATX_ATTR_AUTOMOUNT != 0)
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::{flags::OpenFlags, procfs::GLOBAL_PROCFS_HANDLE, syscalls, utils::FdExt};
+    use crate::{
+        error::ErrorKind,
+        flags::{OpenFlags, ReopenPolicy},
+        procfs::GLOBAL_PROCFS_HANDLE,
+        syscalls,
+        utils::FdExt,
+    };
 
     use std::{
         fs::File,
@@ -371,7 +550,11 @@ mod tests {
     fn reopen_badfd() {
         assert!(
             syscalls::BADFD
-                .reopen(&GLOBAL_PROCFS_HANDLE, OpenFlags::O_PATH)
+                .reopen(
+                    &GLOBAL_PROCFS_HANDLE,
+                    OpenFlags::O_PATH,
+                    ReopenPolicy::default()
+                )
                 .is_err(),
             "reopen should fail for bad file descriptor"
         );
@@ -396,6 +579,28 @@ mod tests {
         );
     }
 
+    #[test]
+    fn is_automount_point() {
+        match super::is_automount_point(
+            File::open("/").expect("should be able to open handle to /"),
+        ) {
+            Ok(is_automount) => assert!(!is_automount, "/ is not an automount point"),
+            Err(err) => assert_eq!(
+                err.kind(),
+                ErrorKind::NotSupported,
+                "is_automount_point should only fail if STATX_ATTR_AUTOMOUNT is unsupported"
+            ),
+        }
+    }
+
+    #[test]
+    fn is_automount_point_badfd() {
+        assert!(
+            super::is_automount_point(syscalls::BADFD).is_err(),
+            "is_automount_point should fail for bad file descriptor"
+        );
+    }
+
     #[test]
     fn metadata_badfd() {
         assert!(